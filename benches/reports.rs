@@ -0,0 +1,54 @@
+//! Benchmarks for reading and reporting on a large journal
+//!
+//! katana is a binary, so these run it the way it's used, on a journal made up by
+//! `katana gen`, and the times include starting the process.
+//!
+//!   $ cargo bench
+//!   $ cargo bench -- balance
+
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use criterion::{Criterion, criterion_group, criterion_main};
+
+const KATANA: &str = env!("CARGO_BIN_EXE_katana");
+
+// enough transactions for several years of a busy journal
+const TRANSACTIONS: &str = "100000";
+
+// the same journal for every run, so the times can be compared
+fn generated_journal() -> PathBuf {
+    let path = std::env::temp_dir().join(format!("katana-bench-{}.journal", TRANSACTIONS));
+    let status = Command::new(KATANA).args(["gen", "--transactions", TRANSACTIONS, "--seed", "1", "-o"])
+                                     .arg(&path)
+                                     .status()
+                                     .expect("couldn't run katana gen");
+    assert!(status.success(), "katana gen failed");
+    path
+}
+
+fn katana(journal: &PathBuf, args: &[&str]) {
+    let status = Command::new(KATANA).arg("-j")
+                                     .arg(journal)
+                                     .args(args)
+                                     .stdout(Stdio::null())
+                                     .status()
+                                     .expect("couldn't run katana");
+    assert!(status.success(), "katana {} failed", args.join(" "));
+}
+
+fn reports(c: &mut Criterion) {
+    let journal = generated_journal();
+    let mut group = c.benchmark_group("100k transactions");
+    group.sample_size(10);
+
+    // check reads and parses the journal and reports nothing
+    group.bench_function("parse", |b| b.iter(|| katana(&journal, &["check"])));
+    group.bench_function("register", |b| b.iter(|| katana(&journal, &["register", "assets:checking"])));
+    group.bench_function("balance", |b| b.iter(|| katana(&journal, &["balance", "expenses"])));
+    group.bench_function("balance --monthly", |b| b.iter(|| katana(&journal, &["balance", "--monthly", "-X", "$"])));
+    group.finish();
+}
+
+criterion_group!(benches, reports);
+criterion_main!(benches);
@@ -0,0 +1,175 @@
+use std::collections::BTreeMap;
+
+use crate::iterators::entriesbyaccount::entries_by_account;
+use crate::journal::Journal;
+use crate::types::{Account, mixedamount::MixedAmount};
+
+/// A tree of accounts following the colons in their names, so expenses:food is a child
+/// of expenses. Each account can hold a value of its own, and the accounts above it are
+/// in the tree whether they hold one or not.
+///
+/// Children are kept in order by name, so a depth-first walk lists the accounts in the
+/// order compare_accounts sorts them, each parent just before its subaccounts.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AccountTree<T> {
+    children: BTreeMap<String, Node<T>>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Node<T> {
+    value   : Option<T>,
+    children: BTreeMap<String, Node<T>>,
+}
+
+/// An account reached by a walk through the tree, with its indentation level.
+pub struct TreeEntry {
+    pub account: Account,
+    pub depth  : usize,  // 0 for a top-level account
+}
+
+impl<T> Default for AccountTree<T> {
+    fn default() -> Self {
+        AccountTree { children: BTreeMap::new() }
+    }
+}
+
+impl<T> AccountTree<T> {
+    /// Sets the account's value, adding it and any of its parents not in the tree yet
+    pub fn insert(&mut self, account: &str, value: T) {
+        self.node_mut(account).value = Some(value);
+    }
+
+    /// The sum of the values of the account and all of its subaccounts, or None if none
+    /// of them has one. The values are added up with the given addition, which can fail,
+    /// like for amounts too large to hold
+    pub fn total<E>(&self, account: &str, add: impl Fn(&mut T, &T) -> Result<(), E>) -> Result<Option<T>, E>
+    where
+        T: Clone
+    {
+        let Some(mut values) = self.node(account).map(Node::values) else { return Ok(None) };
+        let Some(mut total)  = values.next().cloned() else { return Ok(None) };
+        for value in values {
+            add(&mut total, value)?;
+        }
+        Ok(Some(total))
+    }
+
+    /// Every account in the tree, depth first, each parent before its subaccounts
+    pub fn walk(&self) -> impl Iterator<Item = TreeEntry> {
+        let mut entries = vec![];
+        walk_children(&self.children, "", 0, &mut entries);
+        entries.into_iter()
+    }
+
+    fn node(&self, account: &str) -> Option<&Node<T>> {
+        let mut components = account.split(':');
+        let mut node = self.children.get(components.next()?)?;
+        for component in components {
+            node = node.children.get(component)?;
+        }
+        Some(node)
+    }
+
+    fn node_mut(&mut self, account: &str) -> &mut Node<T> {
+        let mut components = account.split(':');
+        let first = components.next().unwrap_or_default();
+        let mut node = self.children.entry(first.to_string()).or_insert_with(Node::empty);
+        for component in components {
+            node = node.children.entry(component.to_string()).or_insert_with(Node::empty);
+        }
+        node
+    }
+}
+
+impl<T> Node<T> {
+    fn empty() -> Self {
+        Node { value: None, children: BTreeMap::new() }
+    }
+
+    // the node's own value and those of every node under it
+    fn values(&self) -> Box<dyn Iterator<Item = &T> + '_> {
+        Box::new(self.value.iter().chain(self.children.values().flat_map(Node::values)))
+    }
+}
+
+fn walk_children<T>(children: &BTreeMap<String, Node<T>>,
+                    parent  : &str,
+                    depth   : usize,
+                    entries : &mut Vec<TreeEntry>)
+{
+    for (name, node) in children {
+        let account = if parent.is_empty() { name.clone() } else { format!("{}:{}", parent, name) };
+        entries.push(TreeEntry { account: Account::from(account.as_str()), depth });
+        walk_children(&node.children, &account, depth + 1, entries);
+    }
+}
+
+/// A tree of balances, or of anything else kept by account.
+impl<T> FromIterator<(Account, T)> for AccountTree<T> {
+    fn from_iter<I: IntoIterator<Item = (Account, T)>>(values: I) -> Self {
+        let mut tree = AccountTree::default();
+        for (account, value) in values {
+            tree.insert(&account, value);
+        }
+        tree
+    }
+}
+
+/// Each account's balance from all of the journal's transactions.
+impl From<&Journal> for AccountTree<MixedAmount> {
+    fn from(journal: &Journal) -> Self {
+        let mut tree = AccountTree::default();
+        for (account, entries) in entries_by_account(journal) {
+            let mut balance = MixedAmount::default();
+            for entry in entries {
+                balance.add_amount(&entry.amount);
+            }
+            tree.insert(account, balance);
+        }
+        tree
+    }
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use crate::journal::Journal;
+    use crate::periodgrid::checked;
+    use crate::types::{Account, mixedamount::MixedAmount};
+    use super::AccountTree;
+
+    #[test]
+    fn test_walk_and_totals() {
+        let mut tree: AccountTree<i32> = [(Account::from("expenses:food:subway"), 12),
+                                          (Account::from("assets:cash"), -20)].into_iter().collect();
+        tree.insert("expenses:food", 3);
+        tree.insert("expenses:food:subway", 17);
+
+        let walked: Vec<(String, usize, Option<i32>)> =
+            tree.walk().map(|entry| (entry.account.to_string(), entry.depth, tree.total(&entry.account, checked).unwrap())).collect();
+        assert_eq!(walked, vec![
+            ("assets".to_string(),               0, Some(-20)),
+            ("assets:cash".to_string(),          1, Some(-20)),
+            ("expenses".to_string(),             0, Some(20)),
+            ("expenses:food".to_string(),        1, Some(20)),
+            ("expenses:food:subway".to_string(), 2, Some(17)),
+        ]);
+
+        tree.insert("expenses:food", 0);
+        assert_eq!(tree.total("expenses", checked), Ok(Some(17)));
+        assert_eq!(tree.total("expenses:rent", checked), Ok(None));
+    }
+
+    #[test]
+    fn test_tree_from_journal() {
+        let journal = "2023/03/18 Groceries\n    expenses:food  $41.06\n    assets:savings\n\
+                       2023/03/19 Gas\n    expenses:auto  $30\n    assets:savings";
+        let tree = AccountTree::from(&Journal::from_lines(journal.lines()).unwrap());
+
+        assert_eq!(tree.total("assets:savings", MixedAmount::checked_add).unwrap().unwrap().to_string(), "$-71.06");
+        assert_eq!(tree.total("expenses", MixedAmount::checked_add).unwrap().unwrap().to_string(), "$71.06");
+        assert_eq!(tree.walk().count(), 5);
+    }
+}
@@ -0,0 +1,118 @@
+use std::io;
+use std::net::SocketAddr;
+
+use crate::color::{ColorChoice, set_color};
+use crate::http::{Request, Response, serve};
+use crate::journal::{Journal, watch::{WatchedJournal, current}};
+use crate::output::{Json, JsonValue, to_json};
+use crate::query::Query;
+use crate::reports::accounts::accounts_report;
+use crate::reports::balance::{BalanceOptions, balance_periodic_report};
+use crate::reports::print::{Printed, print_report};
+use crate::types::interval::Interval;
+
+
+/* JSON API */
+
+// `katana serve` answers read-only requests for the journal with JSON, so other tools and
+// dashboards can query it without shelling out. every endpoint takes a query with the same
+// terms as the command line
+//
+//   /accounts?query=expenses                   the names of the matching accounts
+//   /transactions?query=desc:sub               the transactions with a matching posting
+//   /balance?period=monthly&query=expenses     balance changes by period, like balance -O json
+//
+// errors come back as {"error": "..."} with a 400 or 404 status, or a 500 for a journal
+// whose amounts are too large to total up
+pub fn run(journal: &Journal, mut watched: Option<WatchedJournal>, address: SocketAddr) -> io::Result<()> {
+    // no terminal colour codes in the JSON
+    set_color(ColorChoice::Never);
+
+    eprintln!("Serving the journal's JSON API at http://{}/", address);
+    serve(address, |request| endpoint(current(&mut watched, journal), request))
+}
+
+fn endpoint(journal: &Journal, request: &Request) -> Response {
+    let query = match request.param("query").unwrap_or("").parse::<Query>() {
+        Ok(query)    => query,
+        Err(message) => return error(400, &format!("invalid query: {}", message)),
+    };
+
+    match request.path.as_str() {
+        "/accounts" => {
+            let accounts = accounts_report(journal, &query, false);
+            Response::ok("application/json", to_json(&accounts[..]))
+        },
+        "/transactions" => {
+            let transactions: Vec<Printed> = print_report(journal, &query).into_iter()
+                                                                          .map(|t| Printed(t, &journal.commodities))
+                                                                          .collect();
+            Response::ok("application/json", to_json(&transactions[..]))
+        },
+        "/balance" => {
+            let interval = match request.param("period").unwrap_or("monthly").parse::<Interval>() {
+                Ok(interval) => interval,
+                Err(period)  => return error(400, &format!("invalid period \"{}\", expected daily, weekly, monthly, quarterly or yearly", period)),
+            };
+            let options = BalanceOptions {
+                query,
+                ..Default::default()
+            };
+            match balance_periodic_report(journal, &options, interval) {
+                Ok(report)   => Response::ok("application/json", to_json(&report)),
+                Err(problem) => error(500, &problem.to_string()),
+            }
+        },
+        path => error(404, &format!("no endpoint {}, expected /accounts, /transactions or /balance", path)),
+    }
+}
+
+// {"error":"no endpoint /ledger, expected /accounts, /transactions or /balance"}
+fn error(status: u16, message: &str) -> Response {
+    let body = JsonValue::Object(vec![("error", message.to_json())]);
+    Response::error(status, "application/json", format!("{}\n", body))
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use crate::http::{Request, test_support::sample_journal};
+    use super::endpoint;
+
+    fn get(target: &str) -> (u16, String) {
+        let response = endpoint(&sample_journal(), &Request::from_target(target));
+        (response.status, response.body)
+    }
+
+    #[test]
+    fn test_api_accounts() {
+        assert_eq!(get("/accounts"), (200, r#"["assets:cash","assets:savings","expenses:food:subway","expenses:food:tim-hortons"]"#.to_string() + "\n"));
+        assert_eq!(get("/accounts?query=%5Eassets").1, r#"["assets:cash","assets:savings"]"#.to_string() + "\n");
+    }
+
+    #[test]
+    fn test_api_transactions() {
+        let (status, body) = get("/transactions?query=desc%3Asub");
+        assert_eq!(status, 200);
+        assert!(body.starts_with(r#"[{"date":"2023-04-09","description":"Sub","tags":[],"postings":[{"account":"expenses:food:subway""#));
+        assert!(!body.contains("Sandwich"));
+    }
+
+    #[test]
+    fn test_api_balance() {
+        let (status, body) = get("/balance?period=monthly&query=expenses");
+        assert_eq!(status, 200);
+        assert!(body.starts_with(r#"{"periods":["2023-03","2023-04"],"rows":["#));
+        assert!(!body.contains("assets"));
+    }
+
+    #[test]
+    fn test_api_errors() {
+        assert_eq!(get("/balance?period=hourly"),
+                   (400, r#"{"error":"invalid period \"hourly\", expected daily, weekly, monthly, quarterly or yearly"}"#.to_string() + "\n"));
+        assert_eq!(get("/ledger").0, 404);
+        assert_eq!(get("/accounts?query=amt%3A%3F").0, 400);
+    }
+}
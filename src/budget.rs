@@ -0,0 +1,224 @@
+use regex::Regex;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::journal::{Journal, JournalSummary};
+use crate::periodic::PeriodicTransaction;
+use crate::query::Query;
+use crate::reports::balance::{balance_changes, is_same_or_subaccount};
+use crate::types::commoditystyle::{Styled, WithStyles};
+use crate::types::{Account, amount::{Amount, AmountError}, interval::Interval, mixedamount::MixedAmount, monthyear::MonthYear};
+
+
+/* Budget */
+
+// the monthly target for each account, from the journal's monthly periodic transactions.
+// when some of them are marked as budgets, with budget for a description or a budget: tag,
+// only those are the budget, so the others can forecast recurring bills without being
+// targets as well
+//
+// ~ monthly  budget
+//     expenses:food        $400
+//     expenses:rent       $1000
+//     assets:checking
+//
+// every month has a $400 budget for expenses:food (and its subaccounts). an account can
+// have a target in more than one commodity, from budgets in each. the budget and
+// unbudgeted reports and check --budget-alerts all start from one of these
+pub struct Budget {
+    targets: HashMap<Account, MixedAmount>,
+}
+
+impl Budget {
+    // the targets for the accounts in the budget's postings that match the query
+    pub fn from_journal(journal: &Journal, query: &Query) -> Result<Budget, AmountError> {
+        let monthly: Vec<&PeriodicTransaction> = journal.periodic
+                                                        .iter()
+                                                        .filter(|p| p.interval == Interval::Monthly)
+                                                        .collect();
+
+        let marked = monthly.iter().any(|p| is_marked(p));
+        let mut targets: HashMap<Account, MixedAmount> = HashMap::new();
+
+        for entry in monthly.into_iter()
+                            .filter(|p| !marked || is_marked(p))
+                            .flat_map(|p| query.postings([&p.transaction]))
+        {
+            targets.entry(entry.account.clone())
+                   .or_default()
+                   .checked_add_amount(&entry.amount)?;
+        }
+
+        Ok(Budget { targets })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.targets.is_empty()
+    }
+
+    // each account with its target in each commodity, the accounts in no particular order
+    pub fn targets(&self) -> impl Iterator<Item = (&Account, &Amount)> {
+        self.targets
+            .iter()
+            .flat_map(|(account, targets)| targets.amounts().map(move |target| (account, target)))
+    }
+
+    // whether the account or one of its parents has a target
+    pub fn covers(&self, account: &Account) -> bool {
+        self.targets.keys().any(|budget| is_same_or_subaccount(account, budget))
+    }
+}
+
+fn is_marked(periodic: &PeriodicTransaction) -> bool {
+    periodic.transaction.description.eq_ignore_ascii_case("budget") || periodic.transaction.has_tag("budget", None)
+}
+
+// the spending in the account and its subaccounts in the commodity in each of the months,
+// None for a month without any
+pub fn spending(journal: &Journal,
+                account: &Account,
+                units  : &str,
+                query  : &Query,
+                months : &[MonthYear]) -> Result<Vec<Option<Amount>>, AmountError>
+{
+    let changes = balance_changes(journal, &within(account, query))?;
+    months.iter()
+          .map(|month| Ok(changes.column_total(*month, MixedAmount::checked_add)?.and_then(|total| total.get(units).cloned())))
+          .collect()
+}
+
+// the postings matching the query that are to the account or one of its subaccounts
+fn within(account: &Account, query: &Query) -> Query {
+    let pattern = Regex::new(&format!("^{}(:|$)", regex::escape(account))).unwrap();
+    Query::And(vec![query.clone(), Query::Acct(pattern)])
+}
+
+
+/* Overspending */
+
+// a month where the spending in an account went past its target
+#[derive(Debug, PartialEq)]
+pub struct Overspend {
+    pub account: Account,
+    pub month  : MonthYear,
+    pub budget : Amount,
+    pub actual : Amount,
+}
+
+// every month in the journal where an account with a target above zero spent more than
+// it in the target's commodity, by month and then account
+pub fn overspends(journal: &Journal,
+                  budget : &Budget,
+                  query  : &Query) -> Result<Vec<Overspend>, AmountError>
+{
+    let months = JournalSummary::from(journal).months();
+    let mut overspends = vec![];
+
+    for (account, target) in budget.targets().filter(|(_, target)| target.decimal().is_sign_positive() && !target.is_zero()) {
+        let actuals = spending(journal, account, &target.units, query, &months)?;
+
+        for (month, actual) in months.iter().zip(actuals) {
+            let Some(actual) = actual else { continue };
+            if actual.decimal() > target.decimal() {
+                overspends.push(Overspend { account: account.clone(), month: *month, budget: target.clone(), actual });
+            }
+        }
+    }
+
+    overspends.sort_by(|a, b| a.month.cmp(&b.month).then_with(|| a.account.cmp(&b.account)));
+    Ok(overspends)
+}
+
+// 2023-04 expenses:food: spent $412.50 of a $400.00 budget
+
+impl WithStyles for Overspend {}
+
+impl fmt::Display for Styled<'_, Overspend> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let overspend = self.value;
+        write!(f, "{} {}: spent {} of a {} budget", overspend.month, overspend.account, overspend.actual.styled(self.styles), overspend.budget.styled(self.styles))
+    }
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use crate::journal::Journal;
+    use crate::query::Query;
+    use crate::types::monthyear::MonthYear;
+    use crate::types::commoditystyle::{CommodityStyles, WithStyles};
+    use super::{Budget, overspends};
+
+    const JOURNAL: &str = r#"
+~ monthly  budget
+    expenses:food        $400
+    assets:checking
+
+~ monthly  rent
+    expenses:rent       $1000
+    assets:checking
+
+2023/03/17 HelloFresh
+    expenses:food:hello-fresh            $82.99
+    assets:checking
+
+2023/04/01 Rent
+    expenses:rent                      $1100
+    assets:checking
+
+2023/04/17 HelloFresh
+    expenses:food:hello-fresh           $412.50
+    assets:checking
+"#;
+
+    #[test]
+    fn test_marked_budget() {
+        let journal = Journal::from_lines(JOURNAL.lines()).unwrap();
+        let budget  = Budget::from_journal(&journal, &"expenses".parse().unwrap()).unwrap();
+
+        let targets: Vec<(String, String)> = budget.targets().map(|(account, amount)| (account.to_string(), amount.to_string())).collect();
+        assert_eq!(targets, vec![("expenses:food".to_string(), "$400.00".to_string())]);
+        assert!(budget.covers(&"expenses:food:hello-fresh".into()));
+        assert!(!budget.covers(&"expenses:rent".into()));
+
+        // without a marked one, every monthly periodic transaction is part of the budget
+        let unmarked = JOURNAL.replace("~ monthly  budget", "~ monthly  groceries");
+        let journal  = Journal::from_lines(unmarked.lines()).unwrap();
+        assert!(Budget::from_journal(&journal, &"expenses".parse().unwrap()).unwrap().covers(&"expenses:rent".into()));
+    }
+
+    #[test]
+    fn test_overspends() {
+        let journal = Journal::from_lines(JOURNAL.replace("budget", "groceries").lines()).unwrap();
+        let budget  = Budget::from_journal(&journal, &Query::default()).unwrap();
+        let found   = overspends(&journal, &budget, &Query::default()).unwrap();
+
+        let found: Vec<String> = found.iter().map(|overspend| overspend.styled(&journal.commodities).to_string()).collect();
+        assert_eq!(found, vec!["2023-04 expenses:food: spent $412.50 of a $400.00 budget",
+                               "2023-04 expenses:rent: spent $1100.00 of a $1000.00 budget"]);
+
+        let journal = Journal::from_lines(JOURNAL.lines()).unwrap();
+        let found   = overspends(&journal, &Budget::from_journal(&journal, &Query::default()).unwrap(), &Query::default()).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].month, MonthYear::new(4, 2023));
+    }
+
+    #[test]
+    fn test_budgets_in_two_commodities() {
+        let journal = JOURNAL.to_string() + "\n~ monthly  budget\n    expenses:food        300 EUR\n    assets:checking\n\n\
+                                             2023/04/20 Marché\n    expenses:food                       320 EUR\n    assets:checking\n";
+        let journal = Journal::from_lines(journal.lines()).unwrap();
+        let budget  = Budget::from_journal(&journal, &"expenses".parse().unwrap()).unwrap();
+
+        let mut targets: Vec<String> = budget.targets().map(|(account, amount)| format!("{} {}", account, amount)).collect();
+        targets.sort();
+        assert_eq!(targets, vec!["expenses:food $400.00", "expenses:food 300.000 EUR"]);
+
+        // each commodity's spending is held to its own target
+        let found: Vec<String> = overspends(&journal, &budget, &Query::default()).unwrap().iter().map(|overspend| overspend.styled(&CommodityStyles::default()).to_string()).collect();
+        assert_eq!(found.len(), 2);
+        assert!(found.contains(&"2023-04 expenses:food: spent 320.000 EUR of a 300.000 EUR budget".to_string()));
+    }
+}
@@ -0,0 +1,739 @@
+use std::net::IpAddr;
+
+use chrono::NaiveDate;
+use clap::{ArgAction, ArgGroup, Args, Parser, Subcommand};
+
+use crate::color::ColorChoice;
+use crate::common::today;
+use crate::error::ErrorFormat;
+use crate::output::OutputFormat;
+use crate::query::{Query, check_terms, parse_query};
+use crate::types::daterange::{DateRange, parse_date};
+use crate::types::interval::Interval;
+use crate::types::lineformat::LineFormat;
+use crate::types::monthyear::MonthYear;
+use crate::types::numberformat::NumberFormat;
+use crate::types::periodexpr::parse_period;
+use crate::types::sortkey::SortKey;
+
+
+/* Command line */
+
+// the options that go with every report, then the report to run and its own options.
+// each report's options are a struct here, so a new flag is a new field on its report
+// and shows up in that report's --help
+#[derive(Parser)]
+#[clap(name = "katana", about = "Simple textual reporting for double-entry accounting ledgers")]
+pub struct Cli {
+    #[clap(flatten)]
+    pub global : GlobalArgs,
+
+    #[clap(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Args)]
+pub struct GlobalArgs {
+    /// Set the journal file, or a glob like 'ledger/20??.journal' to merge several, otherwise read from $KATANA_FILE or $LEDGER_FILE
+    #[clap(short, long, value_name = "JOURNAL", global = true)]
+    pub journal      : Option<String>,
+
+    /// Only include transactions on or after this date
+    #[clap(short, long, value_name = "DATE", global = true, value_parser = date)]
+    pub begin        : Option<NaiveDate>,
+
+    /// Only include transactions before this date
+    #[clap(short, long, value_name = "DATE", global = true, value_parser = date)]
+    pub end          : Option<NaiveDate>,
+
+    /// Report on a period like 2023Q2, jan..mar or last month, optionally starting with an interval like monthly
+    #[clap(short, long, value_name = "PERIOD", global = true, value_parser = period)]
+    pub period       : Option<(DateRange, Option<Interval>)>,
+
+    /// Convert amounts to the commodity their market prices are quoted in
+    #[clap(short = 'V', long, global = true)]
+    pub market       : bool,
+
+    /// Convert amounts to this commodity using the market prices
+    #[clap(short = 'X', long, value_name = "COMMODITY", global = true, conflicts_with = "market")]
+    pub exchange     : Option<String>,
+
+    /// Use the prices at the end of the report or on each transaction's date (with -V or -X)
+    #[clap(long, value_name = "WHEN", global = true, possible_values = ["end", "transaction"], default_value = "end")]
+    pub value_at     : String,
+
+    /// Leave out virtual postings, the ones to accounts in parentheses or brackets
+    #[clap(short = 'R', long, global = true)]
+    pub real         : bool,
+
+    /// Add transactions generated by the periodic transactions after the last recorded one, through the end date or the end of next month, marked with a * (balance with an interval, register)
+    #[clap(long, global = true)]
+    pub forecast     : bool,
+
+    /// Write the report as plain text, CSV with a header row, JSON or an HTML table (balance, register), SQL (export) or SVG (chart) [possible values: text, csv, json, html, sql, svg]
+    #[clap(short = 'O', long, value_name = "FORMAT", global = true, value_parser)]
+    pub output_format: Option<OutputFormat>,
+
+    /// Write the report to this file, in the format its extension names (csv, json, html, sql, svg, txt) unless --output-format says otherwise
+    #[clap(short, long, value_name = "FILE", global = true)]
+    pub output_file  : Option<String>,
+
+    /// Write amounts with the digit grouping and decimal mark of a sample number, like 1,234.56 or 1.234,56
+    #[clap(long, value_name = "SAMPLE", global = true, value_parser)]
+    pub number_format: Option<NumberFormat>,
+
+    /// Colour negative amounts, account names and period headers [possible values: auto, always, never]
+    #[clap(long, value_name = "WHEN", global = true, value_parser, default_value = "auto")]
+    pub color        : ColorChoice,
+
+    /// Stop with an error at a transaction dated before the one above it in its journal file, instead of sorting the journal by date
+    #[clap(long, global = true)]
+    pub strict       : bool,
+
+    /// Write errors as plain text or as JSON objects with a code, message, file and line [possible values: text, json]
+    #[clap(long, value_name = "FORMAT", global = true, value_parser, default_value = "text")]
+    pub error_format : ErrorFormat,
+
+    /// Log what's happening to stderr, like how many transactions were parsed and matched and how long each phase took, in more detail with -vv or -vvv
+    #[clap(short, long, global = true, action = ArgAction::Count)]
+    pub verbose      : u8,
+
+    /// Finish by writing the time taken and the number of transactions left after reading, parsing, filtering, aggregating and rendering to stderr
+    #[clap(long, global = true)]
+    pub profile      : bool,
+}
+
+// the environment variables naming the journal when there's no -j, in order. hledger and
+// ledger users will have LEDGER_FILE set already. one that's set but empty is skipped
+const JOURNAL_VARIABLES: [&str; 2] = ["KATANA_FILE", "LEDGER_FILE"];
+
+impl GlobalArgs {
+    // the journal file from -j, or else from the environment
+    pub fn journal_file(&self) -> Option<String> {
+        journal_file(self.journal.as_deref(), |name| std::env::var(name).ok())
+    }
+}
+
+fn journal_file<F>(journal: Option<&str>, variable: F) -> Option<String>
+    where F: Fn(&str) -> Option<String>
+{
+    journal.map(str::to_string)
+           .or_else(|| JOURNAL_VARIABLES.iter().find_map(|name| variable(name).filter(|file| !file.is_empty())))
+}
+
+// the files a journal path names, which is just the path itself unless it's a glob like
+// ledger/20??.journal, for keeping one file per year. the matches are in name order
+pub fn journal_files(path: &str) -> Result<Vec<String>, String> {
+    if !path.contains(['*', '?', '[']) {
+        return Ok(vec![path.to_string()])
+    }
+
+    let mut files = vec![];
+    for entry in glob::glob(path).map_err(|error| format!("invalid glob {}: {}", path, error))? {
+        let file = entry.map_err(|error| error.to_string())?;
+        files.push(file.to_string_lossy().into_owned());
+    }
+
+    if files.is_empty() {
+        return Err(format!("no journal files match {}", path))
+    }
+    files.sort();
+    Ok(files)
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// List the accounts used in the journal
+    Accounts(AccountsArgs),
+    /// Chart the number of transactions in each period
+    Activity(ActivityArgs),
+    /// Show the balance changes of each account
+    Balance(BalanceArgs),
+    /// Show assets and liabilities as of the end of the journal
+    Balancesheet(BalanceSheetArgs),
+    /// Shade each day of each month by its spending
+    Calendar(CalendarArgs),
+    /// Draw periodic balances as an SVG chart
+    Chart(ChartArgs),
+    /// Read the journal and report every problem found in it
+    Check(CheckArgs),
+    /// Show what's been allocated to, spent from and is left in each envelope, or allocate the month's budget to them
+    Envelopes(EnvelopesArgs),
+    /// Write the journal as SQL tables
+    Export,
+    /// Project an account's balance forward from its scheduled and average spending, warning if it goes below zero
+    Forecast(ForecastArgs),
+    /// Write a made-up journal of realistic transactions, starting from -b or 2020/01/01
+    Gen(GenArgs),
+    /// Write out the transactions the periodic transactions would generate after the last recorded one, for keeping in the journal
+    Generate(GenerateArgs),
+    /// Show the progress toward each savings goal, what it needs each month and when it'll be reached at the recent rate
+    Goals,
+    /// List the open lots of commodities bought at a cost and the gains realized selling them, oldest lots first
+    Lots,
+    /// List the payees and how often each appears
+    Payees,
+    /// List the market prices
+    Prices(PricesArgs),
+    /// Print transactions back out in journal format
+    Print(PrintArgs),
+    /// Show the postings to an account with a running total
+    Register(RegisterArgs),
+    /// Develop the rules used to import CSV files
+    #[clap(subcommand)]
+    Rules(RulesCommand),
+    /// Answer read-only requests for the journal with JSON
+    Serve(ServerArgs),
+    /// Summarize the journal
+    Stats,
+    /// Total the postings by the values of a tag
+    Tags(TagsArgs),
+    /// Rank the accounts or payees with the most spending
+    Top(TopArgs),
+    /// Browse the accounts and their registers in the terminal
+    Ui,
+    /// Serve the balance and register reports as web pages
+    Web(ServerArgs),
+}
+
+#[derive(Subcommand)]
+pub enum RulesCommand {
+    /// Show which rule matches a sample CSV line or description and the resulting transaction
+    Test {
+        /// Set the rules file
+        #[clap(short, long, value_name = "RULES")]
+        rules : String,
+
+        /// A CSV line or description to run through the rules
+        sample: String,
+    },
+}
+
+
+/* Shared options */
+
+// a column or summary line for each of these periods
+#[derive(Args)]
+#[clap(group(ArgGroup::new("interval").args(&["daily", "weekly", "monthly", "quarterly", "yearly"])))]
+pub struct IntervalArgs {
+    /// Show a column for, or summarize, each day
+    #[clap(long)]
+    daily    : bool,
+
+    /// Show a column for, or summarize, each week
+    #[clap(long)]
+    weekly   : bool,
+
+    /// Show a column for, or summarize, each month
+    #[clap(long)]
+    monthly  : bool,
+
+    /// Show a column for, or summarize, each quarter
+    #[clap(long)]
+    quarterly: bool,
+
+    /// Show a column for, or summarize, each year
+    #[clap(long)]
+    yearly   : bool,
+}
+
+impl IntervalArgs {
+    pub fn interval(&self) -> Option<Interval> {
+        [(self.daily,     Interval::Daily),
+         (self.weekly,    Interval::Weekly),
+         (self.monthly,   Interval::Monthly),
+         (self.quarterly, Interval::Quarterly),
+         (self.yearly,    Interval::Yearly)]
+            .into_iter()
+            .find_map(|(chosen, interval)| chosen.then_some(interval))
+    }
+}
+
+// positional terms narrowing down the postings, like expenses:food desc:sub amt:>10, in
+// the query language described with Query
+#[derive(Args)]
+pub struct QueryArgs {
+    /// Account patterns and terms like desc:sub, tag:trip=italy, date:2023/03 or amt:>10, combined with and, or and not
+    #[clap(value_name = "QUERY", value_parser = query_term)]
+    terms: Vec<String>,
+}
+
+impl QueryArgs {
+    // the query from the terms, after the ones from the report's own options
+    pub fn query(&self, options: &[String]) -> Result<Query, String> {
+        let terms: Vec<&str> = options.iter()
+                                      .chain(&self.terms)
+                                      .map(String::as_str)
+                                      .collect();
+        parse_query(&terms, today())
+    }
+}
+
+// the -a patterns as account terms for the query
+pub fn account_terms(accounts: &[String]) -> Vec<String> {
+    accounts.iter()
+            .map(|pattern| match pattern.strip_prefix("not:") {
+                Some(pattern) => format!("not:acct:{}", pattern),
+                None          => format!("acct:{}", pattern),
+            })
+            .collect()
+}
+
+
+/* Reports */
+
+#[derive(Args)]
+pub struct AccountsArgs {
+    #[clap(flatten)]
+    pub query  : QueryArgs,
+
+    /// Only list accounts matching this pattern, repeatable, with not: to exclude
+    #[clap(short, long, value_name = "ACCOUNT")]
+    pub account: Vec<String>,
+
+    /// List accounts as a tree
+    #[clap(long)]
+    pub tree   : bool,
+}
+
+#[derive(Args)]
+pub struct ActivityArgs {
+    #[clap(flatten)]
+    pub interval: IntervalArgs,
+}
+
+#[derive(Args)]
+pub struct BalanceArgs {
+    #[clap(flatten)]
+    pub query     : QueryArgs,
+
+    /// Only show accounts matching this pattern, like food for expenses:food:subway, repeatable, with not: to exclude
+    #[clap(short, long, value_name = "ACCOUNT")]
+    pub account   : Vec<String>,
+
+    /// List full account names instead of a tree
+    #[clap(long)]
+    pub flat      : bool,
+
+    /// Fold accounts deeper than this into their parent
+    #[clap(long, value_name = "DEPTH", value_parser = positive)]
+    pub depth     : Option<usize>,
+
+    /// Show the changes in this month, like 2023-04, instead of the journal's latest month, or every month of the period with -p, -b or -e (without an interval)
+    #[clap(long, value_name = "MONTH", value_parser = month)]
+    pub month     : Option<MonthYear>,
+
+    /// Include everything before the begin date in the balances (with an interval)
+    #[clap(long)]
+    pub historical: bool,
+
+    /// Flip the signs of amounts so income shows as positive
+    #[clap(long)]
+    pub invert    : bool,
+
+    /// Add a column with each account's total over all periods (with an interval)
+    #[clap(long)]
+    pub row_total : bool,
+
+    /// Add a column with each account's average per period (with an interval)
+    #[clap(long)]
+    pub average   : bool,
+
+    /// End each row with a sparkline of its amounts over the periods (with an interval)
+    #[clap(long)]
+    pub sparkline : bool,
+
+    /// Show amounts as a percentage of the parent account, or of the column total for top-level accounts and --flat
+    #[clap(short = '%', long)]
+    pub percent   : bool,
+
+    /// Show accounts with no activity, and 0 instead of a blank for periods without any
+    #[clap(short = 'E', long)]
+    pub empty     : bool,
+
+    /// Compare actual amounts to the monthly periodic transactions
+    #[clap(long)]
+    pub budget    : bool,
+
+    /// List the spending in accounts with no monthly periodic transaction, under expenses or matching the query
+    #[clap(long, conflicts_with = "budget")]
+    pub unbudgeted: bool,
+
+    #[clap(flatten)]
+    pub interval  : IntervalArgs,
+}
+
+#[derive(Args)]
+pub struct BalanceSheetArgs {
+    /// List full account names instead of a tree
+    #[clap(long)]
+    pub flat : bool,
+
+    /// Fold accounts deeper than this into their parent
+    #[clap(long, value_name = "DEPTH", value_parser = positive)]
+    pub depth: Option<usize>,
+}
+
+#[derive(Args)]
+pub struct CalendarArgs {
+    #[clap(flatten)]
+    pub query    : QueryArgs,
+
+    /// Total the postings to accounts matching this pattern, repeatable, with not: to exclude
+    #[clap(short, long, value_name = "ACCOUNT")]
+    pub account  : Vec<String>,
+
+    /// Total amounts in this commodity
+    #[clap(long, value_name = "COMMODITY", default_value = "$")]
+    pub commodity: String,
+}
+
+#[derive(Args)]
+pub struct ChartArgs {
+    #[clap(flatten)]
+    pub query     : QueryArgs,
+
+    /// Only chart accounts matching this pattern, repeatable, with not: to exclude
+    #[clap(short, long, value_name = "ACCOUNT")]
+    pub account   : Vec<String>,
+
+    /// Fold accounts deeper than this into their parent
+    #[clap(long, value_name = "DEPTH", value_parser = positive)]
+    pub depth     : Option<usize>,
+
+    /// Chart running balances as lines instead of each period's changes as bars
+    #[clap(long)]
+    pub historical: bool,
+
+    /// Flip the signs of amounts so income shows as positive
+    #[clap(long)]
+    pub invert    : bool,
+
+    #[clap(flatten)]
+    pub interval  : IntervalArgs,
+}
+
+#[derive(Args)]
+pub struct PricesArgs {
+    /// Only show prices for this commodity
+    #[clap(long, value_name = "COMMODITY")]
+    pub commodity: Option<String>,
+
+    /// Show each price from the other side of the pair
+    #[clap(long)]
+    pub invert   : bool,
+
+    /// Interpolate one price per month
+    #[clap(long)]
+    pub monthly  : bool,
+}
+
+#[derive(Args)]
+pub struct PrintArgs {
+    #[clap(flatten)]
+    pub query  : QueryArgs,
+
+    /// Only include transactions with an account matching this pattern, repeatable, with not: to exclude
+    #[clap(short, long, value_name = "ACCOUNT")]
+    pub account: Vec<String>,
+
+    /// Only include transactions with a description matching this pattern
+    #[clap(long, value_name = "PAYEE")]
+    pub payee  : Option<String>,
+
+    /// Only include transactions with this tag, optionally with this value, like trip=japan
+    #[clap(long, value_name = "TAG")]
+    pub tag    : Option<String>,
+}
+
+impl PrintArgs {
+    // the -a patterns, --payee and --tag as terms for the query
+    pub fn terms(&self) -> Vec<String> {
+        let mut terms = account_terms(&self.account);
+        terms.extend(self.payee.iter().map(|payee| format!("desc:{}", payee)));
+        terms.extend(self.tag.iter().map(|tag| format!("tag:{}", tag)));
+        terms
+    }
+}
+
+#[derive(Args)]
+pub struct RegisterArgs {
+    /// Show the postings of accounts matching this pattern, repeatable, with not: to exclude
+    #[clap(short, long, value_name = "ACCOUNT", required_unless_present = "terms")]
+    pub account       : Vec<String>,
+
+    #[clap(flatten)]
+    pub query         : QueryArgs,
+
+    /// Include everything before the begin date in the running totals
+    #[clap(long)]
+    pub historical    : bool,
+
+    /// Show the other postings in the account's transactions
+    #[clap(long)]
+    pub related       : bool,
+
+    /// Flip the signs of amounts so income shows as positive
+    #[clap(long)]
+    pub invert        : bool,
+
+    /// Lay out lines with a template like "%(date) %-30(payee) %10(amount) %10(total)"
+    #[clap(long, value_name = "TEMPLATE", value_parser)]
+    pub format        : Option<LineFormat>,
+
+    /// Add a column with the average of each period's total and the N-1 before it (with an interval)
+    #[clap(long, value_name = "N", value_parser = positive)]
+    pub moving_average: Option<usize>,
+
+    /// Order the lines by date, amount, account or description, with a - like -amount for descending (without an interval)
+    #[clap(long, value_name = "FIELD", value_parser, allow_hyphen_values = true)]
+    pub sort          : Option<SortKey>,
+
+    /// Finish with the number of postings and their total and average
+    #[clap(long)]
+    pub summary       : bool,
+
+    #[clap(flatten)]
+    pub interval      : IntervalArgs,
+}
+
+#[derive(Args)]
+pub struct TagsArgs {
+    /// The tag to total by the values of, like trip
+    #[clap(long, value_name = "TAG", required = true)]
+    pub tag    : String,
+
+    #[clap(flatten)]
+    pub query  : QueryArgs,
+
+    /// Total the postings to accounts matching this pattern, repeatable, with not: to exclude
+    #[clap(short, long, value_name = "ACCOUNT")]
+    pub account: Vec<String>,
+}
+
+#[derive(Args)]
+pub struct TopArgs {
+    #[clap(flatten)]
+    pub query  : QueryArgs,
+
+    /// Rank the accounts matching this pattern, repeatable, with not: to exclude
+    #[clap(short, long, value_name = "ACCOUNT")]
+    pub account: Vec<String>,
+
+    /// Rank payees instead of accounts
+    #[clap(long)]
+    pub payees : bool,
+
+    /// Show this many of the largest
+    #[clap(short = 'n', long, value_name = "LIMIT", default_value = "10")]
+    pub limit  : usize,
+}
+
+#[derive(Args)]
+pub struct EnvelopesArgs {
+    #[clap(subcommand)]
+    pub command: Option<EnvelopesCommand>,
+}
+
+#[derive(Subcommand)]
+pub enum EnvelopesCommand {
+    /// Write a transaction putting each budgeted expense's monthly amount in its envelope, for adding to the journal
+    Allocate {
+        /// Allocate this month, like 2023-04, instead of the current one
+        #[clap(long, value_name = "MONTH", value_parser = month)]
+        month: Option<MonthYear>,
+    },
+}
+
+#[derive(Args)]
+pub struct CheckArgs {
+    /// Also report each month an account spent more than its monthly budget
+    #[clap(long)]
+    pub budget_alerts: bool,
+}
+
+#[derive(Args)]
+pub struct ForecastArgs {
+    #[clap(flatten)]
+    pub query   : QueryArgs,
+
+    /// Project the balance of accounts matching this pattern, repeatable, with not: to exclude
+    #[clap(short, long, value_name = "ACCOUNT")]
+    pub account : Vec<String>,
+
+    #[clap(flatten)]
+    pub interval: IntervalArgs,
+
+    /// Project this many months past the last transaction
+    #[clap(long, value_name = "MONTHS", default_value = "6", value_parser = positive)]
+    pub months  : usize,
+}
+
+#[derive(Args)]
+pub struct GenArgs {
+    /// Write this many transactions
+    #[clap(short = 'n', long, value_name = "COUNT", default_value = "1000")]
+    pub transactions: usize,
+
+    /// Make up a different journal for each seed, the same one every time for a seed
+    #[clap(long, value_name = "SEED", default_value = "1")]
+    pub seed        : u64,
+}
+
+#[derive(Args)]
+pub struct GenerateArgs {
+    /// Generate transactions through the end of this period, like 2024/12 or 2024
+    #[clap(long, value_name = "PERIOD", value_parser = through)]
+    pub until : NaiveDate,
+
+    /// Add them to the end of the journal file, or the last of the files a glob names, instead of writing them out
+    #[clap(long)]
+    pub append: bool,
+}
+
+#[derive(Args)]
+pub struct ServerArgs {
+    /// Listen on this address, or 0.0.0.0 for every network interface
+    #[clap(long, value_name = "ADDRESS", default_value = "127.0.0.1")]
+    pub bind : IpAddr,
+
+    /// Listen on this port
+    #[clap(long, value_name = "PORT", default_value = "8080")]
+    pub port : u16,
+
+    /// Reread the journal when its files change, parsing only the transactions that did
+    #[clap(long)]
+    pub watch: bool,
+}
+
+
+/* Parsing */
+
+fn date(date: &str) -> Result<NaiveDate, String> {
+    parse_date(date).ok_or_else(|| "expected a date like 2023/03/15, 2023/03 or 2023".to_string())
+}
+
+fn month(month: &str) -> Result<MonthYear, String> {
+    month.parse().map_err(|_| "expected a month like 2023-04 or 2023/04".to_string())
+}
+
+fn period(expr: &str) -> Result<(DateRange, Option<Interval>), String> {
+    parse_period(expr, today())
+        .map_err(|expr| format!("couldn't understand the period \"{}\"", expr))
+}
+
+// the day after a period ends, so --until 2024/12 takes in all of December
+fn through(expr: &str) -> Result<NaiveDate, String> {
+    period(expr)?.0
+                 .end
+                 .ok_or_else(|| format!("the period \"{}\" has no end", expr))
+}
+
+fn positive(n: &str) -> Result<usize, String> {
+    match n.parse() {
+        Ok(n) if n > 0 => Ok(n),
+        _              => Err("must be a number greater than zero".to_string()),
+    }
+}
+
+fn query_term(term: &str) -> Result<String, String> {
+    check_terms(term, today()).map(|_| term.to_string())
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use clap::{CommandFactory, Parser};
+    use crate::types::interval::Interval;
+    use super::{Cli, Command, account_terms, journal_file, journal_files};
+
+    #[test]
+    fn test_cli() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_cli_parse() {
+        let cli = Cli::try_parse_from(["katana", "-j", "journal.txt", "register", "-a", "assets", "amt:>100", "--monthly"]).unwrap();
+        assert_eq!(cli.global.journal.as_deref(), Some("journal.txt"));
+
+        let Command::Register(args) = cli.command else { panic!("expected the register report") };
+        assert_eq!(args.account, vec!["assets"]);
+        assert_eq!(args.interval.interval(), Some(Interval::Monthly));
+        assert_eq!(args.query.query(&account_terms(&args.account)).unwrap().to_string(), "assets amt:>100");
+
+        // -a can be given more than once, and goes along with the query
+        let cli = Cli::try_parse_from(["katana", "top", "-a", "expenses", "-a", "not:expenses:tips", "(desc:sub", "or", "tim)"]).unwrap();
+        let Command::Top(args) = cli.command else { panic!("expected the top report") };
+        assert_eq!(args.query.query(&account_terms(&args.account)).unwrap().to_string(), "expenses not:expenses:tips (desc:sub or tim)");
+
+        let cli = Cli::try_parse_from(["katana", "print", "--payee", "tim", "--tag", "trip=italy", "food"]).unwrap();
+        let Command::Print(args) = cli.command else { panic!("expected the print report") };
+        assert_eq!(args.query.query(&args.terms()).unwrap().to_string(), "food desc:tim tag:trip=italy");
+
+        // a register needs either an account or a query
+        assert!(Cli::try_parse_from(["katana", "register", "desc:sub"]).is_ok());
+        assert!(Cli::try_parse_from(["katana", "balance", "desc:(sub"]).is_err());
+
+        // the global options can come after the report too
+        let cli = Cli::try_parse_from(["katana", "balance", "--flat", "-j", "journal.txt"]).unwrap();
+        assert_eq!(cli.global.journal.as_deref(), Some("journal.txt"));
+
+        // each -v logs in more detail, wherever it's given
+        assert_eq!(Cli::try_parse_from(["katana", "balance"]).unwrap().global.verbose, 0);
+        assert_eq!(Cli::try_parse_from(["katana", "-vv", "balance"]).unwrap().global.verbose, 2);
+        assert_eq!(Cli::try_parse_from(["katana", "balance", "-vvv"]).unwrap().global.verbose, 3);
+
+        assert!(Cli::try_parse_from(["katana", "balance", "--weekly", "--monthly"]).is_err());
+        assert!(Cli::try_parse_from(["katana", "top", "amt:lots"]).is_err());
+        assert!(Cli::try_parse_from(["katana", "balance", "--depth", "0"]).is_err());
+        assert!(Cli::try_parse_from(["katana", "register"]).is_err());
+
+        // the servers only listen on this machine unless bound to another address
+        let cli = Cli::try_parse_from(["katana", "web"]).unwrap();
+        let Command::Web(args) = cli.command else { panic!("expected the web server") };
+        assert_eq!(args.bind.to_string(), "127.0.0.1");
+
+        let cli = Cli::try_parse_from(["katana", "serve", "--bind", "0.0.0.0", "--port", "8000"]).unwrap();
+        let Command::Serve(args) = cli.command else { panic!("expected the JSON API server") };
+        assert_eq!((args.bind.to_string(), args.port), ("0.0.0.0".to_string(), 8000));
+        assert!(Cli::try_parse_from(["katana", "serve", "--bind", "localhost:80"]).is_err());
+    }
+
+    #[test]
+    fn test_journal_file() {
+        let both    = |name: &str| Some(format!("{}.journal", name));
+        let ledger  = |name: &str| (name == "LEDGER_FILE").then(|| "ledger.journal".to_string());
+        let neither = |_: &str| None;
+
+        assert_eq!(journal_file(Some("main.journal"), both), Some("main.journal".to_string()));
+        assert_eq!(journal_file(None, both), Some("KATANA_FILE.journal".to_string()));
+        assert_eq!(journal_file(None, ledger), Some("ledger.journal".to_string()));
+        assert_eq!(journal_file(None, neither), None);
+
+        // an empty KATANA_FILE doesn't hide LEDGER_FILE
+        let empty = |name: &str| Some(if name == "KATANA_FILE" { String::new() } else { "ledger.journal".to_string() });
+        assert_eq!(journal_file(None, empty), Some("ledger.journal".to_string()));
+        assert_eq!(journal_file(None, |_: &str| Some(String::new())), None);
+    }
+
+    #[test]
+    fn test_journal_files() {
+        let dir = std::env::temp_dir().join(format!("katana-journal-files-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in ["2023.journal", "2022.journal", "prices.journal"] {
+            std::fs::write(dir.join(name), "").unwrap();
+        }
+        let dir = dir.to_string_lossy().into_owned();
+
+        assert_eq!(journal_files(&format!("{}/20??.journal", dir)),
+                   Ok(vec![format!("{}/2022.journal", dir), format!("{}/2023.journal", dir)]));
+        assert_eq!(journal_files("main.journal"), Ok(vec!["main.journal".to_string()]));
+        assert!(journal_files(&format!("{}/19*.journal", dir)).is_err());
+        assert!(journal_files(&format!("{}/[.journal", dir)).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
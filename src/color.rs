@@ -0,0 +1,92 @@
+use std::io::IsTerminal;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+
+/* Terminal colours */
+
+// whether to write ANSI colour codes, decided once at startup from --color. reports use
+// Display to render themselves, which has nowhere to pass a setting like this through
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColorChoice {
+    Auto,   // only when writing to a terminal, and NO_COLOR isn't set
+    Always,
+    Never,
+}
+
+impl FromStr for ColorChoice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto"   => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never"  => Ok(ColorChoice::Never),
+            _        => Err(s.to_string()),
+        }
+    }
+}
+
+pub fn set_color(choice: ColorChoice) {
+    let enabled = match choice {
+        ColorChoice::Auto   => std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none(),
+        ColorChoice::Always => true,
+        ColorChoice::Never  => false,
+    };
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Style {
+    Plain,
+    Negative, // red, for amounts below zero
+    Dim,      // for account names
+    Bold,     // for period headers
+}
+
+impl Style {
+    // negative amounts are red
+    pub fn amount(negative: bool) -> Style {
+        if negative { Style::Negative } else { Style::Plain }
+    }
+}
+
+// the text wrapped in the style's colour codes, if colour is on. this goes around text
+// that's already been padded, since the codes would otherwise count towards the width
+pub fn paint(text: String, style: Style) -> String {
+    paint_if(ENABLED.load(Ordering::Relaxed), text, style)
+}
+
+fn paint_if(enabled: bool, text: String, style: Style) -> String {
+    let code = match style {
+        Style::Plain    => return text,
+        Style::Negative => "31",
+        Style::Dim      => "2",
+        Style::Bold     => "1",
+    };
+
+    if enabled {
+        format!("\x1b[{}m{}\x1b[0m", code, text)
+    } else {
+        text
+    }
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use super::{Style, paint_if};
+
+    #[test]
+    fn test_paint() {
+        assert_eq!(paint_if(true, "   $-5.00".to_string(), Style::Negative), "\x1b[31m   $-5.00\x1b[0m");
+        assert_eq!(paint_if(true, "assets".to_string(), Style::Dim), "\x1b[2massets\x1b[0m");
+        assert_eq!(paint_if(true, "2023-03".to_string(), Style::Bold), "\x1b[1m2023-03\x1b[0m");
+        assert_eq!(paint_if(true, "$5.00".to_string(), Style::Plain), "$5.00");
+        assert_eq!(paint_if(false, "$-5.00".to_string(), Style::Negative), "$-5.00");
+    }
+}
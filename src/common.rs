@@ -1,4 +1,39 @@
+use chrono::NaiveDate;
+use regex::{Regex, RegexBuilder};
+use unicode_width::UnicodeWidthStr;
 
 pub fn is_all_whitespace(s: &str) -> bool {
     s.chars().all(|c| c.is_whitespace())
 }
+
+// a pattern from the command line, which matches regardless of case
+pub fn case_insensitive(pattern: &str) -> Result<Regex, regex::Error> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(true)
+        .build()
+}
+
+// the number of columns the text takes up in a terminal, where CJK characters and most
+// emoji are two wide. Rust's own padding counts characters, which misaligns those
+pub fn display_width(s: &str) -> usize {
+    s.width()
+}
+
+// the text with spaces after it to fill the width, like {:<width$}
+pub fn pad_right(s: &str, width: usize) -> String {
+    format!("{}{}", s, " ".repeat(width.saturating_sub(display_width(s))))
+}
+
+// the text with spaces before it to fill the width, like {:>width$}
+pub fn pad_left(s: &str, width: usize) -> String {
+    format!("{}{}", " ".repeat(width.saturating_sub(display_width(s))), s)
+}
+
+pub fn today() -> NaiveDate {
+    chrono::Local::now().date_naive()
+}
+
+// a period's label, marked with a * if the period includes forecast transactions
+pub fn mark_forecast(label: String, forecast: bool) -> String {
+    if forecast { label + "*" } else { label }
+}
@@ -0,0 +1,286 @@
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use crate::journal::{ParseProblem, types::LineParseError};
+use crate::lots::LotError;
+use crate::output::{Json, JsonValue};
+use crate::rules::RulesError;
+use crate::transaction::builder::BuildError;
+use crate::types::amount::AmountError;
+
+
+/* Errors */
+
+// how errors are written to stderr, chosen with --error-format. JSON gives scripts one
+// object per error, like
+//
+//   {"code":"parse_error","message":"Couldn't process this line: 'x'","file":"journal.txt","line":12}
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ErrorFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+// what kind of error stopped katana, which decides its exit status so scripts can tell
+// them apart without reading the message
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ErrorKind {
+    Io,         // a file couldn't be read or written
+    Usage,      // a bad option or argument, which is also what clap exits with
+    Parse,      // the journal or rules file isn't in a form that can be read
+    Validation, // the journal was read but doesn't add up, like an unbalanced transaction
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Error {
+    pub kind   : ErrorKind,
+    pub message: String,
+    pub file   : Option<String>,
+    pub line   : Option<usize>, // 1-based, in the file
+    pub cause  : Option<Cause>, // the error from katana's own types, given as its source
+}
+
+// the errors katana's parsers and builders give, all in one type so code reading a
+// journal or rules file can use ? on any of them
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum Cause {
+    #[error(transparent)]
+    Journal(#[from] ParseProblem),
+    #[error(transparent)]
+    Line(#[from] LineParseError),
+    #[error(transparent)]
+    Amount(#[from] AmountError),
+    #[error(transparent)]
+    Rules(#[from] RulesError),
+    #[error(transparent)]
+    Build(#[from] BuildError),
+    #[error(transparent)]
+    Lots(#[from] LotError),
+}
+
+impl ErrorKind {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ErrorKind::Io         => 1,
+            ErrorKind::Usage      => 2,
+            ErrorKind::Parse      => 3,
+            ErrorKind::Validation => 4,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            ErrorKind::Io         => "io_error",
+            ErrorKind::Usage      => "usage_error",
+            ErrorKind::Parse      => "parse_error",
+            ErrorKind::Validation => "validation_failure",
+        }
+    }
+}
+
+impl Cause {
+    // journal lines that can be read but don't add up are validation failures, like
+    // amounts that can't be added or transactions that don't balance
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Cause::Journal(problem) if problem.error.is_validation() => ErrorKind::Validation,
+            Cause::Journal(_) | Cause::Line(_) | Cause::Rules(_)     => ErrorKind::Parse,
+            Cause::Amount(_)  | Cause::Build(_) | Cause::Lots(_)     => ErrorKind::Validation,
+        }
+    }
+}
+
+impl Error {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Error {
+        Error { kind, message: message.into(), file: None, line: None, cause: None }
+    }
+
+    pub fn usage(message: impl Into<String>) -> Error {
+        Error::new(ErrorKind::Usage, message)
+    }
+
+    pub fn in_file(self, file: &str) -> Error {
+        Error { file: Some(file.to_string()), ..self }
+    }
+
+    // a problem found reading the journal, which is a validation failure if the lines
+    // could be read but the transaction they make doesn't balance
+    pub fn from_problem(file: &str, problem: &ParseProblem) -> Error {
+        Error::from(problem.clone()).in_file(file)
+    }
+}
+
+// a ParseProblem's line number goes in the error's line, not its message, so it's
+// written after the file the way every other error's line is
+
+impl<E: Into<Cause>> From<E> for Error {
+    fn from(cause: E) -> Error {
+        let cause   = cause.into();
+        let (message, line) = match &cause {
+            Cause::Journal(problem) => (problem.error.to_string(), Some(problem.line)),
+            cause                   => (cause.to_string(), None),
+        };
+
+        Error { kind: cause.kind(), message, file: None, line, cause: Some(cause) }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.cause.as_ref().map(|cause| cause as &(dyn std::error::Error + 'static))
+    }
+}
+
+// journal.txt:12: Couldn't process this line: 'x'
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.file, self.line) {
+            (Some(file), Some(line)) => write!(f, "{}:{}: {}", file, line, self.message),
+            (Some(file), None)       => write!(f, "{}: {}", file, self.message),
+            _                        => write!(f, "{}", self.message),
+        }
+    }
+}
+
+// {"code":"usage_error","message":"Invalid query: ...","file":null,"line":null}
+
+impl Json for Error {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Object(vec![
+            ("code",    self.kind.code().to_json()),
+            ("message", self.message.to_json()),
+            ("file",    self.file.to_json()),
+            ("line",    self.line.map_or(JsonValue::Null, |line| JsonValue::Number(line.to_string()))),
+        ])
+    }
+}
+
+impl FromStr for ErrorFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(ErrorFormat::Text),
+            "json" => Ok(ErrorFormat::Json),
+            _      => Err(s.to_string()),
+        }
+    }
+}
+
+// chosen once at startup, so errors found anywhere can be written the same way
+static ERROR_FORMAT: OnceLock<ErrorFormat> = OnceLock::new();
+
+pub fn set_error_format(format: ErrorFormat) {
+    ERROR_FORMAT.set(format).expect("The error format is already set");
+}
+
+// the --error-format straight from the arguments, for the usage errors clap finds before
+// the options are parsed
+pub fn error_format_in<I>(args: I) -> ErrorFormat
+    where I: IntoIterator<Item = String>
+{
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let value = match arg.strip_prefix("--error-format") {
+            Some("")    => args.next(),
+            Some(value) => value.strip_prefix('=').map(str::to_string),
+            None        => continue,
+        };
+        if let Some(format) = value.and_then(|value| value.parse().ok()) {
+            return format
+        }
+    }
+    ErrorFormat::Text
+}
+
+// write the error to stderr and exit with its kind's status
+pub fn exit_with(error: Error) -> ! {
+    report(&error);
+    std::process::exit(error.kind.exit_code())
+}
+
+// write the error to stderr in the chosen format
+pub fn report(error: &Error) {
+    match ERROR_FORMAT.get().copied().unwrap_or_default() {
+        ErrorFormat::Text => eprintln!("katana: {}", error),
+        ErrorFormat::Json => eprintln!("{}", error.to_json()),
+    }
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error as _;
+    use crate::journal::{ParseJournalError, ParseProblem};
+    use crate::lots::LotError;
+    use crate::output::Json;
+    use crate::types::amount::AmountError;
+    use crate::rules::RulesError;
+    use crate::transaction::{Transaction, builder::BuildError};
+    use super::{Cause, Error, ErrorFormat, ErrorKind, error_format_in};
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_error_format_in() {
+        assert_eq!(error_format_in(args("katana --error-format json balance")), ErrorFormat::Json);
+        assert_eq!(error_format_in(args("katana balance --error-format=json")), ErrorFormat::Json);
+        assert_eq!(error_format_in(args("katana --error-format text")), ErrorFormat::Text);
+        assert_eq!(error_format_in(args("katana balance")), ErrorFormat::Text);
+        assert_eq!(error_format_in(args("katana --error-format")), ErrorFormat::Text);
+    }
+
+    #[test]
+    fn test_error_from_problem() {
+        let problem = ParseProblem { line: 12, error: ParseJournalError::UnparseableLine("x".to_string()) };
+        let error   = Error::from_problem("journal.txt", &problem);
+        assert_eq!(error.kind, ErrorKind::Parse);
+        assert_eq!(error.to_string(), "journal.txt:12: Couldn't process this line: 'x'");
+        assert_eq!(error.to_json().to_string(),
+                   r#"{"code":"parse_error","message":"Couldn't process this line: 'x'","file":"journal.txt","line":12}"#);
+
+        let problem = ParseProblem { line: 3, error: ParseJournalError::TwoBlankAmounts };
+        assert_eq!(Error::from_problem("journal.txt", &problem).kind, ErrorKind::Validation);
+    }
+
+    #[test]
+    fn test_error_json() {
+        let error = Error::usage("Invalid query: x");
+        assert_eq!(error.to_json().to_string(),
+                   r#"{"code":"usage_error","message":"Invalid query: x","file":null,"line":null}"#);
+        assert_eq!(error.kind.exit_code(), 2);
+        assert_eq!(Error::new(ErrorKind::Io, "gone").in_file("a.txt").to_string(), "a.txt: gone");
+    }
+
+    #[test]
+    fn test_error_sources() {
+        let problem = ParseProblem { line: 12, error: ParseJournalError::UnparseableLine("x".to_string()) };
+        let error   = Error::from_problem("journal.txt", &problem);
+        let source  = error.source().unwrap();
+        assert_eq!(source.to_string(), "line 12: Couldn't process this line: 'x'");
+        assert_eq!(source.source().unwrap().to_string(), "Couldn't process this line: 'x'");
+
+        let error = Error::from(RulesError::BadDate("15-03-2023".to_string()));
+        assert_eq!(error.kind, ErrorKind::Parse);
+        assert_eq!(error.message, "Couldn't parse the date '15-03-2023'");
+
+        fn build() -> Result<Transaction, Error> {
+            Ok(Transaction::builder().build()?)
+        }
+        let error = build().unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Validation);
+        assert_eq!(error.cause, Some(Cause::Build(BuildError::MissingDate)));
+        assert!(Error::usage("Invalid query: x").source().is_none());
+
+        let error = Error::from(LotError::Amount(AmountError::Overflow));
+        assert_eq!(error.kind, ErrorKind::Validation);
+        assert_eq!(error.cause, Some(Cause::Lots(LotError::Amount(AmountError::Overflow))));
+    }
+}
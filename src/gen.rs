@@ -0,0 +1,184 @@
+use chrono::{Datelike, Duration, NaiveDate};
+use std::io::{self, Write};
+
+
+/* Journal generator */
+
+// `katana gen` writes a made-up journal that looks like years of real spending: rent and
+// payroll on schedule, a few purchases most days from a list of payees, a restaurant bill
+// split with a tip now and then, and monthly exchange rates for the odd purchase in USD.
+// the same seed always gives the same journal, so benchmarks run against the same data
+//
+//   $ katana gen --transactions 100000 > big.journal
+pub struct GenOptions {
+    pub transactions: usize,
+    pub seed        : u64,
+    pub start       : NaiveDate,
+}
+
+// where the money goes on an ordinary day, with the range of amounts in cents
+const PURCHASES: [(&str, &str, u64, u64); 10] = [
+    ("Groceries",      "expenses:food:groceries",         2000, 18000),
+    ("Tim Hortons",    "expenses:food:coffee",             200,   900),
+    ("Subway",         "expenses:food:lunch",              900,  1800),
+    ("Gas",            "expenses:auto:gas",               3000,  8000),
+    ("Pharmacy",       "expenses:health:pharmacy",         500,  6000),
+    ("Hardware store", "expenses:home:repairs",           1000, 25000),
+    ("Bookstore",      "expenses:entertainment:books",     800,  4500),
+    ("Movie tickets",  "expenses:entertainment:movies",   1500,  4000),
+    ("Bus pass",       "expenses:transit",                 300,   300),
+    ("Clothing",       "expenses:clothing",               2000, 12000),
+];
+
+const PAID_FROM: [&str; 3] = ["credit:visa", "assets:checking", "assets:cash"];
+
+pub fn generate(out: &mut dyn Write, options: &GenOptions) -> io::Result<()> {
+    let mut rng     = Rng::new(options.seed);
+    let mut date    = options.start;
+    let mut written = 0;
+
+    writeln!(out, "commodity $1,000.00")?;
+    writeln!(out, "commodity USD 1,000.00")?;
+    writeln!(out)?;
+    writeln!(out, "{} Opening balances", date.format("%Y/%m/%d"))?;
+    writeln!(out, "    assets:checking    {}", dollars(500000))?;
+    writeln!(out, "    assets:cash        {}", dollars(20000))?;
+    writeln!(out, "    equity:opening-balances")?;
+    writeln!(out)?;
+    written += 1;
+
+    while written < options.transactions {
+        let mut today = vec![];
+
+        if date.day() == 1 {
+            writeln!(out, "P {} USD {}", date.format("%Y/%m/%d"), dollars(rng.between(128, 140)))?;
+            writeln!(out)?;
+            today.push(scheduled("Rent", "expenses:rent", 145000, "assets:checking"));
+            today.push(scheduled("Hydro", "expenses:utilities:hydro", rng.between(6000, 14000), "assets:checking"));
+            today.push(scheduled("Visa payment", "credit:visa", rng.between(50000, 150000), "assets:checking"));
+        }
+
+        // payday every other friday
+        if date.weekday() == chrono::Weekday::Fri && date.iso_week().week().is_multiple_of(2) {
+            today.push(scheduled("Payroll Deposit", "assets:checking", 240000, "income:payroll:workplace"));
+        }
+
+        for _ in 0..rng.between(0, 4) {
+            today.push(match rng.between(0, 20) {
+                0 => restaurant(&mut rng),
+                1 => online(&mut rng),
+                _ => purchase(&mut rng),
+            });
+        }
+
+        for transaction in today.into_iter().take(options.transactions - written) {
+            writeln!(out, "{} {}", date.format("%Y/%m/%d"), transaction)?;
+            written += 1;
+        }
+        date += Duration::days(1);
+    }
+    Ok(())
+}
+
+// a header's description and the lines under it
+fn scheduled(description: &str, account: &str, cents: u64, from: &str) -> String {
+    format!("{}\n    {:<32}{:>12}\n    {}\n", description, account, dollars(cents), from)
+}
+
+fn purchase(rng: &mut Rng) -> String {
+    let (payee, account, low, high) = *rng.pick(&PURCHASES);
+    let from = rng.pick(&PAID_FROM);
+    scheduled(payee, account, rng.between(low, high), from)
+}
+
+// a meal with the tip on a line of its own, sometimes tagged with who it was with
+fn restaurant(rng: &mut Rng) -> String {
+    let meal = rng.between(2500, 12000);
+    let tip  = meal * 15 / 100;
+    let with = match rng.between(0, 3) {
+        0 => "  ; with:family",
+        1 => "  ; with:friends",
+        _ => "",
+    };
+
+    format!("Restaurant{}\n    {:<32}{:>12}\n    {:<32}{:>12}\n    {}\n",
+            with, "expenses:food:restaurants", dollars(meal), "expenses:tips", dollars(tip), *rng.pick(&PAID_FROM))
+}
+
+// a purchase in another commodity, which -X $ converts at the month's rate
+fn online(rng: &mut Rng) -> String {
+    let cents = rng.between(500, 9000);
+    format!("Online order\n    {:<32}{:>12}\n    {:<32}{:>12}\n",
+            "expenses:shopping:online", usd(cents as i64), "credit:visa", usd(-(cents as i64)))
+}
+
+fn dollars(cents: u64) -> String {
+    format!("${}.{:02}", cents / 100, cents % 100)
+}
+
+fn usd(cents: i64) -> String {
+    let sign = if cents < 0 { "-" } else { "" };
+    format!("USD {}{}.{:02}", sign, cents.abs() / 100, cents.abs() % 100)
+}
+
+
+/* Rng */
+
+// splitmix64, which is plenty random for made-up purchases and keeps katana free of a
+// dependency just for this
+struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    fn new(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    fn next(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    // a number from low up to and including high
+    fn between(&mut self, low: u64, high: u64) -> u64 {
+        low + self.next() % (high - low + 1)
+    }
+
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.between(0, items.len() as u64 - 1) as usize]
+    }
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use crate::journal::Journal;
+    use super::{GenOptions, generate};
+
+    fn generated(transactions: usize, seed: u64) -> String {
+        let options = GenOptions { transactions, seed, start: NaiveDate::from_ymd_opt(2023, 1, 1).unwrap() };
+        let mut out = vec![];
+        generate(&mut out, &options).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_generated_journal_parses() {
+        let text    = generated(2000, 7);
+        let journal = Journal::from_lines(text.lines()).unwrap();
+
+        assert_eq!(journal.transactions.len(), 2000);
+        assert!(!journal.prices.is_empty());
+        assert!(journal.transactions.iter().any(|t| t.has_tag("with", None)));
+
+        assert_eq!(text, generated(2000, 7));
+        assert_ne!(text, generated(2000, 8));
+    }
+}
@@ -0,0 +1,69 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::journal::types::parse_amount;
+use crate::types::{Account, amount::Amount, monthyear::MonthYear};
+
+
+/* Goal */
+
+// an amount to have saved up in an account by the end of a month, from a goal directive:
+//
+// goal assets:vacation-fund $3000 by 2024/06
+//
+// says the vacation fund and its subaccounts should hold $3000 by the end of June 2024
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Goal {
+    pub account: Account,
+    pub target : Amount,
+    pub by     : MonthYear,
+}
+
+impl Goal {
+
+    // parse a "goal account amount by month" line. None means it isn't a goal directive at
+    // all, an error means it is one but something about it is wrong
+    pub fn parse(line: &str) -> Option<Result<Goal, String>> {
+        if !line.starts_with("goal ") {
+            return None
+        }
+
+        let parsed = GOAL_REGEX.captures(line.trim_end()).and_then(|caps| {
+            let account = caps.name("account")?.as_str();
+            let target = parse_amount(caps.name("target")?.as_str())?;
+            let by = caps.name("by")?.as_str().parse().ok()?;
+
+            Some(Goal { account: Account::from(account), target, by })
+        });
+
+        Some(parsed.ok_or_else(|| line.to_string()))
+    }
+}
+
+lazy_static! {
+    static ref GOAL_REGEX: Regex =
+        Regex::new(r"^goal\s+(?P<account>\S+)\s+(?P<target>.+?)\s+by\s+(?P<by>\d{4}[/-]\d{1,2})$").unwrap();
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use crate::types::monthyear::MonthYear;
+    use super::Goal;
+
+    #[test]
+    fn test_parse_goal() {
+        let goal = Goal::parse("goal assets:vacation-fund $3000 by 2024/06").unwrap().unwrap();
+        assert_eq!(goal.account.as_str(), "assets:vacation-fund");
+        assert_eq!(goal.target.to_string(), "$3000.00");
+        assert_eq!(goal.by, MonthYear::new(6, 2024));
+
+        assert_eq!(Goal::parse("goal assets:vacation-fund $3000"), Some(Err("goal assets:vacation-fund $3000".to_string())));
+        assert_eq!(Goal::parse("goal assets:vacation-fund lots by 2024/06"), Some(Err("goal assets:vacation-fund lots by 2024/06".to_string())));
+        assert_eq!(Goal::parse("    assets:vacation-fund  $300"), None);
+    }
+}
@@ -0,0 +1,182 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+
+
+/* HTTP */
+
+// just enough of HTTP/1.1 to serve reports to a browser or another program on this
+// machine, or on the local network when bound to its interface. requests are handled one
+// at a time and every connection is closed after its response, which is plenty for one
+// person browsing their own journal
+
+// a GET request for a path with its query string decoded into parameters
+#[derive(Debug, PartialEq)]
+pub struct Request {
+    pub path  : String,
+    pub params: Vec<(String, String)>,
+}
+
+pub struct Response {
+    pub status      : u16,
+    pub content_type: &'static str,
+    pub body        : String,
+}
+
+impl Request {
+    // the value of the first parameter with this name
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    // /register?query=expenses+not%3Afood
+    pub fn from_target(target: &str) -> Request {
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let params = query.split('&')
+                          .filter(|pair| !pair.is_empty())
+                          .map(|pair| {
+                              let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+                              (percent_decode(name), percent_decode(value))
+                          })
+                          .collect();
+
+        Request {
+            path: percent_decode(path),
+            params,
+        }
+    }
+}
+
+impl Response {
+    pub fn ok(content_type: &'static str, body: String) -> Response {
+        Response { status: 200, content_type, body }
+    }
+
+    pub fn error(status: u16, content_type: &'static str, body: String) -> Response {
+        Response { status, content_type, body }
+    }
+
+    fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            _   => "Internal Server Error",
+        }
+    }
+}
+
+// listen on the address, answering each request with the handler's response
+pub fn serve<F>(address: SocketAddr, mut handler: F) -> io::Result<()>
+    where F: FnMut(&Request) -> Response
+{
+    let listener = TcpListener::bind(address)?;
+
+    for stream in listener.incoming() {
+        // a client hanging up early is its own problem, not the server's
+        let _ = stream.and_then(|stream| respond(stream, &mut handler));
+    }
+    Ok(())
+}
+
+fn respond<F>(mut stream: TcpStream, handler: &mut F) -> io::Result<()>
+    where F: FnMut(&Request) -> Response
+{
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    // the headers aren't needed, but they're read so the client sees its request was taken
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    // GET /balance?interval=monthly HTTP/1.1
+    let response = match request_line.split_whitespace().collect::<Vec<&str>>()[..] {
+        ["GET", target, _] => handler(&Request::from_target(target)),
+        _                  => Response::error(405, "text/plain", "Only GET requests are served\n".to_string()),
+    };
+
+    write!(stream, "HTTP/1.1 {} {}\r\nContent-Type: {}; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+           response.status,
+           response.reason(),
+           response.content_type,
+           response.body.len(),
+           response.body)?;
+    stream.flush()
+}
+
+// a path or form value with its %XX escapes decoded and its + signs turned back into spaces
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let escaped = bytes.get(i+1..i+3)
+                           .and_then(|hex| std::str::from_utf8(hex).ok())
+                           .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match (bytes[i], escaped) {
+            (b'+', _)          => decoded.push(b' '),
+            (b'%', Some(byte)) => { decoded.push(byte); i += 2 },
+            (byte, _)          => decoded.push(byte),
+        }
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+// what the tests of the web pages and the JSON API share
+#[cfg(test)]
+pub mod test_support {
+    use crate::journal::Journal;
+
+    // a journal for them to serve
+    pub fn sample_journal() -> Journal {
+        let journal =
+r#"
+2023/03/07 Sandwich, Chili
+    expenses:food:tim-hortons          $14.99
+    assets:cash
+
+2023/04/09 Sub
+    expenses:food:subway                $12
+    assets:savings
+"#;
+        Journal::from_lines(journal.lines()).unwrap()
+    }
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use super::{Request, percent_decode};
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("expenses+not%3Afood"), "expenses not:food");
+        assert_eq!(percent_decode("caf%C3%A9"), "café");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz"), "%zz");
+    }
+
+    #[test]
+    fn test_request_from_target() {
+        let request = Request::from_target("/register?query=desc%3Asub+amt%3A%3E10&empty=&flag");
+        assert_eq!(request.path, "/register");
+        assert_eq!(request.param("query"), Some("desc:sub amt:>10"));
+        assert_eq!(request.param("empty"), Some(""));
+        assert_eq!(request.param("flag"), Some(""));
+        assert_eq!(request.param("interval"), None);
+
+        assert_eq!(Request::from_target("/"), Request { path: "/".to_string(), params: vec![] });
+    }
+}
@@ -1 +1,3 @@
+pub mod entriesbyaccount;
 pub mod transactionsbymonth;
+pub mod transactionsbyperiod;
@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+
+use crate::journal::Journal;
+use crate::reports::balance::compare_accounts;
+use crate::transaction::Entry;
+use crate::types::Account;
+
+/* Journal -> Iterator< (Account,Item=Iterator<&Entry>) > */
+
+/// Iterate over a journal one account at a time, returning each account with its entries
+/// from every transaction, in journal order. The transactions are scanned once up front,
+/// so a report going through the accounts doesn't filter all of them again for each one.
+/// Accounts come in the order compare_accounts sorts them
+pub struct EntriesByAccount<'a> {
+    accounts: std::vec::IntoIter<(&'a Account, Vec<&'a Entry>)>,
+}
+
+impl<'a> Iterator for EntriesByAccount<'a> {
+    type Item = (&'a Account, std::vec::IntoIter<&'a Entry>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.accounts
+            .next()
+            .map(|(account, entries)| (account, entries.into_iter()))
+    }
+}
+
+pub fn entries_by_account(journal: &Journal) -> EntriesByAccount<'_> {
+    let mut by_account: HashMap<&Account, Vec<&Entry>> = HashMap::new();
+    for (_, _, entry) in journal.postings() {
+        by_account.entry(&entry.account).or_default().push(entry);
+    }
+
+    let mut accounts: Vec<(&Account, Vec<&Entry>)> = by_account.into_iter().collect();
+    accounts.sort_by(|(a, _), (b, _)| compare_accounts(a, b));
+
+    EntriesByAccount {
+        accounts: accounts.into_iter(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::journal::Journal;
+    use super::entries_by_account;
+
+    #[test]
+    fn test_entries_by_account() {
+        let journal =
+r#"
+2023/03/07 Sandwich
+    expenses:food                      $14.99
+    assets:cash
+
+2023/03/09 Sub
+    expenses:food                         $12
+    expenses:food:tips                     $2
+    assets:cash
+"#;
+        let journal = Journal::from_lines(journal.lines()).unwrap();
+
+        let groups: Vec<(String, Vec<String>)> =
+            entries_by_account(&journal).map(|(account, entries)| (account.to_string(),
+                                                                   entries.map(|e| e.amount.to_string()).collect()))
+                                        .collect();
+
+        assert_eq!(groups, vec![
+            ("assets:cash".to_string(),        vec!["$-14.99".to_string(), "$-14.00".to_string()]),
+            ("expenses:food".to_string(),      vec!["$14.99".to_string(), "$12.00".to_string()]),
+            ("expenses:food:tips".to_string(), vec!["$2.00".to_string()]),
+        ]);
+    }
+}
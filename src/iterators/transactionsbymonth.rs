@@ -1,12 +1,14 @@
 use crate::journal::{Journal, JournalSummary};
 use crate::transaction::Transaction;
-use crate::types::monthyear::MonthYear;
+use crate::types::{interval::Interval, monthyear::MonthYear, period::Period};
 
 /* Journal -> (Month, Month) -> Iterator< (MonthYear,Item=&[Transaction]) > */
 
 /// Iterate over a journal starting from a certain month/year, returning slices
 /// of transactions that all fall within the same month. Assumes the journal
-/// is sorted by transaction date because it uses a binary search to locate dates
+/// is sorted by transaction date because it uses a binary search to locate dates,
+/// the same one Journal::transactions_in does for TransactionsByPeriod, which
+/// slices the journal by week, quarter or year instead
 pub struct TransactionsByMonth<'a> {
     journal: &'a Journal,
     current_month: MonthYear,
@@ -21,23 +23,15 @@ impl<'a> Iterator for TransactionsByMonth<'a> {
             return None;
         }
 
-        let transactions = &self.journal.transactions;
-        let start = transactions.partition_point(|t| {
-            MonthYear::from_naivedate(t.date) < self.current_month
-        });
-
-        let next_month = self.current_month.next_month();
-        let end = transactions.partition_point(|t| {
-            MonthYear::from_naivedate(t.date) < next_month
-        });
-
         let month = self.current_month;
-        self.current_month = next_month;
-        Some((month, &transactions[start..end]))
+        self.current_month = month.next_month();
+
+        let range = Period::containing(Interval::Monthly, month.first_day()).range();
+        Some((month, self.journal.transactions_in(&range).as_slice()))
     }
 }
 
-pub fn transactions_by_month(journal: &Journal) -> TransactionsByMonth {
+pub fn transactions_by_month(journal: &Journal) -> TransactionsByMonth<'_> {
     let summary = JournalSummary::from(journal);
 
     TransactionsByMonth {
@@ -51,18 +45,25 @@ pub fn transactions_by_month(journal: &Journal) -> TransactionsByMonth {
 mod tests {
     use chrono::NaiveDate;
     use crate::{journal::Journal, transaction::Transaction, types::monthyear::MonthYear};
-    use super::TransactionsByMonth;
+    use crate::iterators::transactionsbyperiod::transactions_by_period;
+    use crate::types::interval::Interval;
+    use super::{TransactionsByMonth, transactions_by_month};
+
+    fn transaction(y: i32, m: u32, d: u32) -> Transaction {
+        Transaction::builder().date(NaiveDate::from_ymd_opt(y, m, d).unwrap()).build().unwrap()
+    }
 
     fn sample_journal() -> Journal {
         Journal {
             transactions: vec![
-                Transaction { date: NaiveDate::from_ymd_opt(2022, 1, 1).unwrap(), entries: vec![], description: "".to_owned() },
-                Transaction { date: NaiveDate::from_ymd_opt(2022, 1, 15).unwrap(), entries: vec![], description: "".to_owned() },
-                Transaction { date: NaiveDate::from_ymd_opt(2022, 2, 5).unwrap(), entries: vec![], description: "".to_owned() },
-                Transaction { date: NaiveDate::from_ymd_opt(2022, 2, 25).unwrap(), entries: vec![], description: "".to_owned() },
-                Transaction { date: NaiveDate::from_ymd_opt(2022, 3, 10).unwrap(), entries: vec![], description: "".to_owned() },
-                Transaction { date: NaiveDate::from_ymd_opt(2022, 3, 20).unwrap(), entries: vec![], description: "".to_owned() },
+                transaction(2022, 1, 1),
+                transaction(2022, 1, 15),
+                transaction(2022, 2, 5),
+                transaction(2022, 2, 25),
+                transaction(2022, 3, 10),
+                transaction(2022, 3, 20),
             ],
+            ..Default::default()
         }
     }
 
@@ -106,12 +107,13 @@ mod tests {
     fn sample_journal_empty_slice_middle() -> Journal {
         Journal {
             transactions: vec![
-                Transaction { date: NaiveDate::from_ymd_opt(2022, 1, 1).unwrap(), entries: vec![], description: "".to_owned() },
-                Transaction { date: NaiveDate::from_ymd_opt(2022, 1, 15).unwrap(), entries: vec![], description: "".to_owned() },
+                transaction(2022, 1, 1),
+                transaction(2022, 1, 15),
                 // skip february
-                Transaction { date: NaiveDate::from_ymd_opt(2022, 3, 10).unwrap(), entries: vec![], description: "".to_owned() },
-                Transaction { date: NaiveDate::from_ymd_opt(2022, 3, 20).unwrap(), entries: vec![], description: "".to_owned() },
+                transaction(2022, 3, 10),
+                transaction(2022, 3, 20),
             ],
+            ..Default::default()
         }
     }
 
@@ -133,4 +135,31 @@ mod tests {
         assert_eq!(month_slices[1].1, &[]); // Expect an empty slice for February
         assert_eq!(month_slices[2].1, &journal.transactions[2..4]);
     }
+
+    #[test]
+    fn test_iterator_across_years() {
+        let journal = Journal {
+            transactions: vec![
+                transaction(2022, 11, 20),
+                transaction(2022, 12, 5),
+                transaction(2023, 1, 10),
+            ],
+            ..Default::default()
+        };
+
+        let month_slices: Vec<(MonthYear, &[Transaction])> = transactions_by_month(&journal).collect();
+
+        assert_eq!(month_slices.len(), 3);
+        assert_eq!(month_slices[1], (MonthYear { month: 12, year: 2022 }, &journal.transactions[1..2]));
+        assert_eq!(month_slices[2], (MonthYear { month: 1, year: 2023 }, &journal.transactions[2..3]));
+    }
+
+    #[test]
+    fn test_same_slices_as_by_period() {
+        let journal = sample_journal_empty_slice_middle();
+
+        let by_month : Vec<&[Transaction]> = transactions_by_month(&journal).map(|(_, ts)| ts).collect();
+        let by_period: Vec<&[Transaction]> = transactions_by_period(&journal, Interval::Monthly).map(|(_, ts)| ts).collect();
+        assert_eq!(by_month, by_period);
+    }
 }
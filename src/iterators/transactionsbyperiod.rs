@@ -0,0 +1,107 @@
+use chrono::NaiveDate;
+
+use crate::journal::Journal;
+use crate::transaction::Transaction;
+use crate::types::{interval::Interval, period::Period};
+
+/* Journal -> Interval -> Iterator< (Period,Item=&[Transaction]) > */
+
+/// Iterate over a journal one period at a time, returning each period with the slice of
+/// transactions falling within it. Like TransactionsByMonth, every period
+/// between the first and last transaction is returned, even empty ones, and the journal
+/// must be sorted by transaction date because it uses a binary search to locate dates
+pub struct TransactionsByPeriod<'a> {
+    journal: &'a Journal,
+    current: Period,
+    last   : NaiveDate,
+}
+
+impl<'a> Iterator for TransactionsByPeriod<'a> {
+    type Item = (Period, &'a [Transaction]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.current.start > self.last {
+            return None;
+        }
+
+        let period = self.current;
+        self.current = period.next();
+        Some((period, self.journal.transactions_in(&period.range()).as_slice()))
+    }
+}
+
+pub fn transactions_by_period(journal : &Journal,
+                              interval: Interval) -> TransactionsByPeriod<'_>
+{
+    // with no transactions, start after the end so there's nothing to iterate
+    let (first, last) = match (journal.transactions.first(), journal.transactions.last()) {
+        (Some(first), Some(last)) => (first.date, last.date),
+        _                         => (NaiveDate::MAX, NaiveDate::MIN),
+    };
+
+    TransactionsByPeriod {
+        journal,
+        current: Period::containing(interval, first),
+        last,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use crate::{journal::Journal, transaction::Transaction, types::interval::Interval, types::period::Period};
+    use super::transactions_by_period;
+
+    fn quarter(y: i32, m: u32) -> Period {
+        Period { interval: Interval::Quarterly, start: NaiveDate::from_ymd_opt(y, m, 1).unwrap() }
+    }
+
+    fn sample_journal() -> Journal {
+        let dates = [(2022, 1, 1), (2022, 1, 15), (2022, 3, 10), (2022, 3, 20), (2022, 10, 2)];
+
+        Journal {
+            transactions: dates.iter()
+                               .map(|&(y, m, d)| Transaction { date: NaiveDate::from_ymd_opt(y, m, d).unwrap(), ..Default::default() })
+                               .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_iterator_quarterly() {
+        let journal = sample_journal();
+        let periods: Vec<(Period, &[Transaction])> = transactions_by_period(&journal, Interval::Quarterly).collect();
+
+        assert_eq!(periods.len(), 4);
+        assert_eq!(periods[0], (quarter(2022, 1), &journal.transactions[0..4]));
+        assert_eq!(periods[1].1, &[]);
+        assert_eq!(periods[2].1, &[]);
+        assert_eq!(periods[3], (quarter(2022, 10), &journal.transactions[4..5]));
+    }
+
+    #[test]
+    fn test_iterator_weekly() {
+        let journal = sample_journal();
+        let periods: Vec<(Period, &[Transaction])> = transactions_by_period(&journal, Interval::Weekly).collect();
+
+        // 2022/01/01 was a Saturday, so the first week starts on the Monday before it
+        assert_eq!(periods[0].0.start, NaiveDate::from_ymd_opt(2021, 12, 27).unwrap());
+        assert_eq!(periods[0].1, &journal.transactions[0..1]);
+        assert_eq!(periods.iter().map(|(_, ts)| ts.len()).sum::<usize>(), 5);
+    }
+
+    #[test]
+    fn test_iterator_yearly() {
+        let journal = sample_journal();
+        let periods: Vec<(Period, &[Transaction])> = transactions_by_period(&journal, Interval::Yearly).collect();
+
+        assert_eq!(periods.len(), 1);
+        assert_eq!(periods[0].1, &journal.transactions[..]);
+    }
+
+    #[test]
+    fn test_iterator_empty_journal() {
+        let journal = Journal::default();
+        assert_eq!(transactions_by_period(&journal, Interval::Daily).count(), 0);
+    }
+}
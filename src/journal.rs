@@ -1,35 +1,150 @@
+pub mod builder;
 pub mod types;
+pub mod visitor;
+pub mod watch;
 
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::str::FromStr;
-use std::fmt::{Display, Formatter, Result};
 use chrono::NaiveDate;
+use tracing::{debug, info, trace};
 
-use crate::common::is_all_whitespace;
-use crate::transaction::{Transaction, Entry};
-use crate::types::{amount::Amount, Units, monthyear::MonthYear};
-use crate::journal::types::{Line, LineAmount};
+use crate::common::{case_insensitive, is_all_whitespace};
+use crate::goal::Goal;
+use crate::periodic::PeriodicTransaction;
+use crate::price::Price;
+use crate::query::Query;
+use crate::transaction::{Transaction, Entry, PostingKind, parse_tags, posting::Posting};
+use crate::types::{Account, Units, amount::Amount, daterange::DateRange, interval::Interval, monthyear::MonthYear};
+use crate::types::commoditystyle::{CommodityStyle, CommodityStyles};
+use crate::journal::types::{Line, LineAmount, written_style};
+use crate::journal::visitor::JournalVisitor;
 
 
 /* Journal */
 
-// a journal is a list of transactions sorted by date
+// a journal is a list of transactions sorted by date, along with any periodic
+// transaction rules, which are kept in the order they were written, the
+// market prices from price directives, also sorted by date, the savings goals
+// from goal directives, and how each commodity's amounts are written
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Journal {
-    pub transactions: Vec<Transaction>
+    pub transactions: Vec<Transaction>,
+    pub periodic    : Vec<PeriodicTransaction>,
+    pub prices      : Vec<Price>,
+    pub goals       : Vec<Goal>,
+    pub earlier     : Vec<Transaction>, // set aside by restrict_to, for historical balances
+    pub commodities : CommodityStyles,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
 pub enum ParseJournalError {
+    #[error("First character of a debit/credit line must be a space or tab")]
     EntryLineMustStartWithSpace,
+    #[error("Can't have a debit/credit outside a transaction")]
+    EntryOutsideTransaction,
+    #[error("Two blank amounts in one transaction")]
+    TwoBlankAmounts,
+    #[error("Blank transaction entry with no unbalanced commodity")]
+    BlankWithNoUnbalancedCommodity,
+    #[error("Blank transaction entry with more than one unbalanced commodity")]
+    BlankWithManyUnbalancedCommodities,
+    #[error("Unbalanced transaction: {0}")]
+    UnbalancedTransaction(String),
+    #[error("Couldn't process this line: '{0}'")]
+    UnparseableLine(String),
+    #[error("Unknown interval for a periodic transaction: '{0}'")]
+    UnknownInterval(String),
+    #[error("Couldn't read this price directive: '{0}'")]
+    BadPriceDirective(String),
+    #[error("Couldn't read this commodity directive: '{0}'")]
+    BadCommodityDirective(String),
+    #[error("Couldn't read this goal directive: '{0}'")]
+    BadGoalDirective(String),
+    #[error("Amounts in this transaction add up to more than an amount can hold")]
+    Overflow,
+    #[error("Transaction dated {} comes after one dated {}", .0.format("%Y/%m/%d"), .1.format("%Y/%m/%d"))]
+    OutOfOrder(NaiveDate, NaiveDate),
 }
 
-impl Display for ParseJournalError {
-    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
-        match *self {
-            ParseJournalError::EntryLineMustStartWithSpace =>
-                write!(f, "First character of a debit/credit line must be a space or tab"),
+impl ParseJournalError {
+    // whether the lines were read fine but the transaction they make doesn't add up
+    pub fn is_validation(&self) -> bool {
+        matches!(self, ParseJournalError::TwoBlankAmounts
+                     | ParseJournalError::BlankWithNoUnbalancedCommodity
+                     | ParseJournalError::BlankWithManyUnbalancedCommodities
+                     | ParseJournalError::UnbalancedTransaction(_)
+                     | ParseJournalError::Overflow
+                     | ParseJournalError::OutOfOrder(_, _))
+    }
+}
+
+// a parse error along with the (1-based) line number in the journal where it was found
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+#[error("line {line}: {error}")]
+pub struct ParseProblem {
+    pub line : usize,
+    #[source]
+    pub error: ParseJournalError,
+}
+
+// strict parsing stops at the first problem, lenient parsing skips past the bad line or
+// transaction and keeps going so every problem in the journal can be reported at once
+#[derive(Clone, Copy, PartialEq)]
+enum ParseMode {
+    Strict,
+    Lenient,
+}
+
+// transactions written out of date order are sorted into place, or with --strict are
+// reported as a problem at the first one dated before the transaction above it
+#[derive(Clone, Copy, PartialEq)]
+pub enum DateOrder {
+    Sort,
+    Require,
+}
+
+struct Problems {
+    mode    : ParseMode,
+    order   : DateOrder,
+    latest  : Option<NaiveDate>, // the latest date on a transaction so far
+    problems: Vec<ParseProblem>,
+}
+
+impl Problems {
+    fn new(mode: ParseMode, order: DateOrder) -> Self {
+        Problems { mode, order, latest: None, problems: vec![] }
+    }
+
+    // check that a transaction isn't dated before the ones above it, if that's required
+    fn check_order(&mut self, line: usize, date: NaiveDate) -> std::result::Result<(), ParseProblem> {
+        let result = match self.latest {
+            Some(latest) if date < latest && self.order == DateOrder::Require => {
+                Err(ParseJournalError::OutOfOrder(date, latest))
+            },
+            _ => Ok(()),
+        };
+
+        self.latest = self.latest.max(Some(date));
+        self.report(line, result)
+    }
+
+    // record the outcome of a parsing step. in strict mode the first problem is returned
+    // as an error along with its line number, to stop the parse
+    fn report(&mut self,
+              line  : usize,
+              result: std::result::Result<(), ParseJournalError>) -> std::result::Result<(), ParseProblem>
+    {
+        let Err(error) = result else { return Ok(()) };
+
+        match self.mode {
+            ParseMode::Lenient => {
+                debug!(line, %error, "skipping past a problem");
+                self.problems.push(ParseProblem { line, error });
+                Ok(())
+            },
+            ParseMode::Strict => Err(ParseProblem { line, error }),
         }
     }
 }
@@ -43,119 +158,432 @@ impl Journal {
     //    expenses:tips                          $1
     //    expenses:food:tim-hortons
     //
-    pub fn from_lines(lines: std::str::Lines) -> std::result::Result<Journal, ParseJournalError> {
+    // a journal on its own, with no files read before it. the commands read theirs with
+    // from_lines_after, so this is what the tests use
+    #[cfg(test)]
+    pub fn from_lines(lines: std::str::Lines) -> std::result::Result<Journal, ParseProblem> {
+        let (journal, _) = parse_lines(lines, ParseMode::Strict, DateOrder::Sort, &CommodityStyles::default())?;
+        Ok(journal)
+    }
 
-        let mut journal    : Vec<Transaction>    = vec![];
-        let mut transaction: Option<Transaction> = None;
-        let mut blank      : Option<Line>        = None; // we can have up to one unspecified
-                                                         // amount per transaction
-        for line in lines {
-            let (line, _) = split_off_comment(line);
-
-            // "2023/03/15 Sandwich"
-            if let Some(trans) = Transaction::parse_date_and_description(&line) {
-                
-                // this line is the header for a new transaction, so check if we
-                // have one already. process it and move it into the journal if so
-                finalize_transaction(&mut transaction,
-                                     &mut blank,
-                                     &mut journal);
-
-                // our transaction is now the new one we just parsed
-                transaction = Some(trans);
-                continue
-            }
+    // parse a journal read after others, like the next journal file or the next block of
+    // a watched one, with the commodity directives read so far still deciding how far off
+    // its transactions can be and still balance. its styles include the earlier ones. with
+    // DateOrder::Require it stops at a transaction dated before the one above it instead
+    // of sorting it into place, for --strict
+    pub fn from_lines_after(lines  : std::str::Lines,
+                            order  : DateOrder,
+                            earlier: &CommodityStyles) -> std::result::Result<Journal, ParseProblem>
+    {
+        let (journal, _) = parse_lines(lines, ParseMode::Strict, order, earlier)?;
+        Ok(journal)
+    }
 
-            //
-            if is_all_whitespace(&line) {
-                continue
-            }
+    // parse as much of the journal as possible, collecting every problem found along the
+    // way instead of stopping at the first one. transactions with problems are left out,
+    // except ones out of date order, which are sorted into place. the earlier styles are
+    // from any journal files read before this one, the way from_lines_after takes them
+    pub fn from_lines_lenient(lines  : std::str::Lines,
+                              order  : DateOrder,
+                              earlier: &CommodityStyles) -> (Journal, Vec<ParseProblem>)
+    {
+        parse_lines(lines, ParseMode::Lenient, order, earlier)
+            .expect("Lenient parsing should never fail")
+    }
+
+    // drop the transactions falling outside the date range so that every report
+    // run on this journal only sees the ones inside it. the ones before the range are
+    // set aside in `earlier` so historical reports can still start from their balances
+    pub fn restrict_to(&mut self, range: &DateRange) {
+        let earlier = range.begin.map_or(0, |begin| self.transactions.partition_point(|t| t.date < begin));
+        let inside  = self.transactions_in(range).len();
+
+        self.earlier.extend(self.transactions.drain(..earlier));
+        self.transactions.truncate(inside);
+
+        info!(transactions = self.transactions.len(), earlier = self.earlier.len(), "restricted to the date range");
+    }
+
+    // the transactions dated in the range, found by binary search since they're sorted
+    pub fn transactions_in(&self, range: &DateRange) -> std::slice::Iter<'_, Transaction> {
+        let start = range.begin.map_or(0, |begin| self.transactions.partition_point(|t| t.date < begin));
+        let end   = range.end.map_or(self.transactions.len(), |end| self.transactions.partition_point(|t| t.date < end));
+
+        self.transactions[start..end.max(start)].iter()
+    }
+
+    // every posting in the journal with its date and transaction, in journal order, for
+    // going through them all without a loop over the entries inside one over the transactions
+    pub fn postings(&self) -> impl Iterator<Item = (NaiveDate, &Transaction, &Entry)> {
+        self.transactions
+            .iter()
+            .flat_map(|t| t.entries.iter().map(move |e| (t.date, t, e)))
+    }
+
+    // the postings to accounts matching the pattern, a case-insensitive regular expression
+    // like an account term in a query, along with their transactions
+    pub fn postings_for(&self, pattern: &str) -> std::result::Result<impl Iterator<Item = Posting<'_>>, regex::Error> {
+        let pattern = case_insensitive(pattern)?;
+
+        Ok(self.transactions
+               .iter()
+               .flat_map(Transaction::postings)
+               .filter(move |posting| pattern.is_match(posting.account())))
+    }
 
-            if !line.chars().next().unwrap().is_whitespace() {
-                return Err(ParseJournalError::EntryLineMustStartWithSpace)
+    // the transactions the query could match, narrowed by binary search to the dates its
+    // date: terms allow, for a report to go through instead of every transaction
+    pub fn transactions_for(&self, query: &Query) -> std::slice::Iter<'_, Transaction> {
+        self.transactions_in(&query.date_range())
+    }
+
+    // the transactions with a posting matching the query
+    pub fn filter<'a: 'q, 'q>(&'a self, query: &'q Query) -> impl Iterator<Item = &'a Transaction> + 'q {
+        self.transactions_for(query)
+            .filter(|t| query.matches(*t))
+    }
+
+    // add the transactions the periodic transactions would generate from `from` up to
+    // `until`, usually from the day after the last recorded transaction, for --forecast
+    pub fn add_forecast(&mut self, from: NaiveDate, until: NaiveDate) {
+        let forecast: Vec<Transaction> = self.periodic
+                                             .iter()
+                                             .flat_map(|p| p.forecast(from, until))
+                                             .collect();
+
+        info!(transactions = forecast.len(), %from, %until, "forecast from the periodic transactions");
+        for transaction in forecast {
+            self.append(transaction);
+        }
+    }
+
+    // the transactions the periodic transactions would generate from `from` up to `until`
+    // in date order, untagged so they can be written into the journal, for `katana generate`
+    pub fn occurrences(&self, from: NaiveDate, until: NaiveDate) -> Vec<Transaction> {
+        let mut occurrences: Vec<Transaction> = self.periodic
+                                                    .iter()
+                                                    .flat_map(|p| p.occurrences(from, until))
+                                                    .collect();
+
+        // stable, so the ones on the same day keep the order of their periodic transactions
+        occurrences.sort_by_key(|t| t.date);
+        occurrences
+    }
+
+    // add everything from a journal read after this one, like from the next journal file,
+    // keeping the transactions and prices in date order. its commodity directives replace
+    // this journal's the same way they would later in one file
+    pub fn merge(&mut self, other: Journal) {
+        self.transactions.extend(other.transactions);
+        self.periodic.extend(other.periodic);
+        self.prices.extend(other.prices);
+        self.goals.extend(other.goals);
+        self.earlier.extend(other.earlier);
+        self.commodities.merge(other.commodities);
+
+        // the sorts are stable, so transactions on the same day stay in the order they were read
+        self.transactions.sort_by_key(|t| t.date);
+        self.prices.sort_by_key(|p| p.date);
+        self.earlier.sort_by_key(|t| t.date);
+    }
+
+    // leave out every virtual posting, for --real. a transaction with only virtual ones
+    // has nothing left and goes too
+    pub fn drop_virtual(&mut self) {
+        let transactions = self.transactions.iter_mut()
+                               .chain(self.earlier.iter_mut())
+                               .chain(self.periodic.iter_mut().map(|p| &mut p.transaction));
+
+        for transaction in transactions {
+            transaction.entries.retain(|entry| entry.kind == PostingKind::Real);
+        }
+        self.transactions.retain(|t| !t.entries.is_empty());
+        self.earlier.retain(|t| !t.entries.is_empty());
+    }
+
+    // add a transaction after the others on its date, as if it were written at the end of
+    // that day. reports work out their months and totals from the transactions as they go,
+    // so there's nothing else to update
+    pub fn append(&mut self, transaction: Transaction) {
+        let index = self.transactions.partition_point(|t| t.date <= transaction.date);
+        self.transactions.insert(index, transaction);
+    }
+}
+
+fn parse_lines(lines  : std::str::Lines,
+               mode   : ParseMode,
+               order  : DateOrder,
+               earlier: &CommodityStyles) -> std::result::Result<(Journal, Vec<ParseProblem>), ParseProblem>
+{
+    let mut journal    : Vec<Transaction>         = vec![];
+    let mut periodic   : Vec<PeriodicTransaction> = vec![];
+    let mut prices     : Vec<Price>               = vec![];
+    let mut goals      : Vec<Goal>                = vec![];
+    let mut commodities: CommodityStyles          = earlier.clone();
+    let mut transaction: Option<Transaction>      = None;
+    let mut interval   : Option<Interval>         = None; // set when the transaction is periodic
+    let mut blank      : Option<Line>             = None; // we can have up to one unspecified
+                                                          // amount per transaction
+    let mut problems = Problems::new(mode, order);
+    let mut header   = 0;     // line number of the current transaction's header
+    let mut broken   = false; // whether one of its lines had a problem
+
+    for (number, line) in lines.enumerate() {
+        let number = number + 1;
+        let (line, comment) = split_off_comment(line);
+
+        // "2023/03/15 Sandwich" or "~ monthly  budget"
+        let parsed_header = match PeriodicTransaction::parse_header(&line) {
+            Some(Ok((every, trans))) => Some((Some(every), trans, Ok(()))),
+            Some(Err(unknown))       => Some((None, Transaction::default(), Err(ParseJournalError::UnknownInterval(unknown)))),
+            None                     => Transaction::parse_date_and_description(&line)
+                                            .map(|trans| (None, trans, Ok(()))),
+        };
+
+        if let Some((every, mut trans, result)) = parsed_header {
+
+            // this line is the header for a new transaction, so check if we
+            // have one already. process it and move it into the journal if so
+            let finalized = finalize_pending(&mut transaction,
+                                             interval,
+                                             &mut blank,
+                                             &mut journal,
+                                             &mut periodic,
+                                             broken,
+                                             &commodities);
+            problems.report(header, finalized)?;
+
+            trace!(line = number, date = %trans.date, description = trans.description.trim(), "transaction");
+            if every.is_none() && result.is_ok() {
+                problems.check_order(number, trans.date)?;
             }
 
-            //    assets:savings    $-6.76
-            if let Ok(line) = Line::from_str(line.trim()) {
-                process_line(line,
-                             &mut transaction,
-                             &mut blank);
-                continue
+            // our transaction is now the new one we just parsed. if its header was bad
+            // we still hold on to it so its entries are skipped along with it
+            trans.tags = comment.as_deref().map(parse_tags).unwrap_or_default();
+            transaction = Some(trans);
+            interval = every;
+            header = number;
+            broken = result.is_err();
+            problems.report(number, result)?;
+            continue
+        }
+
+        // "P 2023/03/01 USD $1.35" is a directive, so it ends any transaction before it
+        if let Some(price) = Price::parse(&line) {
+            let finalized = finalize_pending(&mut transaction,
+                                             interval,
+                                             &mut blank,
+                                             &mut journal,
+                                             &mut periodic,
+                                             broken,
+                                             &commodities);
+            problems.report(header, finalized)?;
+
+            let result = price.map(|price| prices.push(price))
+                              .map_err(ParseJournalError::BadPriceDirective);
+            problems.report(number, result)?;
+            continue
+        }
+
+        // "goal assets:vacation-fund $3000 by 2024/06" is one too
+        if let Some(goal) = Goal::parse(&line) {
+            let finalized = finalize_pending(&mut transaction,
+                                             interval,
+                                             &mut blank,
+                                             &mut journal,
+                                             &mut periodic,
+                                             broken,
+                                             &commodities);
+            problems.report(header, finalized)?;
+
+            let result = goal.map(|goal| goals.push(goal))
+                              .map_err(ParseJournalError::BadGoalDirective);
+            problems.report(number, result)?;
+            continue
+        }
+
+        // "commodity $1,000.00" is another directive
+        if let Some(style) = CommodityStyle::parse(&line) {
+            let finalized = finalize_pending(&mut transaction,
+                                             interval,
+                                             &mut blank,
+                                             &mut journal,
+                                             &mut periodic,
+                                             broken,
+                                             &commodities);
+            problems.report(header, finalized)?;
+
+            let result = style.map(|(units, style)| commodities.declare(units, style))
+                              .map_err(ParseJournalError::BadCommodityDirective);
+            problems.report(number, result)?;
+            continue
+        }
+
+        // "    ; trip:japan" on an indented line tags the transaction it's in
+        if let (Some(comment), Some(trans)) = (&comment, transaction.as_mut()) {
+            if line.starts_with(char::is_whitespace) {
+                trans.tags.extend(parse_tags(comment));
             }
+        }
 
-            panic!("Couldn't process this line: '{}'", line)
+        //
+        if is_all_whitespace(&line) {
+            continue
         }
 
-        // Add the last pending transaction to the journal, if there is one
-        finalize_transaction(&mut transaction,
-                             &mut blank,
-                             &mut journal);
+        let result = if !line.chars().next().unwrap().is_whitespace() {
+            Err(ParseJournalError::EntryLineMustStartWithSpace)
+        }
 
-        // sort by transaction date
-        journal.sort_by_key(|t| t.date);
+        //    assets:savings    $-6.76
+        else if let Ok(parsed) = Line::from_str(line.trim()) {
+            if let Some((units, style)) = written_style(line.trim()) {
+                commodities.observe(units, style);
+            }
+            process_line(parsed,
+                         &mut transaction,
+                         &mut blank)
+        }
+        else {
+            Err(ParseJournalError::UnparseableLine(line))
+        };
 
-        Ok(Journal { transactions: journal })
+        broken |= result.is_err();
+        problems.report(number, result)?;
     }
+
+    // Add the last pending transaction to the journal, if there is one
+    let result = finalize_pending(&mut transaction,
+                                  interval,
+                                  &mut blank,
+                                  &mut journal,
+                                  &mut periodic,
+                                  broken,
+                                  &commodities);
+    problems.report(header, result)?;
+
+    // sort by transaction date. the sort is stable, so transactions on the same day stay
+    // in the order they were written
+    journal.sort_by_key(|t| t.date);
+    prices.sort_by_key(|p| p.date);
+
+    let journal = Journal { transactions: journal, periodic, prices, goals, earlier: vec![], commodities };
+
+    Ok((journal, problems.problems))
+}
+
+// move the pending transaction into the journal, or into the list of periodic
+// transactions if it was started by a "~ interval" line
+fn finalize_pending(transaction: &mut Option<Transaction>,
+                    interval   : Option<Interval>,
+                    blank      : &mut Option<Line>,
+                    journal    : &mut Vec<Transaction>,
+                    periodic   : &mut Vec<PeriodicTransaction>,
+                    broken     : bool,
+                    commodities: &CommodityStyles) -> std::result::Result<(), ParseJournalError>
+{
+    let Some(interval) = interval else {
+        return finalize_or_discard(transaction, blank, journal, broken, commodities)
+    };
+
+    let mut finished = vec![];
+    finalize_or_discard(transaction, blank, &mut finished, broken, commodities)?;
+
+    periodic.extend(finished.into_iter()
+                            .map(|transaction| PeriodicTransaction { interval, transaction }));
+    Ok(())
 }
 
 
+// a transaction that already had a problem with one of its lines won't balance, so it
+// is dropped rather than reporting a second problem for it
+fn finalize_or_discard(transaction: &mut Option<Transaction>,
+                       blank      : &mut Option<Line>,
+                       journal    : &mut Vec<Transaction>,
+                       broken     : bool,
+                       commodities: &CommodityStyles) -> std::result::Result<(), ParseJournalError>
+{
+    if broken {
+        transaction.take();
+        blank.take();
+        return Ok(())
+    }
+
+    finalize_transaction(transaction, blank, journal, commodities)
+}
+
 // if we have a transaction on hand, balance it and move it to the journal
 fn finalize_transaction(transaction: &mut Option<Transaction>,
                         blank      : &mut Option<Line>,
-                        journal    : &mut Vec<Transaction>)
+                        journal    : &mut Vec<Transaction>,
+                        commodities: &CommodityStyles) -> std::result::Result<(), ParseJournalError>
 {
     if let Some(mut t) = transaction.take() {
-        balance_transaction(blank, &mut t);
+        balance_transaction(blank, &mut t, commodities)?;
         journal.push(t);
     }
+    Ok(())
 }
 
 // balance this transaction if necessary by checking if there's an account line with no
-// amount. if so, set the amount to balance out the other entries in the transaction
-fn balance_transaction(blank      : &mut Option<Line>,
-                       transaction: &mut Transaction)
+// amount. if so, set the amount to balance out the other entries in the transaction.
+// otherwise it balances if each commodity's total is within its tolerance of zero. the
+// real postings balance among themselves, and so do the bracketed virtual ones, while
+// the ones in parentheses don't have to balance at all
+pub fn balance_transaction(blank      : &mut Option<Line>,
+                           transaction: &mut Transaction,
+                           commodities: &CommodityStyles) -> std::result::Result<(), ParseJournalError>
 {
-    let totals = transaction.totals();
-
-    // get only the non-zero amounts, these are the unbalanced units and there
-    // can be no more than one of them if the transaction is to balance
-    let nonzero: HashMap<Units, Amount> =
-        totals.into_iter()
-              .filter(|(_, a)| !a.is_zero())
-              .collect();
-
-    if let Some(line) = blank.take() {
-        if nonzero.is_empty() { panic!("Blank transaction entry with no unbalanced commodity"); }
-        if nonzero.len() > 1  { panic!("Blank transaction entry with more than one unbalanced commodity"); }
-
-        // get the only amount that can be there
-        let (_, amount) = nonzero.into_iter().next().unwrap();
-
-        // create a new entry with the amount that balances the overall transaction to zero
-        transaction.entries.push(Entry {
-            account: line.account,
-            amount : amount.negate()
-        });
+    for kind in [PostingKind::Real, PostingKind::BalancedVirtual] {
+        let totals = transaction.totals(kind).map_err(|_| ParseJournalError::Overflow)?;
+
+        // get only the non-zero amounts, these are the unbalanced units and there
+        // can be no more than one of them if the transaction is to balance
+        let nonzero: Vec<&Amount> = totals.nonzero().collect();
+
+        if let Some(line) = blank.take_if(|line| line.kind == kind) {
+            if nonzero.is_empty() { return Err(ParseJournalError::BlankWithNoUnbalancedCommodity) }
+            if nonzero.len() > 1  { return Err(ParseJournalError::BlankWithManyUnbalancedCommodities) }
+
+            // get the only amount that can be there
+            let amount = nonzero[0].clone();
+
+            // create a new entry with the amount that balances the overall transaction to zero
+            transaction.entries.push(Entry {
+                account: line.account,
+                amount : -amount,
+                cost   : None,
+                lot    : None,
+                kind,
+            });
+        }
+        else if nonzero.iter().any(|amount| amount.decimal().abs() > commodities.tolerance(&amount.units))
+        {
+            return Err(ParseJournalError::UnbalancedTransaction(transaction.to_string()))
+        }
     }
-    else if !nonzero.is_empty()
-    {
-        panic!("Unbalanced transaction: {}", transaction);
+
+    // a blank virtual posting in parentheses has nothing to balance
+    if blank.take().is_some() {
+        return Err(ParseJournalError::BlankWithNoUnbalancedCommodity)
     }
+    Ok(())
 }
 
 // process an entry line and add it to the transaction
 fn process_line(line       : Line,
                 transaction: &mut Option<Transaction>,
-                blank      : &mut Option<Line>)
+                blank      : &mut Option<Line>) -> std::result::Result<(), ParseJournalError>
 {
     if transaction.is_none() {
-        panic!("Can't have a debit/credit outside a transaction")
+        return Err(ParseJournalError::EntryOutsideTransaction)
     }
 
     match line.amount {
         LineAmount::Blank => {
             if blank.is_some() {
-                panic!("Two blank amounts in one transaction");
+                return Err(ParseJournalError::TwoBlankAmounts)
             }
             // update the variable behind the reference, it now owns this line
             *blank = Some(line);
@@ -164,10 +592,14 @@ fn process_line(line       : Line,
             // borrow a mutable reference to the transaction and add an entry
             transaction.as_mut().unwrap().entries.push(Entry {
                 account: line.account,
-                amount
+                amount,
+                cost   : line.cost,
+                lot    : line.lot,
+                kind   : line.kind,
             });
         }
     }
+    Ok(())
 }
 
 // split off any comment from the end of a journal line and return both parts.
@@ -185,33 +617,91 @@ fn split_off_comment(line: &str) -> (String, Option<String>) {
 }
 
 
+// what's in a journal at a glance: how much of it there is, what it covers and when
 pub struct JournalSummary {
-    pub first_month: MonthYear,
-    pub final_month: MonthYear,
+    pub first_month : MonthYear,              // the months of the first and final transactions,
+    pub final_month : MonthYear,              // or of 1970-01-01 for a journal without any
+    pub first_date  : Option<NaiveDate>,
+    pub last_date   : Option<NaiveDate>,
+    pub transactions: usize,
+    pub postings    : usize,
+    pub accounts    : BTreeSet<Account>,
+    pub commodities : BTreeSet<Units>,
+    pub by_month    : Vec<(MonthYear, usize)>, // the number of transactions in each month
+}
+
+impl Journal {
+    pub fn summary(&self) -> JournalSummary {
+        JournalSummary::from(self)
+    }
+}
+
+// the counts a summary is made from, gathered in one walk through the journal
+#[derive(Default)]
+struct Tally {
+    first_date  : Option<NaiveDate>,
+    last_date   : Option<NaiveDate>,
+    transactions: usize,
+    postings    : usize,
+    accounts    : BTreeSet<Account>,
+    commodities : BTreeSet<Units>,
+    per_month   : HashMap<MonthYear, usize>,
+}
+
+impl JournalVisitor for Tally {
+    fn visit_transaction(&mut self, transaction: &Transaction) {
+        let date = transaction.date;
+        self.first_date = Some(self.first_date.map_or(date, |first| first.min(date)));
+        self.last_date  = Some(self.last_date.map_or(date, |last| last.max(date)));
+        self.transactions += 1;
+        *self.per_month.entry(MonthYear::from_naivedate(transaction.date)).or_default() += 1;
+    }
+
+    fn visit_posting(&mut self, _transaction: &Transaction, entry: &Entry) {
+        self.postings += 1;
+        if !self.accounts.contains(&entry.account) {
+            self.accounts.insert(entry.account.clone());
+        }
+        if !self.commodities.contains(&entry.amount.units) {
+            self.commodities.insert(entry.amount.units);
+        }
+    }
 }
 
 impl JournalSummary {
 
     pub fn from(journal: &Journal) -> Self {
-        let first_transaction_date: NaiveDate =
-            journal.transactions
-                   .iter()
-                   .map(|t| t.date)
-                   .min()
-                   .unwrap_or_default();
-
-        let last_transaction_date: NaiveDate =
-            journal.transactions
-                   .iter()
-                   .map(|t| t.date)
-                   .max()
-                   .unwrap_or_default();
+        let mut tally = Tally::default();
+        journal.walk(&mut tally);
+
+        let first_month = MonthYear::from_naivedate(tally.first_date.unwrap_or_default());
+        let final_month = MonthYear::from_naivedate(tally.last_date.unwrap_or_default());
+
+        // every month from the first to the final one, including the ones without any
+        let by_month = match tally.first_date {
+            Some(_) => MonthYear::range(first_month..=final_month)
+                           .map(|month| (month, tally.per_month.get(&month).copied().unwrap_or(0)))
+                           .collect(),
+            None    => vec![],
+        };
 
         JournalSummary {
-            first_month: MonthYear::from_naivedate(first_transaction_date),
-            final_month: MonthYear::from_naivedate(last_transaction_date)
+            first_month,
+            final_month,
+            first_date  : tally.first_date,
+            last_date   : tally.last_date,
+            transactions: tally.transactions,
+            postings    : tally.postings,
+            accounts    : tally.accounts,
+            commodities : tally.commodities,
+            by_month,
         }
     }
+
+    // every month from the first to the final one, inclusive
+    pub fn months(&self) -> Vec<MonthYear> {
+        MonthYear::range(self.first_month..=self.final_month).collect()
+    }
 }
 
 
@@ -219,11 +709,16 @@ impl JournalSummary {
 
 #[cfg(test)]
 mod tests {
+    use std::sync::{Arc, Mutex};
     use super::{Line, Journal, Transaction, process_line, split_off_comment};
-    use crate::journal::{ParseJournalError, finalize_transaction};
+    use crate::journal::{DateOrder, ParseJournalError, ParseProblem, finalize_transaction};
+    use crate::types::daterange::{DateRange, parse_date};
+    use crate::types::interval::Interval;
     use crate::journal::types::LineAmount;
-    use crate::transaction::Entry;
+    use smallvec::smallvec;
+    use crate::transaction::{Entry, PostingKind};
     use crate::types::amount::{AmountType, Amount}; // TODO
+    use crate::types::commoditystyle::{CommodityStyle, CommodityStyles, Side, WithStyles};
 
     // Journal::from_lines()
 
@@ -270,11 +765,10 @@ expenses:food:hello-fresh           $82.99
     credit:visa                         $-82.98
 "#;
         assert_eq!(Journal::from_lines(journal.lines()),
-                   Err(ParseJournalError::EntryLineMustStartWithSpace));
+                   Err(ParseProblem { line: 3, error: ParseJournalError::EntryLineMustStartWithSpace }));
     }
 
     #[test]
-    #[should_panic]
     fn test_journal_from_lines_unbalanced() {
         let journal = 
 r#"
@@ -282,11 +776,25 @@ r#"
     expenses:food:hello-fresh           $82.99
     credit:visa                         $-82.98
 "#;
-        Journal::from_lines(journal.lines()).ok();
+        let problem = Journal::from_lines(journal.lines()).unwrap_err();
+        assert_eq!(problem.line, 2);
+        assert!(matches!(problem.error, ParseJournalError::UnbalancedTransaction(_)));
+    }
+
+    #[test]
+    fn test_journal_from_lines_overflow() {
+        let journal =
+r#"
+2023/03/17 Lottery
+    assets:savings           $60000000000000000
+    assets:savings           $60000000000000000
+    income:lottery
+"#;
+        assert_eq!(Journal::from_lines(journal.lines()),
+                   Err(ParseProblem { line: 2, error: ParseJournalError::Overflow }));
     }
 
     #[test]
-    #[should_panic]
     fn test_journal_from_lines_two_blanks() {
         let journal = 
 r#"
@@ -294,11 +802,11 @@ r#"
     expenses:food:hello-fresh
     credit:visa
 "#;
-        Journal::from_lines(journal.lines()).ok();
+        assert_eq!(Journal::from_lines(journal.lines()),
+                   Err(ParseProblem { line: 4, error: ParseJournalError::TwoBlankAmounts }));
     }
 
     #[test]
-    #[should_panic]
     fn test_journal_from_lines_amount_outside_transaction() {
         let journal = 
 r#"
@@ -308,7 +816,519 @@ r#"
     expenses:food:hello-fresh  $89.99
     credit:visa
 "#;
-        Journal::from_lines(journal.lines()).ok();
+        assert_eq!(Journal::from_lines(journal.lines()),
+                   Err(ParseProblem { line: 2, error: ParseJournalError::EntryOutsideTransaction }));
+    }
+
+    #[test]
+    fn test_journal_from_lines_unparseable_line() {
+        let journal = 
+r#"
+2023/03/17 HelloFresh
+    expenses:food  $$$
+    credit:visa
+"#;
+        assert_eq!(Journal::from_lines(journal.lines()).unwrap_err().to_string(),
+                   "line 3: Couldn't process this line: '    expenses:food  $$$'");
+    }
+
+
+    // Journal::from_lines_lenient()
+
+    #[test]
+    fn test_journal_from_lines_lenient_collects_all_problems() {
+        let journal = 
+r#"    expenses:food:hello-fresh  $89.99
+
+2023/03/17 HelloFresh
+    expenses:food:hello-fresh           $82.99
+    credit:visa                         $-82.98
+
+2023/03/18 Ham Sub
+    assets:savings  $-12.46
+    expenses:food:subway  $12.46
+
+2023/03/19 Groceries
+expenses:food  $40
+    credit:visa
+
+2023/03/20 Pizza
+    expenses:food
+    credit:visa
+"#;
+        let (journal, problems) = Journal::from_lines_lenient(journal.lines(), DateOrder::Sort, &CommodityStyles::default());
+
+        // only the good transaction makes it into the journal
+        assert_eq!(journal.transactions.len(), 1);
+        assert_eq!(journal.transactions[0].description, "Ham Sub");
+
+        let found: Vec<(usize, &ParseJournalError)> =
+            problems.iter()
+                    .map(|p| (p.line, &p.error))
+                    .collect();
+
+        assert_eq!(found.len(), 4);
+        assert_eq!(found[0], (1, &ParseJournalError::EntryOutsideTransaction));
+        assert!(matches!(found[1], (3, ParseJournalError::UnbalancedTransaction(_))));
+        assert_eq!(found[2], (12, &ParseJournalError::EntryLineMustStartWithSpace));
+        assert_eq!(found[3], (17, &ParseJournalError::TwoBlankAmounts));
+    }
+
+    // a log written to memory instead of stderr, so a test can read what was logged
+    #[derive(Clone, Default)]
+    struct LogBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for LogBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_journal_logging() {
+        let log    = LogBuffer::default();
+        let writer = log.clone();
+        let subscriber = tracing_subscriber::fmt()
+                             .with_max_level(tracing::Level::DEBUG)
+                             .with_writer(move || writer.clone())
+                             .with_target(false)
+                             .without_time()
+                             .finish();
+
+        let journal = "2023/03/19 Groceries\nexpenses:food  $40\n    credit:visa\n\n2023/03/20 Sub\n    expenses:food  $12\n    credit:visa\n";
+        tracing::subscriber::with_default(subscriber, || {
+            let (mut journal, _) = Journal::from_lines_lenient(journal.lines(), DateOrder::Sort, &CommodityStyles::default());
+            journal.restrict_to(&DateRange::new(parse_date("2023/03/21"), None));
+        });
+
+        let log = String::from_utf8(log.0.lock().unwrap().clone()).unwrap();
+        assert!(log.contains("skipping past a problem line=2"), "{}", log);
+        assert!(log.contains("restricted to the date range transactions=0 earlier=1"), "{}", log);
+    }
+
+    #[test]
+    fn test_journal_from_lines_lenient_no_problems() {
+        let journal = 
+r#"
+2023/03/17 HelloFresh
+    expenses:food:hello-fresh           $82.99
+    credit:visa
+"#;
+        let (journal, problems) = Journal::from_lines_lenient(journal.lines(), DateOrder::Sort, &CommodityStyles::default());
+        assert_eq!(journal.transactions.len(), 1);
+        assert!(problems.is_empty());
+    }
+
+    #[test]
+    fn test_journal_date_order() {
+        let journal =
+r#"2023/03/18 Groceries
+    expenses:food  $41.06
+    assets:savings
+
+~ monthly  budget
+    expenses:rent  $1450
+    assets:checking
+
+2023/03/02 Gas
+    expenses:auto  $30
+    assets:savings
+
+2023/03/20 Pizza
+    expenses:food  $20
+    assets:savings
+"#;
+        // sorted into place, keeping the journal's order otherwise
+        let sorted = Journal::from_lines(journal.lines()).unwrap();
+        let descriptions: Vec<&str> = sorted.transactions.iter().map(|t| t.description.as_str()).collect();
+        assert_eq!(descriptions, vec!["Gas", "Groceries", "Pizza"]);
+
+        let out_of_order = ParseJournalError::OutOfOrder(parse_date("2023/03/02").unwrap(), parse_date("2023/03/18").unwrap());
+        assert_eq!(Journal::from_lines_after(journal.lines(), DateOrder::Require, &CommodityStyles::default()),
+                   Err(ParseProblem { line: 9, error: out_of_order.clone() }));
+        assert_eq!(out_of_order.to_string(), "Transaction dated 2023/03/02 comes after one dated 2023/03/18");
+
+        let (lenient, problems) = Journal::from_lines_lenient(journal.lines(), DateOrder::Require, &CommodityStyles::default());
+        assert_eq!(lenient, sorted);
+        assert_eq!(problems, vec![ParseProblem { line: 9, error: out_of_order }]);
+    }
+
+    #[test]
+    fn test_journal_from_lines_periodic() {
+        let journal = 
+r#"
+~ monthly  budget
+    expenses:food        $400
+    expenses:rent       $1200
+    assets:checking
+
+2023/03/17 HelloFresh
+    expenses:food:hello-fresh           $82.99
+    credit:visa
+"#;
+        let journal = Journal::from_lines(journal.lines()).unwrap();
+        assert_eq!(journal.transactions.len(), 1);
+        assert_eq!(journal.periodic.len(), 1);
+
+        let periodic = &journal.periodic[0];
+        assert_eq!(periodic.interval, Interval::Monthly);
+        assert_eq!(periodic.transaction.description, "budget");
+        assert_eq!(periodic.transaction.entries.len(), 3);
+        assert_eq!(periodic.transaction.entries[2].amount.to_string(), "$-1600.00");
+    }
+
+    #[test]
+    fn test_journal_add_forecast() {
+        let journal =
+r#"
+~ monthly  budget
+    expenses:food        $400
+    assets:checking
+
+2023/03/17 HelloFresh
+    expenses:food:hello-fresh           $82.99
+    credit:visa
+
+2023/04/03 HelloFresh
+    expenses:food:hello-fresh           $41.50
+    credit:visa
+"#;
+        let mut journal = Journal::from_lines(journal.lines()).unwrap();
+        journal.add_forecast(parse_date("2023/03/18").unwrap(), parse_date("2023/05/02").unwrap());
+
+        let forecast: Vec<(String, bool)> = journal.transactions
+                                                   .iter()
+                                                   .map(|t| (t.date.to_string(), t.is_forecast()))
+                                                   .collect();
+        assert_eq!(forecast, vec![
+            ("2023-03-17".to_string(), false),
+            ("2023-04-01".to_string(), true),
+            ("2023-04-03".to_string(), false),
+            ("2023-05-01".to_string(), true),
+        ]);
+    }
+
+    #[test]
+    fn test_journal_occurrences() {
+        let journal =
+r#"
+~ yearly  insurance
+    expenses:insurance   $900
+    assets:checking
+
+~ monthly  rent
+    expenses:rent       $1000
+    assets:checking
+
+2023/11/03 HelloFresh
+    expenses:food:hello-fresh           $41.50
+    credit:visa
+"#;
+        let journal = Journal::from_lines(journal.lines()).unwrap();
+        let occurrences = journal.occurrences(parse_date("2023/11/04").unwrap(), parse_date("2024/03/01").unwrap());
+
+        let dated: Vec<(String, &str)> = occurrences.iter()
+                                                    .map(|t| (t.date.to_string(), t.description.as_str()))
+                                                    .collect();
+        assert_eq!(dated, vec![
+            ("2023-12-01".to_string(), "rent"),
+            ("2024-01-01".to_string(), "insurance"),
+            ("2024-01-01".to_string(), "rent"),
+            ("2024-02-01".to_string(), "rent"),
+        ]);
+        assert!(occurrences.iter().all(|t| !t.is_forecast()));
+    }
+
+    #[test]
+    fn test_journal_from_lines_lenient_unknown_interval() {
+        let journal = 
+r#"~ fortnightly
+    expenses:food        $400
+    assets:checking
+"#;
+        let (journal, problems) = Journal::from_lines_lenient(journal.lines(), DateOrder::Sort, &CommodityStyles::default());
+        assert!(journal.periodic.is_empty());
+        assert_eq!(problems, vec![ParseProblem { line: 1, error: ParseJournalError::UnknownInterval("fortnightly".to_string()) }]);
+    }
+
+    #[test]
+    fn test_journal_from_lines_prices() {
+        let journal = 
+r#"
+2023/03/17 HelloFresh
+    expenses:food:hello-fresh           $82.99
+    credit:visa
+P 2023/03/02 USD $1.36
+P 2023/03/01 USD $1.35
+"#;
+        let journal = Journal::from_lines(journal.lines()).unwrap();
+        assert_eq!(journal.transactions.len(), 1);
+        assert_eq!(journal.transactions[0].entries.len(), 2);
+        assert_eq!(journal.prices.len(), 2);
+        assert_eq!(journal.prices[0].styled(&journal.commodities).to_string(), "P 2023/03/01 USD $1.35");
+    }
+
+    #[test]
+    fn test_journal_from_lines_tags() {
+        let journal = 
+r#"
+2023/03/17 Ramen  ; trip:japan
+    expenses:food                       $22  ; reimbursable:
+    ; paid:cash, not a tag
+    assets:cash
+; top-level:comment
+
+2023/03/18 Coffee
+    expenses:food                        $4
+    assets:cash
+"#;
+        let journal = Journal::from_lines(journal.lines()).unwrap();
+        assert_eq!(journal.transactions[0].tags, vec![
+            ("trip".to_string(), "japan".to_string()),
+            ("reimbursable".to_string(), "".to_string()),
+            ("paid".to_string(), "cash".to_string()),
+        ]);
+        assert!(journal.transactions[0].has_tag("trip", Some("japan")));
+        assert!(journal.transactions[0].has_tag("reimbursable", None));
+        assert!(!journal.transactions[0].has_tag("trip", Some("peru")));
+        assert!(journal.transactions[1].tags.is_empty());
+    }
+
+    #[test]
+    fn test_journal_from_lines_lenient_bad_price() {
+        let (journal, problems) = Journal::from_lines_lenient("P 2023/03/01 USD".lines(), DateOrder::Sort, &CommodityStyles::default());
+        assert!(journal.prices.is_empty());
+        assert_eq!(problems, vec![ParseProblem { line: 1, error: ParseJournalError::BadPriceDirective("P 2023/03/01 USD".to_string()) }]);
+    }
+
+    #[test]
+    fn test_journal_from_lines_goals() {
+        let journal =
+r#"
+2023/03/17 Savings
+    assets:vacation-fund                 $500
+    assets:checking
+goal assets:vacation-fund $3000 by 2024/06
+goal assets:vacation-fund soon
+"#;
+        let (journal, problems) = Journal::from_lines_lenient(journal.lines(), DateOrder::Sort, &CommodityStyles::default());
+        assert_eq!(journal.transactions[0].entries.len(), 2);
+        assert_eq!(journal.goals.len(), 1);
+        assert_eq!(journal.goals[0].account.as_str(), "assets:vacation-fund");
+        assert_eq!(problems, vec![ParseProblem { line: 6, error: ParseJournalError::BadGoalDirective("goal assets:vacation-fund soon".to_string()) }]);
+    }
+
+    #[test]
+    fn test_journal_from_lines_commodity_styles() {
+        let journal =
+r#"
+commodity 1.000,00 EUR
+
+2023/03/01 Hydro meter
+    usage:power                        308 kWh
+    usage:meter                     -308.00kWh
+    expenses:travel                   12 EUR
+    assets:cash
+commodity kWh
+"#;
+        let (journal, problems) = Journal::from_lines_lenient(journal.lines(), DateOrder::Sort, &CommodityStyles::default());
+        let euros = CommodityStyle::parse("commodity 1.000,00 EUR").unwrap().unwrap().1;
+        let kwh   = CommodityStyle { side: Side::Right, spaced: true, precision: 2, number: None, tolerance: None };
+
+        assert_eq!(journal.commodities.get("EUR"), Some(&euros));
+        assert_eq!(journal.commodities.get("kWh"), Some(&kwh));
+        assert_eq!(problems, vec![ParseProblem { line: 9, error: ParseJournalError::BadCommodityDirective("commodity kWh".to_string()) }]);
+    }
+
+    #[test]
+    fn test_journal_from_lines_tolerance() {
+        let readings = "2023/03/01 Hydro meter\n    usage:day    0.1 kWh\n    usage:evening    0.2 kWh\n    usage:meter    -0.3 kWh\n\
+                        2023/04/01 Hydro meter\n    usage:day    0.33 kWh\n    usage:evening    0.33 kWh\n    usage:meter    -0.7 kWh\n";
+        let problem = Journal::from_lines(readings.lines()).unwrap_err();
+        assert_eq!(problem.line, 5);
+        assert!(matches!(problem.error, ParseJournalError::UnbalancedTransaction(_)));
+
+        let journal = format!("commodity 1000.0 kWh  tolerance 0.05\n{}", readings);
+        assert_eq!(Journal::from_lines(journal.lines()).unwrap().transactions.len(), 2);
+
+        let journal = format!("commodity 1000.0 kWh  tolerance 0.01\n{}", readings);
+        assert_eq!(Journal::from_lines(journal.lines()).unwrap_err().line, 6);
+    }
+
+    #[test]
+    fn test_journal_virtual_postings() {
+        let journal = "2023/04/01 Payroll\n    assets:checking  $2400\n    income:payroll  $-2400\n    (savings:goal)  $100\n    \
+                       [envelopes:food]  $400\n    [envelopes:available]\n";
+        let mut journal = Journal::from_lines(journal.lines()).unwrap();
+
+        // the real postings balance among themselves and so do the bracketed ones
+        let entries: Vec<String> = journal.transactions[0].entries.iter().map(|entry| entry.to_string()).collect();
+        assert_eq!(entries, vec!["assets:checking    $2400.00", "income:payroll    $-2400.00", "(savings:goal)    $100.00",
+                                 "[envelopes:food]    $400.00", "[envelopes:available]    $-400.00"]);
+
+        let unbalanced = "2023/04/01 Allocation\n    [envelopes:food]  $400\n    assets:checking  $-400\n";
+        assert!(matches!(Journal::from_lines(unbalanced.lines()).unwrap_err().error, ParseJournalError::UnbalancedTransaction(_)));
+
+        journal.drop_virtual();
+        assert!(journal.transactions[0].entries.iter().all(|entry| entry.kind == PostingKind::Real));
+        assert_eq!(journal.transactions[0].entries.len(), 2);
+    }
+
+    #[test]
+    fn test_journal_from_lines_after() {
+        let readings = "2023/04/01 Hydro meter\n    usage:day    0.33 kWh\n    usage:evening    0.33 kWh\n    usage:meter    -0.7 kWh\n";
+        let first    = Journal::from_lines("commodity 1000.0 kWh  tolerance 0.05\n".lines()).unwrap();
+
+        // the directive from the journal read first still applies to the next one
+        let next = Journal::from_lines_after(readings.lines(), DateOrder::Sort, &first.commodities).unwrap();
+        assert_eq!(next.transactions.len(), 1);
+        assert_eq!(next.commodities.get("kWh"), first.commodities.get("kWh"));
+
+        let (lenient, problems) = Journal::from_lines_lenient(readings.lines(), DateOrder::Sort, &first.commodities);
+        assert_eq!((lenient.transactions.len(), problems), (1, vec![]));
+        assert!(Journal::from_lines_after(readings.lines(), DateOrder::Sort, &CommodityStyles::default()).is_err());
+    }
+
+    #[test]
+    fn test_restrict_to() {
+        let journal = 
+r#"
+2023/02/28 Ham Sub
+    expenses:food:subway  $10.84
+    assets:savings
+
+2023/03/01 HelloFresh
+    expenses:food:hello-fresh           $82.99
+    credit:visa
+
+2023/04/01 HelloFresh
+    expenses:food:hello-fresh           $82.99
+    credit:visa
+"#;
+        let mut journal = Journal::from_lines(journal.lines()).unwrap();
+        journal.restrict_to(&DateRange::new(parse_date("2023/03"), parse_date("2023/04")));
+
+        assert_eq!(journal.transactions.len(), 1);
+        assert_eq!(journal.transactions[0].date, parse_date("2023/03/01").unwrap());
+        assert_eq!(journal.earlier.len(), 1);
+        assert_eq!(journal.earlier[0].date, parse_date("2023/02/28").unwrap());
+    }
+
+    #[test]
+    fn test_journal_iterators() {
+        let journal = 
+r#"
+2023/02/28 Ham Sub
+    expenses:food:subway  $10.84
+    assets:savings
+
+2023/03/01 HelloFresh
+    expenses:food:hello-fresh           $82.99
+    credit:visa
+
+2023/04/01 Rent
+    expenses:rent                     $1200
+    assets:checking
+"#;
+        let journal = Journal::from_lines(journal.lines()).unwrap();
+        let descriptions = |ts: Vec<&Transaction>| ts.iter().map(|t| t.description.clone()).collect::<Vec<_>>();
+
+        let march = DateRange::new(parse_date("2023/03"), parse_date("2023/04"));
+        assert_eq!(descriptions(journal.transactions_in(&march).collect()), vec!["HelloFresh"]);
+        assert_eq!(journal.transactions_in(&DateRange::new(parse_date("2023/03"), None)).len(), 2);
+        assert_eq!(journal.transactions_in(&DateRange::new(parse_date("2023/05"), parse_date("2023/01"))).len(), 0);
+
+        let food: Vec<(&str, &str)> = journal.postings_for("FOOD")
+                                             .unwrap()
+                                             .map(|posting| (posting.description(), posting.account().as_str()))
+                                             .collect();
+        assert_eq!(food, vec![("Ham Sub", "expenses:food:subway"), ("HelloFresh", "expenses:food:hello-fresh")]);
+        assert!(journal.postings_for("(").is_err());
+
+        let query = "assets desc:rent".parse().unwrap();
+        assert_eq!(descriptions(journal.filter(&query).collect()), vec!["Rent"]);
+    }
+
+    #[test]
+    fn test_merge_and_append() {
+        let first  = "commodity 1000.0 kWh\n\
+                      2023/03/01 HelloFresh\n    expenses:food  $82.99\n    credit:visa\n\
+                      2023/04/01 Rent\n    expenses:rent  $1200\n    assets:checking";
+        let second = "commodity 1000 kWh\n\
+                      2023/03/15 Hydro\n    expenses:utilities  $45.00\n    assets:checking\n\
+                      P 2023/03/15 EUR $1.08";
+
+        let mut journal = Journal::from_lines(first.lines()).unwrap();
+        journal.merge(Journal::from_lines(second.lines()).unwrap());
+
+        let descriptions = |journal: &Journal| journal.transactions.iter().map(|t| t.description.clone()).collect::<Vec<_>>();
+        assert_eq!(descriptions(&journal), vec!["HelloFresh", "Hydro", "Rent"]);
+        assert_eq!(journal.prices.len(), 1);
+        assert_eq!(journal.commodities.get("kWh").unwrap().precision, 0);
+
+        let coffee = Transaction::builder().date(parse_date("2023/03/15").unwrap())
+                                           .description("Coffee")
+                                           .build()
+                                           .unwrap();
+        journal.append(coffee);
+        assert_eq!(descriptions(&journal), vec!["HelloFresh", "Hydro", "Coffee", "Rent"]);
+    }
+
+    #[test]
+    fn test_summary() {
+        let journal = "2022/12/30 Hydro\n    usage:power  308 kWh\n    usage:meter\n\
+                       2023/02/01 Rent\n    expenses:rent  $1200\n    assets:checking";
+        let summary = Journal::from_lines(journal.lines()).unwrap().summary();
+
+        assert_eq!((summary.transactions, summary.postings), (2, 4));
+        assert_eq!(summary.first_date, parse_date("2022/12/30"));
+        assert_eq!(summary.accounts.iter().map(|a| a.as_str()).collect::<Vec<_>>(),
+                   vec!["assets:checking", "expenses:rent", "usage:meter", "usage:power"]);
+        assert_eq!(summary.commodities.into_iter().collect::<Vec<_>>(), vec!["$", "kWh"]);
+        assert_eq!(summary.by_month.iter().map(|(month, count)| format!("{} {}", month, count)).collect::<Vec<_>>(),
+                   vec!["2022-12 1", "2023-01 0", "2023-02 1"]);
+
+        assert!(Journal::default().summary().by_month.is_empty());
+    }
+
+    #[test]
+    fn test_postings() {
+        let journal = "2023/02/01 Rent\n    expenses:rent  $1200\n    assets:checking\n\
+                       2023/02/03 Hydro\n    usage:power  308 kWh\n    usage:meter";
+        let journal = Journal::from_lines(journal.lines()).unwrap();
+
+        let postings: Vec<String> = journal.postings()
+                                           .map(|(date, t, e)| format!("{} {} {}", date.format("%d"), t.description, e.account))
+                                           .collect();
+        assert_eq!(postings, vec!["01 Rent expenses:rent", "01 Rent assets:checking",
+                                  "03 Hydro usage:power", "03 Hydro usage:meter"]);
+    }
+
+    #[test]
+    fn test_names_are_interned() {
+        let journal = "2023/02/01 Rent\n    expenses:rent  $1200\n    assets:checking\n\
+                       2023/02/03 Rent\n    expenses:rent  $1200\n    assets:checking";
+        let journal = Journal::from_lines(journal.lines()).unwrap();
+
+        let (first, second) = (&journal.transactions[0].entries[0], &journal.transactions[1].entries[0]);
+        assert!(std::ptr::eq(first.account.as_str(), second.account.as_str()));
+        assert!(std::ptr::eq(first.amount.units.as_str(), second.amount.units.as_str()));
+    }
+
+    #[test]
+    fn test_merged_names_share_symbols() {
+        let mut journal = Journal::from_lines("2023/02/01 Rent\n    expenses:rent  $1200\n    assets:checking".lines()).unwrap();
+        let next        = Journal::from_lines("2023/03/01 Rent\n    expenses:rent  $1200\n    assets:checking".lines()).unwrap();
+        journal.merge(next);
+
+        // read separately, the two files still name the account with the same symbol
+        let (first, second) = (&journal.transactions[0].entries[0], &journal.transactions[1].entries[0]);
+        assert_eq!(first.account, second.account);
+        assert!(std::ptr::eq(first.account.as_str(), second.account.as_str()));
     }
 
     #[test]
@@ -327,58 +1347,70 @@ r#"
     // process_line()
 
     #[test]
-    #[should_panic(expected = "Can't have a debit/credit outside a transaction")]
-    fn test_process_line_panic_no_transaction() {
+    fn test_process_line_no_transaction() {
         let line = Line {
-            account: "TestAccount".to_string(),
+            account: "TestAccount".into(),
             amount: LineAmount::Blank,
+            cost: None,
+            lot : None,
+            kind: PostingKind::Real,
         };
         let mut transaction: Option<Transaction> = None;
         let mut blank: Option<Line> = None;
 
-        process_line(line, &mut transaction, &mut blank)
+        assert_eq!(process_line(line, &mut transaction, &mut blank),
+                   Err(ParseJournalError::EntryOutsideTransaction));
     }
 
     #[test]
-    #[should_panic(expected = "Two blank amounts in one transaction")]
-    fn test_process_line_panic_two_blank_amounts() {
+    fn test_process_line_two_blank_amounts() {
         let line = Line {
-            account: "TestAccount".to_string(),
+            account: "TestAccount".into(),
             amount: LineAmount::Blank,
+            cost: None,
+            lot : None,
+            kind: PostingKind::Real,
         };
         let mut transaction = Some(Transaction::default());
         // clone the blank transaction line so we have two blank transactions
         let mut blank = Some(line.clone());
 
-        process_line(line, &mut transaction, &mut blank)
+        assert_eq!(process_line(line, &mut transaction, &mut blank),
+                   Err(ParseJournalError::TwoBlankAmounts));
     }
 
     #[test]
     fn test_process_line_blank_amount() {
         let line = Line {
-            account: "TestAccount".to_string(),
+            account: "TestAccount".into(),
             amount: LineAmount::Blank,
+            cost: None,
+            lot : None,
+            kind: PostingKind::Real,
         };
         let mut transaction = Some(Transaction::default());
         let mut blank: Option<Line> = None;
 
-        process_line(line.clone(), &mut transaction, &mut blank);
+        process_line(line.clone(), &mut transaction, &mut blank).unwrap();
         assert_eq!(blank.unwrap().account, line.account);
     }
 
     #[test]
     fn test_process_line_regular_amount() {
         let line = Line {
-            account: "TestAccount".to_string(),
+            account: "TestAccount".into(),
             amount: LineAmount::Amount(Amount {
                 amount: AmountType::Discrete(125, 2),
-                units: "$".to_owned()
-            }) // $1.25
+                units: "$".into()
+            }), // $1.25
+            cost: None,
+            lot : None,
+            kind: PostingKind::Real,
         };
         let mut transaction = Some(Transaction::default());
         let mut blank: Option<Line> = None;
 
-        process_line(line.clone(), &mut transaction, &mut blank);
+        process_line(line.clone(), &mut transaction, &mut blank).unwrap();
 
         let entry = transaction.unwrap().entries.pop().unwrap();
         assert_eq!(entry.account, line.account);
@@ -390,24 +1422,33 @@ r#"
     #[test]
     fn test_move_transaction_blank_line() {
         let line = Line {
-            account: "TestAccount".to_string(),
+            account: "TestAccount".into(),
             amount: LineAmount::Blank,
+            cost: None,
+            lot : None,
+            kind: PostingKind::Real,
         };
         let mut transaction = Some(Transaction {
-            entries: vec![
+            entries: smallvec![
                 Entry {
-                    account: "Account1".to_string(),
+                    account: "Account1".into(),
                     amount: Amount {
                         amount: AmountType::Discrete(100, 2),
-                        units: "$".to_owned()
-                    }
+                        units: "$".into()
+                    },
+                    cost: None,
+                    lot : None,
+                    kind: PostingKind::Real,
                 },
                 Entry {
-                    account: "Account2".to_string(),
+                    account: "Account2".into(),
                     amount: Amount {
                         amount: AmountType::Discrete(-200, 2),
-                        units: "$".to_owned()
-                    }
+                        units: "$".into()
+                    },
+                    cost: None,
+                    lot : None,
+                    kind: PostingKind::Real,
                 },
             ],
             ..Default::default()
@@ -415,7 +1456,7 @@ r#"
         let mut blank = Some(line);
         let mut journal: Vec<Transaction> = Vec::new();
 
-        finalize_transaction(&mut transaction, &mut blank, &mut journal);
+        finalize_transaction(&mut transaction, &mut blank, &mut journal, &CommodityStyles::default()).unwrap();
 
         assert_eq!(journal.len(), 1);
         let journal_entry = &journal[0];
@@ -428,20 +1469,26 @@ r#"
     #[test]
     fn test_move_transaction_no_blank_line() {
         let mut transaction = Some(Transaction {
-            entries: vec![
+            entries: smallvec![
                 Entry {
-                    account: "Account1".to_string(),
+                    account: "Account1".into(),
                     amount: Amount {
                         amount: AmountType::Discrete(100, 2),
-                        units: "$".to_owned()
-                    }
+                        units: "$".into()
+                    },
+                    cost: None,
+                    lot : None,
+                    kind: PostingKind::Real,
                 },
                 Entry {
-                    account: "Account2".to_string(),
+                    account: "Account2".into(),
                     amount: Amount {
                         amount: AmountType::Discrete(-100, 2),
-                        units: "$".to_owned()
-                    }
+                        units: "$".into()
+                    },
+                    cost: None,
+                    lot : None,
+                    kind: PostingKind::Real,
                 },
             ],
             ..Default::default()
@@ -449,7 +1496,7 @@ r#"
         let mut blank: Option<Line> = None;
         let mut journal: Vec<Transaction> = Vec::new();
 
-        finalize_transaction(&mut transaction, &mut blank, &mut journal);
+        finalize_transaction(&mut transaction, &mut blank, &mut journal, &CommodityStyles::default()).unwrap();
 
         assert_eq!(journal.len(), 1);
         let journal_entry = &journal[0];
@@ -458,23 +1505,28 @@ r#"
     }
 
     #[test]
-    #[should_panic(expected = "Unbalanced transaction: 1970-01-01 Description\n    Account1    $1.00\n    Account2    $-2.00")]
     fn test_move_transaction_unbalanced_transaction() {
         let mut transaction = Some(Transaction {
-            entries: vec![
+            entries: smallvec![
                 Entry {
-                    account: "Account1".to_string(),
+                    account: "Account1".into(),
                     amount: Amount {
                         amount: AmountType::Discrete(100, 2),
-                        units: "$".to_owned()
-                    }
+                        units: "$".into()
+                    },
+                    cost: None,
+                    lot : None,
+                    kind: PostingKind::Real,
                 },
                 Entry {
-                    account: "Account2".to_string(),
+                    account: "Account2".into(),
                     amount: Amount {
                         amount: AmountType::Discrete(-200, 2),
-                        units: "$".to_owned()
-                    }
+                        units: "$".into()
+                    },
+                    cost: None,
+                    lot : None,
+                    kind: PostingKind::Real,
                 },
             ],
             description: "Description".to_string(),
@@ -483,7 +1535,49 @@ r#"
         let mut blank: Option<Line> = None;
         let mut journal: Vec<Transaction> = Vec::new();
 
-        finalize_transaction(&mut transaction, &mut blank, &mut journal)
+        let error = finalize_transaction(&mut transaction, &mut blank, &mut journal, &CommodityStyles::default()).unwrap_err();
+
+        assert!(error.to_string().starts_with("Unbalanced transaction: 1970-01-01 Description\n    Account1    $1.00\n    Account2    $-2.00"));
+        assert!(journal.is_empty());
+    }
+
+    #[test]
+    fn test_journal_entries_inline() {
+        let lines = "2023/03/18 Groceries\n    expenses:food  $41.06\n    assets:savings\n\n\
+                     2023/03/19 Split\n    expenses:food  $10\n    expenses:fun  $10\n    expenses:gifts  $10\n    \
+                     expenses:rent  $10\n    assets:savings\n";
+        let journal = Journal::from_lines(lines.lines()).unwrap();
+
+        // up to four entries stay inside the transaction, a fifth moves them all to the heap
+        let groceries = &journal.transactions[0];
+        assert_eq!(groceries.entries.len(), 2);
+        assert!(!groceries.entries.spilled());
+
+        let split = &journal.transactions[1];
+        assert!(split.entries.spilled());
+        assert_eq!(split.entries.iter().map(|entry| entry.account.as_str()).collect::<Vec<_>>(),
+                   vec!["expenses:food", "expenses:fun", "expenses:gifts", "expenses:rent", "assets:savings"]);
+        assert_eq!(split.entries[4].amount, Amount::from("$", -40.0));
+    }
+
+    // serde
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_journal_serde_round_trip() {
+        let lines = "P 2023/03/01 USD $1.35\n\
+                     ~ monthly  budget\n    expenses:food  $400\n    assets:checking\n\
+                     2023/03/18 Groceries  ; trip:japan\n    expenses:food  $41.06\n    assets:savings";
+        let journal = Journal::from_lines(lines.lines()).unwrap();
+
+        let json = serde_json::to_string(&journal).unwrap();
+        assert!(json.contains(r#""date":"2023-03-18""#));
+        assert!(json.contains(r#""amount":{"Discrete":[4106,2]}"#));
+        assert!(json.contains(r#""entries":[{"account":"expenses:food""#));
+        assert_eq!(serde_json::from_str::<Journal>(&json).unwrap(), journal);
+
+        let month = crate::types::monthyear::MonthYear::new(3, 2023);
+        assert_eq!(serde_json::to_string(&month).unwrap(), r#"{"month":3,"year":2023}"#);
     }
 
     /*  Green light, code affirmed
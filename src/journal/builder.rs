@@ -0,0 +1,90 @@
+//! Journal builder
+
+use crate::goal::Goal;
+use crate::journal::Journal;
+use crate::periodic::PeriodicTransaction;
+use crate::price::Price;
+use crate::transaction::Transaction;
+use crate::types::commoditystyle::CommodityStyles;
+
+
+/* JournalBuilder */
+
+// a journal put together in code instead of parsed, with its transactions and prices put
+// in date order the way the parser leaves them, so they can be added in any order
+//
+//   Journal::builder().transactions(recorded)
+//                     .transactions(imported)
+//                     .prices(prices)
+//                     .build()
+#[derive(Debug, Default)]
+pub struct JournalBuilder {
+    journal: Journal,
+}
+
+impl Journal {
+    pub fn builder() -> JournalBuilder {
+        JournalBuilder::default()
+    }
+}
+
+impl JournalBuilder {
+    pub fn transactions(mut self, transactions: impl IntoIterator<Item = Transaction>) -> Self {
+        self.journal.transactions.extend(transactions);
+        self
+    }
+
+    pub fn periodic_transactions(mut self, periodic: impl IntoIterator<Item = PeriodicTransaction>) -> Self {
+        self.journal.periodic.extend(periodic);
+        self
+    }
+
+    pub fn prices(mut self, prices: impl IntoIterator<Item = Price>) -> Self {
+        self.journal.prices.extend(prices);
+        self
+    }
+
+    pub fn goals(mut self, goals: impl IntoIterator<Item = Goal>) -> Self {
+        self.journal.goals.extend(goals);
+        self
+    }
+
+    pub fn commodities(mut self, commodities: CommodityStyles) -> Self {
+        self.journal.commodities.merge(commodities);
+        self
+    }
+
+    // merging into an empty journal is what puts everything in date order
+    pub fn build(self) -> Journal {
+        let mut journal = Journal::default();
+        journal.merge(self.journal);
+        journal
+    }
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use crate::journal::Journal;
+    use crate::transaction::Transaction;
+
+    fn transaction(day: u32, description: &str) -> Transaction {
+        Transaction::builder().date(NaiveDate::from_ymd_opt(2023, 3, day).unwrap())
+                              .description(description)
+                              .build()
+                              .unwrap()
+    }
+
+    #[test]
+    fn test_journal_builder_sorts_by_date() {
+        let journal = Journal::builder().transactions([transaction(18, "Groceries")])
+                                        .transactions([transaction(1, "Rent"), transaction(18, "Gas")])
+                                        .build();
+
+        let descriptions: Vec<&str> = journal.transactions.iter().map(|t| t.description.as_str()).collect();
+        assert_eq!(descriptions, vec!["Rent", "Groceries", "Gas"]);
+    }
+}
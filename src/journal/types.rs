@@ -1,11 +1,14 @@
-/// Journal types
+//! Journal types
 
 use lazy_static::lazy_static;
+use std::borrow::Cow;
 use std::str::FromStr;
 use regex::Regex;
 
 use crate::common::is_all_whitespace;
-use crate::types::{Account, amount::Amount, Units};
+use crate::transaction::PostingKind;
+use crate::types::{Account, Units, amount::Amount};
+use crate::types::commoditystyle::{CommodityStyle, Side};
 
 
 // the two types of input on the right side of an entry line
@@ -18,16 +21,21 @@ pub enum LineAmount {
 
 /* Line */
 
-// an account line from the journal text file, with an optional amount
+// an account line from the journal text file, with an optional amount and what the
+// amount's units cost altogether, if it says
 #[derive(Clone, Debug, PartialEq)]
 pub struct Line {
     pub account: Account,
-    pub amount : LineAmount
+    pub amount : LineAmount,
+    pub cost   : Option<Amount>,
+    pub lot    : Option<Amount>,
+    pub kind   : PostingKind,
 }
 
 #[derive(Debug, PartialEq)]
 enum ParsedLine {
-    AccountWithAmount(Account, Units, f64),
+    AccountWithAmount(Account, Amount),
+    AccountWithCost(Account, Amount, Amount, Option<Amount>),
     AccountOnly(Account),
     Invalid
 }
@@ -35,7 +43,7 @@ enum ParsedLine {
 lazy_static! {
     static ref ACCOUNT_AND_AMOUNT_REGEX: Regex =
         Regex::new(r"(?x)
-            (?P<account>[[:alnum:]:-]+)
+            (?P<account>[\p{L}\p{N}:-]+)
             (?:
                 \s\s+
                 (?P<units>[a-zA-Z\$]+)
@@ -49,17 +57,73 @@ lazy_static! {
             )
         ").unwrap();
 
+    // what follows an amount that cost something: "@ $150" for each unit, "@@ $1500" for
+    // all of them, or a lot price "{$150}" for each or "{{$1500}}" for all, which can come
+    // before a price with @ when units from the lot are sold
+    static ref COST_REGEX: Regex =
+        Regex::new(r"^\s*(?:\{\{(?P<lot_total>[^}]+)\}\}|\{(?P<lot>[^}]+)\})?\s*(?:(?P<at>@@?)\s*(?P<price>.+?))?\s*$").unwrap();
+
     static ref ACCOUNT_ONLY_REGEX: Regex = 
-        Regex::new(r"^\s*(?P<account>[[:alnum:]:-]+)\s*$").unwrap();
+        Regex::new(r"^\s*(?P<account>[\p{L}\p{N}:-]+)\s*$").unwrap();
+
+    static ref AMOUNT_ONLY_REGEX: Regex =
+        Regex::new(r"(?x)
+            ^\s*
+            (?:
+                (?P<units>[a-zA-Z\$]+)
+                \s*
+                (?P<amount>[-+]?\d*\.?\d+(?:[eE][-+]?\d+)?)
+              |
+                (?P<amount2>[-+]?\d*\.?\d+(?:[eE][-+]?\d+)?)
+                \s*
+                (?P<units2>[a-zA-Z\$]+)
+            )
+            \s*$
+        ").unwrap();
+}
+
+// an amount on its own, written the same way as on an entry line: "$1.35" or "308 kWh"
+pub fn parse_amount(input: &str) -> Option<Amount> {
+    let captures = AMOUNT_ONLY_REGEX.captures(input)?;
+    let units = captures.name("units").or_else(|| captures.name("units2"))?.as_str().to_string();
+    let amount = captures.name("amount").or_else(|| captures.name("amount2"))?.as_str();
+    Amount::parse(units, amount)
+}
+
+// how the amount on an entry line is written, for its commodity's style: "$-6.76" has
+// its symbol on the left and two decimal places, "308 kWh" has it on the right
+pub fn written_style(input: &str) -> Option<(Units, CommodityStyle)> {
+    let captures = ACCOUNT_AND_AMOUNT_REGEX.captures(input)?;
+    let (side, units, amount) = match captures.name("units") {
+        Some(units) => (Side::Left, units, captures.name("amount")?),
+        None        => (Side::Right, captures.name("units2")?, captures.name("amount2")?),
+    };
+    let spaced = match side {
+        Side::Left  => units.end() < amount.start(),
+        Side::Right => amount.end() < units.start(),
+    };
+
+    // the decimal places written, not counting any exponent
+    let mantissa  = amount.as_str().split(['e', 'E']).next()?;
+    let precision = mantissa.split_once('.').map_or(0, |(_, fraction)| fraction.len());
+
+    Some((units.as_str().into(), CommodityStyle { side, spaced, precision: precision as u32, number: None, tolerance: None }))
 }
 
 fn parse_account_and_amount(input: &str) -> ParsedLine {
     if let Some(captures) = ACCOUNT_AND_AMOUNT_REGEX.captures(input) {
-        let account = captures.name("account").unwrap().as_str().to_string();
+        let account = captures.name("account").unwrap().as_str().into();
         let units = captures.name("units").or_else(|| captures.name("units2")).unwrap().as_str().to_string();
         let amount_str = captures.name("amount").or_else(|| captures.name("amount2")).unwrap().as_str();
-        let amount = f64::from_str(amount_str).unwrap();
-        ParsedLine::AccountWithAmount(account, units, amount)
+        let rest = &input[captures.get(0).unwrap().end()..];
+        match (Amount::parse(units, amount_str), rest.trim_start().starts_with(['@', '{'])) {
+            (Some(amount), false) => ParsedLine::AccountWithAmount(account, amount),
+            (Some(amount), true)  => match parse_cost(&amount, rest) {
+                Some((cost, lot)) => ParsedLine::AccountWithCost(account, amount, cost, lot),
+                None              => ParsedLine::Invalid,
+            },
+            (None, _) => ParsedLine::Invalid,
+        }
     } else if let Some(account) = parse_account_only(input) {
         ParsedLine::AccountOnly(account)
     } else {
@@ -67,9 +131,50 @@ fn parse_account_and_amount(input: &str) -> ParsedLine {
     }
 }
 
-fn parse_account_only(input: &str) -> Option<String> {
+// the total cost of the amount from what's written after it, as a positive amount. with
+// both a lot price and an @ price, the cost is the @ price, what the units sold for, and
+// the lot's total cost comes along with it for what they were bought for
+fn parse_cost(amount: &Amount, input: &str) -> Option<(Amount, Option<Amount>)> {
+    let captures = COST_REGEX.captures(input)?;
+    let quantity = amount.decimal().abs();
+
+    let lot = match (captures.name("lot_total"), captures.name("lot")) {
+        (Some(total), _) => Some(parse_amount(total.as_str())?),
+        (_, Some(each))  => Some(parse_amount(each.as_str())?.checked_scale(quantity).ok()?),
+        _                => None,
+    };
+    let price = match (captures.name("at").map(|at| at.as_str()), captures.name("price")) {
+        (Some("@@"), Some(total)) => Some(parse_amount(total.as_str())?),
+        (Some(_),    Some(each))  => Some(parse_amount(each.as_str())?.checked_scale(quantity).ok()?),
+        _                         => None,
+    };
+
+    match (price, lot) {
+        (Some(price), lot) => Some((price, lot)),
+        (None, Some(lot))  => Some((lot, None)),
+        (None, None)       => None,
+    }
+}
+
+// the kind of posting from how its account is written, (budget:food) or [budget:food], and
+// the line with the account's parentheses or brackets taken off
+fn posting_kind(line: &str) -> (PostingKind, Cow<'_, str>) {
+    let line = line.trim_start();
+    let (kind, close) = match line.chars().next() {
+        Some('(') => (PostingKind::Virtual, ')'),
+        Some('[') => (PostingKind::BalancedVirtual, ']'),
+        _         => return (PostingKind::Real, Cow::Borrowed(line)),
+    };
+
+    match line.find(close) {
+        Some(end) => (kind, Cow::Owned(format!("{}{}", &line[1..end], &line[end + 1..]))),
+        None      => (PostingKind::Real, Cow::Borrowed(line)),
+    }
+}
+
+fn parse_account_only(input: &str) -> Option<Account> {
     if let Some(captures) = ACCOUNT_ONLY_REGEX.captures(input) {
-        let account = captures.name("account").unwrap().as_str().to_string();
+        let account = captures.name("account").unwrap().as_str().into();
         Some(account)
     } else {
         None
@@ -84,17 +189,33 @@ impl FromStr for Line {
         if is_all_whitespace(line) {
             return Err(LineParseError::MissingAccount)
         }
-        match parse_account_and_amount(line) {
-            ParsedLine::AccountWithAmount(account, units, amount) => {
+        let (kind, line) = posting_kind(line);
+        match parse_account_and_amount(&line) {
+            ParsedLine::AccountWithAmount(account, amount) => {
+                Ok(Line {
+                    account,
+                    amount: LineAmount::Amount(amount),
+                    cost  : None,
+                    lot   : None,
+                    kind,
+                })
+            },
+            ParsedLine::AccountWithCost(account, amount, cost, lot) => {
                 Ok(Line {
                     account,
-                    amount: LineAmount::Amount(Amount::from(units, amount))
+                    amount: LineAmount::Amount(amount),
+                    cost  : Some(cost),
+                    lot,
+                    kind,
                 })
             },
             ParsedLine::AccountOnly(account) => {
                 Ok(Line {
                     account,
-                    amount: LineAmount::Blank
+                    amount: LineAmount::Blank,
+                    cost  : None,
+                    lot   : None,
+                    kind,
                 })
             },
             ParsedLine::Invalid => Err(LineParseError::Unknown),
@@ -102,9 +223,11 @@ impl FromStr for Line {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, thiserror::Error)]
 pub enum LineParseError {
+    #[error("An entry needs an account")]
     MissingAccount,
+    #[error("Couldn't read an account and amount from the entry")]
     Unknown,
 }
 
@@ -114,7 +237,8 @@ pub enum LineParseError {
 #[cfg(test)]
 mod tests {
     use crate::types::amount::{Amount, AmountType};
-    use crate::journal::types::{parse_account_and_amount, ParsedLine, LineParseError};
+    use crate::journal::types::{parse_account_and_amount, parse_amount, ParsedLine, LineParseError};
+    use crate::transaction::PostingKind;
     use super::{LineAmount, FromStr, Line};
 
     #[test]
@@ -125,50 +249,71 @@ mod tests {
 
         // blank amount
         assert_eq!(Line::from_str("acct:sub-acct"),
-                   Ok(Line { account: "acct:sub-acct".to_owned(),
-                             amount : LineAmount::Blank
+                   Ok(Line { account: "acct:sub-acct".into(),
+                             amount : LineAmount::Blank,
+                             cost   : None,
+                             lot    : None,
+                             kind   : PostingKind::Real,
                            }));
 
         assert_eq!(Line::from_str("acct:sub-acct "),
-                   Ok(Line { account: "acct:sub-acct".to_owned(),
-                             amount : LineAmount::Blank
+                   Ok(Line { account: "acct:sub-acct".into(),
+                             amount : LineAmount::Blank,
+                             cost   : None,
+                             lot    : None,
+                             kind   : PostingKind::Real,
                            }));
 
         assert_eq!(Line::from_str("acct:sub-acct             "),
-                   Ok(Line { account: "acct:sub-acct".to_owned(),
-                             amount : LineAmount::Blank
+                   Ok(Line { account: "acct:sub-acct".into(),
+                             amount : LineAmount::Blank,
+                             cost   : None,
+                             lot    : None,
+                             kind   : PostingKind::Real,
                            }));
 
         // an actual amount in dollars/cents
         assert_eq!(Line::from_str("expenses:food:tim-hortons  $-1.25"),
-                   Ok(Line { account: "expenses:food:tim-hortons".to_owned(),
+                   Ok(Line { account: "expenses:food:tim-hortons".into(),
                              amount : LineAmount::Amount(Amount {
-                                    units : "$".to_owned(),
+                                    units : "$".into(),
                                     amount: AmountType::Discrete(-125, 2)
-                            })}));
+                            }),
+                             cost   : None,
+                             lot    : None,
+                             kind   : PostingKind::Real,
+                           }));
 
         // multiple whitespace between the two sides
         assert_eq!(Line::from_str("expenses:food:tim-hortons  \t  $-1.25"),
-                   Ok(Line { account: "expenses:food:tim-hortons".to_owned(),
+                   Ok(Line { account: "expenses:food:tim-hortons".into(),
                              amount : LineAmount::Amount(Amount {
-                                    units : "$".to_owned(),
+                                    units : "$".into(),
                                     amount: AmountType::Discrete(-125, 2)
-                            })}));
+                            }),
+                             cost   : None,
+                             lot    : None,
+                             kind   : PostingKind::Real,
+                           }));
         
 
         assert_eq!(Line::from_str("usage:power  \t  308 kWh"),
-                   Ok(Line { account: "usage:power".to_owned(),
+                   Ok(Line { account: "usage:power".into(),
                              amount : LineAmount::Amount(Amount {
-                                    units:  "kWh".to_owned(),
-                                    amount: AmountType::Float(308.0)
-                            })}));
+                                    units:  "kWh".into(),
+                                    amount: AmountType::Decimal(308.into())
+                            }),
+                             cost   : None,
+                             lot    : None,
+                             kind   : PostingKind::Real,
+                           }));
     }
 
     #[test]
     fn test_parse_account_amount() {
         let input = "acc123  100.5USD";
         let result = parse_account_and_amount(input);
-        assert_eq!(result, ParsedLine::AccountWithAmount("acc123".to_owned(), "USD".to_owned(), 100.5));
+        assert_eq!(result, ParsedLine::AccountWithAmount("acc123".into(), Amount::from("USD".to_owned(), 100.5)));
     }
 
     #[test]
@@ -182,35 +327,60 @@ mod tests {
     fn test_parse_account_amount_dollar_sign_right() {
         let input = "acc123  100.5$";
         let result = parse_account_and_amount(input);
-        assert_eq!(result, ParsedLine::AccountWithAmount("acc123".to_owned(), "$".to_owned(), 100.5));
+        assert_eq!(result, ParsedLine::AccountWithAmount("acc123".into(), Amount::from("$".to_owned(), 100.5)));
     }
 
     #[test]
     fn test_parse_account_amount_dollar_sign_left() {
         let input = "acc123  $100.5";
         let result = parse_account_and_amount(input);
-        assert_eq!(result, ParsedLine::AccountWithAmount("acc123".to_owned(), "$".to_owned(), 100.5));
+        assert_eq!(result, ParsedLine::AccountWithAmount("acc123".into(), Amount::from("$".to_owned(), 100.5)));
     }
 
     #[test]
     fn test_parse_account_amount_dollar_sign_left_with_space() {
         let input = "acc123  $ 100.5";
         let result = parse_account_and_amount(input);
-        assert_eq!(result, ParsedLine::AccountWithAmount("acc123".to_owned(), "$".to_owned(), 100.5));
+        assert_eq!(result, ParsedLine::AccountWithAmount("acc123".into(), Amount::from("$".to_owned(), 100.5)));
     }
 
     #[test]
     fn test_parse_account_amount_kwh() {
         let input = "usage:power  308 kWh";
         let result = parse_account_and_amount(input);
-        assert_eq!(result, ParsedLine::AccountWithAmount("usage:power".to_owned(), "kWh".to_owned(), 308.0));
+        assert_eq!(result, ParsedLine::AccountWithAmount("usage:power".into(), Amount::from("kWh".to_owned(), 308.0)));
     }
 
     #[test]
     fn test_parse_account_amount_kwh_hyphen() {
         let input = "usage-power  kWh308";
         let result = parse_account_and_amount(input);
-        assert_eq!(result, ParsedLine::AccountWithAmount("usage-power".to_owned(), "kWh".to_owned(), 308.0));
+        assert_eq!(result, ParsedLine::AccountWithAmount("usage-power".into(), Amount::from("kWh".to_owned(), 308.0)));
     }
 
+    #[test]
+    fn test_parse_account_amount_cost() {
+        let bought = ParsedLine::AccountWithCost("assets:brokerage".into(), Amount::from("AAPL".to_owned(), 10.0), Amount::from("$".to_owned(), 1500.0), None);
+        assert_eq!(parse_account_and_amount("assets:brokerage  10 AAPL @ $150"), bought);
+        assert_eq!(parse_account_and_amount("assets:brokerage  10 AAPL @@ $1500"), bought);
+        assert_eq!(parse_account_and_amount("assets:brokerage  10 AAPL {$150}"), bought);
+        assert_eq!(parse_account_and_amount("assets:brokerage  10 AAPL {{$1500}}"), bought);
+
+        // a sale's price is what it counts for, and the lot's cost is kept alongside it
+        let sold = ParsedLine::AccountWithCost("assets:brokerage".into(), Amount::from("AAPL".to_owned(), -5.0), Amount::from("$".to_owned(), 850.0), Some(Amount::from("$".to_owned(), 750.0)));
+        assert_eq!(parse_account_and_amount("assets:brokerage  -5 AAPL {$150} @ $170"), sold);
+        assert_eq!(parse_account_and_amount("assets:brokerage  -5 AAPL {{$750}} @@ $850"), sold);
+
+        assert_eq!(parse_account_and_amount("assets:brokerage  10 AAPL @"), ParsedLine::Invalid);
+        assert_eq!(parse_account_and_amount("assets:brokerage  10 AAPL {lots}"), ParsedLine::Invalid);
+    }
+
+    #[test]
+    fn test_parse_amount() {
+        assert_eq!(parse_amount("$1.35"), Some(Amount::from("$".to_owned(), 1.35)));
+        assert_eq!(parse_amount(" 308 kWh "), Some(Amount::from("kWh".to_owned(), 308.0)));
+        assert_eq!(parse_amount("CAD-2"), Some(Amount::from("CAD".to_owned(), -2.0)));
+        assert_eq!(parse_amount("1.35"), None);
+        assert_eq!(parse_amount("$1.35 extra"), None);
+    }
 }
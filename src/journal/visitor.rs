@@ -0,0 +1,109 @@
+//! Journal visitor
+
+use crate::journal::Journal;
+use crate::periodic::PeriodicTransaction;
+use crate::price::Price;
+use crate::transaction::{Entry, Transaction};
+
+
+/* JournalVisitor */
+
+// something that goes through a journal piece by piece, like a lint or a tally, without
+// writing its own loops over each part of it. every callback does nothing unless it's
+// overridden, so a visitor only needs the ones it cares about
+//
+//   struct Payees(BTreeSet<String>);
+//
+//   impl JournalVisitor for Payees {
+//       fn visit_transaction(&mut self, transaction: &Transaction) {
+//           self.0.insert(transaction.description.clone());
+//       }
+//   }
+//
+//   journal.walk(&mut payees);
+pub trait JournalVisitor {
+    fn visit_transaction(&mut self, _transaction: &Transaction) {}
+
+    // each entry of the transaction, right after the transaction itself
+    fn visit_posting(&mut self, _transaction: &Transaction, _entry: &Entry) {}
+
+    fn visit_periodic(&mut self, _periodic: &PeriodicTransaction) {}
+
+    fn visit_price(&mut self, _price: &Price) {}
+}
+
+impl Journal {
+    // visit the transactions and their postings in date order, then the periodic
+    // transactions in the order they were written, then the prices in date order
+    pub fn walk(&self, visitor: &mut impl JournalVisitor) {
+        for transaction in &self.transactions {
+            visitor.visit_transaction(transaction);
+            for entry in &transaction.entries {
+                visitor.visit_posting(transaction, entry);
+            }
+        }
+
+        for periodic in &self.periodic {
+            visitor.visit_periodic(periodic);
+        }
+
+        for price in &self.prices {
+            visitor.visit_price(price);
+        }
+    }
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use crate::journal::Journal;
+    use crate::periodic::PeriodicTransaction;
+    use crate::price::Price;
+    use crate::transaction::{Entry, Transaction};
+    use super::JournalVisitor;
+
+    #[derive(Default)]
+    struct Trail(Vec<String>);
+
+    impl JournalVisitor for Trail {
+        fn visit_transaction(&mut self, transaction: &Transaction) {
+            self.0.push(format!("transaction {}", transaction.description));
+        }
+
+        fn visit_posting(&mut self, _transaction: &Transaction, entry: &Entry) {
+            self.0.push(format!("posting {}", entry.account));
+        }
+
+        fn visit_periodic(&mut self, periodic: &PeriodicTransaction) {
+            self.0.push(format!("periodic {}", periodic.interval));
+        }
+
+        fn visit_price(&mut self, price: &Price) {
+            self.0.push(format!("price {}", price.commodity));
+        }
+    }
+
+    #[test]
+    fn test_walk() {
+        let journal =
+r#"
+P 2023/03/01 USD $1.35
+
+~ monthly
+    expenses:rent                       $1200
+    assets:checking
+
+2023/03/07 Sandwich
+    expenses:food                      $14.99
+    assets:cash
+"#;
+        let journal = Journal::from_lines(journal.lines()).unwrap();
+
+        let mut trail = Trail::default();
+        journal.walk(&mut trail);
+        assert_eq!(trail.0, vec!["transaction Sandwich", "posting expenses:food", "posting assets:cash",
+                                 "periodic monthly", "price USD"]);
+    }
+}
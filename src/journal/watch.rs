@@ -0,0 +1,466 @@
+//! Watched journal
+
+use chrono::NaiveDate;
+use std::fs;
+use std::ops::Range;
+use std::time::SystemTime;
+use tracing::info;
+
+use crate::error::{Error, ErrorKind, report};
+use crate::journal::{DateOrder, Journal, ParseJournalError, ParseProblem, split_off_comment};
+use crate::types::commoditystyle::CommodityStyles;
+
+
+/* WatchedJournal */
+
+// a journal kept up to date with its files, for serve and web --watch. each file is kept
+// in blocks, a transaction, periodic transaction or directive along with the lines under
+// it, and when a file changes only the blocks the edit touched are parsed again. the
+// blocks are then put back together into the journal the way read_journal would. each
+// block is parsed with the commodity directives above it, in its own file or an earlier
+// one, so an edit that changes a directive parses everything after it again. each file
+// is held to the date order it was first read with, so --strict still holds after an edit
+pub struct WatchedJournal<'a> {
+    files  : Vec<WatchedFile>,
+    order  : DateOrder,
+    prepare: Box<dyn Fn(&mut Journal) + 'a>,  // forecast, date range and valuation
+    journal: Journal,
+}
+
+struct WatchedFile {
+    path    : String,
+    modified: Option<SystemTime>,
+    text    : String,
+    earlier : CommodityStyles,  // from the files before it, which its blocks were parsed after
+    blocks  : Vec<Block>,
+}
+
+// the lines from one header or directive up to the next. the first block starts at the
+// top of the file, even if that's only blank and comment lines
+struct Block {
+    start  : usize,    // byte offset in the file's text
+    line   : usize,    // number of its first line
+    journal: Journal,  // what parsing just this block gives, with the styles above it
+}
+
+impl<'a> WatchedJournal<'a> {
+    pub fn new(paths  : &[&str],
+               order  : DateOrder,
+               prepare: impl Fn(&mut Journal) + 'a) -> Result<WatchedJournal<'a>, Error>
+    {
+        let mut files: Vec<WatchedFile> = vec![];
+        for path in paths {
+            let earlier = files.last().map(WatchedFile::styles).cloned().unwrap_or_default();
+            files.push(WatchedFile::read(path, earlier, order)?);
+        }
+
+        let mut watched = WatchedJournal { files, order, prepare: Box::new(prepare), journal: Journal::default() };
+        watched.rebuild();
+        Ok(watched)
+    }
+
+    pub fn journal(&self) -> &Journal {
+        &self.journal
+    }
+
+    // reparse the parts of any files that changed since the last look. a file that can't
+    // be read or has a problem in its changed part is left as it was, so the last good
+    // journal is still there to serve while the problem gets fixed
+    pub fn refresh(&mut self) -> Result<(), Error> {
+        let mut changed = false;
+        let mut earlier = CommodityStyles::default();
+        for file in &mut self.files {
+            changed |= file.refresh(&earlier, self.order)?;
+            earlier = file.styles().clone();
+        }
+
+        if changed {
+            self.rebuild();
+        }
+        Ok(())
+    }
+
+    // merge the blocks back into one journal, in the order read_journal would have them
+    fn rebuild(&mut self) {
+        let mut journal = merge_blocks(self.files.iter().flat_map(|file| &file.blocks));
+        (self.prepare)(&mut journal);
+        self.journal = journal;
+    }
+}
+
+fn merge_blocks<'b>(blocks: impl Iterator<Item = &'b Block>) -> Journal {
+    let mut builder = Journal::builder();
+    for block in blocks {
+        builder = builder.transactions(block.journal.transactions.iter().cloned())
+                         .periodic_transactions(block.journal.periodic.iter().cloned())
+                         .prices(block.journal.prices.iter().cloned())
+                         .goals(block.journal.goals.iter().cloned())
+                         .commodities(block.journal.commodities.clone());
+    }
+    builder.build()
+}
+
+// the watched journal brought up to date, or the one read at the start when not watching.
+// a problem rereading it is reported and the last good journal is used
+pub fn current<'j>(watched: &'j mut Option<WatchedJournal>, read: &'j Journal) -> &'j Journal {
+    let Some(watched) = watched else {
+        return read
+    };
+
+    if let Err(error) = watched.refresh() {
+        report(&error);
+    }
+    watched.journal()
+}
+
+impl WatchedFile {
+    fn read(path: &str, earlier: CommodityStyles, order: DateOrder) -> Result<WatchedFile, Error> {
+        let mut file = WatchedFile { path: path.to_string(), modified: None, text: String::new(), earlier, blocks: vec![] };
+        file.modified = file.last_modified()?;
+        file.text     = file.read_text()?;
+        file.blocks   = parse_file(&file.text, &file.earlier, order).map_err(|problem| Error::from_problem(path, &problem))?;
+        Ok(file)
+    }
+
+    // the commodity styles at the end of the file, for parsing the next one
+    fn styles(&self) -> &CommodityStyles {
+        &self.blocks.last().expect("A file has at least one block").journal.commodities
+    }
+
+    // the styles the block at the index was parsed with
+    fn styles_before(&self, index: usize) -> &CommodityStyles {
+        index.checked_sub(1).map_or(&self.earlier, |previous| &self.blocks[previous].journal.commodities)
+    }
+
+    // whether the file changed and its blocks were updated. a change to the commodity
+    // directives in the files before it means parsing all of it again
+    fn refresh(&mut self, earlier: &CommodityStyles, order: DateOrder) -> Result<bool, Error> {
+        let modified = self.last_modified()?;
+        if *earlier != self.earlier {
+            let text = if modified == self.modified { self.text.clone() } else { self.read_text()? };
+            self.blocks   = parse_file(&text, earlier, order).map_err(|problem| Error::from_problem(&self.path, &problem))?;
+            self.earlier  = earlier.clone();
+            self.modified = modified;
+            self.text     = text;
+            info!(file = self.path, blocks = self.blocks.len(), "reparsed the journal after a commodity directive before it changed");
+            return Ok(true)
+        }
+
+        if modified == self.modified {
+            return Ok(false)
+        }
+
+        let text = self.read_text()?;
+        self.modified = modified;
+        if text == self.text {
+            return Ok(false)
+        }
+
+        let reparsed = self.update(text, order).map_err(|problem| Error::from_problem(&self.path, &problem))?;
+        info!(file = self.path, blocks = reparsed, of = self.blocks.len(), "reparsed the changed part of the journal");
+        Ok(true)
+    }
+
+    // replace the blocks the edit touched with ones parsed from the new text, returning how
+    // many were parsed. when the edit changes the styles after it, like a commodity
+    // directive's tolerance, the rest of the file is parsed again with the new ones
+    fn update(&mut self, text: String, order: DateOrder) -> Result<usize, ParseProblem> {
+        let (old, new) = changed_ranges(&self.text, &text);
+        let old_lines  = self.text[old.clone()].matches('\n').count();
+        let new_lines  = text[new.clone()].matches('\n').count();
+
+        // an edit right at the start of a block could be adding lines to the one before it,
+        // and one right at the end could be joining the next header onto its last line
+        let first = self.blocks.partition_point(|block| block.start < old.start).saturating_sub(1);
+        let mut end = self.blocks.partition_point(|block| block.start <= old.end);
+
+        // the blocks after the edit are in the unchanged text, just moved along by it
+        let shift_bytes = |start: usize| start + new.end - old.end;
+        let shift_lines = |line: usize| line + new_lines - old_lines;
+        let reparse     = self.blocks[first].start .. self.blocks.get(end).map_or(text.len(), |block| shift_bytes(block.start));
+        let mut blocks  = parse_blocks(&text[reparse.clone()], reparse.start, self.blocks[first].line, self.styles_before(first), order)?;
+
+        let styles = &blocks.last().expect("Parsing gives at least one block").journal.commodities;
+        if let Some(next) = self.blocks.get(end).filter(|_| styles != self.styles_before(end)) {
+            let (start, line) = (shift_bytes(next.start), shift_lines(next.line));
+            let rest = parse_blocks(&text[start..], start, line, &styles.clone(), order)?;
+            blocks.extend(rest);
+            end = self.blocks.len();
+        }
+
+        let before = self.blocks[..first].iter().chain(&blocks).map(|block| (block.line, &block.journal));
+        let after  = self.blocks[end..].iter().map(|block| (shift_lines(block.line), &block.journal));
+        check_order(before.chain(after), order)?;
+
+        for block in &mut self.blocks[end..] {
+            block.start = shift_bytes(block.start);
+            block.line  = shift_lines(block.line);
+        }
+
+        let reparsed = blocks.len();
+        self.blocks.splice(first..end, blocks);
+        self.text = text;
+        Ok(reparsed)
+    }
+
+    fn last_modified(&self) -> Result<Option<SystemTime>, Error> {
+        fs::metadata(&self.path).map(|metadata| metadata.modified().ok())
+                                .map_err(|error| self.io_error(error))
+    }
+
+    fn read_text(&self) -> Result<String, Error> {
+        fs::read_to_string(&self.path).map_err(|error| self.io_error(error))
+    }
+
+    fn io_error(&self, error: std::io::Error) -> Error {
+        Error::new(ErrorKind::Io, format!("Couldn't read journal file: {}", error)).in_file(&self.path)
+    }
+}
+
+// the bytes that differ between the old and new text, as a range in each. what's left
+// out is the part at the start and the part at the end they have in common, backed off
+// to the nearest whole character
+fn changed_ranges(old: &str, new: &str) -> (Range<usize>, Range<usize>) {
+    let mut prefix = common_length(old.bytes(), new.bytes());
+    while !old.is_char_boundary(prefix) {
+        prefix -= 1;
+    }
+
+    let mut suffix = common_length(old[prefix..].bytes().rev(), new[prefix..].bytes().rev());
+    while !old.is_char_boundary(old.len() - suffix) {
+        suffix -= 1;
+    }
+
+    (prefix .. old.len() - suffix, prefix .. new.len() - suffix)
+}
+
+fn common_length(a: impl Iterator<Item = u8>, b: impl Iterator<Item = u8>) -> usize {
+    a.zip(b).take_while(|(a, b)| a == b).count()
+}
+
+// parse a whole file into blocks, after the styles of the files before it
+fn parse_file(text   : &str,
+              earlier: &CommodityStyles,
+              order  : DateOrder) -> Result<Vec<Block>, ParseProblem>
+{
+    let blocks = parse_blocks(text, 0, 1, earlier, order)?;
+    check_order(blocks.iter().map(|block| (block.line, &block.journal)), order)?;
+    Ok(blocks)
+}
+
+// parse the text into blocks, each after the styles of the one before it, with their
+// offsets and line numbers counted from where the text is in its file
+fn parse_blocks(text   : &str,
+                start  : usize,
+                line   : usize,
+                earlier: &CommodityStyles,
+                order  : DateOrder) -> Result<Vec<Block>, ParseProblem>
+{
+    let mut starts = vec![(0, 0)];
+    let mut offset = 0;
+    for (number, text_line) in text.split_inclusive('\n').enumerate() {
+        let (content, _) = split_off_comment(text_line.trim_end_matches(['\n', '\r']));
+        if offset > 0 && content.starts_with(|c: char| !c.is_whitespace()) {
+            starts.push((offset, number));
+        }
+        offset += text_line.len();
+    }
+
+    let ends = starts.iter().skip(1).map(|(offset, _)| *offset).chain([text.len()]);
+    let mut blocks: Vec<Block> = vec![];
+    for (&(offset, number), end) in starts.iter().zip(ends) {
+        let styles  = blocks.last().map_or(earlier, |block| &block.journal.commodities);
+        let journal = Journal::from_lines_after(text[offset..end].lines(), order, styles)
+                              .map_err(|problem| ParseProblem { line: problem.line + line + number - 1, ..problem })?;
+        blocks.push(Block { start: start + offset, line: line + number, journal });
+    }
+    Ok(blocks)
+}
+
+// with DateOrder::Require, the first transaction dated before one above it in the file,
+// given each block's first line. a block holds at most one transaction, so parsing them
+// one at a time can't see this
+fn check_order<'b>(blocks: impl Iterator<Item = (usize, &'b Journal)>,
+                   order : DateOrder) -> Result<(), ParseProblem>
+{
+    if order == DateOrder::Sort {
+        return Ok(())
+    }
+
+    let mut latest: Option<NaiveDate> = None;
+    for (line, journal) in blocks {
+        for transaction in &journal.transactions {
+            if let Some(latest) = latest.filter(|latest| transaction.date < *latest) {
+                return Err(ParseProblem { line, error: ParseJournalError::OutOfOrder(transaction.date, latest) })
+            }
+            latest = latest.max(Some(transaction.date));
+        }
+    }
+    Ok(())
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use std::fs::{self, File};
+    use std::time::{Duration, SystemTime};
+    use crate::journal::{DateOrder, Journal, ParseJournalError, ParseProblem};
+    use crate::types::commoditystyle::CommodityStyles;
+    use super::{WatchedFile, WatchedJournal, changed_ranges, merge_blocks, parse_blocks};
+
+    const JOURNAL: &str = "; 2023\n\
+                           P 2023/03/01 USD $1.35\n\
+                           2023/03/18 Groceries  ; trip:japan\n    expenses:food  $41.06\n    assets:savings\n\
+                           \n\
+                           2023/03/02 Rent\n    expenses:rent  $1200\n    ; a comment\n    assets:checking\n\
+                           ~ monthly  budget\n    expenses:food  $400\n    assets:checking\n";
+
+    fn watched_file(text: &str) -> WatchedFile {
+        let blocks = parse_blocks(text, 0, 1, &CommodityStyles::default(), DateOrder::Sort).unwrap();
+        WatchedFile { path: "main.journal".to_string(), modified: None, text: text.to_string(), earlier: CommodityStyles::default(), blocks }
+    }
+
+    // after each edit, the blocks should give the same journal as parsing the whole file
+    #[test]
+    fn test_update() {
+        let mut file = watched_file(JOURNAL);
+        assert_eq!(file.blocks.len(), 5);
+
+        let edits = [
+            JOURNAL.replace("$41.06", "$14.06"),
+            JOURNAL.replace("$41.06", "$14.06").replace("Rent\n", "Rent\n    ; rent:march\n"),
+            JOURNAL.replace("\n\n", "\n2023/03/01 Gas\n    expenses:auto  $30\n    assets:savings\n\n"),
+            JOURNAL.replace("\n\n", "\n"),
+            JOURNAL.replace("; 2023\n", ""),
+            JOURNAL.to_string() + "2023/03/20 Café\n    expenses:food  $5\n    assets:cash\n",
+            JOURNAL.to_string() + "2023/03/20 Cafè\n    expenses:food  $5\n    assets:cash\n",
+            String::new(),
+            JOURNAL.to_string(),
+        ];
+
+        for text in edits {
+            file.update(text.clone(), DateOrder::Sort).unwrap();
+            assert_eq!(merge_blocks(file.blocks.iter()), Journal::from_lines(text.lines()).unwrap(), "{}", text);
+
+            let starts: Vec<(usize, usize)> = file.blocks.iter().map(|block| (block.start, block.line)).collect();
+            let parsed: Vec<(usize, usize)> = watched_file(&text).blocks.iter().map(|block| (block.start, block.line)).collect();
+            assert_eq!(starts, parsed);
+        }
+    }
+
+    #[test]
+    fn test_update_only_parses_the_changed_blocks() {
+        let mut file = watched_file(JOURNAL);
+        let text     = JOURNAL.replace("$1200", "$1250");
+        assert_eq!(file.update(text.clone(), DateOrder::Sort), Ok(1));
+
+        // the edit starts in the rent header, which is now the new transaction's
+        let text = text.replace("2023/03/02", "2023/03/01 Gas\n    expenses:auto  $30\n    assets:savings\n2023/03/02");
+        assert_eq!(file.update(text.clone(), DateOrder::Sort), Ok(2));
+
+        // lines added at the very start of a block could belong to the one before it
+        let text = text.replace("~", "    ; budget:monthly\n~");
+        assert_eq!(file.update(text, DateOrder::Sort), Ok(2));
+    }
+
+    #[test]
+    fn test_problem_keeps_the_last_good_blocks() {
+        let mut file = watched_file(JOURNAL);
+        let text    = JOURNAL.replace("    assets:checking\n~", "    assets:checking  $5\n~");
+        let problem = file.update(text.clone(), DateOrder::Sort).unwrap_err();
+        assert_eq!(problem, Journal::from_lines(text.lines()).unwrap_err());
+        assert_eq!(file.text, JOURNAL);
+        assert_eq!(merge_blocks(file.blocks.iter()), Journal::from_lines(JOURNAL.lines()).unwrap());
+    }
+
+    // with --strict a transaction dated before the one above it is a problem, whether the
+    // edit was to it or to the one above
+    #[test]
+    fn test_update_require_date_order() {
+        let march = |day| NaiveDate::from_ymd_opt(2023, 3, day).unwrap();
+        let text     = JOURNAL.replace("2023/03/18", "2023/03/01");
+        let mut file = watched_file(&text);
+
+        let edited  = text.replace("2023/03/02", "2023/02/28");
+        let problem = file.update(edited.clone(), DateOrder::Require).unwrap_err();
+        assert_eq!(problem, Journal::from_lines_after(edited.lines(), DateOrder::Require, &CommodityStyles::default()).unwrap_err());
+        assert_eq!(file.text, text);
+
+        let problem = file.update(text.replace("2023/03/01 Groceries", "2023/03/05 Groceries\n    ; moved"), DateOrder::Require).unwrap_err();
+        assert_eq!(problem, ParseProblem { line: 8, error: ParseJournalError::OutOfOrder(march(2), march(5)) });
+
+        assert_eq!(file.update(text.replace("$1200", "$1250"), DateOrder::Require), Ok(1));
+    }
+
+    // a directive's tolerance reaches the blocks under it, and changing it parses them again
+    #[test]
+    fn test_update_commodity_directive() {
+        let readings = "commodity 1000.0 kWh  tolerance 0.05\n\
+                        2023/04/01 Hydro meter\n    usage:day    0.33 kWh\n    usage:evening    0.33 kWh\n    usage:meter    -0.7 kWh\n\
+                        2023/05/01 Hydro meter\n    usage:day    0.4 kWh\n    usage:meter    -0.4 kWh\n";
+        let mut file = watched_file(readings);
+        assert_eq!(merge_blocks(file.blocks.iter()).transactions.len(), 2);
+
+        let text = readings.replace("0.05", "0.04");
+        assert_eq!(file.update(text.clone(), DateOrder::Sort), Ok(3));
+        assert_eq!(merge_blocks(file.blocks.iter()), Journal::from_lines(text.lines()).unwrap());
+
+        let problem = file.update(readings.replace("0.05", "0.01"), DateOrder::Sort).unwrap_err();
+        assert_eq!(problem.line, 2);
+        assert_eq!(file.text, text);
+    }
+
+    #[test]
+    fn test_changed_ranges() {
+        assert_eq!(changed_ranges("abcdef", "abXef"), (2..4, 2..3));
+        assert_eq!(changed_ranges("abc", "abc"), (3..3, 3..3));
+        assert_eq!(changed_ranges("aa", "aaa"), (2..2, 2..3));
+        assert_eq!(changed_ranges("é", "è"), (0..2, 0..2));
+    }
+
+    #[test]
+    fn test_refresh() {
+        let dir = std::env::temp_dir().join(format!("katana-watch-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("main.journal");
+        fs::write(&path, JOURNAL).unwrap();
+
+        let path = path.to_string_lossy().into_owned();
+        let mut watched = WatchedJournal::new(&[&path], DateOrder::Sort, |journal| journal.transactions.retain(|t| !t.description.starts_with("Rent"))).unwrap();
+        assert_eq!(watched.journal().transactions.len(), 1);
+
+        fs::write(&path, JOURNAL.replace("Groceries", "Rent")).unwrap();
+        File::options().write(true).open(&path).unwrap()
+                       .set_modified(SystemTime::now() + Duration::from_secs(60)).unwrap();
+        watched.refresh().unwrap();
+        assert!(watched.journal().transactions.is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    // the tolerance from a directive in one file applies to the files after it
+    #[test]
+    fn test_refresh_directive_in_an_earlier_file() {
+        let dir = std::env::temp_dir().join(format!("katana-watch-directive-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (commodities, readings) = (dir.join("c.journal"), dir.join("d.journal"));
+        fs::write(&commodities, "commodity 1000.0 kWh  tolerance 0.05\n").unwrap();
+        fs::write(&readings, "2023/04/01 Hydro meter\n    usage:day    0.33 kWh\n    usage:evening    0.33 kWh\n    usage:meter    -0.7 kWh\n").unwrap();
+
+        let paths = [commodities.to_string_lossy().into_owned(), readings.to_string_lossy().into_owned()];
+        let mut watched = WatchedJournal::new(&[&paths[0], &paths[1]], DateOrder::Sort, |_| ()).unwrap();
+        assert_eq!(watched.journal().transactions.len(), 1);
+
+        // tightening it makes the other file's transaction unbalanced, so the last good one stays
+        fs::write(&commodities, "commodity 1000.0 kWh  tolerance 0.01\n").unwrap();
+        File::options().write(true).open(&commodities).unwrap()
+                       .set_modified(SystemTime::now() + Duration::from_secs(60)).unwrap();
+        assert!(watched.refresh().unwrap_err().to_string().contains("d.journal"));
+        assert_eq!(watched.journal().transactions.len(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
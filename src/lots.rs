@@ -0,0 +1,309 @@
+use chrono::NaiveDate;
+use rust_decimal::{Decimal, prelude::ToPrimitive};
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+
+use crate::common::{pad_left, pad_right};
+use crate::journal::Journal;
+use crate::transaction::{Entry, Transaction};
+use crate::types::commoditystyle::{Styled, WithStyles};
+use crate::types::{Account, Units, amount::{Amount, AmountError, AmountType}};
+
+
+/* Lots */
+
+// the units of a commodity an account took in at a cost in one transaction. a posting
+// with a cost opens a lot, and one taking units out with a price sells the account's
+// oldest lots of the commodity first, all of each one until the last, which can be left
+// partly open:
+//
+// 2023/01/16 Buy
+//     assets:brokerage             10 AAPL @ $150
+//     assets:checking
+//
+// 2023/06/01 Sell
+//     assets:brokerage             -4 AAPL @ $170
+//     assets:checking
+//
+// leaves 6 AAPL of the lot open at a cost of $900 and realizes an $80 gain. a lot price
+// in braces on a buy, {$150}, is what the lot cost even when an @ price is given too.
+// units moved between accounts without a cost or price take their lots with them, the
+// oldest first, so a transfer to another brokerage keeps their cost and dates
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lot {
+    pub account : Account,
+    pub acquired: NaiveDate,
+    pub quantity: Amount,
+    pub cost    : Amount, // for all of the quantity still open
+}
+
+// part or all of a lot sold, with what a capital gains report needs to know about it
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sale {
+    pub account : Account,
+    pub acquired: NaiveDate,
+    pub sold    : NaiveDate,
+    pub quantity: Amount,
+    pub cost    : Amount, // the lot's cost for the quantity sold
+    pub proceeds: Amount, // what the quantity sold for
+    pub gain    : Amount, // the proceeds less the cost, negative for a loss
+}
+
+pub struct Lots {
+    pub open    : Vec<Lot>,  // by account and commodity, oldest first
+    pub realized: Vec<Sale>, // in the order they were sold
+}
+
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum LotError {
+    #[error("{1} sold {2} more than it held on {}", .0.format("%Y/%m/%d"))]
+    Oversold(NaiveDate, Account, Amount),
+    #[error("{1} sold {2} for {3}, which its lots weren't bought in, on {}", .0.format("%Y/%m/%d"))]
+    MixedCosts(NaiveDate, Account, Units, Units),
+    #[error(transparent)]
+    Amount(#[from] AmountError),
+}
+
+// Follows the lots in every account from the start of the journal, including anything
+// set aside by a date range, so a sale always finds the lots bought before it
+pub fn track_lots(journal: &Journal) -> Result<Lots, LotError> {
+    let mut open: BTreeMap<(Account, Units), VecDeque<Lot>> = BTreeMap::new();
+    let mut realized = vec![];
+
+    for transaction in journal.earlier.iter().chain(&journal.transactions) {
+        for entry in &transaction.entries {
+            let Some(cost) = &entry.cost else { continue };
+            let lots = open.entry((entry.account.clone(), entry.amount.units)).or_default();
+
+            if !entry.amount.is_negative() {
+                let basis = entry.lot.as_ref().unwrap_or(cost).clone();
+                lots.push_back(Lot { account: entry.account.clone(), acquired: transaction.date, quantity: entry.amount.clone(), cost: basis });
+                continue
+            }
+
+            let sold = entry.amount.decimal().abs();
+            let mut left     = sold;
+            let mut proceeds = cost.clone();
+
+            while !left.is_zero() {
+                match lots.front() {
+                    None                                      => return Err(LotError::Oversold(transaction.date, entry.account.clone(), with_quantity(&entry.amount, left)?)),
+                    Some(lot) if lot.cost.units != cost.units => return Err(LotError::MixedCosts(transaction.date, entry.account.clone(), entry.amount.units, cost.units)),
+                    Some(_)                                   => {},
+                }
+
+                // the whole lot, or the part of it that's left to sell
+                let lot = take_oldest(lots, left)?.expect("There's a lot at the front");
+
+                // the last part sold gets the rest of the proceeds, so they add up to the price
+                let taken = lot.quantity.decimal();
+                let share = if taken == left { proceeds.clone() } else { cost.checked_scale(taken / sold)? };
+                proceeds = proceeds.checked_add(&share.checked_neg()?)?;
+                left -= taken;
+
+                realized.push(Sale {
+                    account : entry.account.clone(),
+                    acquired: lot.acquired,
+                    sold    : transaction.date,
+                    gain    : share.checked_add(&lot.cost.checked_neg()?)?,
+                    quantity: lot.quantity,
+                    cost    : lot.cost,
+                    proceeds: share,
+                });
+            }
+        }
+
+        move_lots(transaction, &mut open)?;
+    }
+
+    Ok(Lots {
+        open: open.into_values().flatten().collect(),
+        realized,
+    })
+}
+
+// the postings without a cost in the transaction, taking units out of one account and
+// putting them in another. the lots go along with the units, the oldest ones first, and
+// any that don't arrive anywhere go back to the account they came from
+fn move_lots(transaction: &Transaction,
+             open       : &mut BTreeMap<(Account, Units), VecDeque<Lot>>) -> Result<(), AmountError>
+{
+    let transfers = || transaction.entries.iter().filter(|entry| entry.cost.is_none());
+    let mut moving: BTreeMap<Units, VecDeque<Lot>> = BTreeMap::new();
+
+    for entry in transfers().filter(|entry| entry.amount.is_negative()) {
+        let Some(lots) = open.get_mut(&(entry.account.clone(), entry.amount.units)) else { continue };
+        take(lots, entry, moving.entry(entry.amount.units).or_default())?;
+    }
+    if moving.is_empty() {
+        return Ok(())
+    }
+
+    for entry in transfers().filter(|entry| !entry.amount.is_negative()) {
+        let Some(arriving) = moving.get_mut(&entry.amount.units) else { continue };
+        let lots = open.entry((entry.account.clone(), entry.amount.units)).or_default();
+        take(arriving, entry, lots)?;
+        lots.iter_mut().for_each(|lot| lot.account = entry.account.clone());
+        lots.make_contiguous().sort_by_key(|lot| lot.acquired);
+    }
+
+    for lot in moving.into_values().flatten() {
+        let lots = open.entry((lot.account.clone(), lot.quantity.units)).or_default();
+        lots.push_back(lot);
+        lots.make_contiguous().sort_by_key(|lot| lot.acquired);
+    }
+    Ok(())
+}
+
+// move the oldest lots, as many as the posting's units, from one list to the other
+fn take(from: &mut VecDeque<Lot>, entry: &Entry, to: &mut VecDeque<Lot>) -> Result<(), AmountError> {
+    let mut left = entry.amount.decimal().abs();
+    while !left.is_zero() {
+        let Some(lot) = take_oldest(from, left)? else { break };
+        left -= lot.quantity.decimal();
+        to.push_back(lot);
+    }
+    Ok(())
+}
+
+// the oldest lot, or only as much of it as the quantity, taken off the front of the list
+fn take_oldest(lots: &mut VecDeque<Lot>, quantity: Decimal) -> Result<Option<Lot>, AmountError> {
+    let Some(lot) = lots.front_mut() else { return Ok(None) };
+    let held = lot.quantity.decimal();
+    if quantity >= held {
+        return Ok(lots.pop_front())
+    }
+
+    let part = Lot {
+        account : lot.account.clone(),
+        acquired: lot.acquired,
+        quantity: with_quantity(&lot.quantity, quantity)?,
+        cost    : lot.cost.checked_scale(quantity / held)?,
+    };
+    lot.quantity = lot.quantity.checked_add(&part.quantity.checked_neg()?)?;
+    lot.cost     = lot.cost.checked_add(&part.cost.checked_neg()?)?;
+    Ok(Some(part))
+}
+
+// the amount's commodity with another quantity, kept exact instead of scaling the amount
+fn with_quantity(amount: &Amount, quantity: Decimal) -> Result<Amount, AmountError> {
+    let quantity = match amount.amount {
+        AmountType::Discrete(_, dec) => {
+            let smallest = quantity.checked_mul(Decimal::from(10i64.pow(dec as u32))).and_then(|q| q.to_i64());
+            AmountType::Discrete(smallest.ok_or(AmountError::Overflow)?, dec)
+        },
+        AmountType::Decimal(_) => AmountType::Decimal(quantity),
+    };
+    Ok(Amount { units: amount.units, amount: quantity })
+}
+
+// 2023/01/16  assets:brokerage               6.000 AAPL          $900.00
+
+impl WithStyles for Lot {}
+
+impl fmt::Display for Styled<'_, Lot> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lot  = self.value;
+        let cell = |amount: &Amount| pad_left(&amount.styled(self.styles).to_string(), 15);
+        write!(f, "{}  {}  {}  {}", lot.acquired.format("%Y/%m/%d"), pad_right(&lot.account, 24), cell(&lot.quantity), cell(&lot.cost))
+    }
+}
+
+// 2023/06/01  assets:brokerage               4.000 AAPL  2023/01/16          $600.00          $680.00           $80.00
+
+impl WithStyles for Sale {}
+
+impl fmt::Display for Styled<'_, Sale> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sale = self.value;
+        let cell = |amount: &Amount| pad_left(&amount.styled(self.styles).to_string(), 15);
+        write!(f, "{}  {}  {}  {}  {}  {}  {}",
+               sale.sold.format("%Y/%m/%d"), pad_right(&sale.account, 24), cell(&sale.quantity), sale.acquired.format("%Y/%m/%d"),
+               cell(&sale.cost), cell(&sale.proceeds), cell(&sale.gain))
+    }
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use crate::journal::Journal;
+    use crate::types::commoditystyle::{CommodityStyles, WithStyles};
+    use super::{LotError, track_lots};
+
+    const JOURNAL: &str = r#"
+2023/01/16 Buy
+    assets:brokerage                 10 AAPL @ $150
+    assets:checking
+
+2023/03/01 Buy
+    assets:brokerage                  3 AAPL @@ $480
+    assets:checking
+
+2023/06/01 Sell
+    assets:brokerage                -12 AAPL @ $170
+    assets:checking
+
+2023/06/02 Transfer
+    assets:brokerage                 -1 AAPL
+    assets:ira                        1 AAPL
+"#;
+
+    #[test]
+    fn test_track_lots() {
+        let journal = Journal::from_lines(JOURNAL.lines()).unwrap();
+        let lots    = track_lots(&journal).unwrap();
+
+        // all of the first lot and two thirds of the second, leaving one AAPL at $160 that
+        // was then moved to the ira along with its lot
+        let open: Vec<String> = lots.open.iter().map(|lot| lot.styled(&CommodityStyles::default()).to_string()).collect();
+        assert_eq!(open, vec!["2023/03/01  assets:ira                     1.000 AAPL          $160.00"]);
+
+        let realized: Vec<String> = lots.realized.iter().map(|sale| sale.styled(&CommodityStyles::default()).to_string()).collect();
+        assert_eq!(realized, vec![
+            "2023/06/01  assets:brokerage              10.000 AAPL  2023/01/16         $1500.00         $1700.00          $200.00",
+            "2023/06/01  assets:brokerage               2.000 AAPL  2023/03/01          $320.00          $340.00           $20.00",
+        ]);
+    }
+
+    #[test]
+    fn test_track_lots_transfers() {
+        let journal = JOURNAL.replace("-12 AAPL @ $170", "-9 AAPL @ $170")
+                             .replace("-1 AAPL\n    assets:ira                        1 AAPL", "-3 AAPL\n    assets:ira                        3 AAPL");
+        let lots = track_lots(&Journal::from_lines(journal.lines()).unwrap()).unwrap();
+
+        // the oldest lot's last AAPL moves first, then part of the next one
+        let open: Vec<String> = lots.open.iter().map(|lot| lot.styled(&CommodityStyles::default()).to_string()).collect();
+        assert_eq!(open, vec!["2023/03/01  assets:brokerage               1.000 AAPL          $160.00",
+                              "2023/01/16  assets:ira                     1.000 AAPL          $150.00",
+                              "2023/03/01  assets:ira                     2.000 AAPL          $320.00"]);
+
+        // and a sale from the ira later on finds them there
+        let journal = journal + "\n2023/07/01 Sell\n    assets:ira                       -3 AAPL @ $200\n    assets:checking\n";
+        let lots    = track_lots(&Journal::from_lines(journal.lines()).unwrap()).unwrap();
+        let gains: Vec<String> = lots.realized[1..].iter().map(|sale| sale.gain.to_string()).collect();
+        assert_eq!(gains, vec!["$50.00", "$80.00"]);
+    }
+
+    #[test]
+    fn test_track_lots_lot_price_with_price() {
+        let journal = "2023/01/16 Buy\n    assets:brokerage  10 AAPL {$150} @ $151\n    assets:checking\n\n\
+                       2023/06/01 Sell\n    assets:brokerage  -4 AAPL {$150} @ $170\n    assets:checking\n";
+        let lots = track_lots(&Journal::from_lines(journal.lines()).unwrap()).unwrap();
+
+        // the lot cost $150 each, though $151 was paid, and the four sold for $170 each
+        assert_eq!(lots.open[0].cost.to_string(), "$900.00");
+        assert_eq!((lots.realized[0].cost.to_string(), lots.realized[0].proceeds.to_string()), ("$600.00".to_string(), "$680.00".to_string()));
+    }
+
+    #[test]
+    fn test_track_lots_oversold() {
+        let journal = Journal::from_lines(JOURNAL.replace("-12 AAPL", "-14 AAPL").lines()).unwrap();
+        let error   = track_lots(&journal).err().unwrap();
+
+        assert!(matches!(error, LotError::Oversold(date, _, _) if date == NaiveDate::from_ymd_opt(2023, 6, 1).unwrap()));
+        assert_eq!(error.to_string(), "assets:brokerage sold 1.000 AAPL more than it held on 2023/06/01");
+    }
+}
@@ -1,98 +1,1011 @@
+mod accounttree;
+mod api;
+mod budget;
+mod cli;
+mod color;
 mod common;
+mod error;
+mod gen;
+mod goal;
+mod http;
 mod iterators;
 mod journal;
+mod lots;
 mod monthgrid;
+mod output;
+mod periodgrid;
+mod periodic;
+mod price;
+mod profile;
+mod query;
 mod reports;
+mod rules;
 mod transaction;
 mod types;
+mod ui;
+mod valuation;
+mod web;
 
-use clap::{App, Arg};
-use std::fs;
+use chrono::NaiveDate;
+use clap::Parser;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::time::Instant;
+use tracing::{Level, debug, info, info_span};
+use tracing_subscriber::fmt::{format::FmtSpan, time::Uptime};
 
-use journal::Journal;
-use monthgrid::MonthGrid;
-use reports::balance::balance_changes;
-use reports::register::register_report;
-use types::{Account, amount::Amount, monthyear::MonthYear};
+use budget::{Budget, overspends};
+use cli::{AccountsArgs, ActivityArgs, BalanceArgs, BalanceSheetArgs, CalendarArgs, ChartArgs, CheckArgs, Cli, Command, EnvelopesArgs, EnvelopesCommand, ForecastArgs, GlobalArgs,
+          QueryArgs, GenArgs, GenerateArgs, PricesArgs, PrintArgs, RegisterArgs, RulesCommand, ServerArgs, TagsArgs, TopArgs, account_terms, journal_files};
+use color::{ColorChoice, set_color};
+use common::{pad_left, pad_right, today};
+use error::{Error, ErrorFormat, ErrorKind, error_format_in, exit_with, report, set_error_format};
+use journal::{DateOrder, Journal, JournalSummary, watch::WatchedJournal};
+use lots::track_lots;
+use output::{OutputFormat, to_csv, to_html, to_json};
+use profile::{count, enable_profile, phase, profiling, take_profile};
+use query::Query;
+use reports::accounts::accounts_report;
+use reports::activity::activity_report;
+use reports::balance::{BalanceOptions, balance_periodic_report, balance_report};
+use reports::balancesheet::balance_sheet;
+use reports::budget::{budget_report, unbudgeted_report};
+use reports::calendar::{CalendarOptions, calendar_report};
+use reports::chart::chart_report;
+use reports::envelopes::{allocation, envelope_report};
+use reports::export::export_sql;
+use reports::forecast::{ForecastOptions, forecast_report};
+use reports::goals::goals_report;
+use reports::payees::payees_report;
+use reports::prices::{PricesOptions, prices_report};
+use reports::print::{Printed, print_report};
+use reports::register::{RegisterOptions, RegisterSummary, register_periodic_report, register_report, register_summary};
+use reports::stats::stats;
+use reports::tags::{TagsOptions, tags_report};
+use reports::top::{TopOptions, top_report};
+use rules::Rules;
+use types::commoditystyle::WithStyles;
+use types::daterange::DateRange;
+use types::interval::Interval;
+use types::lineformat::LineFormat;
+use types::monthyear::MonthYear;
+use types::numberformat::set_number_format;
+use gen::{GenOptions, generate};
+use valuation::{Valuation, value_journal};
 
 fn main() {
-    let args = get_args();
-    let journal_file = args.value_of("journal").expect("Journal file not specified");
-    let journal = read_journal(journal_file);
-    let report = args.value_of("report").unwrap();
-    
-    match report {
-        "balance" => {
-            let account = args.value_of("account")
-                              .expect("Need an account name for the balance report");
-            balance(&journal, account);
+    let cli = Cli::try_parse().unwrap_or_else(|error| usage_error(error));
+    let global = &cli.global;
+    set_error_format(global.error_format);
+    init_logging(global.verbose);
+    if global.profile {
+        enable_profile(&["read", "parse", "filter", "aggregate", "render"]);
+    }
+
+    // rules and gen don't read a journal, and check and generate read it their own way
+    let report: &dyn Report = match &cli.command {
+        Command::Rules(command)      => return rules(command),
+        Command::Gen(args)           => return gen(global, args),
+        Command::Check(args)         => return check(global, &input_files(global), args),
+        Command::Generate(args)      => return generate_recurring(global, &input_files(global), args),
+        Command::Accounts(args)      => args,
+        Command::Activity(args)      => args,
+        Command::Balance(args)       => args,
+        Command::Balancesheet(args)  => args,
+        Command::Calendar(args)      => args,
+        Command::Chart(args)         => args,
+        Command::Envelopes(args)     => args,
+        Command::Export              => &Export,
+        Command::Forecast(args)      => args,
+        Command::Goals               => &Goals,
+        Command::Lots                => &Lots,
+        Command::Payees              => &Payees,
+        Command::Prices(args)        => args,
+        Command::Print(args)         => args,
+        Command::Register(args)      => args,
+        Command::Serve(args)         => &Serve(args),
+        Command::Stats               => &Stats,
+        Command::Tags(args)          => args,
+        Command::Top(args)           => args,
+        Command::Ui                  => &Ui,
+        Command::Web(args)           => &Web(args),
+    };
+
+    let journal_files = input_files(global);
+
+    // a file gets no colour codes unless they're asked for
+    let color = global.color;
+    set_color(if global.output_file.is_some() && color == ColorChoice::Auto { ColorChoice::Never } else { color });
+
+    if let Some(number_format) = global.number_format {
+        set_number_format(number_format);
+    }
+
+    let format = output_format(global);
+    match (&cli.command, format) {
+        (Command::Chart(_), OutputFormat::Text | OutputFormat::Svg)               => {},
+        (Command::Chart(_), _)                                                    => exit_with(Error::usage("The chart report can only be output as SVG")),
+        (Command::Export, OutputFormat::Text | OutputFormat::Sql)                 => {},
+        (Command::Export, _)                                                      => exit_with(Error::usage("The export report can only be output as SQL")),
+        (_, OutputFormat::Sql | OutputFormat::Svg)                                => exit_with(Error::usage(format!("Only the export and chart reports can be output as {:?}", format))),
+        (Command::Balance(_) | Command::Register(_), _) | (_, OutputFormat::Text) => {},
+        _                                                                         => exit_with(Error::usage("This report can only be output as text")),
+    }
+
+    let mut out = output(global);
+    let out = out.as_mut();
+
+    let mut journal = read_journal(global, &journal_files);
+    let journal_files: Vec<&str> = journal_files.iter().map(String::as_str).collect();
+
+    phase("filter", || prepare_journal(global, &mut journal));
+    count("filter", journal.transactions.len());
+
+    let context = Context {
+        journal        : &journal,
+        global,
+        journal_files  : &journal_files,
+        format,
+        // an interval flag on the report, or else the one starting the -p period
+        period_interval: global.period.as_ref().and_then(|(_, interval)| *interval),
+    };
+
+    let span = info_span!("report").entered();
+    let written = phase("render", || report.run(out, &context).and_then(|_| out.flush()));
+    drop(span);
+
+    // a reader like head closing the pipe early only means it has all the lines it wanted
+    match written {
+        Err(error) if error.kind() == io::ErrorKind::BrokenPipe => {},
+        Err(error) => exit_with(Error::new(ErrorKind::Io, format!("Couldn't write the report: {}", error))),
+        Ok(())     => {},
+    }
+
+    if let Some(profile) = take_profile() {
+        eprint!("{}", profile);
+    }
+}
+
+// the journal files named by -j or the environment, with a glob naming more than one
+fn input_files(global: &GlobalArgs) -> Vec<String> {
+    let journal_file = global.journal_file().unwrap_or_else(|| exit_with(Error::usage("Journal file not specified with -j or in $KATANA_FILE or $LEDGER_FILE")));
+    journal_files(&journal_file).unwrap_or_else(|error| exit_with(Error::usage(error)))
+}
+
+
+/* Reports */
+
+// what every report gets besides its own options
+struct Context<'a> {
+    journal        : &'a Journal,
+    global         : &'a GlobalArgs,
+    journal_files  : &'a [&'a str],
+    format         : OutputFormat,
+    period_interval: Option<Interval>,
+}
+
+// a report on the journal once it's been read and prepared
+trait Report {
+    fn run(&self, out: &mut dyn Write, context: &Context) -> io::Result<()>;
+}
+
+// the reports without options of their own
+struct Export;
+struct Goals;
+struct Lots;
+struct Payees;
+struct Stats;
+struct Ui;
+
+// serve and web take the same options
+struct Serve<'a>(&'a ServerArgs);
+struct Web<'a>(&'a ServerArgs);
+
+impl Report for AccountsArgs {
+    fn run(&self, out: &mut dyn Write, context: &Context) -> io::Result<()> {
+        accounts(out, context.journal, &query(context.journal, &self.query, &account_terms(&self.account)), self.tree)
+    }
+}
+
+impl Report for ActivityArgs {
+    fn run(&self, out: &mut dyn Write, context: &Context) -> io::Result<()> {
+        let interval = self.interval.interval().or(context.period_interval).unwrap_or(Interval::Monthly);
+        write!(out, "{}", phase("aggregate", || activity_report(context.journal, interval)))
+    }
+}
+
+impl Report for BalanceArgs {
+    fn run(&self, out: &mut dyn Write, context: &Context) -> io::Result<()> {
+        let journal = context.journal;
+        let options = BalanceOptions {
+            query     : query(journal, &self.query, &account_terms(&self.account)),
+            flat      : self.flat,
+            depth     : self.depth,
+            historical: self.historical,
+            invert    : self.invert,
+            row_total : self.row_total,
+            average   : self.average,
+            percent   : self.percent,
+            empty     : self.empty,
+            sparkline : self.sparkline,
+        };
+        if self.budget {
+            balance_budget(out, journal, &options, context.format)
+        } else if self.unbudgeted {
+            balance_unbudgeted(out, journal, &options, context.format)
+        } else if let Some(interval) = self.interval.interval().or(context.period_interval) {
+            balance_periodic(out, journal, &options, interval, context.format)
+        } else {
+            // the month asked for, or without a date range, the journal's latest one
+            let month = self.month.or_else(|| date_range(context.global).is_unbounded().then(|| journal.summary().final_month));
+            balance(out, journal, month, &options, context.format)
+        }
+    }
+}
+
+impl Report for BalanceSheetArgs {
+    fn run(&self, out: &mut dyn Write, context: &Context) -> io::Result<()> {
+        let options = BalanceOptions {
+            flat : self.flat,
+            depth: self.depth,
+            ..Default::default()
+        };
+        balancesheet(out, context.journal, &options)
+    }
+}
+
+impl Report for CalendarArgs {
+    fn run(&self, out: &mut dyn Write, context: &Context) -> io::Result<()> {
+        let options = CalendarOptions {
+            query    : &query(context.journal, &self.query, &account_terms(&self.account)),
+            commodity: &self.commodity,
+        };
+        calendar(out, context.journal, &options)
+    }
+}
+
+impl Report for ChartArgs {
+    fn run(&self, out: &mut dyn Write, context: &Context) -> io::Result<()> {
+        let options = BalanceOptions {
+            query     : query(context.journal, &self.query, &account_terms(&self.account)),
+            depth     : self.depth,
+            historical: self.historical,
+            invert    : self.invert,
+            ..Default::default()
+        };
+        let interval = self.interval.interval().or(context.period_interval).unwrap_or(Interval::Monthly);
+        write!(out, "{}", phase("aggregate", || chart_report(context.journal, &options, interval)).unwrap_or_else(|error| exit_with(Error::from(error))))
+    }
+}
+
+impl Report for EnvelopesArgs {
+    fn run(&self, out: &mut dyn Write, context: &Context) -> io::Result<()> {
+        match &self.command {
+            None                                       => envelopes(out, context.journal),
+            Some(EnvelopesCommand::Allocate { month }) => allocate(out, context.journal, month.unwrap_or_else(|| MonthYear::from_naivedate(today()))),
+        }
+    }
+}
+
+impl Report for Export {
+    fn run(&self, out: &mut dyn Write, context: &Context) -> io::Result<()> {
+        write!(out, "{}", phase("aggregate", || export_sql(context.journal)))
+    }
+}
+
+impl Report for ForecastArgs {
+    fn run(&self, out: &mut dyn Write, context: &Context) -> io::Result<()> {
+        let options = ForecastOptions {
+            query   : &query(context.journal, &self.query, &account_terms(&self.account)),
+            interval: self.interval.interval().or(context.period_interval).unwrap_or(Interval::Weekly),
+            months  : self.months,
+        };
+        forecast(out, context.journal, &options)
+    }
+}
+
+impl Report for Goals {
+    fn run(&self, out: &mut dyn Write, context: &Context) -> io::Result<()> {
+        goals(out, context.journal)
+    }
+}
+
+impl Report for Lots {
+    fn run(&self, out: &mut dyn Write, context: &Context) -> io::Result<()> {
+        lots(out, context.journal)
+    }
+}
+
+impl Report for Payees {
+    fn run(&self, out: &mut dyn Write, context: &Context) -> io::Result<()> {
+        payees(out, context.journal)
+    }
+}
+
+impl Report for PricesArgs {
+    fn run(&self, out: &mut dyn Write, context: &Context) -> io::Result<()> {
+        let options = PricesOptions {
+            commodity: self.commodity.as_deref(),
+            invert   : self.invert,
+            monthly  : self.monthly,
+        };
+        prices(out, context.journal, &options)
+    }
+}
+
+impl Report for PrintArgs {
+    fn run(&self, out: &mut dyn Write, context: &Context) -> io::Result<()> {
+        print(out, context.journal, &query(context.journal, &self.query, &self.terms()))
+    }
+}
+
+impl Report for RegisterArgs {
+    fn run(&self, out: &mut dyn Write, context: &Context) -> io::Result<()> {
+        let journal = context.journal;
+        let query   = &query(journal, &self.query, &account_terms(&self.account));
+        let options = RegisterOptions {
+            historical    : self.historical,
+            related       : self.related,
+            invert        : self.invert,
+            moving_average: self.moving_average,
+            sort          : self.sort,
+        };
+        match self.interval.interval().or(context.period_interval) {
+            Some(interval) => register_periodic(out, journal, query, interval, &options, context.format, self.summary),
+            None           => register(out, journal, query, &options, &self.format.clone().unwrap_or_default(), context.format, self.summary),
+        }
+    }
+}
+
+impl Report for Serve<'_> {
+    fn run(&self, _: &mut dyn Write, context: &Context) -> io::Result<()> {
+        api::run(context.journal, watch(context.global, context.journal_files, self.0), SocketAddr::new(self.0.bind, self.0.port))
+    }
+}
+
+impl Report for Stats {
+    fn run(&self, out: &mut dyn Write, context: &Context) -> io::Result<()> {
+        write!(out, "{}", phase("aggregate", || stats(context.journal, context.journal_files)))
+    }
+}
+
+impl Report for TagsArgs {
+    fn run(&self, out: &mut dyn Write, context: &Context) -> io::Result<()> {
+        let options = TagsOptions {
+            tag  : &self.tag,
+            query: &query(context.journal, &self.query, &account_terms(&self.account)),
+        };
+        tags(out, context.journal, &options)
+    }
+}
+
+impl Report for TopArgs {
+    fn run(&self, out: &mut dyn Write, context: &Context) -> io::Result<()> {
+        let options = TopOptions {
+            query : &query(context.journal, &self.query, &account_terms(&self.account)),
+            payees: self.payees,
+            limit : self.limit,
+        };
+        top(out, context.journal, &options)
+    }
+}
+
+impl Report for Ui {
+    fn run(&self, _: &mut dyn Write, context: &Context) -> io::Result<()> {
+        ui::run(context.journal)
+    }
+}
+
+impl Report for Web<'_> {
+    fn run(&self, _: &mut dyn Write, context: &Context) -> io::Result<()> {
+        web::run(context.journal, watch(context.global, context.journal_files, self.0), SocketAddr::new(self.0.bind, self.0.port))
+    }
+}
+
+// $ katana accounts
+fn accounts(out: &mut dyn Write, journal: &Journal, query: &Query, tree: bool) -> io::Result<()> {
+    let report = phase("aggregate", || accounts_report(journal, query, tree));
+
+    for line in report {
+        writeln!(out, "{}", line)?;
+    }
+    Ok(())
+}
+
+// $ katana balance
+fn balance(out: &mut dyn Write, journal: &Journal, month: Option<MonthYear>, options: &BalanceOptions, format: OutputFormat) -> io::Result<()> {
+    let report = phase("aggregate", || balance_report(journal, month, options)).unwrap_or_else(|error| exit_with(Error::from(error)));
+    let styles = &journal.commodities;
+
+    match format {
+        OutputFormat::Csv  => return write!(out, "{}", to_csv(&report[..].styled(styles))),
+        OutputFormat::Json => return write!(out, "{}", to_json(&report[..])),
+        OutputFormat::Html => return write!(out, "{}", to_html(&report[..].styled(styles))),
+        OutputFormat::Text | OutputFormat::Sql | OutputFormat::Svg => {}
+    }
+
+    // an empty journal has no months to name, only the default ones from 1970
+    if journal.transactions.is_empty() {
+        return writeln!(out, "No transactions matched")
+    }
+
+    match month {
+        Some(month) => writeln!(out, "Balance changes in {}:", month)?,
+        None        => {
+            let summary = journal.summary();
+            writeln!(out, "Balance changes from {} to {}:", summary.first_month, summary.final_month)?
         },
-        "register" => {
-            let account = args.value_of("account")
-                              .expect("Need an account name for the register report");
-            register(&journal, account);
+    }
+    for line in report {
+        writeln!(out, "{}", line.styled(styles))?;
+    }
+    Ok(())
+}
+
+// $ katana balance --monthly
+fn balance_periodic(out: &mut dyn Write, journal: &Journal, options: &BalanceOptions, interval: Interval, format: OutputFormat) -> io::Result<()> {
+    let report = phase("aggregate", || balance_periodic_report(journal, options, interval)).unwrap_or_else(|error| exit_with(Error::from(error)));
+    let report = report.styled(&journal.commodities);
+
+    match format {
+        OutputFormat::Csv  => return write!(out, "{}", to_csv(&report)),
+        OutputFormat::Json => return write!(out, "{}", to_json(report.value)),
+        OutputFormat::Html => return write!(out, "{}", to_html(&report)),
+        OutputFormat::Text | OutputFormat::Sql | OutputFormat::Svg => {}
+    }
+
+    if options.historical {
+        writeln!(out, "Ending balances by {}:", interval.period_name())?;
+    } else {
+        writeln!(out, "Balance changes by {}:", interval.period_name())?;
+    }
+    write!(out, "{}", report)
+}
+
+// $ katana balance --budget
+fn balance_budget(out: &mut dyn Write, journal: &Journal, options: &BalanceOptions, format: OutputFormat) -> io::Result<()> {
+    if format != OutputFormat::Text {
+        exit_with(Error::usage("The budget report can only be output as text"));
+    }
+
+    let report = phase("aggregate", || budget_report(journal, options)).unwrap_or_else(|error| exit_with(Error::from(error)));
+
+    writeln!(out, "Budget performance by month:")?;
+    write!(out, "{}", report.styled(&journal.commodities))
+}
+
+// $ katana balance --unbudgeted
+fn balance_unbudgeted(out: &mut dyn Write, journal: &Journal, options: &BalanceOptions, format: OutputFormat) -> io::Result<()> {
+    let report = phase("aggregate", || unbudgeted_report(journal, options)).unwrap_or_else(|error| exit_with(Error::from(error)));
+    let styles = &journal.commodities;
+
+    match format {
+        OutputFormat::Csv  => return write!(out, "{}", to_csv(&report[..].styled(styles))),
+        OutputFormat::Json => return write!(out, "{}", to_json(&report[..])),
+        OutputFormat::Html => return write!(out, "{}", to_html(&report[..].styled(styles))),
+        OutputFormat::Text | OutputFormat::Sql | OutputFormat::Svg => {}
+    }
+
+    writeln!(out, "Spending without a budget:")?;
+    for line in report {
+        writeln!(out, "{}", line.styled(styles))?;
+    }
+    Ok(())
+}
+
+// $ katana balancesheet
+fn balancesheet(out: &mut dyn Write, journal: &Journal, options: &BalanceOptions) -> io::Result<()> {
+    let month = JournalSummary::from(journal).final_month;
+    let report = phase("aggregate", || balance_sheet(journal, month, options)).unwrap_or_else(|error| exit_with(Error::from(error)));
+
+    write!(out, "{}", report.styled(&journal.commodities))
+}
+
+// $ katana calendar
+fn calendar(out: &mut dyn Write, journal: &Journal, options: &CalendarOptions) -> io::Result<()> {
+    let report = phase("aggregate", || calendar_report(journal, options));
+
+    write!(out, "{}", report)
+}
+
+// $ katana payees
+fn payees(out: &mut dyn Write, journal: &Journal) -> io::Result<()> {
+    let report = phase("aggregate", || payees_report(journal));
+    let similar = report.iter().any(|line| line.similar);
+
+    for line in report {
+        writeln!(out, "{}", line)?;
+    }
+    if similar {
+        writeln!(out)?;
+        writeln!(out, "* differs from another payee only by case, spacing or punctuation")?;
+    }
+    Ok(())
+}
+
+// $ katana prices
+fn prices(out: &mut dyn Write, journal: &Journal, options: &PricesOptions) -> io::Result<()> {
+    for price in phase("aggregate", || prices_report(journal, options)) {
+        writeln!(out, "{}", price.styled(&journal.commodities))?;
+    }
+    Ok(())
+}
+
+// $ katana print
+fn print(out: &mut dyn Write, journal: &Journal, query: &Query) -> io::Result<()> {
+    let report = phase("aggregate", || print_report(journal, query));
+
+    let printed: Vec<String> = report.into_iter()
+                                     .map(|t| Printed(t, &journal.commodities).to_string())
+                                     .collect();
+    write!(out, "{}", printed.join("\n"))
+}
+
+// $ katana envelopes
+fn envelopes(out: &mut dyn Write, journal: &Journal) -> io::Result<()> {
+    let report = phase("aggregate", || envelope_report(journal)).unwrap_or_else(|error| exit_with(Error::from(error)));
+
+    writeln!(out, "{}  {}  {}  Envelope", pad_left("Allocated", 15), pad_left("Spent", 15), pad_left("Remaining", 15))?;
+    for line in &report {
+        writeln!(out, "{}", line.styled(&journal.commodities))?;
+    }
+    Ok(())
+}
+
+// $ katana envelopes allocate --month 2023-04
+//
+// nothing is written when there's nothing to allocate, so a monthly job appending it to
+// the journal can run more than once
+fn allocate(out: &mut dyn Write, journal: &Journal, month: MonthYear) -> io::Result<()> {
+    match phase("aggregate", || allocation(journal, month)).unwrap_or_else(|error| exit_with(Error::from(error))) {
+        Some(transaction) => write!(out, "{}", Printed(&transaction, &journal.commodities)),
+        None              => {
+            eprintln!("Nothing to allocate in {}, every budgeted expense has its allocation", month);
+            Ok(())
         },
-        _ => panic!("Unknown report type"),
     }
 }
 
-// $ katana balance
-fn balance(journal: &Journal, account: &str) {
-    let account = account.to_string();
-    let month: MonthYear = MonthYear::new(4, 2023);
-    let report: MonthGrid<Account, Amount> = balance_changes(journal);
+// $ katana forecast -a assets:checking --months 6
+//
+// the warning goes to stderr, so it's seen even when the report goes to a file
+fn forecast(out: &mut dyn Write, journal: &Journal, options: &ForecastOptions) -> io::Result<()> {
+    let report = phase("aggregate", || forecast_report(journal, options));
+
+    writeln!(out, "Projected from {} on {}, changing by {} on an average day besides the periodic transactions:", report.balance.styled(&journal.commodities), report.from, report.daily.styled(&journal.commodities))?;
+    for line in &report.lines {
+        writeln!(out, "{}", line.styled(&journal.commodities))?;
+    }
+
+    if let Some((date, balance)) = &report.below_zero {
+        eprintln!("Warning: the balance is projected to go below zero on {}, to {}", date, balance.styled(&journal.commodities));
+    }
+    Ok(())
+}
 
-    println!("Balance changes for {} in {}: {:?}",
-        account,
-        month,
-        report[(month, &account)]);
+// $ katana goals
+fn goals(out: &mut dyn Write, journal: &Journal) -> io::Result<()> {
+    let report = phase("aggregate", || goals_report(journal, today()));
+
+    writeln!(out, "{}  {}  {}  {}  {}  {}  Goal", pad_left("Saved", 15), pad_left("Target", 15), pad_left("Progress", 8),
+                                                pad_left("Monthly", 15), pad_left("Recent", 15), pad_right("Projected", 9))?;
+    for line in &report {
+        writeln!(out, "{}", line.styled(&journal.commodities))?;
+    }
+    Ok(())
+}
+
+// $ katana lots
+fn lots(out: &mut dyn Write, journal: &Journal) -> io::Result<()> {
+    let report = phase("aggregate", || track_lots(journal)).unwrap_or_else(|error| exit_with(Error::from(error)));
+
+    writeln!(out, "Open lots:")?;
+    writeln!(out, "{}  {}  {}  {}", pad_right("Acquired", 10), pad_right("Account", 24), pad_left("Quantity", 15), pad_left("Cost", 15))?;
+    for lot in &report.open {
+        writeln!(out, "{}", lot.styled(&journal.commodities))?;
+    }
+
+    writeln!(out)?;
+    writeln!(out, "Realized gains:")?;
+    writeln!(out, "{}  {}  {}  {}  {}  {}  {}", pad_right("Sold", 10), pad_right("Account", 24), pad_left("Quantity", 15), pad_right("Acquired", 10),
+                                                pad_left("Cost", 15), pad_left("Proceeds", 15), pad_left("Gain", 15))?;
+    for sale in &report.realized {
+        writeln!(out, "{}", sale.styled(&journal.commodities))?;
+    }
+    Ok(())
+}
+
+// $ katana tags --tag trip
+fn tags(out: &mut dyn Write, journal: &Journal, options: &TagsOptions) -> io::Result<()> {
+    let report = phase("aggregate", || tags_report(journal, options));
+
+    writeln!(out, "Totals by {}:", options.tag)?;
+    for line in &report {
+        writeln!(out, "{}", line.styled(&journal.commodities))?;
+    }
+    Ok(())
+}
+
+// $ katana top
+fn top(out: &mut dyn Write, journal: &Journal, options: &TopOptions) -> io::Result<()> {
+    let report = phase("aggregate", || top_report(journal, options));
+
+    for line in &report {
+        writeln!(out, "{}", line.styled(&journal.commodities))?;
+    }
+    Ok(())
 }
 
 // $ katana register
-fn register(journal: &Journal, account: &str) {
-    let account = account.to_string();
-    let report = register_report(journal, &account);
+fn register(out: &mut dyn Write, journal: &Journal, query: &Query, options: &RegisterOptions, line_format: &LineFormat, format: OutputFormat, summary: bool) -> io::Result<()> {
+    // the lines are made as they're written, so a text report piped to head stops early.
+    // making each one is timed as aggregating, not just setting up the report
+    let mut report = phase("aggregate", || register_report(journal, query, options));
+    let lines = std::iter::from_fn(|| phase("aggregate", || report.next()))
+                     .map(|line| line.unwrap_or_else(|error| exit_with(Error::from(error))));
 
-    println!("Register report for account {}:", account);
-    for line in report {
-        println!("{}", line);
-    }
-}
-
-fn read_journal(journal_file: &str) -> Journal {
-    let contents = fs::read_to_string(journal_file)
-                      .expect("Couldn't read journal file");
-
-    Journal::from_lines(contents.lines())
-            .unwrap_or_else(|error| panic!("Error reading journal: {}", error))
-}
-
-fn get_args() -> clap::ArgMatches {
-    App::new("katana")
-        .arg(
-            Arg::new("report")
-                .help("The report to run")
-                .index(1)
-                .required(true)
-                .possible_values(&["balance", "register"])
-        )
-        .arg(
-            Arg::new("account")
-                .short('a')
-                .long("account")
-                .value_name("ACCOUNT")
-                .help("Set the account name")
-                .takes_value(true)
-                .required(false),
-        )
-        .arg(
-            Arg::new("journal")
-                .short('j')
-                .long("journal")
-                .value_name("JOURNAL")
-                .help("Set the journal file")
-                .takes_value(true)
-                .required(true),
-        )
-        .get_matches()
+    match format {
+        OutputFormat::Csv  => return write!(out, "{}", to_csv(&lines.collect::<Vec<_>>()[..].styled(&journal.commodities))),
+        OutputFormat::Json => return write!(out, "{}", to_json(&lines.collect::<Vec<_>>()[..])),
+        OutputFormat::Html => return write!(out, "{}", to_html(&lines.collect::<Vec<_>>()[..].styled(&journal.commodities))),
+        OutputFormat::Text | OutputFormat::Sql | OutputFormat::Svg => {}
+    }
+
+    // the summary adds up the lines as they go by instead of running the report again
+    let mut totals = RegisterSummary::default();
+
+    writeln!(out, "Register report for {}:", query)?;
+    for line in lines {
+        writeln!(out, "{}", line.render(line_format, &journal.commodities))?;
+        if summary {
+            phase("aggregate", || totals.add(&line)).unwrap_or_else(|error| exit_with(Error::from(error)));
+        }
+    }
+
+    if summary {
+        writeln!(out, "{}", totals.styled(&journal.commodities))?;
+    }
+    Ok(())
+}
+
+// $ katana check
+fn check(global: &GlobalArgs, journal_files: &[String], args: &CheckArgs) {
+    let mut transactions = 0;
+    let mut problems     = vec![];
+    let mut merged       = Journal::default();
+
+    for journal_file in journal_files {
+        let contents = read_journal_file(journal_file);
+        let (journal, found) = Journal::from_lines_lenient(contents.lines(), date_order(global), &merged.commodities);
+
+        transactions += journal.transactions.len();
+        problems.extend(found.into_iter().map(|problem| (journal_file, problem)));
+        merged.merge(journal);
+    }
+
+    // the months an account went over its budget, which are problems with --budget-alerts
+    let overspent = if args.budget_alerts {
+        prepare_journal(global, &mut merged);
+        Budget::from_journal(&merged, &Query::default()).and_then(|budget| overspends(&merged, &budget, &Query::default()))
+                                                        .unwrap_or_else(|error| exit_with(Error::from(error)))
+    } else {
+        vec![]
+    };
+
+    if problems.is_empty() && overspent.is_empty() {
+        println!("No problems found in {} transactions", transactions);
+        return;
+    }
+
+    let errors: Vec<Error> = problems.iter()
+                                     .map(|(journal_file, problem)| Error::from_problem(journal_file, problem))
+                                     .chain(overspent.iter().map(|overspend| Error::new(ErrorKind::Validation, format!("Over budget: {}", overspend.styled(&merged.commodities)))))
+                                     .collect();
+
+    if global.error_format == ErrorFormat::Json {
+        errors.iter().for_each(report);
+    } else {
+        for (journal_file, problem) in &problems {
+            println!("{}: {}", journal_file, problem);
+        }
+        for overspend in &overspent {
+            println!("Over budget: {}", overspend.styled(&merged.commodities));
+        }
+        println!("Found {} problem(s)", errors.len());
+    }
+
+    // a problem the parser couldn't get past outranks one that only doesn't add up
+    let kind = if errors.iter().any(|error| error.kind == ErrorKind::Parse) { ErrorKind::Parse } else { ErrorKind::Validation };
+    std::process::exit(kind.exit_code());
+}
+
+// $ katana rules test
+fn rules(command: &RulesCommand) {
+    match command {
+        RulesCommand::Test { rules, sample } => rules_test(rules, sample),
+    }
+}
+
+fn rules_test(rules_file: &str, sample: &str) {
+    let contents = fs::read_to_string(rules_file)
+                      .unwrap_or_else(|error| exit_with(Error::new(ErrorKind::Io, format!("Couldn't read rules file: {}", error)).in_file(rules_file)));
+
+    let rules = Rules::from_lines(contents.lines())
+                      .unwrap_or_else(|error| exit_with(Error::from(error).in_file(rules_file)));
+
+    let (rule, transaction) = rules.apply(sample)
+                                   .unwrap_or_else(|error| exit_with(Error { message: format!("Error applying rules: {}", error),
+                                                                            ..Error::from(error) }));
+
+    match rule {
+        Some(rule) => println!("Matched the rule at line {}: if {}", rule.line, rule.pattern),
+        None       => println!("No rule matched, using the defaults"),
+    }
+    println!();
+    print!("{}", transaction);
+}
+
+// $ katana register --monthly
+fn register_periodic(out: &mut dyn Write, journal: &Journal, query: &Query, interval: Interval, options: &RegisterOptions, format: OutputFormat, summary: bool) -> io::Result<()> {
+    let report = phase("aggregate", || register_periodic_report(journal, query, interval, options)).unwrap_or_else(|error| exit_with(Error::from(error)));
+
+    match format {
+        OutputFormat::Csv  => return write!(out, "{}", to_csv(&report[..].styled(&journal.commodities))),
+        OutputFormat::Json => return write!(out, "{}", to_json(&report[..])),
+        OutputFormat::Html => return write!(out, "{}", to_html(&report[..].styled(&journal.commodities))),
+        OutputFormat::Text | OutputFormat::Sql | OutputFormat::Svg => {}
+    }
+
+    writeln!(out, "Register report for {} ({}):", query, interval)?;
+    for line in &report {
+        writeln!(out, "{}", line.styled(&journal.commodities))?;
+    }
+
+    // the periods don't have the postings' lines to add up, so the summary sums them itself
+    if summary {
+        let totals = phase("aggregate", || register_summary(journal, query, options)).unwrap_or_else(|error| exit_with(Error::from(error)));
+        writeln!(out, "{}", totals.styled(&journal.commodities))?;
+    }
+    Ok(())
+}
+
+// the journal files read one after the other and merged into one journal, with the
+// transactions and prices kept in date order. with --strict each file has to be in date
+// order already
+fn read_journal(global: &GlobalArgs, journal_files: &[String]) -> Journal {
+    let mut journal = Journal::default();
+
+    // each file is parsed with the commodity directives from the ones before it
+    for journal_file in journal_files {
+        let contents = info_span!("read", file = journal_file).in_scope(|| phase("read", || read_journal_file(journal_file)));
+        info!(bytes = contents.len(), "read the journal file");
+
+        let read = info_span!("parse").in_scope(|| phase("parse", || Journal::from_lines_after(contents.lines(), date_order(global), &journal.commodities)))
+                                      .unwrap_or_else(|problem| exit_with(Error::from_problem(journal_file, &problem)));
+        journal.merge(read);
+    }
+
+    count("parse", journal.transactions.len());
+    info!(transactions = journal.transactions.len(),
+          periodic     = journal.periodic.len(),
+          prices       = journal.prices.len(),
+          "parsed the journal");
+
+    journal
+}
+
+// the report goes to the -o file, or else stdout
+fn output(global: &GlobalArgs) -> Box<dyn Write> {
+    match &global.output_file {
+        Some(path) => Box::new(BufWriter::new(File::create(path).unwrap_or_else(|error| exit_with(Error::new(ErrorKind::Io, format!("Couldn't create it: {}", error)).in_file(path))))),
+        None       => Box::new(io::stdout()),
+    }
+}
+
+// $ katana gen --transactions 100000
+fn gen(global: &GlobalArgs, args: &GenArgs) {
+    let options = GenOptions {
+        transactions: args.transactions,
+        seed        : args.seed,
+        start       : global.begin.unwrap_or_else(|| NaiveDate::from_ymd_opt(2020, 1, 1).unwrap()),
+    };
+
+    let mut out = output(global);
+    generate(out.as_mut(), &options).and_then(|_| out.flush())
+                                    .unwrap_or_else(|error| exit_with(Error::new(ErrorKind::Io, format!("Couldn't write the journal: {}", error))));
+}
+
+// $ katana generate --until 2024/12 --append
+//
+// the transactions start from -b or the day after the last recorded one, so running it
+// again after appending them carries on from where the last run left off
+fn generate_recurring(global: &GlobalArgs, journal_files: &[String], args: &GenerateArgs) {
+    let journal = read_journal(global, journal_files);
+
+    let from = global.begin.unwrap_or_else(|| {
+        journal.transactions
+               .last()
+               .and_then(|t| t.date.succ_opt())
+               .unwrap_or_else(today)
+    });
+
+    let generated = journal.occurrences(from, args.until);
+    if generated.is_empty() {
+        eprintln!("Nothing to generate from {} until {}", from, args.until);
+        return;
+    }
+
+    let printed: Vec<String> = generated.iter()
+                                        .map(|t| Printed(t, &journal.commodities).to_string())
+                                        .collect();
+
+    if args.append {
+        let journal_file = journal_files.last().expect("A journal path names at least one file");
+        OpenOptions::new().append(true)
+                          .open(journal_file)
+                          .and_then(|mut file| write!(file, "\n{}", printed.join("\n")))
+                          .unwrap_or_else(|error| exit_with(Error::new(ErrorKind::Io, format!("Couldn't add to it: {}", error)).in_file(journal_file)));
+    } else {
+        let mut out = output(global);
+        write!(out, "{}", printed.join("\n")).and_then(|_| out.flush())
+                                             .unwrap_or_else(|error| exit_with(Error::new(ErrorKind::Io, format!("Couldn't write the transactions: {}", error))));
+    }
+}
+
+// leave out virtual postings, add the forecast, keep to the date range and value the amounts,
+// as the global options ask
+fn prepare_journal(global: &GlobalArgs, journal: &mut Journal) {
+    if global.real {
+        journal.drop_virtual();
+    }
+
+    let range = date_range(global);
+    if global.forecast {
+        let (from, until) = forecast_dates(journal, &range);
+        journal.add_forecast(from, until);
+    }
+    if !range.is_unbounded() {
+        journal.restrict_to(&range);
+    }
+
+    if let Some(valuation) = valuation(global) {
+        let date = match global.value_at.as_str() {
+            "transaction" => None,
+            _             => Some(valuation_date(journal, &range)),
+        };
+        value_journal(journal, &valuation, date).unwrap_or_else(|error| exit_with(Error::from(error)));
+        debug!(?date, "valued the journal");
+    }
+}
+
+// with --strict each journal file has to be in date order already, instead of being sorted
+fn date_order(global: &GlobalArgs) -> DateOrder {
+    if global.strict { DateOrder::Require } else { DateOrder::Sort }
+}
+
+// the journal files to reread as they change, for serve and web --watch
+fn watch<'a>(global: &'a GlobalArgs, journal_files: &[&str], args: &ServerArgs) -> Option<WatchedJournal<'a>> {
+    if !args.watch {
+        return None
+    }
+
+    let watched = WatchedJournal::new(journal_files, date_order(global), |journal| prepare_journal(global, journal));
+    Some(watched.unwrap_or_else(|error| exit_with(error)))
+}
+
+// log to stderr in more detail with each -v: progress, counts and the time each phase
+// took with one, skipped problems and the query with two, every transaction with three
+fn init_logging(verbose: u8) {
+    let level = match verbose {
+        0 => return,
+        1 => Level::INFO,
+        2 => Level::DEBUG,
+        _ => Level::TRACE,
+    };
+
+    tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_writer(io::stderr)
+        .with_target(false)
+        .with_span_events(FmtSpan::CLOSE)
+        .with_timer(Uptime::default())
+        .init();
+}
+
+fn read_journal_file(journal_file: &str) -> String {
+    fs::read_to_string(journal_file)
+       .unwrap_or_else(|error| exit_with(Error::new(ErrorKind::Io, format!("Couldn't read journal file: {}", error)).in_file(journal_file)))
+}
+
+// a bad option or argument found by clap, which writes it the usual way unless JSON errors
+// were asked for. --help and --version come through here too
+fn usage_error(error: clap::Error) -> ! {
+    let json = error_format_in(std::env::args()) == ErrorFormat::Json;
+
+    if !json || !error.use_stderr() {
+        error.exit()
+    }
+
+    let message = error.to_string();
+    let message = message.lines().next().unwrap_or_default().trim_start_matches("error: ");
+    set_error_format(ErrorFormat::Json);
+    exit_with(Error::usage(message))
+}
+
+// the report's query, from its terms after the ones from its own options like -a
+fn query(journal: &Journal, args: &QueryArgs, options: &[String]) -> Query {
+    let query = args.query(options).unwrap_or_else(|error| exit_with(Error::usage(format!("Invalid query: {}", error))));
+    debug!(%query, "parsed the query");
+
+    // counting takes a pass over the journal, so only when it'll be seen
+    if tracing::enabled!(Level::INFO) || profiling() {
+        let start   = Instant::now();
+        let matched = phase("filter", || journal.filter(&query).count());
+        info!(transactions = matched, of = journal.transactions.len(), elapsed = ?start.elapsed(), "matched the query");
+        count("filter", matched);
+    }
+    query
+}
+
+// the format from --output-format, or else from the extension of the --output-file, like
+// report.csv, and otherwise plain text
+fn output_format(global: &GlobalArgs) -> OutputFormat {
+    if let Some(format) = global.output_format {
+        return format
+    }
+
+    global.output_file
+          .as_deref()
+          .and_then(|path| Path::new(path).extension())
+          .and_then(|extension| extension.to_str()?.parse().ok())
+          .unwrap_or(OutputFormat::Text)
+}
+
+// the dates to report on, from -p with -b and -e taking precedence over either side of it
+fn date_range(global: &GlobalArgs) -> DateRange {
+    let period = global.period.as_ref().map(|(range, _)| *range).unwrap_or_default();
+
+    DateRange::new(global.begin.or(period.begin),
+                   global.end.or(period.end))
+}
+
+// forecast transactions start the day after the last recorded one, or today for an empty
+// journal, and run up to the end date if there is one or otherwise to the end of next month
+fn forecast_dates(journal: &Journal, range: &DateRange) -> (NaiveDate, NaiveDate) {
+    let from = journal.transactions
+                      .last()
+                      .and_then(|t| t.date.succ_opt())
+                      .unwrap_or_else(today);
+
+    let until = range.end.unwrap_or_else(|| {
+        let next_month = Interval::Monthly.next_start(today());
+        Interval::Monthly.next_start(next_month)
+    });
+
+    (from, until)
+}
+
+fn valuation(global: &GlobalArgs) -> Option<Valuation> {
+    if let Some(commodity) = &global.exchange {
+        Some(Valuation::Exchange(commodity.as_str().into()))
+    } else if global.market {
+        Some(Valuation::Market)
+    } else {
+        None
+    }
+}
+
+// the day to look up market prices for, which is the last day of the report. that's the
+// day before the end date if there is one, or otherwise the date of the latest transaction
+// or price, whichever is later
+fn valuation_date(journal: &Journal, range: &DateRange) -> NaiveDate {
+    if let Some(end) = range.end {
+        return end.pred_opt().unwrap()
+    }
+
+    journal.transactions.last().map(|t| t.date)
+           .max(journal.prices.last().map(|p| p.date))
+           .unwrap_or_else(today)
 }
@@ -1,91 +1,16 @@
-use std::collections::HashMap;
-use std::ops::{Index, IndexMut};
-use std::hash::Hash;
-
+use crate::periodgrid::{GridPeriod, PeriodGrid};
 use crate::types::monthyear::MonthYear;
 
-/// This is a 2D structure with consecutive months as column keys and a generic
-/// type for row keys, usually an Account name.
-///
-/// Internally, MonthGrid uses a utility function month_year_to_index to convert a
-/// given MonthYear to an index within a row's Vec, for efficient retrieval of data.
-pub struct MonthGrid<K, T>
-where
-    K: Hash + Eq,
-{
-    grid: HashMap<K, Vec<Option<T>>>,
-    start_month: MonthYear,
-    total_months: usize,
-}
-
-impl<K, T> MonthGrid<K, T>
-where
-    K: Hash + Eq + Clone,
-    T: Clone
-{
-    pub fn new(first: MonthYear, last: MonthYear) -> Self {
-        assert!(last >= first);
-        
-        let total_months = ((last.year - first.year) * 12
-            + (last.month - first.month)) as usize
-            + 1;
-
-        Self {
-            grid: HashMap::new(),
-            start_month: first,
-            total_months,
-        }
-    }
-
-    pub fn insert(&mut self, key: K, month_year: MonthYear, value: T) {
-        if let Some(row) = self.grid.get_mut(&key) {
-            let index = Self::month_year_to_index(self.start_month, month_year);
-            row[index] = Some(value);
-        } else {
-            let mut row = vec![None; self.total_months];
-            let index = Self::month_year_to_index(self.start_month, month_year);
-            row[index] = Some(value);
-            self.grid.insert(key, row);
-        }
-    }
-
-    fn month_year_to_index(first: MonthYear, this: MonthYear) -> usize {
-        ((this.year - first.year) * 12
-            + (this.month - first.month)) as usize
-    }
-}
+/// A PeriodGrid with a column for each month.
+pub type MonthGrid<K, T> = PeriodGrid<MonthYear, K, T>;
 
-impl<K, T> Index<(MonthYear, &K)> for MonthGrid<K, T>
-where
-    K: Hash + Eq + Clone,
-    T: Clone
-{
-    type Output = Option<T>;
-
-    fn index(&self, index: (MonthYear, &K)) -> &Self::Output {
-        let (month, key) = index;
-        match self.grid.get(key) {
-            Some(row) => {
-                let idx = Self::month_year_to_index(self.start_month, month);
-                &row[idx]
-            }
-            None => &None,
-        }
+impl GridPeriod for MonthYear {
+    fn next(&self) -> Self {
+        self.next_month()
     }
-}
 
-impl<K, T> IndexMut<(MonthYear, &K)> for MonthGrid<K, T>
-where
-    K: Hash + Eq + Clone,
-    T: Clone
-{
-    fn index_mut(&mut self, index: (MonthYear, &K)) -> &mut Self::Output {
-        let (month, key) = index;
-        self.grid
-            .entry(key.clone())
-            .or_insert_with(|| vec![None; self.total_months])
-            .get_mut(Self::month_year_to_index(self.start_month, month))
-            .expect("Index out of bounds")
+    fn periods_since(&self, first: &Self) -> i64 {
+        first.months_between(self)
     }
 }
 
@@ -96,6 +21,7 @@ where
 #[cfg(test)]
 mod tests {
     use crate::monthgrid::MonthGrid;
+    use crate::periodgrid::checked;
     use crate::types::monthyear::MonthYear;
 
     #[test]
@@ -117,13 +43,11 @@ mod tests {
         // Test indexing a non-existent key
         assert_eq!(grid[(month_year, &"non_existent_key".to_string())], None);
 
-        // TODO
         // Test indexing a non-existent month-year
-        //assert_eq!(grid[(MonthYear::new(12, 1999), &key)], None);
+        assert_eq!(grid[(MonthYear::new(12, 1999), &key)], None);
     }
 
     #[test]
-    #[should_panic(expected = "attempt to subtract with overflow")] // TODO
     fn test_index_out_of_bounds_too_low() {
         let start_month_year = MonthYear::new(1, 2000);
         let end_month_year = MonthYear::new(12, 2023);
@@ -138,7 +62,6 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = "index out of bounds: the len is 288 but the index is 288")] // TODO
     fn test_index_out_of_bounds_too_high() {
         let start_month_year = MonthYear::new(1, 2000);
         let end_month_year = MonthYear::new(12, 2023);
@@ -152,6 +75,79 @@ mod tests {
         assert_eq!(grid[(MonthYear::new(1, 2024), &key)], None);
     }
 
+    fn sample_grid() -> MonthGrid<String, i32> {
+        let mut grid = MonthGrid::new(MonthYear::new(1, 2023), MonthYear::new(4, 2023));
+        grid.insert("food".to_string(), MonthYear::new(1, 2023), 10);
+        grid.insert("food".to_string(), MonthYear::new(3, 2023), 20);
+        grid.insert("rent".to_string(), MonthYear::new(3, 2023), 100);
+        grid
+    }
+
+    #[test]
+    fn test_rows_and_columns() {
+        let grid = sample_grid();
+
+        let columns: Vec<String> = grid.columns().map(|month| month.to_string()).collect();
+        assert_eq!(columns, vec!["2023-01", "2023-02", "2023-03", "2023-04"]);
+
+        let mut rows: Vec<(&String, &[Option<i32>])> = grid.rows().collect();
+        rows.sort();
+        assert_eq!(rows, vec![(&"food".to_string(), &[Some(10), None, Some(20), None][..]),
+                              (&"rent".to_string(), &[None, None, Some(100), None][..])]);
+
+        let mut cells: Vec<(MonthYear, &String, &i32)> = grid.cells().collect();
+        cells.sort_by_key(|(month, key, _)| (month.year, month.month, *key));
+        assert_eq!(cells, vec![(MonthYear::new(1, 2023), &"food".to_string(), &10),
+                               (MonthYear::new(3, 2023), &"food".to_string(), &20),
+                               (MonthYear::new(3, 2023), &"rent".to_string(), &100)]);
+    }
+
+    #[test]
+    fn test_totals() {
+        let grid = sample_grid();
+
+        assert_eq!(grid.row_total(&"food".to_string(), checked), Ok(Some(30)));
+        assert_eq!(grid.row_total(&"gas".to_string(), checked), Ok(None));
+        assert_eq!(grid.column_total(MonthYear::new(3, 2023), checked), Ok(Some(120)));
+        assert_eq!(grid.column_total(MonthYear::new(2, 2023), checked), Ok(None));
+    }
+
+    #[test]
+    fn test_get() {
+        let mut grid = sample_grid();
+        let food = "food".to_string();
+
+        assert_eq!(grid.get(MonthYear::new(1, 2023), &food), Some(&10));
+        assert_eq!(grid.get(MonthYear::new(2, 2023), &food), None);
+        assert_eq!(grid.get(MonthYear::new(1, 2023), &"gas".to_string()), None);
+        assert_eq!(grid.get(MonthYear::new(12, 2022), &food), None);
+        assert_eq!(grid.get(MonthYear::new(5, 2023), &food), None);
+
+        *grid.get_mut(MonthYear::new(3, 2023), &food).unwrap() += 5;
+        assert_eq!(grid.get(MonthYear::new(3, 2023), &food), Some(&25));
+        assert_eq!(grid.get_mut(MonthYear::new(5, 2023), &food), None);
+    }
+
+    #[test]
+    fn test_insert_grows() {
+        let mut grid = sample_grid();
+        let food = "food".to_string();
+
+        grid.insert(food.clone(), MonthYear::new(11, 2022), 5);
+        grid.insert("gas".to_string(), MonthYear::new(6, 2023), 40);
+
+        let columns: Vec<String> = grid.columns().map(|month| month.to_string()).collect();
+        assert_eq!(columns.first().unwrap(), "2022-11");
+        assert_eq!(columns.last().unwrap(), "2023-06");
+        assert_eq!(columns.len(), 8);
+
+        assert_eq!(grid.get(MonthYear::new(11, 2022), &food), Some(&5));
+        assert_eq!(grid.get(MonthYear::new(1, 2023), &food), Some(&10));
+        assert_eq!(grid.get(MonthYear::new(3, 2023), &"rent".to_string()), Some(&100));
+        assert_eq!(grid.row_total(&food, checked), Ok(Some(35)));
+        assert_eq!(grid.column_total(MonthYear::new(6, 2023), checked), Ok(Some(40)));
+    }
+
     #[test]
     fn test_index_mut() {
         let start_month_year = MonthYear::new(1, 2000);
@@ -168,8 +164,9 @@ mod tests {
         grid[(month_year, &key)] = Some(24);
         assert_eq!(grid[(month_year, &key)], Some(24));
 
-        // Test setting a non-existent month-year to None (no effect)
-        // grid[(MonthYear::new(12, 1999), &key)] = None;
-        // assert_eq!(grid[(MonthYear::new(12, 1999), &key)], None);
+        // Test setting a month-year before the grid, which grows it
+        grid[(MonthYear::new(12, 1999), &key)] = Some(7);
+        assert_eq!(grid[(MonthYear::new(12, 1999), &key)], Some(7));
+        assert_eq!(grid[(month_year, &key)], Some(24));
     }
 }
@@ -0,0 +1,294 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::types::amount::{Amount, AmountType};
+use crate::types::mixedamount::MixedAmount;
+
+
+/* Output formats */
+
+// how a report is written out, chosen with --output-format
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    Text,
+    Csv,
+    Json,
+    Html,
+    Sql,  // only for the export report
+    Svg,  // only for the chart report
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" | "txt" => Ok(OutputFormat::Text),
+            "csv"          => Ok(OutputFormat::Csv),
+            "json"         => Ok(OutputFormat::Json),
+            "html" | "htm" => Ok(OutputFormat::Html),
+            "sql"          => Ok(OutputFormat::Sql),
+            "svg"          => Ok(OutputFormat::Svg),
+            _              => Err(s.to_string()),
+        }
+    }
+}
+
+
+/* CSV */
+
+// a report that can be written as CSV: a header naming the columns, which stay the same
+// from run to run for the same options, then one record per row of the report
+pub trait Csv {
+    fn csv_header(&self) -> Vec<String>;
+    fn csv_records(&self) -> Vec<Vec<String>>;
+}
+
+pub fn to_csv<T: Csv + ?Sized>(report: &T) -> String {
+    std::iter::once(report.csv_header())
+        .chain(report.csv_records())
+        .map(|record| csv_record(&record) + "\n")
+        .collect()
+}
+
+// the fields separated by commas, with any field that needs it quoted
+fn csv_record(fields: &[String]) -> String {
+    fields.iter()
+          .map(|field| csv_field(field))
+          .collect::<Vec<String>>()
+          .join(",")
+}
+
+// a field with a comma, quote, line break or surrounding space is wrapped in quotes, with
+// its own quotes doubled up
+fn csv_field(field: &str) -> String {
+    let needs_quotes = field.contains([',', '"', '\n', '\r'])
+                       || field.trim() != field;
+
+    if needs_quotes {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+
+/* JSON */
+
+// just enough of JSON to write out reports. numbers are kept as the text to write so
+// amounts come out with exactly their decimal places
+#[derive(Clone, Debug, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(&'static str, JsonValue)>),
+}
+
+// a report, or part of one, that can be written as JSON
+pub trait Json {
+    fn to_json(&self) -> JsonValue;
+}
+
+impl<T: Json> Json for Option<T> {
+    fn to_json(&self) -> JsonValue {
+        self.as_ref().map_or(JsonValue::Null, Json::to_json)
+    }
+}
+
+impl<T: Json> Json for [T] {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Array(self.iter().map(Json::to_json).collect())
+    }
+}
+
+impl Json for str {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::String(self.to_string())
+    }
+}
+
+impl Json for String {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::String(self.clone())
+    }
+}
+
+// {"quantity": 10.25, "commodity": "$", "decimals": 2}, where a commodity without a fixed
+// number of decimal places has null decimals
+impl Json for Amount {
+    fn to_json(&self) -> JsonValue {
+        let (quantity, decimals) = match self.amount {
+            AmountType::Discrete(_, dec) => (JsonValue::Number(self.quantity()), JsonValue::Number(dec.to_string())),
+            AmountType::Decimal(_)       => (JsonValue::Number(self.quantity()), JsonValue::Null),
+        };
+
+        JsonValue::Object(vec![
+            ("quantity",  quantity),
+            ("commodity", self.units.to_json()),
+            ("decimals",  decimals),
+        ])
+    }
+}
+
+// a list of the amounts, one per commodity
+impl Json for MixedAmount {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Array(self.amounts().map(Json::to_json).collect())
+    }
+}
+
+pub fn to_json<T: Json + ?Sized>(report: &T) -> String {
+    format!("{}\n", report.to_json())
+}
+
+impl fmt::Display for JsonValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonValue::Null          => write!(f, "null"),
+            JsonValue::Bool(b)       => write!(f, "{}", b),
+            JsonValue::Number(n)     => write!(f, "{}", n),
+            JsonValue::String(s)     => write!(f, "{}", json_string(s)),
+            JsonValue::Array(values) => {
+                let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+                write!(f, "[{}]", values.join(","))
+            },
+            JsonValue::Object(fields) => {
+                let fields: Vec<String> = fields.iter()
+                                                .map(|(name, value)| format!("{}:{}", json_string(name), value))
+                                                .collect();
+                write!(f, "{{{}}}", fields.join(","))
+            },
+        }
+    }
+}
+
+// a quoted string with quotes, backslashes and control characters escaped
+fn json_string(s: &str) -> String {
+    let mut quoted = String::from('"');
+    for c in s.chars() {
+        match c {
+            '"'                 => quoted.push_str("\\\""),
+            '\\'                => quoted.push_str("\\\\"),
+            '\n'                => quoted.push_str("\\n"),
+            '\r'                => quoted.push_str("\\r"),
+            '\t'                => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c                   => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+
+/* HTML */
+
+// the same rows as the CSV, as a table for a web page. the cells are left as text, so
+// amounts keep their usual formatting
+pub fn to_html<T: Csv + ?Sized>(report: &T) -> String {
+    let row = |tag: &str, fields: &[String]| {
+        let cells: String = fields.iter()
+                                  .map(|field| format!("<{tag}>{}</{tag}>", html_escape(field), tag = tag))
+                                  .collect();
+        format!("<tr>{}</tr>\n", cells)
+    };
+
+    let mut html = String::from("<table>\n<thead>\n");
+    html += &row("th", &report.csv_header());
+    html += "</thead>\n<tbody>\n";
+    for record in report.csv_records() {
+        html += &row("td", &record);
+    }
+    html += "</tbody>\n</table>\n";
+    html
+}
+
+pub fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use crate::types::amount::Amount;
+    use crate::types::mixedamount::MixedAmount;
+    use super::{Csv, Json, JsonValue, csv_field, json_string, to_csv, to_html};
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!(csv_field("assets:cash"), "assets:cash");
+        assert_eq!(csv_field("Sandwich, Chili"), "\"Sandwich, Chili\"");
+        assert_eq!(csv_field("the \"good\" kind"), "\"the \"\"good\"\" kind\"");
+        assert_eq!(csv_field(" padded"), "\" padded\"");
+        assert_eq!(csv_field(""), "");
+    }
+
+    struct Sample;
+
+    impl Csv for Sample {
+        fn csv_header(&self) -> Vec<String> {
+            vec!["account".to_string(), "amount".to_string()]
+        }
+
+        fn csv_records(&self) -> Vec<Vec<String>> {
+            vec![vec!["expenses:food".to_string(), "$12.00, 308.000".to_string()]]
+        }
+    }
+
+    #[test]
+    fn test_to_csv() {
+        assert_eq!(to_csv(&Sample), "account,amount\nexpenses:food,\"$12.00, 308.000\"\n");
+    }
+
+    #[test]
+    fn test_to_html() {
+        assert_eq!(to_html(&Sample),
+"<table>
+<thead>
+<tr><th>account</th><th>amount</th></tr>
+</thead>
+<tbody>
+<tr><td>expenses:food</td><td>$12.00, 308.000</td></tr>
+</tbody>
+</table>
+");
+    }
+
+    #[test]
+    fn test_amount_json() {
+        assert_eq!(Amount::from("$".to_string(), -14.99).to_json().to_string(),
+                   r#"{"quantity":-14.99,"commodity":"$","decimals":2}"#);
+        assert_eq!(Amount::from("kWh".to_string(), 308.5).to_json().to_string(),
+                   r#"{"quantity":308.5,"commodity":"kWh","decimals":null}"#);
+
+        let mut mixed = MixedAmount::from(Amount::from("$".to_string(), 1.0));
+        mixed.add_amount(&Amount::from("kWh".to_string(), 2.0));
+        assert_eq!(mixed.to_json().to_string(),
+                   r#"[{"quantity":1.00,"commodity":"$","decimals":2},{"quantity":2,"commodity":"kWh","decimals":null}]"#);
+    }
+
+    #[test]
+    fn test_json_string() {
+        assert_eq!(json_string("Sandwich, \"Chili\""), r#""Sandwich, \"Chili\"""#);
+        assert_eq!(json_string("a\\b\nc"), r#""a\\b\nc""#);
+        assert_eq!(json_string("\u{1}"), r#""\u0001""#);
+    }
+
+    #[test]
+    fn test_json_value() {
+        let value = JsonValue::Object(vec![
+            ("name",   JsonValue::String("katana".to_string())),
+            ("tags",   JsonValue::Array(vec![JsonValue::Bool(true), JsonValue::Null])),
+            ("amount", None::<Amount>.to_json()),
+        ]);
+        assert_eq!(value.to_string(), r#"{"name":"katana","tags":[true,null],"amount":null}"#);
+    }
+}
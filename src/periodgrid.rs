@@ -0,0 +1,277 @@
+use chrono::Datelike;
+use std::collections::HashMap;
+use std::ops::{Index, IndexMut};
+use std::hash::Hash;
+
+use crate::types::{interval::Interval, period::Period};
+
+/// A period that can label the columns of a PeriodGrid. The columns are consecutive
+/// periods, so the grid finds a period's column by how many periods it is after the
+/// grid's first one.
+pub trait GridPeriod: Copy {
+    fn next(&self) -> Self;
+
+    /// How many periods this one is after the first one, negative if it's before it
+    fn periods_since(&self, first: &Self) -> i64;
+}
+
+/// This is a 2D structure with consecutive periods as column keys and a generic
+/// type for row keys, usually an Account name. MonthGrid is the one with a column
+/// per month, and a grid of Periods can have a column per week, quarter or year.
+///
+/// Internally, PeriodGrid uses GridPeriod::periods_since to convert a given period
+/// to an index within a row's Vec, for efficient retrieval of data.
+pub struct PeriodGrid<P, K, T>
+where
+    K: Hash + Eq,
+{
+    grid: HashMap<K, Vec<Option<T>>>,
+    start: P,
+    total_periods: usize,
+}
+
+impl<P, K, T> PeriodGrid<P, K, T>
+where
+    P: GridPeriod,
+    K: Hash + Eq + Clone,
+    T: Clone
+{
+    pub fn new(first: P, last: P) -> Self {
+        let span = last.periods_since(&first);
+        assert!(span >= 0);
+
+        Self {
+            grid: HashMap::new(),
+            start: first,
+            total_periods: span as usize + 1,
+        }
+    }
+
+    /// Sets the cell, growing the grid to take in the period if it's outside the
+    /// periods the grid covers so far
+    pub fn insert(&mut self, key: K, period: P, value: T) {
+        self.grow_to(period);
+        let index = period.periods_since(&self.start) as usize;
+
+        if let Some(row) = self.grid.get_mut(&key) {
+            row[index] = Some(value);
+        } else {
+            let mut row = vec![None; self.total_periods];
+            row[index] = Some(value);
+            self.grid.insert(key, row);
+        }
+    }
+
+    /// The value in the cell, or None if it's empty, its row doesn't exist or the
+    /// period is outside the grid
+    pub fn get(&self, period: P, key: &K) -> Option<&T> {
+        let index = self.index_of(period)?;
+        self.grid.get(key)?[index].as_ref()
+    }
+
+    pub fn get_mut(&mut self, period: P, key: &K) -> Option<&mut T> {
+        let index = self.index_of(period)?;
+        self.grid.get_mut(key)?[index].as_mut()
+    }
+
+    /// Each row key along with its cells, one per period of the grid. Rows come in no
+    /// particular order
+    pub fn rows(&self) -> impl Iterator<Item = (&K, &[Option<T>])> {
+        self.grid.iter().map(|(key, row)| (key, row.as_slice()))
+    }
+
+    /// The periods of the grid, from the first to the last
+    pub fn columns(&self) -> impl Iterator<Item = P> {
+        std::iter::successors(Some(self.start), |period| Some(period.next()))
+            .take(self.total_periods)
+    }
+
+    /// Every cell holding a value, along with its period and row key
+    pub fn cells(&self) -> impl Iterator<Item = (P, &K, &T)> {
+        self.rows().flat_map(move |(key, row)| {
+            self.columns()
+                .zip(row)
+                .filter_map(move |(period, cell)| cell.as_ref().map(|value| (period, key, value)))
+        })
+    }
+
+    /// The sum of the values in a row, or None if it has none. The values are added up
+    /// with the given addition, which can fail, like for amounts too large to hold
+    pub fn row_total<E>(&self, key: &K, add: impl Fn(&mut T, &T) -> Result<(), E>) -> Result<Option<T>, E> {
+        match self.grid.get(key) {
+            Some(row) => total(row.iter().flatten(), add),
+            None      => Ok(None),
+        }
+    }
+
+    /// The sum of the values in a period, or None if it has none
+    pub fn column_total<E>(&self, period: P, add: impl Fn(&mut T, &T) -> Result<(), E>) -> Result<Option<T>, E> {
+        match self.index_of(period) {
+            Some(index) => total(self.grid.values().filter_map(|row| row[index].as_ref()), add),
+            None        => Ok(None),
+        }
+    }
+
+    // add empty periods to the start or end of every row until the grid covers the period
+    fn grow_to(&mut self, period: P) {
+        let offset = period.periods_since(&self.start);
+
+        if offset < 0 {
+            let before = offset.unsigned_abs() as usize;
+            for row in self.grid.values_mut() {
+                row.splice(0..0, vec![None; before]);
+            }
+            self.start          = period;
+            self.total_periods += before;
+        } else if offset as usize >= self.total_periods {
+            self.total_periods = offset as usize + 1;
+            for row in self.grid.values_mut() {
+                row.resize(self.total_periods, None);
+            }
+        }
+    }
+
+    // the index of the period within a row, if the grid covers it
+    fn index_of(&self, period: P) -> Option<usize> {
+        usize::try_from(period.periods_since(&self.start))
+            .ok()
+            .filter(|index| *index < self.total_periods)
+    }
+}
+
+fn total<'a, T, E>(mut values: impl Iterator<Item = &'a T>,
+                   add       : impl Fn(&mut T, &T) -> Result<(), E>) -> Result<Option<T>, E>
+where
+    T: Clone + 'a
+{
+    let Some(first) = values.next() else { return Ok(None) };
+    let mut total = first.clone();
+    for value in values {
+        add(&mut total, value)?;
+    }
+    Ok(Some(total))
+}
+
+// an addition for the tests' grids of numbers, which says so when they overflow
+#[cfg(test)]
+pub fn checked(total: &mut i32, value: &i32) -> Result<(), &'static str> {
+    *total = total.checked_add(*value).ok_or("overflow")?;
+    Ok(())
+}
+
+/// Indexing a period outside the grid, or a row that isn't there, gives an empty cell
+impl<P, K, T> Index<(P, &K)> for PeriodGrid<P, K, T>
+where
+    P: GridPeriod,
+    K: Hash + Eq + Clone,
+    T: Clone
+{
+    type Output = Option<T>;
+
+    fn index(&self, index: (P, &K)) -> &Self::Output {
+        let (period, key) = index;
+        match (self.grid.get(key), self.index_of(period)) {
+            (Some(row), Some(index)) => &row[index],
+            _                        => &None,
+        }
+    }
+}
+
+/// Indexing a cell to set it grows the grid to take in the period, the same as insert
+impl<P, K, T> IndexMut<(P, &K)> for PeriodGrid<P, K, T>
+where
+    P: GridPeriod,
+    K: Hash + Eq + Clone,
+    T: Clone
+{
+    fn index_mut(&mut self, index: (P, &K)) -> &mut Self::Output {
+        let (period, key) = index;
+        self.grow_to(period);
+
+        let index = period.periods_since(&self.start) as usize;
+        &mut self.grid
+            .entry(key.clone())
+            .or_insert_with(|| vec![None; self.total_periods])[index]
+    }
+}
+
+// periods of the same length line up by their start dates: weeks seven days apart,
+// quarters three months apart
+impl GridPeriod for Period {
+    fn next(&self) -> Self {
+        Period::next(self)
+    }
+
+    fn periods_since(&self, first: &Self) -> i64 {
+        assert_eq!(self.interval, first.interval, "Periods of different lengths in one grid");
+
+        let days   = (self.start - first.start).num_days();
+        let months = (self.start.year() as i64 - first.start.year() as i64) * 12
+                   + (self.start.month() as i64 - first.start.month() as i64);
+
+        match self.interval {
+            Interval::Daily     => days,
+            Interval::Weekly    => days.div_euclid(7),
+            Interval::Monthly   => months,
+            Interval::Quarterly => months.div_euclid(3),
+            Interval::Yearly    => months.div_euclid(12),
+        }
+    }
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use crate::types::{interval::Interval, period::Period};
+    use super::{GridPeriod, PeriodGrid, checked};
+
+    fn week(y: i32, m: u32, d: u32) -> Period {
+        Period::containing(Interval::Weekly, NaiveDate::from_ymd_opt(y, m, d).unwrap())
+    }
+
+    #[test]
+    fn test_periods_since() {
+        let quarter = Period::containing(Interval::Quarterly, NaiveDate::from_ymd_opt(2023, 2, 1).unwrap());
+        assert_eq!(quarter.next().next().periods_since(&quarter), 2);
+        assert_eq!(quarter.previous().periods_since(&quarter), -1);
+
+        assert_eq!(week(2023, 1, 2).periods_since(&week(2022, 12, 26)), 1);
+    }
+
+    #[test]
+    fn test_weekly_grid() {
+        let mut grid = PeriodGrid::new(week(2023, 1, 2), week(2023, 1, 16));
+        grid.insert("food", week(2023, 1, 4), 10);
+        grid.insert("food", week(2023, 1, 20), 5);
+        grid.insert("rent", week(2022, 12, 28), 100);
+
+        let columns: Vec<String> = grid.columns().map(|week| week.to_string()).collect();
+        assert_eq!(columns, vec!["2022-W52", "2023-W01", "2023-W02", "2023-W03"]);
+
+        assert_eq!(grid.get(week(2023, 1, 3), &"food"), Some(&10));
+        assert_eq!(grid[(week(2023, 1, 16), &"food")], Some(5));
+        assert_eq!(grid.row_total(&"food", checked), Ok(Some(15)));
+        assert_eq!(grid.column_total(week(2022, 12, 26), checked), Ok(Some(100)));
+    }
+
+    #[test]
+    fn test_index_outside_grid() {
+        let mut grid = PeriodGrid::new(week(2023, 1, 2), week(2023, 1, 16));
+        grid.insert("food", week(2023, 1, 4), 10);
+
+        // reading a week before or after the grid is an empty cell, not a panic
+        assert_eq!(grid[(week(2022, 12, 28), &"food")], None);
+        assert_eq!(grid[(week(2023, 1, 25), &"food")], None);
+        assert_eq!(grid[(week(2023, 1, 25), &"rent")], None);
+
+        // setting one grows the grid to take it in
+        grid[(week(2022, 12, 28), &"food")] = Some(3);
+        grid[(week(2023, 1, 25), &"rent")] = Some(100);
+        assert_eq!(grid.columns().count(), 5);
+        assert_eq!(grid.row_total(&"food", checked), Ok(Some(13)));
+        assert_eq!(grid[(week(2023, 1, 25), &"rent")], Some(100));
+    }
+}
@@ -0,0 +1,146 @@
+use chrono::NaiveDate;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::fmt::{Display, Formatter, Result};
+use std::str::FromStr;
+
+use crate::transaction::{FORECAST_TAG, Transaction};
+use crate::types::interval::Interval;
+
+
+/* Periodic transaction */
+
+// a rule for a transaction that recurs, written in the journal like hledger does:
+//
+// ~ monthly  budget
+//     expenses:food              $400
+//     expenses:rent             $1200
+//     assets:checking
+//
+// the entries are kept in an undated transaction, which is balanced the same way as
+// a regular one. periodic transactions are the basis for budgets
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PeriodicTransaction {
+    pub interval   : Interval,
+    pub transaction: Transaction,
+}
+
+impl PeriodicTransaction {
+
+    // start a (temporarily empty) periodic transaction from a "~ monthly  description" line.
+    // the interval comes back as an error if it's one we don't know
+    pub fn parse_header(line: &str) -> Option<std::result::Result<(Interval, Transaction), String>> {
+        let caps = PERIODIC_REGEX.captures(line)?;
+        let interval = caps.name("interval")?.as_str();
+        let description = caps.name("description")
+                              .map(|d| d.as_str().trim().to_owned())
+                              .unwrap_or_default();
+
+        let interval = match Interval::from_str(interval) {
+            Ok(interval) => interval,
+            Err(error)   => return Some(Err(error)),
+        };
+
+        Some(Ok((interval, Transaction {
+            description,
+            ..Default::default()
+        })))
+    }
+
+    // a dated copy of the transaction on the first day of each period starting on or after
+    // `from` and before `until`, tagged so reports can tell them from recorded transactions
+    pub fn forecast(&self, from: NaiveDate, until: NaiveDate) -> Vec<Transaction> {
+        self.expand(from, until, Some(FORECAST_TAG))
+    }
+
+    // the same copies without the tag, for writing into the journal as recorded transactions
+    pub fn occurrences(&self, from: NaiveDate, until: NaiveDate) -> Vec<Transaction> {
+        self.expand(from, until, None)
+    }
+
+    fn expand(&self, from: NaiveDate, until: NaiveDate, tag: Option<&str>) -> Vec<Transaction> {
+        let mut date = self.interval.start_of(from);
+        if date < from {
+            date = self.interval.next_start(date);
+        }
+
+        let mut expanded = vec![];
+        while date < until {
+            let builder = Transaction::builder().date(date)
+                                                .description(self.transaction.description.clone());
+            let builder = match tag {
+                Some(tag) => builder.tag(tag, ""),
+                None      => builder,
+            };
+            let transaction = self.transaction
+                                  .entries
+                                  .iter()
+                                  .fold(builder, |builder, entry| builder.entry(entry.clone()))
+                                  .build()
+                                  .expect("A periodic transaction was balanced when it was parsed");
+            expanded.push(transaction);
+            date = self.interval.next_start(date);
+        }
+        expanded
+    }
+}
+
+impl Display for PeriodicTransaction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+
+        writeln!(f, "~ {}  {}", self.interval, self.transaction.description)?;
+
+        for entry in &self.transaction.entries {
+            writeln!(f, "    {}", entry)?;
+        }
+
+        Ok(())
+    }
+}
+
+lazy_static! {
+    static ref PERIODIC_REGEX: Regex =
+        Regex::new(r"^~\s*(?P<interval>\S+)(?P<description>\s\s.*)?$").unwrap();
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use crate::journal::Journal;
+    use crate::types::interval::Interval;
+    use super::PeriodicTransaction;
+
+    #[test]
+    fn test_parse_header() {
+        let (interval, transaction) = PeriodicTransaction::parse_header("~ monthly  budget").unwrap().unwrap();
+        assert_eq!(interval, Interval::Monthly);
+        assert_eq!(transaction.description, "budget");
+
+        let (interval, transaction) = PeriodicTransaction::parse_header("~ yearly").unwrap().unwrap();
+        assert_eq!(interval, Interval::Yearly);
+        assert_eq!(transaction.description, "");
+
+        assert_eq!(PeriodicTransaction::parse_header("~ fortnightly").unwrap(), Err("fortnightly".to_string()));
+        assert_eq!(PeriodicTransaction::parse_header("2023/03/01 monthly"), None);
+    }
+
+    #[test]
+    fn test_forecast() {
+        let journal = Journal::from_lines("~ monthly  rent\n    expenses:rent  $1000\n    assets:checking".lines()).unwrap();
+        let date = |m, d| NaiveDate::from_ymd_opt(2023, m, d).unwrap();
+
+        let forecast = journal.periodic[0].forecast(date(3, 18), date(6, 1));
+        assert_eq!(forecast.iter().map(|t| t.date).collect::<Vec<_>>(), vec![date(4, 1), date(5, 1)]);
+        assert_eq!(forecast[0].description, "rent");
+        assert_eq!(forecast[0].entries, journal.periodic[0].transaction.entries);
+        assert!(forecast[0].is_forecast());
+        assert!(!journal.periodic[0].occurrences(date(3, 18), date(6, 1))[0].is_forecast());
+
+        assert_eq!(journal.periodic[0].forecast(date(4, 1), date(4, 2)).len(), 1);
+    }
+}
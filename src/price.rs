@@ -0,0 +1,287 @@
+use chrono::NaiveDate;
+use lazy_static::lazy_static;
+use regex::Regex;
+use rust_decimal::{Decimal, RoundingStrategy};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::{Display, Formatter, Result};
+
+use crate::journal::types::parse_amount;
+use crate::types::{Units, amount::{Amount, AmountError, AmountType}};
+use crate::types::commoditystyle::{CommodityStyle, CommodityStyles, Styled, WithStyles};
+
+
+/* Price */
+
+// the market price of one unit of a commodity on a date, from a price directive:
+//
+// P 2023/03/01 USD $1.35
+//
+// says one USD was worth $1.35 on March 1st
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Price {
+    pub date     : NaiveDate,
+    pub commodity: Units,
+    pub price    : Amount,
+}
+
+impl Price {
+
+    // parse a "P date commodity amount" line. None means it isn't a price directive at all,
+    // an error means it is one but something about it is wrong
+    pub fn parse(line: &str) -> Option<std::result::Result<Price, String>> {
+        if !line.starts_with("P ") {
+            return None
+        }
+
+        let parsed = PRICE_REGEX.captures(line.trim_end()).and_then(|caps| {
+            let date = NaiveDate::parse_from_str(caps.name("date")?.as_str(), "%Y/%m/%d").ok()?;
+            let commodity = caps.name("commodity")?.as_str().to_owned();
+            let price = parse_amount(caps.name("price")?.as_str())?;
+
+            Some(Price { date, commodity: commodity.into(), price })
+        });
+
+        Some(parsed.ok_or_else(|| line.to_string()))
+    }
+
+    // the same price from the other side: if a USD is $1.35, a $ is 0.7407 USD
+    pub fn invert(&self) -> Price {
+        Price {
+            date     : self.date,
+            commodity: self.price.units,
+            price    : Amount::from(self.commodity, 1.0 / self.price.to_f64()),
+        }
+    }
+}
+
+impl WithStyles for Price {}
+
+impl Display for Styled<'_, Price> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let price = self.value;
+        write!(f, "P {} {} ", price.date.format("%Y/%m/%d"), price.commodity)?;
+
+        // exchange rates between commodities are shown to at least four places
+        match price.price.amount {
+            AmountType::Discrete(_, _) => write!(f, "{}", price.price.styled(self.styles)),
+            AmountType::Decimal(amt)   => {
+                let style = self.styles.style(&price.price.units);
+                let style = CommodityStyle { precision: style.precision.max(4), ..style };
+                write!(f, "{}", style.format(&price.price.units, amt))
+            },
+        }
+    }
+}
+
+lazy_static! {
+    static ref PRICE_REGEX: Regex =
+        Regex::new(r"^P\s+(?P<date>\d{4}/\d{2}/\d{2})\s+(?P<commodity>\S+)\s+(?P<price>.+)$").unwrap();
+}
+
+
+/* PriceDb */
+
+// the journal's prices arranged for lookups as of any date. a price works both ways, so
+// once a USD is $1.35, a $ is 0.7407 USD. commodities without a price between them can
+// still be converted through others that have one, like USD into CAD into $
+#[derive(Debug, Default)]
+pub struct PriceDb {
+    quoted: HashMap<Units, Vec<Price>>,                         // each commodity's own price directives
+    rates : HashMap<(Units, Units), Vec<(NaiveDate, Decimal)>>, // one unit of the first in the second
+    pairs : HashMap<Units, Vec<Units>>,                         // the commodities each has a rate into
+    styles: CommodityStyles,                                    // for the precision of converted amounts
+}
+
+impl PriceDb {
+    // the prices are in date order, the way the journal keeps them
+    pub fn new(prices: &[Price], styles: &CommodityStyles) -> PriceDb {
+        let mut db = PriceDb { styles: styles.clone(), ..PriceDb::default() };
+
+        for price in prices {
+            let (from, to) = (&price.commodity, &price.price.units);
+            let rate = price.price.decimal();
+
+            db.quoted.entry(*from).or_default().push(price.clone());
+            db.add_rate(from, to, price.date, rate);
+
+            // a price of zero has no other side
+            if let Some(inverse) = Decimal::ONE.checked_div(rate) {
+                db.add_rate(to, from, price.date, inverse);
+            }
+        }
+        db
+    }
+
+    fn add_rate(&mut self, from: &Units, to: &Units, date: NaiveDate, rate: Decimal) {
+        let key = (*from, *to);
+        if !self.rates.contains_key(&key) {
+            self.pairs.entry(*from).or_default().push(*to);
+        }
+        self.rates.entry(key).or_default().push((date, rate));
+    }
+
+    // the commodity's market price as of the date, from its most recent price directive
+    pub fn price_at(&self, commodity: &str, date: NaiveDate) -> Option<&Amount> {
+        let prices = self.quoted.get(&Units::from(commodity))?;
+        let index  = prices.partition_point(|p| p.date <= date);
+
+        index.checked_sub(1).map(|last| &prices[last].price)
+    }
+
+    // the price of one unit of a commodity in another as of the date, from the most recent
+    // price between them in either direction, or else through the fewest commodities in
+    // between that all have prices. an error means the rates multiply out to more than a
+    // decimal can hold
+    pub fn rate(&self, from: &str, to: &str, date: NaiveDate) -> std::result::Result<Option<Decimal>, AmountError> {
+        let mut seen : HashSet<&str>             = HashSet::from([from]);
+        let mut queue: VecDeque<(&str, Decimal)> = VecDeque::from([(from, Decimal::ONE)]);
+
+        while let Some((commodity, rate)) = queue.pop_front() {
+            for next in self.pairs.get(&Units::from(commodity)).into_iter().flatten() {
+                let Some(step) = self.direct_rate(commodity, next, date) else { continue };
+                let rate = rate.checked_mul(step).ok_or(AmountError::Overflow)?;
+                if next == to {
+                    return Ok(Some(rate))
+                }
+                if seen.insert(next) {
+                    queue.push_back((next, rate));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn direct_rate(&self, from: &str, to: &str, date: NaiveDate) -> Option<Decimal> {
+        let rates = self.rates.get(&(Units::from(from), Units::from(to)))?;
+        let index = rates.partition_point(|(day, _)| *day <= date);
+
+        index.checked_sub(1).map(|last| rates[last].1)
+    }
+
+    // the amount in the target commodity as of the date, if there's a way to get there,
+    // rounded to the places the journal writes the target commodity with
+    pub fn convert(&self, amount: &Amount, target: &str, date: NaiveDate) -> std::result::Result<Option<Amount>, AmountError> {
+        let Some(rate) = self.rate(&amount.units, target, date)? else { return Ok(None) };
+
+        let precision = self.styles.style(target).precision;
+        let quantity  = amount.decimal()
+                              .checked_mul(rate)
+                              .ok_or(AmountError::Overflow)?
+                              .round_dp_with_strategy(precision, RoundingStrategy::MidpointAwayFromZero);
+
+        Amount::from_decimal(target, quantity).map(Some).ok_or(AmountError::Overflow)
+    }
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use rust_decimal::Decimal;
+    use crate::types::amount::{Amount, AmountError};
+    use crate::types::commoditystyle::{CommodityStyle, CommodityStyles, WithStyles};
+    use super::{Price, PriceDb};
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn price_db(lines: &str) -> PriceDb {
+        let prices: Vec<Price> = lines.lines().map(|line| Price::parse(line.trim()).unwrap().unwrap()).collect();
+        PriceDb::new(&prices, &CommodityStyles::default())
+    }
+
+    #[test]
+    fn test_parse() {
+        let price = Price::parse("P 2023/03/01 USD $1.35").unwrap().unwrap();
+        assert_eq!(price.date, NaiveDate::from_ymd_opt(2023, 3, 1).unwrap());
+        assert_eq!(price.commodity, "USD");
+        assert_eq!(price.price, Amount::from("$".to_owned(), 1.35));
+
+        assert_eq!(Price::parse("P 2023/03/01 USD"), Some(Err("P 2023/03/01 USD".to_owned())));
+        assert_eq!(Price::parse("2023/03/01 Price"), None);
+        assert_eq!(Price::parse("    P  $1"), None);
+    }
+
+    #[test]
+    fn test_invert() {
+        let price = Price::parse("P 2023/03/01 USD $1.25").unwrap().unwrap().invert();
+        assert_eq!(price.commodity, "$");
+        assert_eq!(price.styled(&CommodityStyles::default()).to_string(), "P 2023/03/01 $ 0.8000 USD");
+    }
+
+    #[test]
+    fn test_display() {
+        let price = Price::parse("P 2023/03/01 USD $1.35").unwrap().unwrap();
+        assert_eq!(price.styled(&CommodityStyles::default()).to_string(), "P 2023/03/01 USD $1.35");
+    }
+
+    #[test]
+    fn test_price_at() {
+        let db = price_db("P 2023/01/01 USD $1.30
+                           P 2023/03/01 USD $1.40");
+        assert_eq!(db.price_at("USD", date(2023, 2, 1)), Some(&Amount::from("$".to_owned(), 1.30)));
+        assert_eq!(db.price_at("USD", date(2023, 3, 1)), Some(&Amount::from("$".to_owned(), 1.40)));
+        assert_eq!(db.price_at("USD", date(2022, 12, 31)), None);
+        assert_eq!(db.price_at("$", date(2023, 3, 1)), None);
+    }
+
+    #[test]
+    fn test_rate() {
+        let db = price_db("P 2023/01/01 USD $1.30
+                           P 2023/03/01 USD $1.40
+                           P 2023/03/01 AAPL 150 USD");
+        assert_eq!(db.rate("USD", "$", date(2023, 2, 1)), Ok(Some(Decimal::new(130, 2))));
+        assert_eq!(db.rate("USD", "$", date(2023, 3, 1)), Ok(Some(Decimal::new(140, 2))));
+        assert_eq!(db.rate("USD", "AAPL", date(2023, 3, 1)), Ok(Some(Decimal::ONE / Decimal::from(150))));
+        assert_eq!(db.rate("USD", "$", date(2022, 12, 31)), Ok(None));
+    }
+
+    #[test]
+    fn test_convert_through_other_commodities() {
+        let db = price_db("P 2023/01/01 USD 1.25 CAD
+                           P 2023/01/01 $ 0.50 CAD
+                           P 2023/02/01 EUR 1.10 USD");
+
+        let usd = Amount::from("USD".to_owned(), 100.0);
+        assert_eq!(db.convert(&usd, "$", date(2023, 1, 1)), Ok(Some(Amount::from("$", 250.0))));
+        assert_eq!(db.convert(&usd, "EUR", date(2023, 1, 31)), Ok(None));
+
+        let euros = Amount::from("EUR".to_owned(), 10.0);
+        assert_eq!(db.convert(&euros, "$", date(2023, 2, 1)), Ok(Some(Amount::from("$", 27.5))));
+    }
+
+    #[test]
+    fn test_convert_rounds_to_the_target_precision() {
+        let prices: Vec<Price> = vec![Price::parse("P 2023/01/01 USD 1.23456 CAD").unwrap().unwrap()];
+        let usd = Amount::from("USD", 10.0);
+
+        // three places for a commodity the journal doesn't style, otherwise the journal's
+        let db = PriceDb::new(&prices, &CommodityStyles::default());
+        assert_eq!(db.convert(&usd, "CAD", date(2023, 1, 1)), Ok(Some(Amount::from("CAD", 12.346))));
+
+        let mut styles = CommodityStyles::default();
+        let (units, style) = CommodityStyle::parse("commodity 1000.00 CAD").unwrap().unwrap();
+        styles.declare(units, style);
+
+        let db = PriceDb::new(&prices, &styles);
+        assert_eq!(db.convert(&usd, "CAD", date(2023, 1, 1)), Ok(Some(Amount::from("CAD", 12.35))));
+    }
+
+    #[test]
+    fn test_convert_overflow() {
+        let db = price_db("P 2023/01/01 GOLD 10000000000000000000 XAU
+                           P 2023/01/01 XAU 10000000000000000000 CAD");
+
+        // each rate fits, but together they're more than a decimal can hold
+        assert_eq!(db.rate("GOLD", "CAD", date(2023, 1, 1)), Err(AmountError::Overflow));
+
+        // and so does the rate into XAU, but not the amount it's multiplied by
+        let gold = Amount::from("GOLD", 100000000000.0);
+        assert_eq!(db.convert(&gold, "XAU", date(2023, 1, 1)), Err(AmountError::Overflow));
+    }
+}
@@ -0,0 +1,192 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+
+/* Profile */
+
+// with --profile, the wall time spent in each phase of a run and how many transactions
+// went through it, so it's clear whether parsing or reporting is the slow part. a phase
+// started inside another one is timed on its own, with its time taken out of the outer
+// phase's, so the aggregating a report does isn't also counted as rendering it
+#[derive(Debug, Default)]
+pub struct Profile {
+    pub phases: Vec<Phase>,        // in the order they were listed or first started
+    running   : Vec<Running>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Phase {
+    pub name        : &'static str,
+    pub elapsed     : Duration,
+    pub transactions: Option<usize>,
+}
+
+#[derive(Debug)]
+struct Running {
+    name    : &'static str,
+    start   : Instant,
+    children: Duration,           // the time spent in phases started inside this one
+}
+
+// profiling is on for the whole run or not at all. main is the only thread
+thread_local! {
+    static PROFILE: RefCell<Option<Profile>> = const { RefCell::new(None) };
+}
+
+// start profiling, with the phases listed in this order whichever starts first
+pub fn enable_profile(phases: &[&'static str]) {
+    let mut enabled = Profile::default();
+    for name in phases {
+        enabled.phase_mut(name);
+    }
+    PROFILE.with(|profile| *profile.borrow_mut() = Some(enabled));
+}
+
+pub fn profiling() -> bool {
+    PROFILE.with(|profile| profile.borrow().is_some())
+}
+
+// run the function as part of the phase, if profiling
+pub fn phase<T, F>(name: &'static str, f: F) -> T
+    where F: FnOnce() -> T
+{
+    let enabled = PROFILE.with(|profile| profile.borrow_mut()
+                                                .as_mut()
+                                                .map(|profile| profile.start(name, Instant::now()))
+                                                .is_some());
+    let result = f();
+    if enabled {
+        PROFILE.with(|profile| profile.borrow_mut().as_mut().map(|profile| profile.finish(Instant::now())));
+    }
+    result
+}
+
+// note how many transactions the phase has left to work with, if profiling
+pub fn count(name: &'static str, transactions: usize) {
+    PROFILE.with(|profile| {
+        if let Some(profile) = profile.borrow_mut().as_mut() {
+            profile.phase_mut(name).transactions = Some(transactions);
+        }
+    });
+}
+
+// the profile of the run so far, if profiling
+pub fn take_profile() -> Option<Profile> {
+    PROFILE.with(|profile| profile.borrow_mut().take())
+}
+
+impl Profile {
+    fn start(&mut self, name: &'static str, now: Instant) {
+        self.phase_mut(name);
+        self.running.push(Running { name, start: now, children: Duration::ZERO });
+    }
+
+    fn finish(&mut self, now: Instant) {
+        let running = self.running.pop().expect("A phase finished without starting");
+        let elapsed = now - running.start;
+
+        self.phase_mut(running.name).elapsed += elapsed.saturating_sub(running.children);
+        if let Some(parent) = self.running.last_mut() {
+            parent.children += elapsed;
+        }
+    }
+
+    fn phase_mut(&mut self, name: &'static str) -> &mut Phase {
+        let index = match self.phases.iter().position(|phase| phase.name == name) {
+            Some(index) => index,
+            None        => {
+                self.phases.push(Phase { name, elapsed: Duration::ZERO, transactions: None });
+                self.phases.len() - 1
+            },
+        };
+        &mut self.phases[index]
+    }
+
+    pub fn total(&self) -> Duration {
+        self.phases.iter().map(|phase| phase.elapsed).sum()
+    }
+}
+
+// phase           time  transactions
+// read          0.05ms
+// parse        15.70ms             5
+// filter        0.13ms             2
+// aggregate     1.20ms
+// render        0.31ms
+// total        17.39ms
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let millis = |duration: Duration| format!("{:.2}ms", duration.as_secs_f64() * 1000.0);
+
+        writeln!(f, "{:<10} {:>10}  transactions", "phase", "time")?;
+        for phase in &self.phases {
+            write!(f, "{:<10} {:>10}", phase.name, millis(phase.elapsed))?;
+            if let Some(transactions) = phase.transactions {
+                write!(f, "  {:>12}", transactions)?;
+            }
+            writeln!(f)?;
+        }
+        writeln!(f, "{:<10} {:>10}", "total", millis(self.total()))
+    }
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+    use super::{Phase, Profile};
+
+    fn ms(n: u64) -> Duration {
+        Duration::from_millis(n)
+    }
+
+    #[test]
+    fn test_profile_nested_phases() {
+        let t0 = Instant::now();
+        let mut profile = Profile::default();
+
+        profile.start("parse", t0);
+        profile.finish(t0 + ms(10));
+
+        // 2ms of rendering around 5ms of aggregating, twice
+        profile.start("render", t0 + ms(10));
+        profile.start("aggregate", t0 + ms(11));
+        profile.finish(t0 + ms(16));
+        profile.finish(t0 + ms(17));
+        profile.start("render", t0 + ms(17));
+        profile.start("aggregate", t0 + ms(18));
+        profile.finish(t0 + ms(23));
+        profile.finish(t0 + ms(24));
+
+        profile.phase_mut("parse").transactions = Some(5);
+
+        assert_eq!(profile.phases, vec![
+            Phase { name: "parse",     elapsed: ms(10), transactions: Some(5) },
+            Phase { name: "render",    elapsed: ms(4),  transactions: None },
+            Phase { name: "aggregate", elapsed: ms(10), transactions: None },
+        ]);
+        assert_eq!(profile.total(), ms(24));
+    }
+
+    #[test]
+    fn test_profile_display() {
+        let profile = Profile {
+            phases : vec![
+                Phase { name: "parse",  elapsed: Duration::from_micros(15_700), transactions: Some(5) },
+                Phase { name: "render", elapsed: Duration::from_micros(310),    transactions: None },
+            ],
+            running: vec![],
+        };
+
+        assert_eq!(profile.to_string(),
+"phase            time  transactions
+parse         15.70ms             5
+render         0.31ms
+total         16.01ms
+");
+    }
+}
@@ -0,0 +1,453 @@
+use chrono::{Duration, NaiveDate};
+use regex::Regex;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::common::{case_insensitive, today};
+use crate::transaction::{Entry, Transaction, posting::Posting};
+use crate::types::{amountfilter::AmountFilter, daterange::DateRange, periodexpr::parse_period};
+
+
+/* Queries */
+
+// which postings a report covers, from the terms on the command line, like
+//
+//   expenses:food desc:sub tag:trip=italy date:2023/03 amt:>10
+//
+// a plain term is a pattern for the account, and the others are
+//
+//   acct:PATTERN          the account, same as a plain term
+//   desc:PATTERN          the transaction's description, also payee:PATTERN
+//   tag:NAME[=VALUE]      a tag on the transaction, with any value or just this one
+//   date:PERIOD           the transaction's date, in a period like -p takes
+//   amt:>100              the posting's amount, like amt:<-50 or amt:=0
+//   not:TERM              postings the term doesn't match
+//
+// patterns are case-insensitive regular expressions matching anywhere in the text. terms
+// side by side must all match, except that account patterns with no `and` between them
+// match any of them like ledger's do. `and`, `or`, `not` and parentheses combine terms
+// explicitly, with not binding tightest and or loosest:
+//
+//   food or desc:coffee not tag:reimbursable
+#[derive(Clone, Debug)]
+pub enum Query {
+    And(Vec<Query>),              // every one of the queries matches
+    Or(Vec<Query>),               // any of them matches
+    Not(Box<Query>),              // the query doesn't match
+    Acct(Regex),                  // the posting's account
+    Desc(Regex),                  // the transaction's description
+    Tag(String, Option<String>),  // a tag on the transaction, with this value if there's one
+    Amt(AmountFilter),            // the posting's amount
+    DateRange(DateRange),         // the transaction's date
+}
+
+// what a query can be matched against. a posting is matched on its own and a transaction
+// matches when any of its postings does, so code outside the reports can filter the way
+// they do:
+//
+//   journal.transactions.iter().filter(|t| query.matches(*t))
+//   transaction.postings().filter(|p| query.matches(p))
+pub trait Queryable {
+    fn matched_by(&self, query: &Query) -> bool;
+}
+
+impl Queryable for Posting<'_> {
+    fn matched_by(&self, query: &Query) -> bool {
+        query.matches_entry(self.transaction, self.entry)
+    }
+}
+
+impl Queryable for Transaction {
+    fn matched_by(&self, query: &Query) -> bool {
+        self.entries.iter().any(|entry| query.matches_entry(self, entry))
+    }
+}
+
+impl Query {
+    // whether the transaction or posting matches
+    pub fn matches(&self, item: &impl Queryable) -> bool {
+        item.matched_by(self)
+    }
+
+    // whether the entry, which is one of the transaction's, matches
+    fn matches_entry(&self, transaction: &Transaction, entry: &Entry) -> bool {
+        match self {
+            Query::And(queries)       => queries.iter().all(|q| q.matches_entry(transaction, entry)),
+            Query::Or(queries)        => queries.iter().any(|q| q.matches_entry(transaction, entry)),
+            Query::Not(query)         => !query.matches_entry(transaction, entry),
+            Query::Acct(pattern)      => pattern.is_match(&entry.account),
+            Query::Desc(pattern)      => pattern.is_match(&transaction.description),
+            Query::Tag(name, value)   => transaction.has_tag(name, value.as_deref()),
+            Query::Amt(filter)        => filter.matches(&entry.amount),
+            Query::DateRange(range)   => range.contains(transaction.date),
+        }
+    }
+
+    // the matching postings of the transactions
+    pub fn postings<'a>(&'a self, transactions: impl IntoIterator<Item = &'a Transaction> + 'a) -> impl Iterator<Item = &'a Entry> + 'a {
+        transactions.into_iter()
+                    .flat_map(move |t| t.entries.iter().filter(move |e| self.matches_entry(t, e)))
+    }
+
+    // whether there weren't any terms, so everything matches
+    pub fn is_empty(&self) -> bool {
+        matches!(self, Query::And(queries) if queries.is_empty())
+    }
+
+    // the dates the query's date: terms keep a matching transaction to, so a report can
+    // binary search the sorted transactions for them instead of trying every one. it can
+    // be wider than the query, like for "not date:2023/03", but never narrower
+    pub fn date_range(&self) -> DateRange {
+        match self {
+            Query::DateRange(range) => *range,
+            Query::And(queries)     => queries.iter().fold(DateRange::default(), |range, query| range.intersect(&query.date_range())),
+            Query::Or(queries)      => queries.iter()
+                                              .map(Query::date_range)
+                                              .reduce(|a, b| a.span(&b))
+                                              .unwrap_or_default(),
+            _                       => DateRange::default(),
+        }
+    }
+}
+
+// no terms at all, which matches every posting
+impl Default for Query {
+    fn default() -> Self {
+        Query::And(vec![])
+    }
+}
+
+// a query written out in one string, with spaces between its terms
+impl FromStr for Query {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let terms: Vec<&str> = s.split_whitespace().collect();
+        parse_query(&terms, today())
+    }
+}
+
+// Parse the terms from the command line into a query. Each term is usually its own
+// argument, though parentheses can be stuck to the terms they enclose. Dates in date:
+// terms are relative to today like -p's
+pub fn parse_query(terms: &[&str], today: NaiveDate) -> Result<Query, String> {
+    let tokens = tokenize(terms);
+    if tokens.is_empty() {
+        return Ok(Query::default())
+    }
+
+    let mut parser = Parser { tokens, next: 0, today };
+    let query = parser.or()?;
+
+    match parser.peek() {
+        Some(token) => Err(format!("unexpected \"{}\" in the query", token)),
+        None        => Ok(query),
+    }
+}
+
+// check the terms in one argument on their own, leaving how they're combined for when the
+// whole query is parsed
+pub fn check_terms(argument: &str, today: NaiveDate) -> Result<(), String> {
+    for token in tokenize(&[argument]) {
+        if !["(", ")", "and", "or", "not"].iter().any(|keyword| token.eq_ignore_ascii_case(keyword)) {
+            parse_term(&token, today)?;
+        }
+    }
+    Ok(())
+}
+
+// parse a single term, like desc:sub or not:expenses:tips
+pub fn parse_term(term: &str, today: NaiveDate) -> Result<Query, String> {
+    let regex = |pattern: &str| case_insensitive(pattern).map_err(|error| format!("invalid pattern \"{}\": {}", pattern, error));
+
+    if let Some(term) = term.strip_prefix("not:") {
+        return Ok(Query::Not(Box::new(parse_term(term, today)?)))
+    }
+
+    if let Some(pattern) = term.strip_prefix("acct:") {
+        Ok(Query::Acct(regex(pattern)?))
+    } else if let Some(pattern) = term.strip_prefix("desc:").or_else(|| term.strip_prefix("payee:")) {
+        Ok(Query::Desc(regex(pattern)?))
+    } else if let Some(tag) = term.strip_prefix("tag:") {
+        match tag.split_once('=') {
+            Some((name, value)) => Ok(Query::Tag(name.to_string(), Some(value.to_string()))),
+            None                => Ok(Query::Tag(tag.to_string(), None)),
+        }
+    } else if let Some(period) = term.strip_prefix("date:") {
+        parse_period(period, today).map(|(range, _)| Query::DateRange(range))
+                                   .map_err(|_| format!("couldn't understand the period in \"{}\"", term))
+    } else if term.starts_with("amt:") {
+        term.parse().map(Query::Amt)
+                    .map_err(|_| format!("expected an amount filter like amt:>100, not \"{}\"", term))
+    } else {
+        Ok(Query::Acct(regex(term)?))
+    }
+}
+
+// split off the parentheses around terms, leaving the ones inside a pattern like
+// (food|tips) that are balanced
+fn tokenize(terms: &[&str]) -> Vec<String> {
+    let mut tokens = vec![];
+
+    for term in terms {
+        let mut term = *term;
+        while let Some(rest) = term.strip_prefix('(') {
+            tokens.push("(".to_string());
+            term = rest;
+        }
+
+        let mut closing = 0;
+        while term.ends_with(')') && term.matches(')').count() > term.matches('(').count() {
+            term = &term[..term.len()-1];
+            closing += 1;
+        }
+
+        if !term.is_empty() {
+            tokens.push(term.to_string());
+        }
+        tokens.extend(std::iter::repeat_n(")".to_string(), closing));
+    }
+
+    tokens
+}
+
+struct Parser {
+    tokens: Vec<String>,
+    next  : usize,
+    today : NaiveDate,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.next).map(String::as_str)
+    }
+
+    // take the next token if it's the given keyword, in any case
+    fn keyword(&mut self, keyword: &str) -> bool {
+        let found = self.peek().is_some_and(|token| token.eq_ignore_ascii_case(keyword));
+        if found {
+            self.next += 1;
+        }
+        found
+    }
+
+    // a or b or ...
+    fn or(&mut self) -> Result<Query, String> {
+        let mut queries = vec![self.and()?];
+        while self.keyword("or") {
+            queries.push(self.and()?);
+        }
+
+        Ok(if queries.len() == 1 { queries.remove(0) } else { Query::Or(queries) })
+    }
+
+    // a and b, or just a b
+    fn and(&mut self) -> Result<Query, String> {
+        let mut queries = vec![self.not()?];
+        let mut explicit = false;
+
+        loop {
+            if self.keyword("and") {
+                explicit = true;
+            } else if self.peek().is_none_or(|token| token == ")" || token.eq_ignore_ascii_case("or")) {
+                break
+            }
+            queries.push(self.not()?);
+        }
+
+        // account patterns side by side match any of them
+        if !explicit {
+            let (accounts, mut others): (Vec<Query>, Vec<Query>) = queries.into_iter().partition(|q| matches!(q, Query::Acct(_)));
+            match accounts.len() {
+                0 => {},
+                1 => others.insert(0, accounts.into_iter().next().unwrap()),
+                _ => others.insert(0, Query::Or(accounts)),
+            }
+            queries = others;
+        }
+
+        Ok(if queries.len() == 1 { queries.remove(0) } else { Query::And(queries) })
+    }
+
+    // not a, or a
+    fn not(&mut self) -> Result<Query, String> {
+        if self.keyword("not") {
+            return Ok(Query::Not(Box::new(self.not()?)))
+        }
+
+        match self.peek() {
+            None | Some(")") => Err("expected a term at the end of the query".to_string()),
+            Some("(") => {
+                self.next += 1;
+                let query = self.or()?;
+                if self.peek() != Some(")") {
+                    return Err("missing a ) in the query".to_string())
+                }
+                self.next += 1;
+                Ok(query)
+            },
+            Some(term) => {
+                let query = parse_term(term, self.today)?;
+                self.next += 1;
+                Ok(query)
+            },
+        }
+    }
+}
+
+// expenses:food desc:sub
+// (food or desc:coffee) not:tag:reimbursable
+
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Query::And(queries) => {
+                let terms: Vec<String> = queries.iter()
+                                                .map(|q| match q {
+                                                    Query::Or(_) => format!("({})", q),
+                                                    _            => q.to_string(),
+                                                })
+                                                .collect();
+                write!(f, "{}", terms.join(" "))
+            },
+            Query::Or(queries) => {
+                let terms: Vec<String> = queries.iter()
+                                                .map(|q| match q {
+                                                    Query::And(qs) if qs.len() > 1 => format!("({})", q),
+                                                    _                              => q.to_string(),
+                                                })
+                                                .collect();
+                write!(f, "{}", terms.join(" or "))
+            },
+            Query::Not(query) => match query.as_ref() {
+                Query::And(_) | Query::Or(_) => write!(f, "not ({})", query),
+                _                            => write!(f, "not:{}", query),
+            },
+            Query::Acct(pattern)           => write!(f, "{}", pattern.as_str()),
+            Query::Desc(pattern)           => write!(f, "desc:{}", pattern.as_str()),
+            Query::Tag(name, None)         => write!(f, "tag:{}", name),
+            Query::Tag(name, Some(value))  => write!(f, "tag:{}={}", name, value),
+            Query::Amt(filter)             => write!(f, "{}", filter),
+            Query::DateRange(range)        => {
+                // the end is exclusive, so the last day is the one before it
+                let day = |date: Option<NaiveDate>| date.map_or(String::new(), |d| d.format("%Y/%m/%d").to_string());
+                write!(f, "date:{}..{}", day(range.begin), day(range.end.map(|end| end - Duration::days(1))))
+            },
+        }
+    }
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use crate::journal::Journal;
+    use crate::transaction::posting::Posting;
+    use super::{Query, check_terms, parse_query, parse_term};
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2023, 6, 15).unwrap()
+    }
+
+    fn sample_journal() -> Journal {
+        let journal =
+r#"
+2023/03/07 Subway  ; trip:italy
+    expenses:food:subway                $12
+    expenses:tips                        $2
+    assets:cash
+
+2023/04/02 Tim Hortons
+    expenses:food:tim-hortons            $3
+    assets:cash
+"#;
+        Journal::from_lines(journal.lines()).unwrap()
+    }
+
+    // the accounts and amounts of the postings the query matches
+    fn matching(terms: &[&str]) -> Vec<String> {
+        let journal = sample_journal();
+        let query   = parse_query(terms, today()).unwrap();
+
+        query.postings(&journal.transactions)
+             .map(|entry| format!("{} {}", entry.account, entry.amount))
+             .collect()
+    }
+
+    #[test]
+    fn test_query_terms() {
+        assert_eq!(matching(&["FOOD"]), vec!["expenses:food:subway $12.00", "expenses:food:tim-hortons $3.00"]);
+        assert_eq!(matching(&["desc:sub"]), vec!["expenses:food:subway $12.00", "expenses:tips $2.00", "assets:cash $-14.00"]);
+        assert_eq!(matching(&["tag:trip=italy", "amt:<10"]), vec!["expenses:tips $2.00"]);
+        assert_eq!(matching(&["tag:trip=peru"]), Vec::<String>::new());
+        assert_eq!(matching(&["date:2023/04", "^assets"]), vec!["assets:cash $-3.00"]);
+        assert_eq!(matching(&[]).len(), 5);
+    }
+
+    #[test]
+    fn test_query_combinations() {
+        assert_eq!(matching(&["expenses", "not:food"]), vec!["expenses:tips $2.00"]);
+        assert_eq!(matching(&["tips", "subway"]), vec!["expenses:food:subway $12.00", "expenses:tips $2.00"]);
+        assert_eq!(matching(&["tips", "and", "subway"]), Vec::<String>::new());
+        assert_eq!(matching(&["tips", "or", "desc:tim", "amt:>0"]), vec!["expenses:tips $2.00", "expenses:food:tim-hortons $3.00"]);
+        assert_eq!(matching(&["expenses", "not", "(desc:sub", "or", "amt:>5)"]), vec!["expenses:food:tim-hortons $3.00"]);
+        assert_eq!(matching(&["(food|tips)", "amt:<5"]), vec!["expenses:tips $2.00", "expenses:food:tim-hortons $3.00"]);
+    }
+
+    #[test]
+    fn test_matches() {
+        let journal = sample_journal();
+        let query   = parse_query(&["tips", "or", "desc:tim"], today()).unwrap();
+
+        let transactions: Vec<_> = journal.transactions.iter().filter(|t| query.matches(*t)).collect();
+        assert_eq!(transactions.len(), 2);
+
+        let postings: Vec<Posting> = journal.transactions[0].postings().filter(|p| query.matches(p)).collect();
+        assert_eq!(postings.len(), 1);
+        assert_eq!(postings[0].account(), "expenses:tips");
+        assert!(!Query::Not(Box::new(query)).matches(&postings[0]));
+    }
+
+    #[test]
+    fn test_date_range() {
+        let journal = sample_journal();
+        let range   = |terms: &[&str]| Query::DateRange(parse_query(terms, today()).unwrap().date_range()).to_string();
+        let found   = |terms: &[&str]| journal.transactions_for(&parse_query(terms, today()).unwrap()).count();
+
+        assert_eq!(range(&["food", "date:2023/03"]), "date:2023/03/01..2023/03/31");
+        assert_eq!(range(&["date:2023", "date:2023/04.."]), "date:2023/04/01..2023/12/31");
+        assert_eq!(range(&["date:2023/03", "or", "date:2023/05"]), "date:2023/03/01..2023/05/31");
+        assert_eq!(range(&["date:2023/03", "or", "food"]), "date:..");
+        assert_eq!(range(&["not:date:2023/03"]), "date:..");
+
+        assert_eq!(found(&["date:2023/04"]), 1);
+        assert_eq!(found(&["date:2023/05.."]), 0);
+        assert_eq!(found(&["food"]), 2);
+    }
+
+    #[test]
+    fn test_query_display() {
+        let display = |terms: &[&str]| parse_query(terms, today()).unwrap().to_string();
+
+        assert_eq!(display(&["expenses:food", "desc:sub"]), "expenses:food desc:sub");
+        assert_eq!(display(&["food", "tips", "not:tag:trip"]), "(food or tips) not:tag:trip");
+        assert_eq!(display(&["not", "(food", "or", "amt:>10)"]), "not (food or amt:>10)");
+        assert_eq!(display(&["date:2023/03"]), "date:2023/03/01..2023/03/31");
+        assert!(Query::default().is_empty());
+    }
+
+    #[test]
+    fn test_query_errors() {
+        assert!(parse_query(&["(food"], today()).is_err());
+        assert!(parse_query(&["food", "or"], today()).is_err());
+        assert!(parse_query(&["food)"], today()).is_err());
+        assert!(parse_query(&["not"], today()).is_err());
+        assert!(parse_term("(food", today()).is_err());
+        assert!(parse_term("amt:lots", today()).is_err());
+        assert!(parse_term("date:someday", today()).is_err());
+        assert!(check_terms("(acct:(food|tips))", today()).is_ok());
+        assert!(check_terms("desc:(tim", today()).is_err());
+    }
+}
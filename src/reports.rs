@@ -1,2 +1,18 @@
+pub mod accounts;
+pub mod activity;
 pub mod balance;
+pub mod balancesheet;
+pub mod budget;
+pub mod calendar;
+pub mod chart;
+pub mod envelopes;
+pub mod export;
+pub mod forecast;
+pub mod goals;
+pub mod payees;
+pub mod prices;
+pub mod print;
 pub mod register;
+pub mod stats;
+pub mod tags;
+pub mod top;
@@ -0,0 +1,82 @@
+use std::collections::BTreeSet;
+
+use crate::accounttree::AccountTree;
+use crate::journal::Journal;
+use crate::query::Query;
+use crate::reports::balance::compare_accounts;
+
+
+/* Accounts */
+
+// The names of the accounts with postings matching the query. As a tree, the parent
+// accounts are listed too and each name is shortened to its last component and indented
+// by its depth
+pub fn accounts_report(journal: &Journal,
+                       query  : &Query,
+                       tree   : bool) -> Vec<String>
+{
+    if tree {
+        let tree: AccountTree<()> = query.postings(journal.transactions_for(query))
+                                         .map(|e| (e.account.clone(), ()))
+                                         .collect();
+
+        return tree.walk()
+                   .map(|entry| format!("{}{}", "  ".repeat(entry.depth), entry.account.name()))
+                   .collect()
+    }
+
+    let mut accounts: Vec<&str> = query.postings(journal.transactions_for(query))
+                                       .map(|e| e.account.as_str())
+                                       .collect::<BTreeSet<&str>>()
+                                       .into_iter()
+                                       .collect();
+    accounts.sort_by(|a, b| compare_accounts(a, b));
+
+    accounts.into_iter().map(String::from).collect()
+}
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+use crate::journal::Journal;
+    use crate::query::Query;
+    use super::accounts_report;
+
+    fn sample_journal() -> Journal {
+        let journal =
+r#"
+2023/03/07 Sandwich, Chili
+    assets:savings                    $-14.99
+    expenses:tips                       $0.50
+    assets:cash                        $-0.50
+    expenses:food:tim-hortons
+
+2023/03/09 Sub
+    expenses:food:subway                $12
+    assets:cash
+"#;
+        Journal::from_lines(journal.lines()).unwrap()
+    }
+
+    #[test]
+    fn test_accounts_report_flat() {
+        assert_eq!(accounts_report(&sample_journal(), &Query::default(), false), vec![
+            "assets:cash",
+            "assets:savings",
+            "expenses:food:subway",
+            "expenses:food:tim-hortons",
+            "expenses:tips",
+        ]);
+    }
+
+    #[test]
+    fn test_accounts_report_tree() {
+        assert_eq!(accounts_report(&sample_journal(), &"FOOD".parse().unwrap(), true), vec![
+            "expenses",
+            "  food",
+            "    subway",
+            "    tim-hortons",
+        ]);
+    }
+}
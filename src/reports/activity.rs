@@ -0,0 +1,102 @@
+use std::fmt;
+
+use crate::iterators::transactionsbyperiod::transactions_by_period;
+use crate::journal::Journal;
+use crate::types::interval::Interval;
+
+
+/* Activity */
+
+// the widest a bar gets, for the busiest period
+const BAR_WIDTH: usize = 50;
+
+// how many transactions were entered in each period, including the empty ones, so gaps in
+// record-keeping stand out
+pub struct ActivityReport {
+    pub periods: Vec<(String, usize)>,
+}
+
+pub fn activity_report(journal : &Journal,
+                       interval: Interval) -> ActivityReport
+{
+    let periods = transactions_by_period(journal, interval)
+                      .map(|(period, transactions)| (period.to_string(), transactions.len()))
+                      .collect();
+
+    ActivityReport { periods }
+}
+
+// 2023-01      12 ########################################
+// 2023-02       0
+// 2023-03      15 ##################################################
+
+impl fmt::Display for ActivityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let busiest = self.periods.iter().map(|(_, count)| *count).max().unwrap_or(0);
+
+        for (period, count) in &self.periods {
+            // round to the nearest character but never hide a period that had any activity
+            let width = (count * BAR_WIDTH + busiest / 2).checked_div(busiest)
+                                                         .unwrap_or(0)
+                                                         .max(usize::from(*count > 0));
+
+            let line = format!("{:<10} {:>4} {}", period, count, "#".repeat(width));
+            writeln!(f, "{}", line.trim_end())?;
+        }
+
+        Ok(())
+    }
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use crate::journal::Journal;
+    use crate::types::interval::Interval;
+    use super::activity_report;
+
+    fn sample_journal() -> Journal {
+        let mut journal = String::new();
+        for day in 1..=20 {
+            journal.push_str(&format!("2023/01/{:02} Coffee\n    expenses:food  $2\n    assets:cash\n\n", day));
+        }
+        journal.push_str("2023/03/06 Coffee\n    expenses:food  $2\n    assets:cash\n");
+
+        Journal::from_lines(journal.lines()).unwrap()
+    }
+
+    #[test]
+    fn test_activity_report_monthly() {
+        let report = activity_report(&sample_journal(), Interval::Monthly);
+
+        assert_eq!(report.periods, vec![
+            ("2023-01".to_string(), 20),
+            ("2023-02".to_string(), 0),
+            ("2023-03".to_string(), 1),
+        ]);
+        assert_eq!(report.to_string(), format!(
+"2023-01      20 {}
+2023-02       0
+2023-03       1 ###
+", "#".repeat(50)));
+    }
+
+    #[test]
+    fn test_activity_report_weekly() {
+        let report = activity_report(&sample_journal(), Interval::Weekly);
+
+        // 2023-01-01 was a Sunday, the last day of 2022's final week
+        assert_eq!(report.periods.first(), Some(&("2022-W52".to_string(), 1)));
+        assert_eq!(report.periods.len(), 11);
+        assert_eq!(report.periods.iter().map(|(_, count)| count).sum::<usize>(), 21);
+    }
+
+    #[test]
+    fn test_activity_report_empty() {
+        let report = activity_report(&Journal::default(), Interval::Monthly);
+        assert!(report.periods.is_empty());
+        assert_eq!(report.to_string(), "");
+    }
+}
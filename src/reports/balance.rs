@@ -1,32 +1,1243 @@
-use std::collections::HashMap;
+use rust_decimal::{Decimal, prelude::ToPrimitive};
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
 
+use crate::accounttree::AccountTree;
+use crate::color::{Style, paint};
+use crate::common::{display_width, mark_forecast, pad_left, pad_right};
 use crate::monthgrid::MonthGrid;
-use crate::types::{Account, amount::Amount};
+use crate::output::{Csv, Json, JsonValue};
+use crate::periodgrid::{GridPeriod, PeriodGrid};
+use crate::query::Query;
+use crate::types::commoditystyle::{CommodityStyles, Styled, WithStyles};
+use crate::types::{Account, amount::{Amount, AmountError}, interval::Interval, mixedamount::MixedAmount, monthyear::MonthYear, period::Period};
 use crate::journal::{Journal, JournalSummary};
 use crate::iterators::transactionsbymonth::transactions_by_month;
+use crate::iterators::transactionsbyperiod::transactions_by_period;
+use crate::transaction::Transaction;
 
-// TODO: Assumes the same unit for all entries
-pub fn balance_changes(journal: &Journal) -> MonthGrid<Account, Amount> {
+// the change in each account's balance for each month from the postings matching the query.
+// an account with entries in more than one commodity gets a cell holding an amount for each
+pub fn balance_changes(journal: &Journal,
+                       query  : &Query) -> Result<MonthGrid<Account, MixedAmount>, AmountError>
+{
     let summary = JournalSummary::from(journal);
+    let grid    = MonthGrid::new(summary.first_month, summary.final_month);
 
-    transactions_by_month(journal)
-        .into_iter()
-        .map(|(month, ts)| {
-            let by_account = ts.iter()
-                .flat_map(|transaction| &transaction.entries)
-                .map(|entry| (entry.account.clone(), entry.amount.clone()))
-                .fold(HashMap::<Account,Amount>::new(), |mut map, (account, amount)| {
-                    map.entry(account)
-                        .and_modify(|existing| existing.add(&amount))
-                        .or_insert(amount);
-                    map
-                });
-            (month, by_account)
-        })
-        .fold(MonthGrid::new(summary.first_month, summary.final_month), |mut grid, (month, by_account)| {
-            for (account, amount) in by_account {
-                grid.insert(account, month, amount);
+    add_changes(grid, transactions_by_month(journal), query)
+}
+
+// the same for each period of the given length
+fn period_changes(journal : &Journal,
+                  query   : &Query,
+                  interval: Interval) -> Result<PeriodGrid<Period, Account, MixedAmount>, AmountError>
+{
+    let first = journal.transactions.first().map(|t| t.date).unwrap_or_default();
+    let last  = journal.transactions.last().map(|t| t.date).unwrap_or_default();
+    let grid  = PeriodGrid::new(Period::containing(interval, first), Period::containing(interval, last));
+
+    add_changes(grid, transactions_by_period(journal, interval), query)
+}
+
+fn add_changes<'a, P: GridPeriod>(mut grid: PeriodGrid<P, Account, MixedAmount>,
+                                  periods : impl Iterator<Item = (P, &'a [Transaction])>,
+                                  query   : &Query) -> Result<PeriodGrid<P, Account, MixedAmount>, AmountError>
+{
+    // a cell's first posting is the only one whose account and amount get copied, the rest
+    // are added to the cell through references
+    for (period, transactions) in periods {
+        for entry in query.postings(transactions) {
+            match grid.get_mut(period, &entry.account) {
+                Some(change) => change.checked_add_amount(&entry.amount)?,
+                None         => grid.insert(entry.account.clone(), period, MixedAmount::from(entry.amount.clone())),
             }
-            grid
+        }
+    }
+    Ok(grid)
+}
+
+
+/* Balance report */
+
+#[derive(Clone, Default)]
+pub struct BalanceOptions {
+    pub query     : Query,           // only the postings matching this
+    pub flat      : bool,            // list full account names instead of an indented tree
+    pub depth     : Option<usize>,   // fold accounts deeper than this into their ancestor
+    pub historical: bool,            // show balances including everything before the report
+    pub invert    : bool,            // flip the signs, so income shows as positive
+    pub row_total : bool,            // add a column with each account's total over all periods
+    pub average   : bool,            // add a column with each account's average per period
+    pub percent   : bool,            // show each amount as a percentage of its parent's, or for
+                                     // top-level accounts and flat listings, of the column total
+    pub empty     : bool,            // show every selected account, with zero where it has no activity
+    pub sparkline : bool,            // end each row with a sparkline of its amounts over the periods
+}
+
+// one line of the balance report
+pub struct BalanceLine {
+    pub account: Account,
+    pub depth  : usize,               // indentation level, always 0 for a flat listing
+    pub amount : Option<MixedAmount>, // none for a percentage with nothing to be a share of
+}
+
+// The balance changes of each account in the given month, or over every month of the
+// journal without one. As a tree, every parent account gets a line of its own with the total
+// of its subaccounts, and each line shows just the last component of the account name,
+// indented by its depth. A flat listing shows full names. A balance too large for an
+// amount to hold is an error instead of a wrong number
+pub fn balance_report(journal: &Journal,
+                      month  : Option<MonthYear>,
+                      options: &BalanceOptions) -> Result<Vec<BalanceLine>, AmountError>
+{
+    let grid     = balance_changes(journal, &options.query)?;
+    let accounts = selected_accounts(journal, options);
+    let balances = match month {
+        Some(month) => {
+            let changes = grid.rows()
+                              .filter_map(|(account, _)| Some((account, grid.get(month, account)?)));
+            fold_balances(changes, options)?
+        },
+        None => {
+            let totals: Vec<(&Account, MixedAmount)> = grid.rows()
+                                                           .filter_map(|(account, _)| row_total(&grid, account))
+                                                           .collect::<Result<_, _>>()?;
+            fold_balances(totals.iter().map(|(account, total)| (*account, total)), options)?
+        },
+    };
+
+    Ok(to_lines(with_empty(percentages(balances, options), &accounts, options)?, options))
+}
+
+// the account's balance changes over every period of the grid, if it has any
+pub fn row_total<'a, P: GridPeriod>(grid   : &PeriodGrid<P, Account, MixedAmount>,
+                                    account: &'a Account) -> Option<Result<(&'a Account, MixedAmount), AmountError>>
+{
+    let total = grid.row_total(account, MixedAmount::checked_add).transpose()?;
+    Some(total.map(|total| (account, total)))
+}
+
+// the accounts with postings matching the query, in sorted order. this includes accounts
+// only used before the start of the report, which only matter for historical ones
+fn selected_accounts<'a>(journal: &'a Journal,
+                         options: &'a BalanceOptions) -> BTreeSet<&'a Account>
+{
+    options.query
+           .postings(journal.transactions.iter().chain(&journal.earlier))
+           .map(|e| &e.account)
+           .collect()
+}
+
+// fold every account deeper than the requested depth into its ancestor at that depth and,
+// for a tree, give every ancestor the total of its subaccounts
+pub fn fold_balances<'a>(balances: impl Iterator<Item = (&'a Account, &'a MixedAmount)>,
+                         options : &BalanceOptions) -> Result<HashMap<Account, MixedAmount>, AmountError>
+{
+    let mut folded: HashMap<Account, MixedAmount> = HashMap::new();
+    for (account, amount) in balances {
+        let account = match options.depth {
+            Some(depth) => account.ancestor(depth),
+            None        => account.as_str(),
+        };
+        add_to(&mut folded, account, amount)?;
+    }
+
+    if options.flat {
+        return Ok(folded)
+    }
+
+    let tree: AccountTree<MixedAmount> = folded.into_iter().collect();
+    tree.walk()
+        .filter_map(|entry| {
+            let total = tree.total(&entry.account, MixedAmount::checked_add).transpose()?;
+            Some(total.map(|total| (entry.account, total)))
         })
+        .collect()
+}
+
+// with the percent option, replace the folded balances with their share of their parent
+// account's balance, or the total of the top-level accounts. a zero total has no shares,
+// and neither does a balance in more than one commodity since they can't be compared, but
+// their accounts keep their place in the report with nothing to show
+fn percentages(balances: HashMap<Account, MixedAmount>,
+               options : &BalanceOptions) -> HashMap<Account, Option<MixedAmount>>
+{
+    if !options.percent {
+        return balances.into_iter().map(|(account, amount)| (account, Some(amount))).collect()
+    }
+
+    // added up exactly, so the accounts of a balanced journal come to zero instead of a
+    // float's rounding error, which would make every share enormous
+    let single = |amount: &MixedAmount| amount.single().map(Amount::decimal);
+
+    let column_total: Option<Decimal> = balances.iter()
+                                                .filter(|(account, _)| options.flat || account.parent().is_none())
+                                                .filter_map(|(_, amount)| single(amount))
+                                                .try_fold(Decimal::ZERO, |sum, amount| sum.checked_add(amount));
+
+    let share = |account: &Account, amount: &MixedAmount| {
+        let parent = account.parent()
+                            .filter(|_| !options.flat)
+                            .and_then(|parent| balances.get(&Account::from(parent)));
+
+        let total = match parent {
+            Some(parent) => single(parent)?,
+            None         => column_total?,
+        };
+        if total.is_zero() {
+            return None
+        }
+
+        let share = single(amount)?.to_f64()? / total.to_f64()? * 100.0;
+        Some(MixedAmount::from(Amount::from("%", share)))
+    };
+
+    balances.iter()
+            .map(|(account, amount)| (account.clone(), share(account, amount)))
+            .collect()
+}
+
+// with the empty option, give every selected account a balance, which is zero for the
+// ones with no activity, so the same accounts show up no matter what's in the report
+fn with_empty(mut balances: HashMap<Account, Option<MixedAmount>>,
+              accounts    : &BTreeSet<&Account>,
+              options     : &BalanceOptions) -> Result<HashMap<Account, Option<MixedAmount>>, AmountError>
+{
+    if options.empty {
+        let zero = MixedAmount::default();
+        for account in fold_balances(accounts.iter().map(|a| (*a, &zero)), options)?.into_keys() {
+            balances.entry(account).or_insert_with(|| Some(MixedAmount::default()));
+        }
+    }
+    Ok(balances)
+}
+
+// sort the folded balances into the lines of a report
+pub fn to_lines(balances: HashMap<Account, impl Into<Option<MixedAmount>>>,
+                options : &BalanceOptions) -> Vec<BalanceLine>
+{
+    let mut lines: Vec<BalanceLine> =
+        balances.into_iter()
+                .map(|(account, amount)| BalanceLine {
+                    depth : line_depth(&account, options),
+                    account,
+                    amount: amount.into().map(|amount| if options.invert { amount.negate() } else { amount }),
+                })
+                .collect();
+
+    lines.sort_by(|a, b| compare_accounts(&a.account, &b.account));
+    lines
+}
+
+fn line_depth(account: &Account, options: &BalanceOptions) -> usize {
+    if options.flat { 0 } else { account.depth() - 1 }
+}
+
+
+/* Periodic balance report */
+
+// the balance changes of each account with one column per period of the journal, or with
+// the historical option, each account's balance at the end of each period
+pub struct PeriodicBalanceReport {
+    pub periods  : Vec<String>,
+    pub rows     : Vec<PeriodicBalanceRow>,
+    pub totals   : PeriodicBalanceRow, // the total of each column, along the bottom
+    pub row_total: bool,               // whether to show the total column, which is never
+                                       // shown for historical balances since they don't add up
+    pub average  : bool,
+    pub sparkline: bool,
+}
+
+pub struct PeriodicBalanceRow {
+    pub account: Account,
+    pub depth  : usize,
+    pub amounts: Vec<Option<MixedAmount>>, // one per period, None for no activity
+    pub total  : Option<MixedAmount>,      // the sum of the amounts
+    pub average: Option<MixedAmount>,      // the total divided by the number of periods
+}
+
+// a column for each period of the given length, from the grid of balance changes for periods
+// of that length. periods with forecast transactions in them are marked with a *
+pub fn balance_periodic_report(journal : &Journal,
+                               options : &BalanceOptions,
+                               interval: Interval) -> Result<PeriodicBalanceReport, AmountError>
+{
+    let grid     = period_changes(journal, &options.query, interval)?;
+    let accounts = selected_accounts(journal, options);
+    let periods: Vec<Period> = grid.columns().collect();
+
+    // the grid's balance changes gathered up by period
+    let mut changes: HashMap<Period, Vec<(&Account, &MixedAmount)>> = HashMap::new();
+    for (period, account, amount) in grid.cells() {
+        changes.entry(period).or_default().push((account, amount));
+    }
+
+    let columns: Vec<HashMap<Account, MixedAmount>> = if options.historical {
+        historical_balances(journal, &changes, &periods, options)?
+    } else {
+        periods.iter()
+               .map(|period| fold_balances(changes.get(period).into_iter().flatten().copied(), options))
+               .collect::<Result<_, _>>()?
+    };
+
+    let forecast: HashSet<Period> = journal.transactions
+                                           .iter()
+                                           .filter(|t| t.is_forecast())
+                                           .map(|t| Period::containing(interval, t.date))
+                                           .collect();
+
+    let labels = periods.iter()
+                        .map(|period| mark_forecast(period.to_string(), forecast.contains(period)))
+                        .collect();
+    to_periodic_report(labels, columns, &accounts, options)
+}
+
+// one row per account in any of the columns, in sorted order
+fn to_periodic_report(periods : Vec<String>,
+                      columns : Vec<HashMap<Account, MixedAmount>>,
+                      accounts: &BTreeSet<&Account>,
+                      options : &BalanceOptions) -> Result<PeriodicBalanceReport, AmountError>
+{
+    let columns: Vec<HashMap<Account, Option<MixedAmount>>> =
+        columns.into_iter()
+               .map(|column| with_empty(percentages(column, options), accounts, options))
+               .collect::<Result<_, _>>()?;
+
+    let mut names: Vec<&Account> = columns.iter().flat_map(|c| c.keys()).collect();
+    names.sort_by(|a, b| compare_accounts(a, b));
+    names.dedup();
+
+    let rows: Vec<PeriodicBalanceRow> =
+        names.into_iter()
+             .map(|account| to_periodic_row(account.clone(),
+                                            line_depth(account, options),
+                                            columns.iter()
+                                                   .map(|c| c.get(account).cloned().flatten())
+                                                   .map(|a| if options.invert { a.map(MixedAmount::negate) } else { a })
+                                                   .collect()))
+             .collect::<Result<_, _>>()?;
+
+    // the top-level rows of a tree already include their subaccounts
+    let top_level: Vec<&PeriodicBalanceRow> =
+        rows.iter()
+            .filter(|row| options.flat || row.account.parent().is_none())
+            .collect();
+
+    let column_totals = (0..periods.len())
+                            .map(|i| sum(top_level.iter().filter_map(|row| row.amounts[i].as_ref())))
+                            .collect::<Result<_, _>>()?;
+
+    Ok(PeriodicBalanceReport {
+        totals   : to_periodic_row(Account::default(), 0, column_totals)?,
+        periods,
+        rows,
+        row_total: options.row_total && !options.historical,
+        average  : options.average,
+        sparkline: options.sparkline,
+    })
+}
+
+fn to_periodic_row(account: Account,
+                   depth  : usize,
+                   amounts: Vec<Option<MixedAmount>>) -> Result<PeriodicBalanceRow, AmountError>
+{
+    let total   = sum(amounts.iter().flatten())?;
+    let average = total.as_ref().map(|total| total.divide(amounts.len() as i64));
+
+    Ok(PeriodicBalanceRow { account, depth, amounts, total, average })
+}
+
+// the sum of the amounts, if there are any
+fn sum<'a>(mut amounts: impl Iterator<Item = &'a MixedAmount>) -> Result<Option<MixedAmount>, AmountError> {
+    let Some(first) = amounts.next() else { return Ok(None) };
+    let mut total = first.clone();
+    for amount in amounts {
+        total.checked_add(amount)?;
+    }
+    Ok(Some(total))
+}
+
+// each account's balance at the end of each period, starting from its balance from the
+// transactions set aside before the start of the report
+fn historical_balances(journal: &Journal,
+                       changes: &HashMap<Period, Vec<(&Account, &MixedAmount)>>,
+                       periods: &[Period],
+                       options: &BalanceOptions) -> Result<Vec<HashMap<Account, MixedAmount>>, AmountError>
+{
+    let mut running = opening_balances(journal, &options.query)?;
+
+    let mut columns = vec![];
+    for period in periods {
+        for (account, change) in changes.get(period).into_iter().flatten() {
+            add_to(&mut running, account, change)?;
+        }
+        columns.push(fold_balances(running.iter(), options)?);
+    }
+    Ok(columns)
+}
+
+// the balances from the matching postings set aside before the start of the report
+fn opening_balances(journal: &Journal,
+                    query  : &Query) -> Result<HashMap<Account, MixedAmount>, AmountError>
+{
+    let mut balances: HashMap<Account, MixedAmount> = HashMap::new();
+    for entry in query.postings(&journal.earlier) {
+        balances.entry(entry.account.clone())
+                .or_default()
+                .checked_add_amount(&entry.amount)?;
+    }
+    Ok(balances)
+}
+
+fn add_to(balances: &mut HashMap<Account, MixedAmount>,
+          account : &str,
+          amount  : &MixedAmount) -> Result<(), AmountError>
+{
+    match balances.get_mut(&Account::from(account)) {
+        Some(balance) => balance.checked_add(amount)?,
+        None          => { balances.insert(account.into(), amount.clone()); }
+    }
+    Ok(())
+}
+
+pub fn is_same_or_subaccount(account: &Account, parent: &str) -> bool {
+    account == parent || account.is_descendant_of(parent)
+}
+
+// order accounts component by component so subaccounts always follow their parent
+pub fn compare_accounts(a: &str, b: &str) -> Ordering {
+    a.split(':').cmp(b.split(':'))
+}
+
+//        $1000.00  assets
+//          $50.00    cash
+//         $950.00    savings
+//
+// with a dash for a percentage that has nothing to be a share of
+//
+//          100.0%  expenses
+//               -  income
+
+impl WithStyles for BalanceLine {}
+
+impl fmt::Display for Styled<'_, BalanceLine> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let line   = self.value;
+        let amount = match &line.amount {
+            Some(amount) => padded_amount(amount, self.styles, 15),
+            None         => pad_left("-", 15),
+        };
+        write!(f, "{}  {}", amount, paint(indented_name(&line.account, line.depth), Style::Dim))
+    }
+}
+
+//                2023-03      2023-04        Total      Average
+// assets        $1022.51      $-35.10      $987.41      $493.71
+//   cash          $37.50       $-5.00       $32.50       $16.25
+//   savings      $985.01      $-30.10      $954.91      $477.46
+// -------------------------------------------------------------
+//               $1022.51      $-35.10      $987.41      $493.71
+//
+// each column is at least 12 wide, or as wide as its widest cell, like one with amounts
+// in more than one commodity. with the sparkline option, each row ends with its trend
+// across the periods, like  █▁
+
+impl WithStyles for PeriodicBalanceReport {}
+
+impl fmt::Display for Styled<'_, PeriodicBalanceReport> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let report = self.value;
+        let names: Vec<String> =
+            report.rows
+                .iter()
+                .map(|row| indented_name(&row.account, row.depth))
+                .collect();
+
+        let width = names.iter().map(|n| display_width(n)).max().unwrap_or(0);
+
+        let mut headers: Vec<&str> = report.periods.iter().map(String::as_str).collect();
+        if report.row_total { headers.push("Total"); }
+        if report.average   { headers.push("Average"); }
+
+        let mut widths: Vec<usize> = headers.iter().map(|header| display_width(header).max(12)).collect();
+        for row in report.rows.iter().chain(std::iter::once(&report.totals)) {
+            for (width, amount) in widths.iter_mut().zip(self.cells(row)) {
+                let cell = amount.map(|a| a.styled(self.styles).to_string()).unwrap_or_default();
+                *width = (*width).max(display_width(&cell));
+            }
+        }
+
+        write!(f, "{:width$}", "")?;
+        for (header, width) in headers.iter().zip(&widths) {
+            write!(f, " {}", paint(pad_left(header, *width), Style::Bold))?;
+        }
+        writeln!(f)?;
+
+        for (row, name) in report.rows.iter().zip(names) {
+            write!(f, "{}", paint(pad_right(&name, width), Style::Dim))?;
+            self.write_amounts(f, row, &widths)?;
+        }
+
+        writeln!(f, "{}", "-".repeat(width + widths.iter().map(|w| w + 1).sum::<usize>()))?;
+        write!(f, "{:width$}", "")?;
+        self.write_amounts(f, &report.totals, &widths)
+    }
+}
+
+impl Styled<'_, PeriodicBalanceReport> {
+    // the row's amount for each column, then its total and average if they're shown
+    fn cells<'r>(&self, row: &'r PeriodicBalanceRow) -> impl Iterator<Item = Option<&'r MixedAmount>> {
+        let report = self.value;
+        row.amounts
+           .iter()
+           .chain(report.row_total.then_some(&row.total))
+           .chain(report.average.then_some(&row.average))
+           .map(Option::as_ref)
+    }
+
+    fn write_amounts(&self, f: &mut fmt::Formatter<'_>, row: &PeriodicBalanceRow, widths: &[usize]) -> fmt::Result {
+        for (amount, width) in self.cells(row).zip(widths) {
+            match amount {
+                Some(amount) => write!(f, " {}", padded_amount(amount, self.styles, *width))?,
+                None         => write!(f, " {}", " ".repeat(*width))?,
+            }
+        }
+
+        let sparkline = sparkline(&row.amounts);
+        if self.value.sparkline && !sparkline.is_empty() {
+            write!(f, "  {}", sparkline)?;
+        }
+        writeln!(f)
+    }
+}
+
+// the amount right-aligned to the width, in red if any of it is negative
+fn padded_amount(amount: &MixedAmount, styles: &CommodityStyles, width: usize) -> String {
+    paint(pad_left(&amount.styled(styles).to_string(), width), Style::amount(amount.is_negative()))
+}
+
+// the amounts as bars from lowest to highest, with no activity counting as zero. there's
+// no sparkline for amounts in more than one commodity since they can't be compared
+fn sparkline(amounts: &[Option<MixedAmount>]) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+    let values: Option<Vec<f64>> =
+        amounts.iter()
+               .map(|amount| match amount {
+                   Some(amount) => amount.single().map(Amount::to_f64),
+                   None         => Some(0.0),
+               })
+               .collect();
+
+    let Some(values) = values else { return String::new() };
+
+    let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let range = max - min;
+
+    values.iter()
+          .map(|value| if range == 0.0 { 0 } else { ((value - min) / range * 7.0).round() as usize })
+          .map(|level| BARS[level])
+          .collect()
+}
+
+// account,amount
+// assets,$1000.00
+// assets:cash,$50.00
+
+impl WithStyles for [BalanceLine] {}
+
+impl Csv for Styled<'_, [BalanceLine]> {
+    fn csv_header(&self) -> Vec<String> {
+        vec!["account".to_string(), "amount".to_string()]
+    }
+
+    fn csv_records(&self) -> Vec<Vec<String>> {
+        self.value
+            .iter()
+            .map(|line| vec![line.account.to_string(), line.amount.as_ref().map(|a| a.styled(self.styles).to_string()).unwrap_or_default()])
+            .collect()
+    }
+}
+
+// account,2023-03,2023-04,total,average
+// assets,$1022.51,$-35.10,$987.41,$493.71
+// total,$1022.51,$-35.10,$987.41,$493.71
+
+impl Csv for Styled<'_, PeriodicBalanceReport> {
+    fn csv_header(&self) -> Vec<String> {
+        let report = self.value;
+        let mut header = vec!["account".to_string()];
+        header.extend(report.periods.iter().cloned());
+        if report.row_total { header.push("total".to_string()); }
+        if report.average   { header.push("average".to_string()); }
+        header
+    }
+
+    fn csv_records(&self) -> Vec<Vec<String>> {
+        let report = self.value;
+        let cell   = |amount: &Option<MixedAmount>| amount.as_ref().map(|a| a.styled(self.styles).to_string()).unwrap_or_default();
+
+        report.rows
+              .iter()
+              .chain(std::iter::once(&report.totals))
+              .map(|row| {
+                  let name = if row.account.is_empty() { "total".to_string() } else { row.account.to_string() };
+                  let mut record = vec![name];
+                  record.extend(row.amounts.iter().map(cell));
+                  if report.row_total { record.push(cell(&row.total)); }
+                  if report.average   { record.push(cell(&row.average)); }
+                  record
+              })
+              .collect()
+    }
+}
+
+// {"account":"assets:cash","amount":[{"quantity":50.00,"commodity":"$","decimals":2}]}
+
+impl Json for BalanceLine {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Object(vec![
+            ("account", self.account.to_json()),
+            ("amount",  self.amount.to_json()),
+        ])
+    }
+}
+
+// {"periods":["2023-03","2023-04"],
+//  "rows":[{"account":"assets","amounts":[[{"quantity":1022.51,...}],[...]]}],
+//  "totals":{"account":"total","amounts":[...]}}
+//
+// with "total" and "average" in each row when those columns are shown
+
+impl Json for PeriodicBalanceReport {
+    fn to_json(&self) -> JsonValue {
+        let row = |row: &PeriodicBalanceRow, name: &str| {
+            let mut fields = vec![
+                ("account", name.to_json()),
+                ("amounts", row.amounts.to_json()),
+            ];
+            if self.row_total { fields.push(("total",   row.total.to_json())); }
+            if self.average   { fields.push(("average", row.average.to_json())); }
+            JsonValue::Object(fields)
+        };
+
+        JsonValue::Object(vec![
+            ("periods", self.periods.to_json()),
+            ("rows",    JsonValue::Array(self.rows.iter().map(|r| row(r, &r.account)).collect())),
+            ("totals",  row(&self.totals, "total")),
+        ])
+    }
+}
+
+// a tree shows just the last component of the account name, indented by its depth
+fn indented_name(account: &Account, depth: usize) -> String {
+    if depth == 0 {
+        account.to_string()
+    } else {
+        format!("{}{}", "  ".repeat(depth), account.name())
+    }
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use crate::journal::Journal;
+    use crate::types::daterange::{DateRange, parse_date};
+    use crate::types::interval::Interval;
+    use crate::types::monthyear::MonthYear;
+    use crate::output::{to_csv, to_json};
+    use crate::types::{amount::{Amount, AmountError}, mixedamount::MixedAmount};
+    use crate::types::Account;
+    use crate::types::commoditystyle::{CommodityStyles, WithStyles};
+    use super::{BalanceOptions, sparkline, balance_changes, balance_periodic_report, balance_report, compare_accounts, is_same_or_subaccount};
+
+    fn sample_journal() -> Journal {
+        let journal =
+r#"
+2023/03/01 opening balances
+    assets:cash                  $50
+    assets:savings               $1000.00
+    equity:opening-balances
+
+2023/03/07 Sandwich, Chili
+    assets:savings                    $-14.99
+    expenses:tips                       $0.50
+    assets:cash                        $-0.50
+    expenses:food:tim-hortons
+
+2023/03/09 Sub
+    expenses:food:subway                $12
+    assets:cash
+"#;
+        Journal::from_lines(journal.lines()).unwrap()
+    }
+
+    fn render(options: &BalanceOptions) -> Vec<String> {
+        let journal = sample_journal();
+        balance_report(&journal, Some(MonthYear::new(3, 2023)), options).unwrap()
+            .iter()
+            .map(|line| line.styled(&journal.commodities).to_string().trim().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_balance_report_tree() {
+        assert_eq!(render(&BalanceOptions::default()), vec![
+            "$1022.51  assets",
+            "$37.50    cash",
+            "$985.01    savings",
+            "$-1050.00  equity",
+            "$-1050.00    opening-balances",
+            "$27.49  expenses",
+            "$26.99    food",
+            "$12.00      subway",
+            "$14.99      tim-hortons",
+            "$0.50    tips",
+        ]);
+    }
+
+    #[test]
+    fn test_balance_report_flat() {
+        let options = BalanceOptions { flat: true, ..Default::default() };
+        assert_eq!(render(&options), vec![
+            "$37.50  assets:cash",
+            "$985.01  assets:savings",
+            "$-1050.00  equity:opening-balances",
+            "$12.00  expenses:food:subway",
+            "$14.99  expenses:food:tim-hortons",
+            "$0.50  expenses:tips",
+        ]);
+    }
+
+    #[test]
+    fn test_balance_report_flat_depth() {
+        let options = BalanceOptions { flat: true, depth: Some(2), ..Default::default() };
+        assert_eq!(render(&options), vec![
+            "$37.50  assets:cash",
+            "$985.01  assets:savings",
+            "$-1050.00  equity:opening-balances",
+            "$26.99  expenses:food",
+            "$0.50  expenses:tips",
+        ]);
+    }
+
+    #[test]
+    fn test_balance_report_tree_depth() {
+        let options = BalanceOptions { depth: Some(2), ..Default::default() };
+        assert_eq!(render(&options), vec![
+            "$1022.51  assets",
+            "$37.50    cash",
+            "$985.01    savings",
+            "$-1050.00  equity",
+            "$-1050.00    opening-balances",
+            "$27.49  expenses",
+            "$26.99    food",
+            "$0.50    tips",
+        ]);
+
+        let options = BalanceOptions { depth: Some(1), ..Default::default() };
+        assert_eq!(render(&options), vec![
+            "$1022.51  assets",
+            "$-1050.00  equity",
+            "$27.49  expenses",
+        ]);
+    }
+
+    #[test]
+    fn test_balance_report_account() {
+        let options = BalanceOptions { query: "expenses:food".parse().unwrap(), depth: Some(1), ..Default::default() };
+        assert_eq!(render(&options), vec![
+            "$26.99  expenses",
+        ]);
+    }
+
+    #[test]
+    fn test_balance_report_every_month() {
+        let journal = "2023/03/09 Sub\n    expenses:food:subway  $12\n    assets:cash\n\
+                       2023/05/02 Sub\n    expenses:food:subway  $13\n    assets:cash\n";
+        let journal = Journal::from_lines(journal.lines()).unwrap();
+        let options = BalanceOptions { flat: true, ..Default::default() };
+
+        let lines = |month| -> Vec<String> {
+            balance_report(&journal, month, &options).unwrap().iter().map(|line| line.styled(&CommodityStyles::default()).to_string().trim().to_string()).collect()
+        };
+        assert_eq!(lines(Some(MonthYear::new(5, 2023))), vec!["$-13.00  assets:cash", "$13.00  expenses:food:subway"]);
+        assert_eq!(lines(Some(MonthYear::new(4, 2023))), Vec::<String>::new());
+        assert_eq!(lines(None), vec!["$-25.00  assets:cash", "$25.00  expenses:food:subway"]);
+    }
+
+    #[test]
+    fn test_balance_report_invert() {
+        let options = BalanceOptions { query: "equity".parse().unwrap(), flat: true, invert: true, ..Default::default() };
+        assert_eq!(render(&options), vec![
+            "$1050.00  equity:opening-balances",
+        ]);
+    }
+
+    #[test]
+    fn test_balance_report_percent() {
+        let options = BalanceOptions { query: "expenses".parse().unwrap(), percent: true, ..Default::default() };
+        assert_eq!(render(&options), vec![
+            "100.0%  expenses",
+            "98.2%    food",
+            "44.5%      subway",
+            "55.5%      tim-hortons",
+            "1.8%    tips",
+        ]);
+
+        let options = BalanceOptions { query: "expenses".parse().unwrap(), flat: true, percent: true, ..Default::default() };
+        assert_eq!(render(&options), vec![
+            "43.7%  expenses:food:subway",
+            "54.5%  expenses:food:tim-hortons",
+            "1.8%  expenses:tips",
+        ]);
+    }
+
+    #[test]
+    fn test_balance_report_percent_balanced() {
+        // every account of a balanced journal adds up to zero, so the top-level accounts have
+        // no total to be a share of, but they keep their lines for their subaccounts' shares
+        let options = BalanceOptions { percent: true, ..Default::default() };
+        assert_eq!(render(&options), vec![
+            "-  assets",
+            "3.7%    cash",
+            "96.3%    savings",
+            "-  equity",
+            "100.0%    opening-balances",
+            "-  expenses",
+            "98.2%    food",
+            "44.5%      subway",
+            "55.5%      tim-hortons",
+            "1.8%    tips",
+        ]);
+
+        let journal = sample_journal();
+        let options = BalanceOptions { percent: true, flat: true, ..Default::default() };
+        let report  = balance_report(&journal, None, &options).unwrap();
+        assert_eq!(to_csv(&report[..].styled(&journal.commodities)).lines().take(3).collect::<Vec<_>>(),
+                   vec!["account,amount", "assets:cash,", "assets:savings,"]);
+
+        // and in a periodic report, a blank cell in the account's row
+        let options = BalanceOptions { percent: true, depth: Some(1), ..Default::default() };
+        let report  = balance_periodic_report(&journal, &options, Interval::Monthly).unwrap();
+        let rows: Vec<(String, Option<MixedAmount>)> = report.rows.into_iter().map(|row| (row.account.to_string(), row.amounts[0].clone())).collect();
+        assert_eq!(rows, vec![("assets".to_string(), None), ("equity".to_string(), None), ("expenses".to_string(), None)]);
+    }
+
+    #[test]
+    fn test_balance_monthly_report() {
+        let journal =
+r#"
+2023/01/05 Sub
+    expenses:food:subway                $12
+    assets:cash
+
+2023/03/07 Sandwich
+    expenses:food:tim-hortons         $4.50
+    assets:cash
+"#;
+        let journal = Journal::from_lines(journal.lines()).unwrap();
+        let options = BalanceOptions { depth: Some(2), ..Default::default() };
+        let report  = balance_periodic_report(&journal, &options, Interval::Monthly).unwrap();
+
+        assert_eq!(report.periods.len(), 3);
+        assert_eq!(report.styled(&journal.commodities).to_string(),
+"              2023-01      2023-02      2023-03
+assets        $-12.00                    $-4.50
+  cash        $-12.00                    $-4.50
+expenses       $12.00                     $4.50
+  food         $12.00                     $4.50
+-----------------------------------------------
+                $0.00                     $0.00
+");
+
+        let options = BalanceOptions { query: "expenses".parse().unwrap(), flat: true, row_total: true, average: true, ..Default::default() };
+        let report  = balance_periodic_report(&journal, &options, Interval::Monthly).unwrap();
+
+        assert_eq!(report.styled(&journal.commodities).to_string(),
+"                               2023-01      2023-02      2023-03        Total      Average
+expenses:food:subway            $12.00                                 $12.00        $4.00
+expenses:food:tim-hortons                                  $4.50        $4.50        $1.50
+------------------------------------------------------------------------------------------
+                                $12.00                     $4.50       $16.50        $5.50
+");
+    }
+
+    #[test]
+    fn test_balance_monthly_report_mixed_commodities() {
+        let journal =
+r#"
+2023/01/05 Hydro
+    expenses:utilities                $80.25
+    expenses:utilities              310.5 kWh
+    assets:cash                      $-80.25
+    usage:meter                    -310.5 kWh
+
+2023/02/05 Hydro
+    expenses:utilities                $75.00
+    assets:cash
+"#;
+        let journal = Journal::from_lines(journal.lines()).unwrap();
+        let options = BalanceOptions { query: "expenses".parse().unwrap(), flat: true, row_total: true, ..Default::default() };
+        let report  = balance_periodic_report(&journal, &options, Interval::Monthly).unwrap();
+
+        // the columns with both commodities in a cell widen to fit it, the others stay put
+        assert_eq!(report.styled(&journal.commodities).to_string(),
+"                             2023-01      2023-02              Total
+expenses:utilities $80.25, 310.5 kWh       $75.00 $155.25, 310.5 kWh
+--------------------------------------------------------------------
+                   $80.25, 310.5 kWh       $75.00 $155.25, 310.5 kWh
+");
+    }
+
+    #[test]
+    fn test_balance_monthly_report_empty() {
+        let journal =
+r#"
+2022/12/01 Opening
+    assets:savings                     $100
+    equity:opening-balances
+
+2023/01/05 Sub
+    expenses:food:subway                $12
+    assets:savings
+
+2023/03/07 Sandwich
+    expenses:food:tim-hortons         $4.50
+    assets:savings
+"#;
+        let mut journal = Journal::from_lines(journal.lines()).unwrap();
+        journal.restrict_to(&DateRange::new(parse_date("2023/01"), None));
+
+        let options = BalanceOptions { flat: true, empty: true, ..Default::default() };
+        let report  = balance_periodic_report(&journal, &options, Interval::Monthly).unwrap();
+
+        assert_eq!(report.styled(&journal.commodities).to_string(),
+"                               2023-01      2023-02      2023-03
+assets:savings                 $-12.00            0       $-4.50
+equity:opening-balances              0            0            0
+expenses:food:subway            $12.00            0            0
+expenses:food:tim-hortons            0            0        $4.50
+----------------------------------------------------------------
+                                 $0.00            0        $0.00
+");
+    }
+
+    #[test]
+    fn test_balance_periodic_report_quarterly() {
+        let journal =
+r#"
+2023/01/05 Sub
+    expenses:food:subway                $12
+    assets:cash
+
+2023/03/07 Sandwich
+    expenses:food:tim-hortons         $4.50
+    assets:cash
+
+2023/07/02 Sub
+    expenses:food:subway                 $9
+    assets:cash
+"#;
+        let journal = Journal::from_lines(journal.lines()).unwrap();
+        let options = BalanceOptions { query: "expenses".parse().unwrap(), depth: Some(2), ..Default::default() };
+        let report  = balance_periodic_report(&journal, &options, Interval::Quarterly).unwrap();
+
+        assert_eq!(report.styled(&journal.commodities).to_string(),
+"               2023Q1       2023Q2       2023Q3
+expenses       $16.50                     $9.00
+  food         $16.50                     $9.00
+-----------------------------------------------
+               $16.50                     $9.00
+");
+
+        let options = BalanceOptions { historical: true, ..options };
+        let report  = balance_periodic_report(&journal, &options, Interval::Quarterly).unwrap();
+        assert_eq!(report.rows[0].amounts.iter().map(|a| a.as_ref().map(|a| a.to_string())).collect::<Vec<_>>(),
+                   vec![Some("$16.50".to_string()), Some("$16.50".to_string()), Some("$25.50".to_string())]);
+    }
+
+    #[test]
+    fn test_balance_periodic_report_totals() {
+        let journal =
+r#"
+2023/01/05 Sub
+    expenses:food:subway                $12
+    assets:cash
+
+2023/02/10 Hydro
+    expenses:utilities                  $61
+    assets:cash
+
+2023/02/20 Sandwich
+    expenses:food:tim-hortons         $4.50
+    assets:cash
+"#;
+        let journal = Journal::from_lines(journal.lines()).unwrap();
+        let options = BalanceOptions { query: "expenses".parse().unwrap(), row_total: true, average: true, ..Default::default() };
+        let report  = balance_periodic_report(&journal, &options, Interval::Monthly).unwrap();
+        let cells   = |amounts: &[Option<MixedAmount>]| amounts.iter().map(|a| a.as_ref().map(|a| a.to_string())).collect::<Vec<_>>();
+
+        // the column totals only add up the top of the tree, not its subaccounts again
+        assert_eq!(cells(&report.totals.amounts), vec![Some("$12.00".to_string()), Some("$65.50".to_string())]);
+        assert_eq!(report.totals.total.as_ref().map(|a| a.to_string()), Some("$77.50".to_string()));
+        assert_eq!(report.totals.average.as_ref().map(|a| a.to_string()), Some("$38.75".to_string()));
+
+        // an account with nothing in a period still averages over every period
+        let subway = report.rows.iter().find(|row| row.account.as_str() == "expenses:food:subway").unwrap();
+        assert_eq!(subway.average.as_ref().map(|a| a.to_string()), Some("$6.00".to_string()));
+        assert!(report.row_total);
+
+        // historical balances don't add up across periods, so there's no total column
+        let options = BalanceOptions { historical: true, ..options };
+        assert!(!balance_periodic_report(&journal, &options, Interval::Monthly).unwrap().row_total);
+    }
+
+    #[test]
+    fn test_balance_changes() {
+        let journal =
+r#"
+2023/03/01 Hydro
+    expenses:utilities:hydro         $61.20
+    assets:chequing
+
+2023/03/01 Hydro meter
+    expenses:utilities:hydro         308 kWh
+    equity:meter
+
+2023/03/15 Hydro refund
+    expenses:utilities:hydro         $-6.20
+    assets:chequing
+
+2023/04/01 Hydro
+    expenses:utilities:hydro         $58.80
+    assets:chequing
+"#;
+        let journal = Journal::from_lines(journal.lines()).unwrap();
+        let grid    = balance_changes(&journal, &"hydro".parse().unwrap()).unwrap();
+        let cell    = |month| grid.get(month, &Account::from("expenses:utilities:hydro")).map(|change| change.to_string());
+
+        // a month's postings add up in their cell, each commodity on its own
+        assert_eq!(cell(MonthYear::new(3, 2023)), Some("$55.00, 308.000 kWh".to_string()));
+        assert_eq!(cell(MonthYear::new(4, 2023)), Some("$58.80".to_string()));
+        assert_eq!(grid.rows().count(), 1);
+    }
+
+    #[test]
+    fn test_balance_report_overflow() {
+        let journal = Journal::from_lines(r#"
+2023/03/01 Windfall
+    assets:savings     $60000000000000000
+    income:windfall
+
+2023/04/01 Windfall
+    assets:savings     $60000000000000000
+    income:windfall
+"#.lines()).unwrap();
+
+        // each month's change fits in an amount, but not their total
+        let options = BalanceOptions { flat: true, ..Default::default() };
+        assert!(balance_report(&journal, Some(MonthYear::new(3, 2023)), &options).is_ok());
+        assert_eq!(balance_report(&journal, None, &options).err(), Some(AmountError::Overflow));
+
+        let options = BalanceOptions { historical: true, ..options };
+        assert_eq!(balance_periodic_report(&journal, &options, Interval::Monthly).err(), Some(AmountError::Overflow));
+    }
+
+    #[test]
+    fn test_balance_report_mixed_units() {
+        let journal =
+r#"
+2023/03/01 Hydro
+    expenses:utilities:hydro         $61.20
+    assets:chequing
+
+2023/03/01 Hydro meter
+    expenses:utilities:hydro         308 kWh
+    equity:meter
+"#;
+        let journal = Journal::from_lines(journal.lines()).unwrap();
+        let options = BalanceOptions { query: "expenses".parse().unwrap(), depth: Some(1), ..Default::default() };
+
+        let lines: Vec<String> = balance_report(&journal, Some(MonthYear::new(3, 2023)), &options).unwrap()
+                                     .iter()
+                                     .map(|line| line.styled(&CommodityStyles::default()).to_string().trim().to_string())
+                                     .collect();
+        assert_eq!(lines, vec!["$61.20, 308.000 kWh  expenses"]);
+
+        let report = balance_periodic_report(&journal, &options, Interval::Monthly).unwrap();
+        assert_eq!(report.totals.amounts[0].as_ref().map(|a| a.to_string()),
+                   Some("$61.20, 308.000 kWh".to_string()));
+    }
+
+    #[test]
+    fn test_balance_monthly_report_historical() {
+        let journal =
+r#"
+2022/12/01 Opening
+    assets:savings                     $100
+    equity:opening-balances
+
+2023/01/05 Sub
+    expenses:food:subway                $12
+    assets:savings
+
+2023/03/07 Sandwich
+    expenses:food:tim-hortons         $4.50
+    assets:savings
+"#;
+        let mut journal = Journal::from_lines(journal.lines()).unwrap();
+        journal.restrict_to(&DateRange::new(parse_date("2023/01"), None));
+
+        let options = BalanceOptions { query: "assets".parse().unwrap(), historical: true, ..Default::default() };
+        let report  = balance_periodic_report(&journal, &options, Interval::Monthly).unwrap();
+
+        assert_eq!(report.styled(&journal.commodities).to_string(),
+"               2023-01      2023-02      2023-03
+assets          $88.00       $88.00       $83.50
+  savings       $88.00       $88.00       $83.50
+------------------------------------------------
+                $88.00       $88.00       $83.50
+");
+    }
+
+    #[test]
+    fn test_balance_monthly_report_wide_characters() {
+        let journal =
+r#"
+2023/01/05 Ramen
+    expenses:food:ラーメン              $12
+    assets:cash
+
+2023/01/07 Sandwich
+    expenses:food:sandwich            $4.50
+    assets:cash
+"#;
+        let journal = Journal::from_lines(journal.lines()).unwrap();
+        let options = BalanceOptions { query: "expenses".parse().unwrap(), flat: true, ..Default::default() };
+        let report  = balance_periodic_report(&journal, &options, Interval::Monthly).unwrap();
+
+        assert_eq!(report.styled(&journal.commodities).to_string(),
+"                            2023-01
+expenses:food:sandwich        $4.50
+expenses:food:ラーメン       $12.00
+-----------------------------------
+                             $16.50
+");
+    }
+
+    #[test]
+    fn test_balance_monthly_report_sparkline() {
+        let journal =
+r#"
+2023/01/05 Sub
+    expenses:food:subway                $12
+    assets:cash
+
+2023/03/07 Sandwich
+    expenses:food:tim-hortons         $4.50
+    assets:cash
+"#;
+        let journal = Journal::from_lines(journal.lines()).unwrap();
+        let options = BalanceOptions { query: "expenses".parse().unwrap(), flat: true, sparkline: true, ..Default::default() };
+        let report  = balance_periodic_report(&journal, &options, Interval::Monthly).unwrap();
+
+        assert_eq!(report.styled(&journal.commodities).to_string(),
+"                               2023-01      2023-02      2023-03
+expenses:food:subway            $12.00                            █▁▁
+expenses:food:tim-hortons                                  $4.50  ▁▁█
+----------------------------------------------------------------
+                                $12.00                     $4.50  █▁▄
+");
+    }
+
+    #[test]
+    fn test_balance_report_csv() {
+        let options = BalanceOptions { query: "expenses".parse().unwrap(), depth: Some(2), ..Default::default() };
+        let journal = sample_journal();
+        let report  = balance_report(&journal, Some(MonthYear::new(3, 2023)), &options).unwrap();
+        assert_eq!(to_csv(&report[..].styled(&journal.commodities)),
+"account,amount
+expenses,$27.49
+expenses:food,$26.99
+expenses:tips,$0.50
+");
+
+        let options = BalanceOptions { flat: true, row_total: true, ..options };
+        let report  = balance_periodic_report(&journal, &options, Interval::Monthly).unwrap();
+        assert_eq!(to_csv(&report.styled(&journal.commodities)),
+"account,2023-03,total
+expenses:food,$26.99,$26.99
+expenses:tips,$0.50,$0.50
+total,$27.49,$27.49
+");
+    }
+
+    #[test]
+    fn test_balance_report_styles() {
+        let journal = "2023/03/01 Payroll\n    assets:checking    $2000\n    income:payroll";
+        let plain   = Journal::from_lines(journal.lines()).unwrap();
+        let styled  = Journal::from_lines(format!("commodity $1,000.00\n\n{}", journal).lines()).unwrap();
+
+        // each journal's report is written in that journal's styles, so a reread journal with a
+        // new commodity directive changes the output without anything left over from the first
+        let render = |journal: &Journal| balance_report(journal, None, &BalanceOptions { flat: true, ..Default::default() })
+                                             .unwrap()
+                                             .iter()
+                                             .map(|line| line.styled(&journal.commodities).to_string().trim().to_string())
+                                             .collect::<Vec<String>>();
+
+        assert_eq!(render(&plain),  vec!["$2000  assets:checking", "$-2000  income:payroll"]);
+        assert_eq!(render(&styled), vec!["$2,000.00  assets:checking", "$-2,000.00  income:payroll"]);
+        assert_eq!(render(&plain),  vec!["$2000  assets:checking", "$-2000  income:payroll"]);
+    }
+
+    #[test]
+    fn test_balance_report_json() {
+        let options = BalanceOptions { query: "expenses:tips".parse().unwrap(), flat: true, ..Default::default() };
+        let report  = balance_report(&sample_journal(), Some(MonthYear::new(3, 2023)), &options).unwrap();
+        assert_eq!(to_json(&report[..]),
+r#"[{"account":"expenses:tips","amount":[{"quantity":0.50,"commodity":"$","decimals":2}]}]
+"#);
+
+        let options = BalanceOptions { row_total: true, ..options };
+        let report  = balance_periodic_report(&sample_journal(), &options, Interval::Monthly).unwrap();
+        assert_eq!(to_json(&report),
+r#"{"periods":["2023-03"],"rows":[{"account":"expenses:tips","amounts":[[{"quantity":0.50,"commodity":"$","decimals":2}]],"total":[{"quantity":0.50,"commodity":"$","decimals":2}]}],"totals":{"account":"total","amounts":[[{"quantity":0.50,"commodity":"$","decimals":2}]],"total":[{"quantity":0.50,"commodity":"$","decimals":2}]}}
+"#);
+    }
+
+    #[test]
+    fn test_sparkline() {
+        let amounts = |values: &[f64]| values.iter()
+                                             .map(|v| Some(MixedAmount::from(Amount::from("$".to_string(), *v))))
+                                             .collect::<Vec<_>>();
+
+        assert_eq!(sparkline(&amounts(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0])), "▁▂▃▄▅▆▇█");
+        assert_eq!(sparkline(&amounts(&[-5.0, 5.0])), "▁█");
+        assert_eq!(sparkline(&amounts(&[3.0, 3.0])), "▁▁");
+
+        let mut mixed = MixedAmount::from(Amount::from("$".to_string(), 1.0));
+        mixed.add_amount(&Amount::from("kWh".to_string(), 308.0));
+        assert_eq!(sparkline(&[Some(mixed), None]), "");
+    }
+
+    #[test]
+    fn test_is_same_or_subaccount() {
+        assert!(is_same_or_subaccount(&"expenses:food".into(), "expenses:food"));
+        assert!(is_same_or_subaccount(&"expenses:food:subway".into(), "expenses:food"));
+        assert!(!is_same_or_subaccount(&"expenses:foodstuff".into(), "expenses:food"));
+        assert!(!is_same_or_subaccount(&"expenses".into(), "expenses:food"));
+    }
+
+    #[test]
+    fn test_compare_accounts() {
+        let mut accounts = vec!["assets:cash", "assets-x", "assets"];
+        accounts.sort_by(|a, b| compare_accounts(a, b));
+        assert_eq!(accounts, vec!["assets", "assets:cash", "assets-x"]);
+    }
 }
@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::journal::Journal;
+use crate::reports::balance::{BalanceLine, BalanceOptions, fold_balances, to_lines};
+use crate::types::commoditystyle::{Styled, WithStyles};
+use crate::types::{Account, amount::AmountError, mixedamount::MixedAmount, monthyear::MonthYear};
+
+
+/* Balance sheet */
+
+// what we own and what we owe at the end of a month, and the difference between the two.
+// unlike the balance report, which shows the changes within a month, these are the
+// cumulative balances of every transaction up to and including that month
+pub struct BalanceSheet {
+    pub month      : MonthYear,
+    pub assets     : Vec<BalanceLine>,
+    pub liabilities: Vec<BalanceLine>,
+    pub net_worth  : Option<MixedAmount>,
+}
+
+#[derive(PartialEq)]
+enum AccountClass {
+    Asset,
+    Liability,
+    Other,
+}
+
+// accounts are classified by their top-level name. katana journals have always kept
+// credit cards under credit: so those count as liabilities along with the usual names
+fn classify(account: &str) -> AccountClass {
+    let top = account.split(':').next().unwrap_or_default();
+
+    match top {
+        "asset" | "assets"                              => AccountClass::Asset,
+        "liability" | "liabilities" | "credit" | "debt" => AccountClass::Liability,
+        _                                               => AccountClass::Other,
+    }
+}
+
+pub fn balance_sheet(journal: &Journal,
+                     month  : MonthYear,
+                     options: &BalanceOptions) -> Result<BalanceSheet, AmountError>
+{
+    // the historical balance of every asset/liability account as of the end of the month
+    let mut balances: HashMap<&Account, MixedAmount> = HashMap::new();
+    for entry in journal.transactions
+                        .iter()
+                        .take_while(|t| MonthYear::from_naivedate(t.date) <= month)
+                        .flat_map(|t| &t.entries)
+                        .filter(|e| classify(&e.account) != AccountClass::Other)
+    {
+        balances.entry(&entry.account)
+                .or_default()
+                .checked_add_amount(&entry.amount)?;
+    }
+
+    let of_class = |class: AccountClass| {
+        let selected = balances.iter()
+                               .filter(|(account, _)| classify(account) == class)
+                               .map(|(account, amount)| (*account, amount));
+        Ok::<_, AmountError>(to_lines(fold_balances(selected, options)?, options))
+    };
+
+    let mut net_worth: Option<MixedAmount> = None;
+    for amount in balances.values() {
+        match &mut net_worth {
+            Some(total) => total.checked_add(amount)?,
+            None        => net_worth = Some(amount.clone()),
+        }
+    }
+
+    Ok(BalanceSheet {
+        month,
+        assets     : of_class(AccountClass::Asset)?,
+        liabilities: of_class(AccountClass::Liability)?,
+        net_worth,
+    })
+}
+
+// Balance sheet at the end of 2023-03
+//
+// Assets:
+//        $3034.51  assets
+//          $49.50    cash
+//
+// Liabilities:
+//         $-25.90  credit
+//         $-25.90    visa
+//
+// Net worth:
+//        $3008.61
+
+impl WithStyles for BalanceSheet {}
+
+impl fmt::Display for Styled<'_, BalanceSheet> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sheet = self.value;
+        writeln!(f, "Balance sheet at the end of {}", sheet.month)?;
+
+        writeln!(f)?;
+        writeln!(f, "Assets:")?;
+        for line in &sheet.assets {
+            writeln!(f, "{}", line.styled(self.styles))?;
+        }
+
+        writeln!(f)?;
+        writeln!(f, "Liabilities:")?;
+        for line in &sheet.liabilities {
+            writeln!(f, "{}", line.styled(self.styles))?;
+        }
+
+        writeln!(f)?;
+        writeln!(f, "Net worth:")?;
+        let net_worth = sheet.net_worth
+                             .as_ref()
+                             .map(|a| a.styled(self.styles).to_string())
+                             .unwrap_or_else(|| "0".to_string());
+        writeln!(f, "{:>15}", net_worth)
+    }
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use crate::journal::Journal;
+    use crate::reports::balance::BalanceOptions;
+    use crate::types::monthyear::MonthYear;
+    use crate::types::commoditystyle::WithStyles;
+    use super::{AccountClass, balance_sheet, classify};
+
+    fn sample_journal() -> Journal {
+        let journal =
+r#"
+2023/03/01 opening balances
+    assets:cash                  $50
+    assets:savings               $1000.00
+    equity:opening-balances
+
+2023/03/03 AWS
+    expenses:internet:aws           $12.35
+    credit:visa
+
+2023/04/07 Sandwich
+    expenses:food                $10
+    assets:cash
+"#;
+        Journal::from_lines(journal.lines()).unwrap()
+    }
+
+    #[test]
+    fn test_classify() {
+        assert!(classify("assets:cash") == AccountClass::Asset);
+        assert!(classify("liabilities:mortgage") == AccountClass::Liability);
+        assert!(classify("credit:visa") == AccountClass::Liability);
+        assert!(classify("expenses:assets") == AccountClass::Other);
+    }
+
+    #[test]
+    fn test_balance_sheet_is_cumulative() {
+        let journal = sample_journal();
+        let options = BalanceOptions { flat: true, ..Default::default() };
+
+        let sheet = balance_sheet(&journal, MonthYear::new(3, 2023), &options).unwrap();
+        assert_eq!(sheet.assets.len(), 2);
+        assert_eq!(sheet.assets[0].amount.as_ref().unwrap().to_string(), "$50.00");
+        assert_eq!(sheet.liabilities[0].amount.as_ref().unwrap().to_string(), "$-12.35");
+        assert_eq!(sheet.net_worth.unwrap().to_string(), "$1037.65");
+
+        let sheet = balance_sheet(&journal, MonthYear::new(4, 2023), &options).unwrap();
+        assert_eq!(sheet.assets[0].amount.as_ref().unwrap().to_string(), "$40.00");
+        assert_eq!(sheet.net_worth.unwrap().to_string(), "$1027.65");
+    }
+
+    #[test]
+    fn test_balance_sheet_display() {
+        let journal = sample_journal();
+        let sheet = balance_sheet(&journal, MonthYear::new(3, 2023), &BalanceOptions::default()).unwrap();
+
+        assert_eq!(sheet.styled(&journal.commodities).to_string(),
+"Balance sheet at the end of 2023-03
+
+Assets:
+       $1050.00  assets
+         $50.00    cash
+       $1000.00    savings
+
+Liabilities:
+        $-12.35  credit
+        $-12.35    visa
+
+Net worth:
+       $1037.65
+");
+    }
+}
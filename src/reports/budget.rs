@@ -0,0 +1,252 @@
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::budget::{Budget, spending};
+use crate::common::{display_width, pad_left, pad_right};
+use crate::journal::{Journal, JournalSummary};
+use crate::reports::balance::{BalanceLine, BalanceOptions, balance_changes, compare_accounts, fold_balances, row_total, to_lines};
+use crate::types::commoditystyle::{Styled, WithStyles};
+use crate::types::{Account, amount::{Amount, AmountError}, mixedamount::MixedAmount, monthyear::MonthYear};
+
+
+/* Budget report */
+
+// actual balance changes compared to the budget for each month, with a row for each
+// account in the budget. a month's spending is the total change in the account and its
+// subaccounts
+
+pub struct BudgetReport {
+    pub months: Vec<MonthYear>,
+    pub rows  : Vec<BudgetRow>,
+}
+
+pub struct BudgetRow {
+    pub account: Account,
+    pub budget : Amount,
+    pub actuals: Vec<Option<Amount>>, // one per month, None for no activity
+}
+
+pub fn budget_report(journal: &Journal,
+                     options: &BalanceOptions) -> Result<BudgetReport, AmountError>
+{
+    let budget = Budget::from_journal(journal, &options.query)?;
+    let months = JournalSummary::from(journal).months();
+
+    let mut rows: Vec<BudgetRow> =
+        budget.targets()
+              .map(|(account, target)| Ok(BudgetRow {
+                  account: account.clone(),
+                  budget : target.clone(),
+                  actuals: spending(journal, account, &target.units, &options.query, &months)?,
+              }))
+              .collect::<Result<_, AmountError>>()?;
+
+    rows.sort_by(|a, b| compare_accounts(&a.account, &b.account));
+
+    Ok(BudgetReport { months, rows })
+}
+
+// the spending in accounts that aren't covered by any monthly budget, so categories that
+// were forgotten when drawing up the budget stand out. spending is the postings matching the
+// query, or anything under expenses if there isn't one. with no budget nothing is unbudgeted
+pub fn unbudgeted_report(journal: &Journal,
+                         options: &BalanceOptions) -> Result<Vec<BalanceLine>, AmountError>
+{
+    let budget = Budget::from_journal(journal, &options.query)?;
+    if budget.is_empty() {
+        return Ok(vec![])
+    }
+
+    let grid     = balance_changes(journal, &options.query)?;
+    let spending: BTreeSet<&Account> = if options.query.is_empty() {
+        journal.postings_for("^expenses(:|$)").unwrap().map(|posting| posting.account()).collect()
+    } else {
+        grid.rows().map(|(account, _)| account).collect()
+    };
+
+    // each account's spending over the whole journal
+    let unbudgeted: Vec<(&Account, MixedAmount)> =
+        spending.into_iter()
+                .filter(|account| !budget.covers(account))
+                .filter_map(|account| row_total(&grid, account))
+                .collect::<Result<_, _>>()?;
+
+    let unbudgeted = unbudgeted.iter().map(|(account, amount)| (*account, amount));
+    Ok(to_lines(fold_balances(unbudgeted, options)?, options))
+}
+
+impl BudgetRow {
+    // how much of the budget the actual amount used up, as a percentage
+    pub fn percent_used(&self, month: usize) -> f64 {
+        let budget = self.budget.to_f64();
+        let actual = self.actuals[month].as_ref().map_or(0.0, |a| a.to_f64());
+
+        if budget == 0.0 { 0.0 } else { actual / budget * 100.0 }
+    }
+}
+
+//                                   2023-03                      2023-04
+// expenses:food      $82.99 [21% of $400.00]    $412.50 [103% of $400.00]
+
+impl WithStyles for BudgetReport {}
+
+impl fmt::Display for Styled<'_, BudgetReport> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let report = self.value;
+        let cells: Vec<Vec<String>> =
+            report.rows
+                .iter()
+                .map(|row| {
+                    (0..report.months.len())
+                        .map(|month| {
+                            let actual = row.actuals[month]
+                                            .as_ref()
+                                            .map_or("0".to_string(), |a| a.styled(self.styles).to_string());
+                            format!("{} [{:.0}% of {}]", actual, row.percent_used(month), row.budget.styled(self.styles))
+                        })
+                        .collect()
+                })
+                .collect();
+
+        let width = report.rows.iter().map(|r| display_width(&r.account)).max().unwrap_or(0);
+        let cell_width = cells.iter().flatten().map(|c| display_width(c)).max().unwrap_or(0).max(7);
+
+        write!(f, "{:width$}", "")?;
+        for month in &report.months {
+            write!(f, "  {}", pad_left(&month.to_string(), cell_width))?;
+        }
+        writeln!(f)?;
+
+        for (row, cells) in report.rows.iter().zip(cells) {
+            write!(f, "{}", pad_right(&row.account, width))?;
+            for cell in cells {
+                write!(f, "  {}", pad_left(&cell, cell_width))?;
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use crate::journal::Journal;
+    use crate::reports::balance::BalanceOptions;
+    use crate::types::{amount::Amount, monthyear::MonthYear};
+    use crate::types::commoditystyle::{CommodityStyles, WithStyles};
+    use super::{BudgetReport, BudgetRow, budget_report, unbudgeted_report};
+
+    fn sample_journal() -> Journal {
+        let journal =
+r#"
+~ monthly
+    expenses:food        $400
+    expenses:rent       $1000
+    assets:checking
+
+~ yearly
+    expenses:gifts       $500
+    assets:checking
+
+2023/03/17 HelloFresh
+    expenses:food:hello-fresh            $82.99
+    assets:checking
+
+2023/04/01 Rent
+    expenses:rent                      $1000
+    assets:checking
+
+2023/04/17 HelloFresh
+    expenses:food:hello-fresh           $412.50
+    assets:checking
+"#;
+        Journal::from_lines(journal.lines()).unwrap()
+    }
+
+    #[test]
+    fn test_budget_report() {
+        let options = BalanceOptions { query: "expenses".parse().unwrap(), ..Default::default() };
+        let report  = budget_report(&sample_journal(), &options).unwrap();
+
+        assert_eq!(report.months.len(), 2);
+        assert_eq!(report.rows.len(), 2);
+
+        let food = &report.rows[0];
+        assert_eq!(food.account, "expenses:food");
+        assert_eq!(food.budget.to_string(), "$400.00");
+        assert_eq!(food.actuals[0].as_ref().unwrap().to_string(), "$82.99");
+        assert_eq!(food.percent_used(1).round(), 103.0);
+
+        let rent = &report.rows[1];
+        assert_eq!(rent.actuals[0], None);
+        assert_eq!(rent.percent_used(0), 0.0);
+    }
+
+    #[test]
+    fn test_unbudgeted_report() {
+        let mut journal = sample_journal();
+        let extra =
+r#"
+2023/04/20 Cineplex
+    expenses:fun:movies                  $24.00
+    assets:checking
+
+2023/04/21 Bookstore
+    expenses:fun:books                   $15.50
+    assets:checking
+"#;
+        journal.transactions.extend(Journal::from_lines(extra.lines()).unwrap().transactions);
+
+        let lines: Vec<String> = unbudgeted_report(&journal, &BalanceOptions::default()).unwrap()
+                                     .iter()
+                                     .map(|line| line.styled(&journal.commodities).to_string().trim().to_string())
+                                     .collect();
+
+        assert_eq!(lines, vec![
+            "$39.50  expenses",
+            "$39.50    fun",
+            "$15.50      books",
+            "$24.00      movies",
+        ]);
+    }
+
+    #[test]
+    fn test_unbudgeted_report_no_budget() {
+        let journal = Journal::from_lines("2023/04/20 Cineplex\n    expenses:fun  $24\n    assets:checking".lines()).unwrap();
+        assert!(unbudgeted_report(&journal, &BalanceOptions::default()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_budget_report_display() {
+        let options = BalanceOptions { query: "expenses".parse().unwrap(), ..Default::default() };
+        let journal = sample_journal();
+        let report  = budget_report(&journal, &options).unwrap();
+
+        assert_eq!(report.styled(&journal.commodities).to_string(),
+"                                   2023-03                      2023-04
+expenses:food      $82.99 [21% of $400.00]    $412.50 [103% of $400.00]
+expenses:rent           0 [0% of $1000.00]  $1000.00 [100% of $1000.00]
+");
+    }
+
+    #[test]
+    fn test_budget_report_display_width() {
+        let report = BudgetReport {
+            months: vec![MonthYear::new(3, 2023)],
+            rows  : vec![BudgetRow {
+                account: "expenses:café".into(),
+                budget : Amount::from("円", 4000.0),
+                actuals: vec![Some(Amount::from("円", 850.0))],
+            }],
+        };
+
+        assert_eq!(report.styled(&CommodityStyles::default()).to_string(),
+"                                       2023-03
+expenses:café  850.000 円 [21% of 4000.000 円]
+");
+    }
+}
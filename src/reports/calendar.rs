@@ -0,0 +1,165 @@
+use chrono::{Datelike, NaiveDate};
+use std::fmt;
+
+use crate::common::case_insensitive;
+use crate::iterators::transactionsbyperiod::transactions_by_period;
+use crate::journal::Journal;
+use crate::query::Query;
+use crate::types::interval::Interval;
+
+
+/* Spending calendar */
+
+pub struct CalendarOptions<'a> {
+    pub query    : &'a Query, // the postings to total, the ones to expenses by default
+    pub commodity: &'a str,   // only amounts in this commodity are totalled
+}
+
+// the total spending on each day of each month of the journal, laid out like a calendar
+pub struct Calendar {
+    pub months: Vec<CalendarMonth>,
+    pub max   : f64, // the largest daily total in any month, which the shading is relative to
+}
+
+pub struct CalendarMonth {
+    pub start: NaiveDate,
+    pub days : Vec<f64>, // the total for each day of the month, the 1st at index 0
+}
+
+// The daily totals of the postings matching the query in the given commodity, one
+// month after another. Days are shaded by how their total compares to the heaviest day's
+pub fn calendar_report(journal: &Journal,
+                       options: &CalendarOptions) -> Calendar
+{
+    let expenses = Query::Acct(case_insensitive("^expenses").unwrap());
+    let query    = if options.query.is_empty() { &expenses } else { options.query };
+
+    let mut months = vec![];
+    for (month, transactions) in transactions_by_period(journal, Interval::Monthly) {
+        let mut days = vec![0.0; month.days() as usize];
+
+        for transaction in transactions {
+            days[transaction.date.day0() as usize] +=
+                query.postings([transaction])
+                     .filter(|e| e.amount.units == options.commodity)
+                     .map(|e| e.amount.to_f64())
+                     .sum::<f64>();
+        }
+
+        months.push(CalendarMonth { start: month.start, days });
+    }
+
+    let max = months.iter()
+                    .flat_map(|m| &m.days)
+                    .fold(0.0, |max: f64, day| max.max(*day));
+
+    Calendar { months, max }
+}
+
+// light to heavy, for the days with a total above zero
+const SHADES: [char; 4] = ['░', '▒', '▓', '█'];
+
+fn shade(total: f64, max: f64) -> char {
+    if total <= 0.0 || max <= 0.0 {
+        return ' '
+    }
+
+    let level = (total / max * SHADES.len() as f64).ceil() as usize;
+    SHADES[level.clamp(1, SHADES.len()) - 1]
+}
+
+// 2023-03
+//    Mon       Tue       Wed       Thu       Fri       Sat       Sun
+//                      1    60▓  2    10░  3         4         5
+//  6         7    14░  8         9   105█ 10        11    33▒ 12
+
+impl fmt::Display for Calendar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, month) in self.months.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            writeln!(f, "{}", month.start.format("%Y-%m"))?;
+
+            let names = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+            let header: Vec<String> = names.iter().map(|name| format!("{:^9}", name)).collect();
+            writeln!(f, "{}", header.join(" ").trim_end())?;
+
+            // blank cells for the days of the first week before the 1st
+            let mut cells: Vec<String> = vec![" ".repeat(9); month.start.weekday().num_days_from_monday() as usize];
+
+            for (day, total) in month.days.iter().enumerate() {
+                let amount = if *total == 0.0 { String::new() } else { format!("{:.0}", total) };
+                cells.push(format!("{:>2} {:>5}{}", day + 1, amount, shade(*total, self.max)));
+
+                if cells.len() == 7 {
+                    writeln!(f, "{}", cells.join(" ").trim_end())?;
+                    cells.clear();
+                }
+            }
+            if !cells.is_empty() {
+                writeln!(f, "{}", cells.join(" ").trim_end())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use crate::journal::Journal;
+    use crate::query::Query;
+    use super::{CalendarOptions, calendar_report, shade};
+
+    #[test]
+    fn test_calendar_report() {
+        let journal =
+r#"
+2023/02/01 Loblaws
+    expenses:food:groceries            $60
+    assets:cash
+
+2023/02/01 Tim Hortons
+    expenses:food:restaurant         $4.50
+    assets:cash
+
+2023/02/14 Flowers
+    expenses:gifts                    $130
+    assets:cash
+
+2023/02/20 Hydro meter
+    expenses:utilities              308 kWh
+    equity:meter
+"#;
+        let journal = Journal::from_lines(journal.lines()).unwrap();
+        let options = CalendarOptions { query: &Query::default(), commodity: "$" };
+        let report  = calendar_report(&journal, &options);
+
+        assert_eq!(report.months.len(), 1);
+        assert_eq!(report.months[0].days.len(), 28);
+        assert_eq!(report.max, 130.0);
+
+        assert_eq!(report.to_string(),
+"2023-02
+   Mon       Tue       Wed       Thu       Fri       Sat       Sun
+                     1    64▒  2         3         4         5
+ 6         7         8         9        10        11        12
+13        14   130█ 15        16        17        18        19
+20        21        22        23        24        25        26
+27        28
+");
+    }
+
+    #[test]
+    fn test_shade() {
+        assert_eq!(shade(0.0, 100.0), ' ');
+        assert_eq!(shade(-5.0, 100.0), ' ');
+        assert_eq!(shade(1.0, 100.0), '░');
+        assert_eq!(shade(50.0, 100.0), '▒');
+        assert_eq!(shade(51.0, 100.0), '▓');
+        assert_eq!(shade(100.0, 100.0), '█');
+    }
+}
@@ -0,0 +1,198 @@
+use std::fmt;
+
+use crate::journal::Journal;
+use crate::reports::balance::{BalanceOptions, balance_periodic_report};
+use crate::types::{amount::{Amount, AmountError}, interval::Interval};
+
+
+/* Charts */
+
+// the balances of some accounts over time, drawn as an SVG chart. changes in each period
+// are bars side by side, while historical balances are lines
+pub struct Chart {
+    pub periods: Vec<String>,
+    pub series : Vec<Series>,
+    pub kind   : ChartKind,
+}
+
+// one account's amount in each period
+pub struct Series {
+    pub name  : String,
+    pub values: Vec<f64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChartKind {
+    Bar,
+    Line,
+}
+
+// The periodic balances of the accounts the options select, listed flat, one series per
+// account. Amounts in more than one commodity can't be put on one axis, so those periods
+// are charted as zero
+pub fn chart_report(journal : &Journal,
+                    options : &BalanceOptions,
+                    interval: Interval) -> Result<Chart, AmountError>
+{
+    let options = BalanceOptions { flat: true, row_total: false, average: false, ..options.clone() };
+    let report  = balance_periodic_report(journal, &options, interval)?;
+
+    let series = report.rows
+                       .into_iter()
+                       .map(|row| Series {
+                           name  : row.account.to_string(),
+                           values: row.amounts
+                                      .iter()
+                                      .map(|amount| amount.as_ref()
+                                                          .and_then(|a| a.single())
+                                                          .map_or(0.0, Amount::to_f64))
+                                      .collect(),
+                       })
+                       .collect();
+
+    let kind = if options.historical { ChartKind::Line } else { ChartKind::Bar };
+
+    Ok(Chart { periods: report.periods, series, kind })
+}
+
+// the size of the whole image and the space around the plot for the labels
+const WIDTH : f64 = 800.0;
+const HEIGHT: f64 = 400.0;
+const LEFT  : f64 = 80.0;
+const RIGHT : f64 = 200.0;
+const TOP   : f64 = 20.0;
+const BOTTOM: f64 = 40.0;
+
+const COLOURS: [&str; 8] = ["#4e79a7", "#f28e2b", "#e15759", "#76b7b2", "#59a14f", "#edc948", "#b07aa1", "#9c755f"];
+
+impl Chart {
+    // the lowest and highest values on the y axis, which always includes zero
+    fn range(&self) -> (f64, f64) {
+        let values = self.series.iter().flat_map(|s| &s.values);
+        let min = values.clone().fold(0.0, |min: f64, v| min.min(*v));
+        let max = values.fold(0.0, |max: f64, v| max.max(*v));
+
+        if min == max { (min, min + 1.0) } else { (min, max) }
+    }
+}
+
+// <svg xmlns="http://www.w3.org/2000/svg" width="800" height="400" ...>
+//   <line .../>                       the zero line and y axis ticks
+//   <text ...>2023-03</text>          period labels along the bottom
+//   <rect .../> or <polyline .../>    the bars or lines for each account
+//   <text ...>expenses:food</text>    a legend to the right
+// </svg>
+
+impl fmt::Display for Chart {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (min, max) = self.range();
+        let plot_width  = WIDTH - LEFT - RIGHT;
+        let plot_height = HEIGHT - TOP - BOTTOM;
+        let y = |value: f64| TOP + (max - value) / (max - min) * plot_height;
+        let slot = plot_width / self.periods.len().max(1) as f64;
+
+        writeln!(f, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" font-family="sans-serif" font-size="12">"#, WIDTH, HEIGHT)?;
+        writeln!(f, r#"<rect width="{}" height="{}" fill="white"/>"#, WIDTH, HEIGHT)?;
+
+        // five ticks up the y axis, labelled with their values
+        for i in 0..=4 {
+            let value = min + (max - min) * i as f64 / 4.0;
+            writeln!(f, r##"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="#ddd"/>"##, LEFT, y(value), LEFT + plot_width, y(value))?;
+            writeln!(f, r#"<text x="{:.1}" y="{:.1}" text-anchor="end">{:.2}</text>"#, LEFT - 6.0, y(value) + 4.0, value)?;
+        }
+        writeln!(f, r#"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="black"/>"#, LEFT, y(0.0), LEFT + plot_width, y(0.0))?;
+
+        for (i, period) in self.periods.iter().enumerate() {
+            let x = LEFT + slot * (i as f64 + 0.5);
+            writeln!(f, r#"<text x="{:.1}" y="{:.1}" text-anchor="middle">{}</text>"#, x, HEIGHT - BOTTOM + 18.0, escape(period))?;
+        }
+
+        let bar = slot * 0.8 / self.series.len().max(1) as f64;
+        for (s, series) in self.series.iter().enumerate() {
+            let colour = COLOURS[s % COLOURS.len()];
+
+            match self.kind {
+                ChartKind::Bar => for (i, value) in series.values.iter().enumerate() {
+                    let x = LEFT + slot * i as f64 + slot * 0.1 + bar * s as f64;
+                    let (top, bottom) = (y(value.max(0.0)), y(value.min(0.0)));
+                    writeln!(f, r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="{}"/>"#, x, top, bar, bottom - top, colour)?;
+                },
+                ChartKind::Line => {
+                    let points: Vec<String> = series.values
+                                                    .iter()
+                                                    .enumerate()
+                                                    .map(|(i, value)| format!("{:.1},{:.1}", LEFT + slot * (i as f64 + 0.5), y(*value)))
+                                                    .collect();
+                    writeln!(f, r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="2"/>"#, points.join(" "), colour)?;
+                },
+            }
+
+            let legend = TOP + 10.0 + 18.0 * s as f64;
+            writeln!(f, r#"<rect x="{:.1}" y="{:.1}" width="10" height="10" fill="{}"/>"#, WIDTH - RIGHT + 20.0, legend - 9.0, colour)?;
+            writeln!(f, r#"<text x="{:.1}" y="{:.1}">{}</text>"#, WIDTH - RIGHT + 36.0, legend, escape(&series.name))?;
+        }
+
+        writeln!(f, "</svg>")
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use crate::journal::Journal;
+    use crate::reports::balance::BalanceOptions;
+    use crate::types::interval::Interval;
+    use super::{ChartKind, chart_report};
+
+    fn sample_journal() -> Journal {
+        let journal =
+r#"
+2023/01/05 Loblaws
+    expenses:food                       $60
+    assets:cash
+
+2023/03/07 Hydro
+    expenses:utilities                  $90
+    assets:cash
+"#;
+        Journal::from_lines(journal.lines()).unwrap()
+    }
+
+    #[test]
+    fn test_chart_report() {
+        let options = BalanceOptions { query: "expenses".parse().unwrap(), ..Default::default() };
+        let chart   = chart_report(&sample_journal(), &options, Interval::Monthly).unwrap();
+
+        assert_eq!(chart.kind, ChartKind::Bar);
+        assert_eq!(chart.periods, vec!["2023-01", "2023-02", "2023-03"]);
+        assert_eq!(chart.series.len(), 2);
+        assert_eq!(chart.series[0].name, "expenses:food");
+        assert_eq!(chart.series[0].values, vec![60.0, 0.0, 0.0]);
+        assert_eq!(chart.series[1].values, vec![0.0, 0.0, 90.0]);
+        assert_eq!(chart.range(), (0.0, 90.0));
+
+        let svg = chart.to_string();
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("<rect ").count(), 1 + 6 + 2); // background, bars, legend
+    }
+
+    #[test]
+    fn test_chart_report_historical() {
+        let options = BalanceOptions { query: "assets".parse().unwrap(), historical: true, ..Default::default() };
+        let chart   = chart_report(&sample_journal(), &options, Interval::Monthly).unwrap();
+
+        assert_eq!(chart.kind, ChartKind::Line);
+        assert_eq!(chart.series[0].values, vec![-60.0, -60.0, -150.0]);
+        assert_eq!(chart.range(), (-150.0, 0.0));
+        assert_eq!(chart.to_string().matches("<polyline ").count(), 1);
+    }
+}
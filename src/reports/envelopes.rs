@@ -0,0 +1,212 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::ops::Bound;
+
+use crate::budget::Budget;
+use crate::common::pad_left;
+use crate::journal::Journal;
+use crate::query::Query;
+use crate::reports::balance::compare_accounts;
+use crate::transaction::Transaction;
+use crate::types::commoditystyle::{Styled, WithStyles};
+use crate::types::{Account, amount::AmountError, mixedamount::MixedAmount, monthyear::MonthYear};
+
+
+/* Envelopes */
+
+// envelope budgeting sets money aside for each kind of spending as income comes in, and
+// the spending comes out of its envelope. the envelopes are virtual accounts under
+// envelopes:, one for each expense account in the budget, filled by a transaction each
+// month that `katana envelopes allocate` writes out for the journal:
+//
+// 2023/04/01 Envelope allocation
+//     [envelopes:food]                      $400.00
+//     [envelopes:rent]                     $1000.00
+//     [envelopes:available]               $-1400.00
+//
+// the postings are virtual ones in brackets that balance among themselves, so they don't
+// change any real account, and --real leaves them out of the other reports. an envelope's
+// remaining balance is what went into it less the spending in its expense account and
+// that account's subaccounts
+
+const ENVELOPES: &str = "envelopes";
+const AVAILABLE: &str = "envelopes:available";
+
+pub struct EnvelopeLine {
+    pub envelope : Account,
+    pub allocated: MixedAmount,
+    pub spent    : MixedAmount,
+    pub remaining: MixedAmount,
+}
+
+// Each envelope with what's been allocated to it, spent from it and is left in it, as of
+// the end of the journal. Envelopes come from the allocations in the journal and from the
+// budget, so a budgeted expense shows up before its first allocation
+pub fn envelope_report(journal: &Journal) -> Result<Vec<EnvelopeLine>, AmountError> {
+    // every account's total, from one pass over the postings
+    let mut totals: BTreeMap<Account, MixedAmount> = BTreeMap::new();
+    for entry in journal.earlier.iter().chain(&journal.transactions).flat_map(|t| &t.entries) {
+        totals.entry(entry.account.clone()).or_default().checked_add_amount(&entry.amount)?;
+    }
+
+    let mut envelopes: BTreeSet<Account> = Budget::from_journal(journal, &Query::default())?
+                                               .targets()
+                                               .filter_map(|(expense, _)| envelope_for(expense))
+                                               .collect();
+    envelopes.extend(totals.keys().filter(|account| account.is_descendant_of(ENVELOPES) && account.as_str() != AVAILABLE).cloned());
+
+    let mut lines: Vec<EnvelopeLine> =
+        envelopes.into_iter()
+                 .map(|envelope| {
+                     let allocated = totals.get(&envelope).cloned().unwrap_or_default();
+                     let spent     = total_within(&totals, &expense_for(&envelope))?;
+
+                     let mut remaining = allocated.clone();
+                     remaining.checked_add(&spent.clone().negate())?;
+                     Ok(EnvelopeLine { envelope, allocated, spent, remaining })
+                 })
+                 .collect::<Result<_, AmountError>>()?;
+
+    lines.sort_by(|a, b| compare_accounts(&a.envelope, &b.envelope));
+    Ok(lines)
+}
+
+// the total of the account and its subaccounts, which sort right after it along with
+// names like expenses:foodstuff that only start the same way
+fn total_within(totals: &BTreeMap<Account, MixedAmount>, account: &str) -> Result<MixedAmount, AmountError> {
+    let mut total = MixedAmount::default();
+    for (_, amount) in totals.range((Bound::Included(Account::from(account)), Bound::Unbounded))
+                             .take_while(|(name, _)| name.starts_with(account))
+                             .filter(|(name, _)| name.as_str() == account || name.is_descendant_of(account))
+    {
+        total.checked_add(amount)?;
+    }
+    Ok(total)
+}
+
+// the transaction putting each budgeted expense's monthly target into its envelope on the
+// first of the month, or None if there's nothing to allocate. envelopes that already have
+// an allocation that month are left as they are, so allocating twice adds nothing
+pub fn allocation(journal: &Journal, month: MonthYear) -> Result<Option<Transaction>, AmountError> {
+    let allocated_already = |envelope: &Account| {
+        journal.transactions
+               .iter()
+               .filter(|t| MonthYear::from_naivedate(t.date) == month)
+               .any(|t| t.entries.iter().any(|e| &e.account == envelope))
+    };
+
+    let mut targets: Vec<(Account, _)> =
+        Budget::from_journal(journal, &Query::default())?
+            .targets()
+            .filter(|(_, target)| target.decimal().is_sign_positive() && !target.is_zero())
+            .filter_map(|(expense, target)| Some((envelope_for(expense)?, target.clone())))
+            .filter(|(envelope, _)| !allocated_already(envelope))
+            .collect();
+    if targets.is_empty() {
+        return Ok(None)
+    }
+    targets.sort_by(|(a, _), (b, _)| compare_accounts(a, b));
+
+    let mut total = MixedAmount::default();
+    let mut builder = Transaction::builder().date(month.first_day())
+                                            .description("Envelope allocation");
+    for (envelope, target) in targets {
+        total.checked_add_amount(&target)?;
+        builder = builder.balanced_virtual_posting(envelope, target);
+    }
+    for amount in total.amounts() {
+        builder = builder.balanced_virtual_posting(AVAILABLE, -amount.clone());
+    }
+
+    Ok(Some(builder.build().expect("An allocation balances against envelopes:available")))
+}
+
+// the envelope for spending in an expense account, envelopes:food for expenses:food
+fn envelope_for(expense: &Account) -> Option<Account> {
+    let name = expense.strip_prefix("expenses:")?;
+    Some(Account::from(format!("{}:{}", ENVELOPES, name).as_str()))
+}
+
+// the expense account an envelope is spent from, expenses:food for envelopes:food
+fn expense_for(envelope: &Account) -> String {
+    format!("expenses{}", &envelope[ENVELOPES.len()..])
+}
+
+//         $800.00          $495.49          $304.51  food
+
+impl WithStyles for EnvelopeLine {}
+
+impl fmt::Display for Styled<'_, EnvelopeLine> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let line = self.value;
+        let name = &line.envelope[ENVELOPES.len() + 1..];
+        let cell = |amount: &MixedAmount| pad_left(&amount.styled(self.styles).to_string(), 15);
+        write!(f, "{}  {}  {}  {}", cell(&line.allocated), cell(&line.spent), cell(&line.remaining), name)
+    }
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use crate::journal::Journal;
+    use crate::reports::print::Printed;
+    use crate::types::monthyear::MonthYear;
+    use crate::types::commoditystyle::WithStyles;
+    use super::{allocation, envelope_report};
+
+    const JOURNAL: &str = r#"
+~ monthly  budget
+    expenses:food        $400
+    expenses:rent       $1000
+    assets:checking
+
+2023/03/01 Payroll
+    assets:checking                    $2400
+    income:payroll
+
+2023/03/01 Envelope allocation
+    [envelopes:food]                    $400
+    [envelopes:rent]                   $1000
+    [envelopes:available]
+
+2023/03/17 HelloFresh
+    expenses:food:hello-fresh            $82.99
+    assets:checking
+
+2023/04/01 Rent
+    expenses:rent                      $1000
+    assets:checking
+"#;
+
+    #[test]
+    fn test_envelope_report() {
+        let journal = Journal::from_lines(JOURNAL.lines()).unwrap();
+        let lines: Vec<String> = envelope_report(&journal).unwrap().iter().map(|line| line.styled(&journal.commodities).to_string()).collect();
+
+        assert_eq!(lines, vec![
+            "        $400.00           $82.99          $317.01  food",
+            "       $1000.00         $1000.00            $0.00  rent",
+        ]);
+    }
+
+    #[test]
+    fn test_allocation() {
+        let mut journal = Journal::from_lines(JOURNAL.lines()).unwrap();
+        assert!(allocation(&journal, MonthYear::new(3, 2023)).unwrap().is_none());
+
+        let april = allocation(&journal, MonthYear::new(4, 2023)).unwrap().unwrap();
+        assert_eq!(Printed(&april, &journal.commodities).to_string(),
+"2023/04/01 Envelope allocation
+    [envelopes:food]                         $400.00
+    [envelopes:rent]                        $1000.00
+    [envelopes:available]                  $-1400.00
+");
+
+        journal.transactions.push(april);
+        let lines: Vec<String> = envelope_report(&journal).unwrap().iter().map(|line| line.styled(&journal.commodities).to_string().trim().to_string()).collect();
+        assert_eq!(lines, vec!["$800.00           $82.99          $717.01  food",
+                               "$2000.00         $1000.00         $1000.00  rent"]);
+    }
+}
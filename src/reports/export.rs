@@ -0,0 +1,117 @@
+use crate::journal::Journal;
+
+
+/* SQL export */
+
+// the tables the export creates. quantities are exact decimals, so sums in the database
+// match the journal's to the cent
+const SCHEMA: &str =
+"CREATE TABLE transactions (
+    id          INTEGER PRIMARY KEY,
+    date        DATE NOT NULL,
+    description TEXT NOT NULL
+);
+
+CREATE TABLE postings (
+    id             INTEGER PRIMARY KEY,
+    transaction_id INTEGER NOT NULL REFERENCES transactions (id),
+    account        TEXT NOT NULL,
+    commodity      TEXT NOT NULL,
+    quantity       NUMERIC NOT NULL
+);
+
+CREATE TABLE tags (
+    transaction_id INTEGER NOT NULL REFERENCES transactions (id),
+    name           TEXT NOT NULL,
+    value          TEXT NOT NULL
+);
+";
+
+// The journal as SQL statements that create its tables and insert each transaction, its
+// postings and its tags, numbering the transactions from 1 in journal order. The inserts
+// are wrapped in a transaction so the load is all or nothing. Written in plain SQL that
+// both SQLite and Postgres accept
+pub fn export_sql(journal: &Journal) -> String {
+    let mut sql = String::from(SCHEMA);
+    sql += "\nBEGIN;\n";
+
+    let mut posting_id = 0;
+    for (transaction_id, transaction) in journal.transactions.iter().enumerate() {
+        let transaction_id = transaction_id + 1;
+
+        sql += &format!("INSERT INTO transactions (id, date, description) VALUES ({}, '{}', {});\n",
+                        transaction_id,
+                        transaction.date.format("%Y-%m-%d"),
+                        sql_string(transaction.description.trim()));
+
+        for entry in &transaction.entries {
+            posting_id += 1;
+            sql += &format!("INSERT INTO postings (id, transaction_id, account, commodity, quantity) VALUES ({}, {}, {}, {}, {});\n",
+                            posting_id,
+                            transaction_id,
+                            sql_string(&entry.account),
+                            sql_string(&entry.amount.units),
+                            entry.amount.quantity());
+        }
+
+        for (name, value) in &transaction.tags {
+            sql += &format!("INSERT INTO tags (transaction_id, name, value) VALUES ({}, {}, {});\n",
+                            transaction_id,
+                            sql_string(name),
+                            sql_string(value));
+        }
+    }
+
+    sql += "COMMIT;\n";
+    sql
+}
+
+// a quoted string literal, with its own quotes doubled up
+fn sql_string(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use crate::journal::Journal;
+    use super::{SCHEMA, export_sql, sql_string};
+
+    #[test]
+    fn test_export_sql() {
+        let journal =
+r#"
+2023/03/07 Tim's Sandwich  ; trip:ottawa
+    expenses:food                      $14.99
+    assets:cash
+
+2023/03/20 Hydro meter
+    expenses:utilities                308 kWh
+    equity:meter
+"#;
+        let journal = Journal::from_lines(journal.lines()).unwrap();
+        let sql = export_sql(&journal);
+
+        assert!(sql.starts_with(SCHEMA));
+        assert_eq!(&sql[SCHEMA.len()..],
+"
+BEGIN;
+INSERT INTO transactions (id, date, description) VALUES (1, '2023-03-07', 'Tim''s Sandwich');
+INSERT INTO postings (id, transaction_id, account, commodity, quantity) VALUES (1, 1, 'expenses:food', '$', 14.99);
+INSERT INTO postings (id, transaction_id, account, commodity, quantity) VALUES (2, 1, 'assets:cash', '$', -14.99);
+INSERT INTO tags (transaction_id, name, value) VALUES (1, 'trip', 'ottawa');
+INSERT INTO transactions (id, date, description) VALUES (2, '2023-03-20', 'Hydro meter');
+INSERT INTO postings (id, transaction_id, account, commodity, quantity) VALUES (3, 2, 'expenses:utilities', 'kWh', 308);
+INSERT INTO postings (id, transaction_id, account, commodity, quantity) VALUES (4, 2, 'equity:meter', 'kWh', -308);
+COMMIT;
+");
+    }
+
+    #[test]
+    fn test_sql_string() {
+        assert_eq!(sql_string("assets:cash"), "'assets:cash'");
+        assert_eq!(sql_string("Tim's"), "'Tim''s'");
+    }
+}
@@ -0,0 +1,205 @@
+use chrono::{Duration, Months, NaiveDate};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+use crate::common::{case_insensitive, today};
+use crate::journal::Journal;
+use crate::query::Query;
+use crate::types::commoditystyle::{Styled, WithStyles};
+use crate::types::{interval::Interval, mixedamount::MixedAmount, period::Period};
+
+
+/* Balance forecast */
+
+// how far back the spending is averaged, in days
+const HISTORY: i64 = 90;
+
+pub struct ForecastOptions<'a> {
+    pub query   : &'a Query,  // the accounts to project, the ones under assets by default
+    pub interval: Interval,   // a line for each of these
+    pub months  : usize,      // how far ahead to project, in months
+}
+
+// the balance projected forward from the end of the journal
+pub struct Forecast {
+    pub from      : NaiveDate,                       // the first day projected
+    pub balance   : MixedAmount,                     // the balance before it
+    pub daily     : MixedAmount,                     // the unscheduled change in an average day
+    pub lines     : Vec<ForecastLine>,
+    pub below_zero: Option<(NaiveDate, MixedAmount)>, // the first day the balance goes below zero
+}
+
+// the projected balance at the end of a period, and whether it's below zero on any day in it
+pub struct ForecastLine {
+    pub period    : Period,
+    pub balance   : MixedAmount,
+    pub below_zero: bool,
+}
+
+// Projects the balance of the accounts matching the query day by day from the day after
+// the last transaction. Each day adds what the periodic transactions post to the accounts
+// that day, plus the average daily change over the last 90 days of the journal from the
+// transactions that aren't one of the periodic ones, which are told apart by description,
+// or transfers from equity like opening balances. So the scheduled bills and paycheques
+// land on their days and everyday spending is spread evenly between them
+pub fn forecast_report(journal: &Journal,
+                       options: &ForecastOptions) -> Forecast
+{
+    let assets = Query::Acct(case_insensitive("^assets").unwrap());
+    let query  = if options.query.is_empty() { &assets } else { options.query };
+
+    let last  = journal.transactions.last().map(|t| t.date).unwrap_or_else(today);
+    let from  = last + Duration::days(1);
+    let until = from.checked_add_months(Months::new(options.months as u32)).unwrap_or(NaiveDate::MAX);
+
+    let mut balance = MixedAmount::default();
+    for entry in query.postings(journal.earlier.iter().chain(&journal.transactions)) {
+        balance.add_amount(&entry.amount);
+    }
+
+    // the recent unscheduled change, over the history or as much of it as the journal has
+    let scheduled: HashSet<String> = journal.periodic
+                                            .iter()
+                                            .map(|p| p.transaction.description.to_lowercase())
+                                            .collect();
+    let first   = journal.transactions.first().map(|t| t.date).unwrap_or(last);
+    let days    = HISTORY.min((last - first).num_days() + 1);
+    let recent  = journal.transactions
+                         .iter()
+                         .filter(|t| (last - t.date).num_days() < days)
+                         .filter(|t| !scheduled.contains(&t.description.to_lowercase()))
+                         .filter(|t| !t.entries.iter().any(|e| e.account.as_str() == "equity" || e.account.is_descendant_of("equity")));
+    let mut unscheduled = MixedAmount::default();
+    for entry in query.postings(recent) {
+        unscheduled.add_amount(&entry.amount);
+    }
+
+    let occurrences = journal.occurrences(from, until);
+    let mut on_day: BTreeMap<NaiveDate, MixedAmount> = BTreeMap::new();
+    for transaction in &occurrences {
+        for entry in query.postings([transaction]) {
+            on_day.entry(transaction.date).or_default().add_amount(&entry.amount);
+        }
+    }
+
+    let mut forecast = Forecast {
+        from,
+        balance   : balance.clone(),
+        daily     : unscheduled.divide(days),
+        lines     : vec![],
+        below_zero: None,
+    };
+
+    // the average is worked out from the start each day, so rounding doesn't add up
+    let mut scheduled_balance = balance;
+    let mut date = from;
+    while date < until {
+        if let Some(change) = on_day.get(&date) {
+            scheduled_balance.add(change);
+        }
+
+        let mut projected = scheduled_balance.clone();
+        let elapsed = (date - from).num_days() + 1;
+        for amount in unscheduled.amounts() {
+            projected.add_amount(&(amount.clone() * elapsed / days));
+        }
+
+        let period = Period::containing(options.interval, date);
+        let below_zero = projected.is_negative();
+        if below_zero && forecast.below_zero.is_none() {
+            forecast.below_zero = Some((date, projected.clone()));
+        }
+
+        match forecast.lines.last_mut() {
+            Some(line) if line.period == period => {
+                line.balance = projected;
+                line.below_zero |= below_zero;
+            },
+            _ => forecast.lines.push(ForecastLine { period, balance: projected, below_zero }),
+        }
+        date += Duration::days(1);
+    }
+
+    forecast
+}
+
+// 2023-W18          $312.40  below zero
+
+impl WithStyles for ForecastLine {}
+
+impl fmt::Display for Styled<'_, ForecastLine> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let line = self.value;
+        write!(f, "{:<10}  {:>15}", line.period.to_string(), line.balance.styled(self.styles).to_string())?;
+        if line.below_zero {
+            write!(f, "  below zero")?;
+        }
+        Ok(())
+    }
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use crate::journal::Journal;
+    use crate::query::Query;
+    use crate::types::interval::Interval;
+    use crate::types::commoditystyle::{CommodityStyles, WithStyles};
+    use super::{ForecastOptions, forecast_report};
+
+    const JOURNAL: &str = r#"
+~ monthly  rent
+    expenses:rent                      $1000
+    assets:checking
+
+~ monthly  payroll
+    assets:checking                    $1500
+    income:payroll
+
+2023/03/01 Opening balance
+    assets:checking                    $1000
+    equity:opening-balances
+
+2023/03/10 Groceries
+    expenses:food                       $300
+    assets:checking
+
+2023/03/15 Payroll
+    assets:checking                    $1500
+    income:payroll
+
+2023/03/30 Groceries
+    expenses:food                       $300
+    assets:checking
+"#;
+
+    #[test]
+    fn test_forecast_report() {
+        let journal  = Journal::from_lines(JOURNAL.lines()).unwrap();
+        let options  = ForecastOptions { query: &Query::default(), interval: Interval::Monthly, months: 2 };
+        let forecast = forecast_report(&journal, &options);
+
+        // the recorded payroll is one of the periodic transactions and the opening balance is
+        // from equity, so neither is averaged in as everyday spending
+        assert_eq!(forecast.from, NaiveDate::from_ymd_opt(2023, 3, 31).unwrap());
+        assert_eq!(forecast.balance.to_string(), "$1900.00");
+        assert_eq!(forecast.daily.to_string(), "$-20.00");
+
+        let lines: Vec<String> = forecast.lines.iter().map(|line| line.styled(&CommodityStyles::default()).to_string()).collect();
+        assert_eq!(lines, vec!["2023-03            $1880.00",
+                               "2023-04            $1780.00",
+                               "2023-05            $1680.00"]);
+        assert!(forecast.below_zero.is_none());
+
+        // four times the groceries and twice the rent run it dry when April's rent comes out
+        let journal  = Journal::from_lines(JOURNAL.replace("$300", "$1200").replace("$1000\n    assets", "$2000\n    assets").lines()).unwrap();
+        let forecast = forecast_report(&journal, &options);
+        let (date, balance) = forecast.below_zero.unwrap();
+        assert_eq!(date, NaiveDate::from_ymd_opt(2023, 4, 1).unwrap());
+        assert_eq!(balance.to_string(), "$-560.00");
+        assert!(forecast.lines[1].below_zero);
+    }
+}
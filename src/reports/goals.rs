@@ -0,0 +1,146 @@
+use chrono::NaiveDate;
+use std::fmt;
+
+use crate::common::{pad_left, pad_right};
+use crate::goal::Goal;
+use crate::journal::Journal;
+use crate::reports::balance::is_same_or_subaccount;
+use crate::types::commoditystyle::{Styled, WithStyles};
+use crate::types::{amount::Amount, monthyear::MonthYear};
+
+
+/* Savings goals */
+
+// how many of the months before this one the recent contributions are averaged over
+const RECENT: i64 = 3;
+
+pub struct GoalLine {
+    pub goal     : Goal,
+    pub saved    : Amount,            // in the goal's account and its subaccounts, in its commodity
+    pub needed   : Option<Amount>,    // each month from this one on to reach it in time, None once it's reached
+    pub recent   : Amount,            // the average monthly contribution in the last three months
+    pub projected: Option<MonthYear>, // the month it's reached at the recent rate, None if it won't be
+}
+
+// The progress toward each goal in the journal as of today, with what it needs each month
+// from now to be reached by its month and when it'll be reached if the contributions keep
+// up the way they have over the last three full months. A goal past its month needs all of
+// what's left this month. Goals are in the order they were written
+pub fn goals_report(journal: &Journal, today: NaiveDate) -> Vec<GoalLine> {
+    let this_month = MonthYear::from_naivedate(today);
+    let recent     = MonthYear::range(this_month.add_months(-RECENT)..=this_month.prev_month()).collect::<Vec<_>>();
+
+    journal.goals
+           .iter()
+           .map(|goal| {
+               let zero = Amount::from(goal.target.units, 0.0);
+               let mut saved       = zero.clone();
+               let mut contributed = zero;
+
+               let entries = journal.earlier
+                                    .iter()
+                                    .chain(&journal.transactions)
+                                    .flat_map(|t| t.entries.iter().map(move |e| (t.date, e)))
+                                    .filter(|(_, e)| e.amount.units == goal.target.units)
+                                    .filter(|(_, e)| is_same_or_subaccount(&e.account, &goal.account));
+               for (date, entry) in entries {
+                   saved += &entry.amount;
+                   if recent.contains(&MonthYear::from_naivedate(date)) {
+                       contributed += &entry.amount;
+                   }
+               }
+
+               let left      = goal.target.clone() - saved.clone();
+               let recent    = contributed / RECENT;
+               let reached   = !left.decimal().is_sign_positive() || left.is_zero();
+               let months    = this_month.months_between(&goal.by) + 1;
+               let needed    = (!reached).then(|| left.clone() / months.max(1));
+               let projected = if reached {
+                   Some(this_month)
+               } else if recent.decimal().is_sign_positive() && !recent.is_zero() {
+                   let months = (left.to_f64() / recent.to_f64()).ceil() as i64;
+                   Some(this_month.add_months(months - 1))
+               } else {
+                   None
+               };
+
+               GoalLine { goal: goal.clone(), saved, needed, recent, projected }
+           })
+           .collect()
+}
+
+//        $1000.00         $3000.00       33%          $333.33          $300.00  2024-07    assets:vacation-fund by 2024-06
+
+impl WithStyles for GoalLine {}
+
+impl fmt::Display for Styled<'_, GoalLine> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let line = self.value;
+        let progress = match line.goal.target.is_zero() {
+            true  => 100.0,
+            false => line.saved.to_f64() / line.goal.target.to_f64() * 100.0,
+        };
+        let styled = |amount: &Amount| amount.styled(self.styles).to_string();
+        let needed = line.needed.as_ref().map(styled).unwrap_or_else(|| "reached".to_string());
+        let projected = line.projected.map(|month| month.to_string()).unwrap_or_else(|| "never".to_string());
+
+        write!(f, "{}  {}  {}  {}  {}  {}  {} by {}",
+               pad_left(&styled(&line.saved), 15), pad_left(&styled(&line.goal.target), 15), pad_left(&format!("{:.0}%", progress.floor()), 8),
+               pad_left(&needed, 15), pad_left(&styled(&line.recent), 15), pad_right(&projected, 9), line.goal.account, line.goal.by)
+    }
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use crate::journal::Journal;
+    use crate::types::commoditystyle::{CommodityStyles, WithStyles};
+    use super::goals_report;
+
+    const JOURNAL: &str = r#"
+goal assets:vacation-fund $3000 by 2024/06
+goal assets:emergency $500 by 2023/12
+goal assets:car $5000 by 2023/12
+
+2023/09/01 Opening balances
+    assets:emergency                    $600
+    equity:opening-balances
+
+2023/10/15 Savings
+    assets:vacation-fund                $200
+    assets:checking
+
+2023/11/15 Savings
+    assets:vacation-fund:flights        $300
+    assets:checking
+
+2023/12/15 Savings
+    assets:vacation-fund                $400
+    assets:checking
+
+2024/01/05 Savings
+    assets:vacation-fund                $100
+    assets:checking
+"#;
+
+    #[test]
+    fn test_goals_report() {
+        let journal = Journal::from_lines(JOURNAL.lines()).unwrap();
+        let lines: Vec<String> = goals_report(&journal, NaiveDate::from_ymd_opt(2024, 1, 20).unwrap())
+                                     .iter()
+                                     .map(|line| line.styled(&CommodityStyles::default()).to_string())
+                                     .collect();
+
+        // the vacation fund needs $2000 more over six months, but at $300 a month takes seven.
+        // the car's month has passed, so it needs everything now and won't get there without
+        // any contributions
+        assert_eq!(lines, vec![
+            "       $1000.00         $3000.00       33%          $333.33          $300.00  2024-07    assets:vacation-fund by 2024-06",
+            "        $600.00          $500.00      120%          reached            $0.00  2024-01    assets:emergency by 2023-12",
+            "          $0.00         $5000.00        0%         $5000.00            $0.00  never      assets:car by 2023-12",
+        ]);
+    }
+}
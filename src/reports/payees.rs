@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::journal::Journal;
+
+
+/* Payees */
+
+// one distinct transaction description and how many transactions use it
+pub struct PayeeLine<'a> {
+    pub payee  : &'a str,
+    pub count  : usize,
+    pub similar: bool,  // another payee differs from this one only by case or punctuation
+}
+
+// The distinct payees (transaction descriptions) in the journal with their usage counts.
+// They're sorted by their normalized name so near-duplicates like "Tim Hortons" and
+// "tim-hortons" end up next to each other, and both are flagged as similar
+pub fn payees_report(journal: &Journal) -> Vec<PayeeLine<'_>> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for transaction in &journal.transactions {
+        *counts.entry(transaction.description.trim_end()).or_default() += 1;
+    }
+
+    let mut spellings: HashMap<String, usize> = HashMap::new();
+    for payee in counts.keys() {
+        *spellings.entry(normalize(payee)).or_default() += 1;
+    }
+
+    let mut lines: Vec<PayeeLine> =
+        counts.into_iter()
+              .map(|(payee, count)| PayeeLine {
+                  payee,
+                  count,
+                  similar: spellings[&normalize(payee)] > 1,
+              })
+              .collect();
+
+    lines.sort_by(|a, b| normalize(a.payee).cmp(&normalize(b.payee))
+                             .then(a.payee.cmp(b.payee)));
+    lines
+}
+
+// lowercase with only the letters and digits, so "Tim Hortons" and "tim-hortons" are the same
+fn normalize(payee: &str) -> String {
+    payee.chars()
+         .filter(|c| c.is_alphanumeric())
+         .flat_map(|c| c.to_lowercase())
+         .collect()
+}
+
+//    3  Payroll Deposit
+//    1  Tim Hortons  *
+//    4  tim-hortons  *
+
+impl fmt::Display for PayeeLine<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:>5}  {}", self.count, self.payee)?;
+        if self.similar {
+            write!(f, "  *")?;
+        }
+        Ok(())
+    }
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use crate::journal::Journal;
+    use super::{normalize, payees_report};
+
+    #[test]
+    fn test_payees_report() {
+        let journal =
+r#"
+2023/03/01 tim-hortons
+    expenses:food    $2
+    assets:cash
+
+2023/03/02 Payroll Deposit
+    assets:savings    $2000
+    income:payroll
+
+2023/03/03 Tim Hortons
+    expenses:food    $2
+    assets:cash
+
+2023/03/04 tim-hortons  ; coffee:
+    expenses:food    $2
+    assets:cash
+"#;
+        let journal = Journal::from_lines(journal.lines()).unwrap();
+        let lines: Vec<String> = payees_report(&journal).iter().map(|l| l.to_string()).collect();
+
+        assert_eq!(lines, vec![
+            "    1  Payroll Deposit",
+            "    1  Tim Hortons  *",
+            "    2  tim-hortons  *",
+        ]);
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("Tim Hortons"), "timhortons");
+        assert_eq!(normalize("tim-hortons #123"), "timhortons123");
+    }
+}
@@ -0,0 +1,160 @@
+use chrono::{Datelike, NaiveDate};
+
+use crate::journal::Journal;
+use crate::price::Price;
+use crate::types::amount::Amount;
+
+
+/* Prices */
+
+pub struct PricesOptions<'a> {
+    pub commodity: Option<&'a str>, // only prices for this commodity
+    pub invert   : bool,            // show the price of the other side of each pair
+    pub monthly  : bool,            // one price for the start of each month, interpolated
+}
+
+// The market price history from the journal's price directives
+pub fn prices_report(journal: &Journal,
+                     options: &PricesOptions) -> Vec<Price>
+{
+    let mut prices: Vec<Price> =
+        journal.prices
+               .iter()
+               .filter(|p| options.commodity.is_none_or(|c| p.commodity == c))
+               .cloned()
+               .collect();
+
+    if options.monthly {
+        prices = interpolate_monthly(&prices);
+    }
+
+    if options.invert {
+        prices = prices.iter().map(Price::invert).collect();
+    }
+
+    prices
+}
+
+// one price on the first of every month between the first and last price of each commodity
+// pair, linearly interpolated by day between the two known prices on either side of it
+fn interpolate_monthly(prices: &[Price]) -> Vec<Price> {
+    let mut pairs: Vec<(&str, &str)> =
+        prices.iter()
+              .map(|p| (p.commodity.as_str(), p.price.units.as_str()))
+              .collect();
+    pairs.sort();
+    pairs.dedup();
+
+    let mut interpolated = vec![];
+
+    for (commodity, units) in pairs {
+        let history: Vec<&Price> =
+            prices.iter()
+                  .filter(|p| p.commodity == commodity && p.price.units == units)
+                  .collect();
+
+        let first = history.first().unwrap().date;
+        let last  = history.last().unwrap().date;
+
+        let mut month = first_of_month(first);
+        if month < first {
+            month = next_month(month);
+        }
+
+        while month <= last {
+            // the first known price on or after this date, and the one before it
+            let after = history.partition_point(|p| p.date < month);
+            let p1 = history[after];
+
+            let value = if p1.date == month {
+                p1.price.to_f64()
+            } else {
+                let p0 = history[after - 1];
+                let span    = (p1.date - p0.date).num_days() as f64;
+                let elapsed = (month - p0.date).num_days() as f64;
+                p0.price.to_f64() + (p1.price.to_f64() - p0.price.to_f64()) * elapsed / span
+            };
+
+            interpolated.push(Price {
+                date     : month,
+                commodity: commodity.into(),
+                price    : Amount::from(units.to_owned(), value),
+            });
+
+            month = next_month(month);
+        }
+    }
+
+    interpolated.sort_by_key(|p| p.date);
+    interpolated
+}
+
+fn first_of_month(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).unwrap()
+}
+
+fn next_month(date: NaiveDate) -> NaiveDate {
+    if date.month() == 12 {
+        NaiveDate::from_ymd_opt(date.year() + 1, 1, 1).unwrap()
+    } else {
+        NaiveDate::from_ymd_opt(date.year(), date.month() + 1, 1).unwrap()
+    }
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use crate::journal::Journal;
+    use crate::types::commoditystyle::WithStyles;
+    use super::{PricesOptions, prices_report};
+
+    fn sample_journal() -> Journal {
+        let journal =
+r#"
+P 2023/01/01 USD $1.30
+P 2023/01/15 EUR $1.45
+P 2023/03/01 USD $1.40
+P 2023/04/15 USD $1.36
+"#;
+        Journal::from_lines(journal.lines()).unwrap()
+    }
+
+    fn render(options: &PricesOptions) -> Vec<String> {
+        let journal = sample_journal();
+        prices_report(&journal, options)
+            .iter()
+            .map(|p| p.styled(&journal.commodities).to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_prices_report() {
+        let options = PricesOptions { commodity: Some("USD"), invert: false, monthly: false };
+        assert_eq!(render(&options), vec![
+            "P 2023/01/01 USD $1.30",
+            "P 2023/03/01 USD $1.40",
+            "P 2023/04/15 USD $1.36",
+        ]);
+    }
+
+    #[test]
+    fn test_prices_report_inverted() {
+        let options = PricesOptions { commodity: Some("EUR"), invert: true, monthly: false };
+        assert_eq!(render(&options), vec![
+            "P 2023/01/15 $ 0.6897 EUR",
+        ]);
+    }
+
+    #[test]
+    fn test_prices_report_monthly() {
+        let options = PricesOptions { commodity: Some("USD"), invert: false, monthly: true };
+        assert_eq!(render(&options), vec![
+            "P 2023/01/01 USD $1.30",
+            "P 2023/02/01 USD $1.35",
+            "P 2023/03/01 USD $1.40",
+            "P 2023/04/01 USD $1.37",
+        ]);
+    }
+}
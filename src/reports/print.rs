@@ -0,0 +1,204 @@
+use std::fmt;
+
+use crate::common::pad_right;
+use crate::journal::Journal;
+use crate::output::{Json, JsonValue};
+use crate::query::Query;
+use crate::transaction::Transaction;
+use crate::types::amount::Amount;
+use crate::types::commoditystyle::{CommodityStyle, CommodityStyles};
+use crate::types::numberformat::NumberFormat;
+
+
+/* Print */
+
+// The journal's transactions with a posting matching the query, for printing back out in
+// journal format
+pub fn print_report<'a>(journal: &'a Journal,
+                        query  : &Query) -> Vec<&'a Transaction>
+{
+    journal.filter(query).collect()
+}
+
+// a transaction written the way it would appear in a journal file, in its journal's
+// commodity styles, so the output of the print report can be read back in
+pub struct Printed<'a>(pub &'a Transaction, pub &'a CommodityStyles);
+
+// 2023/03/17 Ramen  ; trip:japan, reimbursable:
+//     expenses:food                             $22.00
+//     assets:cash                              $-22.00
+
+impl fmt::Display for Printed<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let transaction = self.0;
+
+        write!(f, "{} {}", transaction.date.format("%Y/%m/%d"), transaction.description.trim_end())?;
+        if !transaction.tags.is_empty() {
+            let tags: Vec<String> = transaction.tags
+                                               .iter()
+                                               .map(|(name, value)| format!("{}:{}", name, value))
+                                               .collect();
+            write!(f, "  ; {}", tags.join(", "))?;
+        }
+        writeln!(f)?;
+
+        for entry in &transaction.entries {
+            write!(f, "    {}  {:>12}", pad_right(&entry.written_account(), 34), journal_amount(&entry.amount, self.1))?;
+            if let Some(lot) = &entry.lot {
+                write!(f, " {{{{{}}}}}", journal_amount(lot, self.1))?;
+            }
+            if let Some(cost) = &entry.cost {
+                write!(f, " @@ {}", journal_amount(cost, self.1))?;
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+// an amount as it's written in the journal: "$-22.00" or "308 kWh", in its commodity's
+// style but with exactly its own decimal places and plain digits, so it reads back the same
+fn journal_amount(amount: &Amount, styles: &CommodityStyles) -> String {
+    let quantity = amount.decimal();
+    let style    = CommodityStyle {
+        precision: quantity.scale(),
+        number   : Some(NumberFormat::default()),
+        ..styles.style(&amount.units)
+    };
+    style.format(&amount.units, quantity)
+}
+
+// {"date":"2023-01-17","description":"Ramen","tags":[{"name":"trip","value":"japan"}],
+//  "postings":[{"account":"expenses:food:restaurant",
+//               "amount":{"quantity":22.00,"commodity":"$","decimals":2}},...]}
+
+impl Json for Printed<'_> {
+    fn to_json(&self) -> JsonValue {
+        let transaction = self.0;
+        let tags = transaction.tags
+                              .iter()
+                              .map(|(name, value)| JsonValue::Object(vec![
+                                  ("name",  name.to_json()),
+                                  ("value", value.to_json()),
+                              ]))
+                              .collect();
+        let postings = transaction.entries
+                                  .iter()
+                                  .map(|entry| JsonValue::Object(vec![
+                                      ("account", entry.account.to_json()),
+                                      ("amount",  entry.amount.to_json()),
+                                  ]))
+                                  .collect();
+
+        JsonValue::Object(vec![
+            ("date",        transaction.date.to_string().to_json()),
+            ("description", transaction.description.trim().to_json()),
+            ("tags",        JsonValue::Array(tags)),
+            ("postings",    JsonValue::Array(postings)),
+        ])
+    }
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use crate::journal::Journal;
+    use crate::output::Json;
+    use super::{Printed, print_report};
+
+    fn sample_journal() -> Journal {
+        let journal =
+r#"
+2023/01/05 Tim Hortons
+    expenses:food:tim-hortons            $3
+    assets:cash
+
+2023/01/17 Ramen  ; trip:japan, reimbursable:
+    expenses:food:restaurant            $22
+    assets:cash
+
+2023/02/07 Hydro
+    usage:power                  308 kWh
+    usage:meter
+"#;
+        Journal::from_lines(journal.lines()).unwrap()
+    }
+
+    fn descriptions(query: &str) -> Vec<String> {
+        let journal = sample_journal();
+        print_report(&journal, &query.parse().unwrap()).iter()
+                                                       .map(|t| t.description.trim().to_string())
+                                                       .collect()
+    }
+
+    #[test]
+    fn test_print_report_filters() {
+        assert_eq!(descriptions(""), vec!["Tim Hortons", "Ramen", "Hydro"]);
+        assert_eq!(descriptions("FOOD"), vec!["Tim Hortons", "Ramen"]);
+        assert_eq!(descriptions("desc:^ram"), vec!["Ramen"]);
+        assert_eq!(descriptions("tag:trip"), vec!["Ramen"]);
+        assert_eq!(descriptions("tag:trip=peru"), Vec::<String>::new());
+        assert_eq!(descriptions("food desc:tim"), vec!["Tim Hortons"]);
+        assert_eq!(descriptions("amt:>20"), vec!["Ramen", "Hydro"]);
+        assert_eq!(descriptions("amt:<-20"), vec!["Ramen", "Hydro"]);
+        assert_eq!(descriptions("not:tag:trip not:usage"), vec!["Tim Hortons"]);
+    }
+
+    #[test]
+    fn test_printed_round_trip() {
+        let journal = sample_journal();
+        let printed: String = journal.transactions
+                                     .iter()
+                                     .map(|t| Printed(t, &journal.commodities).to_string())
+                                     .collect::<Vec<_>>()
+                                     .join("\n");
+
+        assert_eq!(printed,
+"2023/01/05 Tim Hortons
+    expenses:food:tim-hortons                  $3.00
+    assets:cash                               $-3.00
+
+2023/01/17 Ramen  ; trip:japan, reimbursable:
+    expenses:food:restaurant                  $22.00
+    assets:cash                              $-22.00
+
+2023/02/07 Hydro
+    usage:power                              308 kWh
+    usage:meter                             -308 kWh
+");
+
+        assert_eq!(Journal::from_lines(printed.lines()).unwrap().transactions, journal.transactions);
+    }
+
+    #[test]
+    fn test_printed_cost_round_trip() {
+        let journal = Journal::from_lines("2023/01/16 Buy\n    assets:brokerage  10 AAPL @ $150.10\n    assets:checking".lines()).unwrap();
+        let printed = Printed(&journal.transactions[0], &journal.commodities).to_string();
+
+        assert_eq!(printed,
+"2023/01/16 Buy
+    assets:brokerage                         10 AAPL @@ $1501.00
+    assets:checking                        $-1501.00
+");
+        assert_eq!(Journal::from_lines(printed.lines()).unwrap().transactions, journal.transactions);
+
+        // a sale keeps the lot's cost along with its price
+        let journal = Journal::from_lines("2023/06/01 Sell\n    assets:brokerage  -4 AAPL {$150} @ $170\n    assets:checking".lines()).unwrap();
+        let printed = Printed(&journal.transactions[0], &journal.commodities).to_string();
+        assert!(printed.contains("-4 AAPL {{$600.00}} @@ $680.00"), "{}", printed);
+        assert_eq!(Journal::from_lines(printed.lines()).unwrap().transactions, journal.transactions);
+    }
+
+    #[test]
+    fn test_printed_json() {
+        let journal = sample_journal();
+        assert_eq!(Printed(&journal.transactions[1], &journal.commodities).to_json().to_string(),
+                   concat!(r#"{"date":"2023-01-17","description":"Ramen","#,
+                           r#""tags":[{"name":"trip","value":"japan"},{"name":"reimbursable","value":""}],"#,
+                           r#""postings":[{"account":"expenses:food:restaurant","amount":{"quantity":22.00,"commodity":"$","decimals":2}},"#,
+                           r#"{"account":"assets:cash","amount":{"quantity":-22.00,"commodity":"$","decimals":2}}]}"#));
+    }
+}
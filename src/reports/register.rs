@@ -1,136 +1,860 @@
-use chrono::NaiveDate;
-use std::collections::HashMap;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
 use std::fmt;
-use crate::transaction::Entry;
-use crate::types::{Account, amount::Amount, Units};
+use crate::color::{Style, paint};
+use crate::common::{mark_forecast, pad_right};
+use crate::output::{Csv, Json, JsonValue};
+use crate::transaction::posting::Posting;
+use crate::query::Query;
+use crate::types::commoditystyle::{CommodityStyles, Styled, WithStyles};
+use crate::types::{amount::{Amount, AmountError}, interval::Interval, mixedamount::MixedAmount, period::Period, Units};
+use crate::types::lineformat::{Field, LineFormat};
+use crate::types::sortkey::{SortField, SortKey};
 use crate::journal::Journal;
 use crate::transaction::Transaction;
 
 
 // one line of the register report
 pub struct ReportLine<'a> {
-    date         : Option<NaiveDate>,          // only render the first date and
-    description  : Option<&'a String>,         // description per transaction
-    account      : &'a String,
-    amount       : String,
-    running_total: String,
+    posting      : Posting<'a>,
+    first        : bool,                       // only render the first date and description
+                                               // per transaction
+    amount       : Amount,                     // with its sign flipped for an inverted report
+    running_total: Amount,
+    forecast     : bool,                       // generated by --forecast, marked with a *
 }
 
-// a mask on a transaction that selects only certain entries, references to which are
-// stored in a new vector. the selected entries may no longer balance to zero, though
-// a reference to the underlying transaction is kept, which has all the original entries
-struct FilteredTransaction<'a> {
-    transaction: &'a Transaction,
-    entries    : Vec<&'a Entry>
+#[derive(Default)]
+pub struct RegisterOptions {
+    pub historical    : bool,                 // start the running total from the balance before the report
+    pub related       : bool,                 // show the other postings of the account's transactions instead
+    pub invert        : bool,                 // flip the signs, so income shows as positive
+    pub moving_average: Option<usize>,        // add the average of the last this many periods (periodic only)
+    pub sort          : Option<SortKey>,      // the order of the lines instead of journal order (not periodic)
 }
 
-// Generates a register report for a given account, showing each debit or credit
-// transaction with a running total for each line. Displays the date and description
-// information only once for each transaction, leaving blanks for the other lines.
+// Generates a register report for the postings matching the query, showing each debit or
+// credit transaction with a running total for each line. Displays the date and description
+// information only once for each transaction, leaving blanks for the other lines. With a
+// sort key the postings are put in that order first, so the running totals follow it.
+// The lines are made as they're read, so only a sorted report holds all of its postings.
+// A running total too large for an amount to hold ends the report with an error
 pub fn register_report<'a>(journal: &'a Journal,
-                           account: &'a Account) -> Vec<ReportLine<'a>>
+                           query  : &'a Query,
+                           options: &RegisterOptions) -> Box<dyn Iterator<Item = Result<ReportLine<'a>, AmountError>> + 'a>
 {
-    let fts = filter_by_account(&journal.transactions, account);
-    let mut report_lines: Vec<ReportLine> = vec![];
-    let mut running_totals: HashMap<Units, Amount> = HashMap::new();
-
-    for filtered in fts {
-
-        // for a multi-entry transaction we only want to print the date/description
-        // for the first line
-        let mut is_first_entry = true;
-
-        for entry in filtered.entries {
-            update_running_totals(&mut running_totals, entry);
-
-            let units = &entry.amount.units;
-            let running_total = running_totals.get(units).unwrap().clone();
-            let report_line = create_report_line(filtered.transaction,
-                                                 entry,
-                                                 running_total,
-                                                 is_first_entry);
-            
-            report_lines.push(report_line);
-            is_first_entry = false;
+    let mut running_totals = match opening_totals(journal, query, options) {
+        Ok(totals) => totals,
+        Err(error) => return Box::new(std::iter::once(Err(error))),
+    };
+    let invert = options.invert;
+
+    let postings = filter_by_query(journal.transactions_for(query), query, options.related);
+    let postings: Box<dyn Iterator<Item = Posting<'a>>> = match options.sort {
+        None       => Box::new(postings),
+        Some(sort) => {
+            let mut postings: Vec<Posting> = postings.collect();
+            postings.sort_by(|a, b| {
+                let order = compare_postings(sort.field, a, b, invert);
+                if sort.descending { order.reverse() } else { order }
+            });
+            Box::new(postings.into_iter())
+        },
+    };
+
+    let mut previous: Option<Posting> = None;
+    Box::new(postings.map(move |posting| {
+        // only print the date/description for the first line of a run of lines from
+        // the same transaction
+        let is_first_entry = previous.is_none_or(|previous| !previous.same_transaction(&posting));
+        previous = Some(posting);
+
+        running_totals.checked_add_amount(posting.amount())?;
+        let running_total = running_totals.get(&posting.amount().units).unwrap().clone();
+        create_report_line(posting,
+                           running_total,
+                           is_first_entry,
+                           invert)
+    }))
+}
+
+// the order of two postings by one of their fields. amounts are compared as they're
+// displayed, so with the signs flipped for an inverted report
+fn compare_postings(field : SortField,
+                    a     : &Posting,
+                    b     : &Posting,
+                    invert: bool) -> Ordering
+{
+    match field {
+        SortField::Date        => a.date().cmp(&b.date()),
+        SortField::Account     => a.account().cmp(b.account()),
+        SortField::Description => a.description().trim().to_lowercase().cmp(&b.description().trim().to_lowercase()),
+        SortField::Amount      => {
+            let sign = if invert { -1.0 } else { 1.0 };
+            a.amount().units.cmp(&b.amount().units)
+                            .then_with(|| (sign * a.amount().to_f64()).total_cmp(&(sign * b.amount().to_f64())))
+        },
+    }
+}
+
+// a footer for the register report with the number of postings and the total and average
+// posting amount for each commodity
+#[derive(Default)]
+pub struct RegisterSummary {
+    postings: usize,
+    totals  : BTreeMap<Units, (Amount, Amount, usize)>, // last running total, sum of the postings
+                                                         // and number of postings
+}
+
+// Sums up the lines of the register report run with the same options, so the total in each
+// commodity is the report's last running total, starting from the opening balance of a
+// historical report and with its sign flipped for an inverted one
+pub fn register_summary(journal: &Journal,
+                        query  : &Query,
+                        options: &RegisterOptions) -> Result<RegisterSummary, AmountError>
+{
+    let mut summary = RegisterSummary::default();
+    for line in register_report(journal, query, options) {
+        summary.add(&line?)?;
+    }
+    Ok(summary)
+}
+
+impl RegisterSummary {
+    // count the next line of the report, for summing it up as it's written
+    pub fn add(&mut self, line: &ReportLine) -> Result<(), AmountError> {
+        self.postings += 1;
+        match self.totals.get_mut(&line.amount.units) {
+            Some((total, sum, count)) => { *total = line.running_total.clone(); *sum = sum.checked_add(&line.amount)?; *count += 1; },
+            None                      => { self.totals.insert(line.amount.units, (line.running_total.clone(), line.amount.clone(), 1)); },
         }
+        Ok(())
     }
+}
+
+// one line of the periodic register report, summarizing a period's postings in one commodity
+pub struct PeriodLine {
+    period        : String,
+    account       : String,     // the query the report was run for
+    total         : Amount,
+    running_total : Amount,
+    moving_average: Option<Amount>, // with the moving average option, once there's enough periods
+}
 
-    report_lines
+// the postings of one period, totalled by commodity
+struct PeriodTotals {
+    period  : Period,
+    totals  : MixedAmount,
+    forecast: bool, // whether any of its postings are forecast
 }
 
-fn create_report_line<'a>(transaction   : &'a Transaction,
-                          entry         : &'a Entry,
-                          running_total : Amount,
-                          is_first_entry: bool) -> ReportLine<'a>
+// Generates a register report for the postings matching the query that collapses the
+// postings in each period into one line per commodity with the period's total and the
+// running total. Periods without any postings are left out
+pub fn register_periodic_report(journal : &Journal,
+                                query   : &Query,
+                                interval: Interval,
+                                options : &RegisterOptions) -> Result<Vec<PeriodLine>, AmountError>
 {
-    ReportLine {
-        date         : if is_first_entry { Some(transaction.date) } else { None },
-        description  : if is_first_entry { Some(&transaction.description) } else { None },
-        account      : &entry.account,
-        amount       : entry.amount.to_string(),
-        running_total: running_total.to_string()
+    let mut periods: Vec<PeriodTotals> = vec![];
+
+    for posting in filter_by_query(journal.transactions_for(query), query, options.related) {
+        let period = Period::containing(interval, posting.date());
+
+        if periods.last().is_none_or(|last| last.period != period) {
+            periods.push(PeriodTotals { period, totals: MixedAmount::default(), forecast: false });
+        }
+
+        let last = periods.last_mut().unwrap();
+        last.forecast |= posting.transaction.is_forecast();
+        last.totals.checked_add_amount(posting.amount())?;
     }
+
+    let mut report_lines: Vec<PeriodLine> = vec![];
+    let mut running_totals = opening_totals(journal, query, options)?;
+
+    for (i, period) in periods.iter().enumerate() {
+        for total in period.totals.amounts() {
+            running_totals.checked_add_amount(total)?;
+            let running_total = running_totals.get(&total.units).unwrap();
+
+            let moving_average = match options.moving_average {
+                Some(n) => moving_average(&periods[..=i], &total.units, n)?.map(|average| signed(average, options.invert)).transpose()?,
+                None    => None,
+            };
+
+            report_lines.push(PeriodLine {
+                period        : mark_forecast(period.period.to_string(), period.forecast),
+                account       : query.to_string(),
+                total         : signed(total.clone(), options.invert)?,
+                running_total : signed(running_total.clone(), options.invert)?,
+                moving_average,
+            });
+        }
+    }
+
+    Ok(report_lines)
+}
+
+// the average total in the commodity over the n periods ending with the last one, where
+// periods without postings count as zero. there's no average until n periods have gone by
+fn moving_average(periods: &[PeriodTotals],
+                  units  : &Units,
+                  n      : usize) -> Result<Option<Amount>, AmountError>
+{
+    let Some((last, earlier)) = periods.split_last() else { return Ok(None) };
+
+    let mut window_start = last.period;
+    for _ in 1..n {
+        window_start = window_start.previous();
+    }
+    if window_start.start < periods[0].period.start {
+        return Ok(None)
+    }
+
+    let Some(mut total) = last.totals.get(units).cloned() else { return Ok(None) };
+    for amount in earlier.iter()
+                         .filter(|period| period.period.start >= window_start.start)
+                         .filter_map(|period| period.totals.get(units))
+    {
+        total = total.checked_add(amount)?;
+    }
+
+    total.checked_div(n as i64).map(Some)
 }
 
-// Filters the transactions by the given account and returns a vector of FilteredTransaction.
-// For each transaction, it checks if there are any entries associated with the account.
-// If there are any, it creates a FilteredTransaction with a reference to the transaction
-// and the relevant entries. If not, it skips the transaction.
-fn filter_by_account<'a>(transactions: &'a [Transaction],
-                         account     : &'a Account) -> Vec<FilteredTransaction<'a>>
+fn create_report_line(posting       : Posting,
+                      running_total : Amount,
+                      is_first_entry: bool,
+                      invert        : bool) -> Result<ReportLine, AmountError>
+{
+    Ok(ReportLine {
+        posting,
+        first        : is_first_entry,
+        amount       : signed(posting.amount().clone(), invert)?,
+        running_total: signed(running_total, invert)?,
+        forecast     : posting.transaction.is_forecast(),
+    })
+}
+
+// the amount as it's displayed, with its sign flipped for an inverted report
+fn signed(amount: Amount, invert: bool) -> Result<Amount, AmountError> {
+    if invert { amount.checked_neg() } else { Ok(amount) }
+}
+
+// Filters the transactions by the given query and returns the postings that match, in
+// journal order. For each transaction, it checks if there are any entries matching the
+// query, so food picks out the postings to expenses:food:subway and
+// expenses:food:tim-hortons. If not, it skips the transaction. With related set, the
+// postings returned are the ones for all the other accounts in the matching transactions.
+fn filter_by_query<'a>(transactions: impl Iterator<Item = &'a Transaction>,
+                       query       : &'a Query,
+                       related     : bool) -> impl Iterator<Item = Posting<'a>>
 {
     transactions
-        .iter()
-        .filter_map(|transaction| {
-            let entries: Vec<&Entry> =
-                transaction.entries
-                           .iter()
-                           .filter(|entry| entry.account.as_str() == account)
-                           .collect();
-
-            if entries.is_empty() {
-                return None;
-            }
-
-            Some(FilteredTransaction {
-                transaction,
-                entries
-            })
-        })
-        .collect()
-}
-
-fn update_running_totals(totals: &mut HashMap<Units, Amount>,
-                         entry : &Entry)
+        .filter(|transaction| query.matches(*transaction))
+        .flat_map(|transaction| transaction.postings())
+        .filter(move |posting| query.matches(posting) != related)
+}
+
+// the running totals to start the register from, which for a historical report are the
+// balances of the matching postings from the transactions set aside before the start of the report
+fn opening_totals(journal: &Journal,
+                  query  : &Query,
+                  options: &RegisterOptions) -> Result<MixedAmount, AmountError>
 {
-    let units = &entry.amount.units;
+    let mut totals = MixedAmount::default();
 
-    if let Some(amount) = totals.get_mut(units) {
-        amount.add(&entry.amount);
-    } else {
-        totals.insert(units.clone(), entry.amount.clone());
+    if options.historical {
+        for posting in filter_by_query(journal.earlier.iter(), query, options.related) {
+            totals.checked_add_amount(posting.amount())?;
+        }
     }
+
+    Ok(totals)
 }
 
 
+impl ReportLine<'_> {
+    // the line laid out by the template, with the date and description left blank after
+    // the transaction's first line, with the amounts in the journal's commodity styles
+    pub fn render(&self, format: &LineFormat, styles: &CommodityStyles) -> String {
+        let mut line = format.render(|field| match field {
+            Field::Date    if self.first => (self.posting.date().format("%Y/%m/%d").to_string(), Style::Plain),
+            Field::Payee   if self.first => (self.posting.description().to_string(), Style::Plain),
+            Field::Date | Field::Payee   => (String::new(), Style::Plain),
+            Field::Account               => (self.posting.account().to_string(), Style::Dim),
+            Field::Amount                => (self.amount.styled(styles).to_string(), Style::amount(self.amount.is_negative())),
+            Field::Total                 => (self.running_total.styled(styles).to_string(), Style::amount(self.running_total.is_negative())),
+        });
+
+        if self.forecast {
+            line.push_str(" *");
+        }
+        line
+    }
+}
+
 // 2023/03/18 Groceries                      assets:savings                      $-41.06       $399.64
 // 2023/03/18 Crunchy Chicken Bowl           assets:savings                      $-16.10       $368.59
+// 2023/04/01 rent                           assets:checking                   $-1000.00      $-631.41 *
+
+impl WithStyles for ReportLine<'_> {}
 
-impl fmt::Display for ReportLine<'_> {
+impl fmt::Display for Styled<'_, ReportLine<'_>> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.value.render(&LineFormat::default(), self.styles))
+    }
+}
 
-        let date = self.date
-            .map(|date| format!("{}", date.format("%Y/%m/%d")))
-            .unwrap_or_else(|| " ".repeat(10));
+// 2023-03    assets:savings                   $2985.01   $2985.01
+// 2023-04    assets:savings                    $-41.06   $2943.95
 
-        let empty = "".to_string();
-        let description = self.description.unwrap_or(&empty);
+impl WithStyles for PeriodLine {}
 
+impl fmt::Display for Styled<'_, PeriodLine> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let line = self.value;
         write!(
             f,
-            "{} {:<30} {:<30} {:>10} {:>10}",
-            date, description, self.account, self.amount, self.running_total
-        )
+            "{} {} {} {}",
+            paint(format!("{:<10}", line.period), Style::Bold),
+            paint(pad_right(&line.account, 30), Style::Dim),
+            padded_amount(&line.total, self.styles, 10),
+            padded_amount(&line.running_total, self.styles, 10)
+        )?;
+
+        if let Some(average) = &line.moving_average {
+            write!(f, " {}", padded_amount(average, self.styles, 10))?;
+        }
+        Ok(())
+    }
+}
+
+// the amount right-aligned to the width, in red if it's negative
+fn padded_amount(amount: &Amount, styles: &CommodityStyles, width: usize) -> String {
+    paint(format!("{:>width$}", amount.styled(styles).to_string(), width = width), Style::amount(amount.is_negative()))
+}
+
+// date,description,account,amount,total
+// 2023-03-18,Groceries,assets:savings,$-41.06,$399.64
+
+impl<'a> WithStyles for [ReportLine<'a>] {}
+
+impl Csv for Styled<'_, [ReportLine<'_>]> {
+    fn csv_header(&self) -> Vec<String> {
+        ["date", "description", "account", "amount", "total"].map(String::from).to_vec()
+    }
+
+    fn csv_records(&self) -> Vec<Vec<String>> {
+        self.value
+            .iter()
+            .map(|line| vec![line.posting.date().to_string(),
+                             line.posting.description().trim().to_string(),
+                             line.posting.account().to_string(),
+                             line.amount.styled(self.styles).to_string(),
+                             line.running_total.styled(self.styles).to_string()])
+            .collect()
+    }
+}
+
+// period,account,total,running_total,moving_average
+// 2023-03,assets:savings,$2985.01,$2985.01,
+
+impl WithStyles for [PeriodLine] {}
+
+impl Csv for Styled<'_, [PeriodLine]> {
+    fn csv_header(&self) -> Vec<String> {
+        ["period", "account", "total", "running_total", "moving_average"].map(String::from).to_vec()
+    }
+
+    fn csv_records(&self) -> Vec<Vec<String>> {
+        self.value
+            .iter()
+            .map(|line| vec![line.period.clone(),
+                             line.account.to_string(),
+                             line.total.styled(self.styles).to_string(),
+                             line.running_total.styled(self.styles).to_string(),
+                             line.moving_average.as_ref().map(|a| a.styled(self.styles).to_string()).unwrap_or_default()])
+            .collect()
+    }
+}
+
+// {"date":"2023-03-18","description":"Groceries","account":"assets:savings",
+//  "amount":{"quantity":-41.06,"commodity":"$","decimals":2},
+//  "running_total":{"quantity":399.64,"commodity":"$","decimals":2},"forecast":false}
+
+impl Json for ReportLine<'_> {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Object(vec![
+            ("date",          self.posting.date().to_string().to_json()),
+            ("description",   self.posting.description().trim().to_json()),
+            ("account",       self.posting.account().to_json()),
+            ("amount",        self.amount.to_json()),
+            ("running_total", self.running_total.to_json()),
+            ("forecast",      JsonValue::Bool(self.forecast)),
+        ])
+    }
+}
+
+// {"period":"2023-03","account":"assets:savings",
+//  "total":{"quantity":2985.01,"commodity":"$","decimals":2},
+//  "running_total":{"quantity":2985.01,"commodity":"$","decimals":2},"moving_average":null}
+
+impl Json for PeriodLine {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Object(vec![
+            ("period",         self.period.to_json()),
+            ("account",        self.account.to_json()),
+            ("total",          self.total.to_json()),
+            ("running_total",  self.running_total.to_json()),
+            ("moving_average", self.moving_average.to_json()),
+        ])
+    }
+}
+
+
+// ---------------------------------------------------------------------------------------------
+// 3 postings
+// Total                                                                                $2985.01
+// Average                                                                               $995.00
+
+impl WithStyles for RegisterSummary {}
+
+impl fmt::Display for Styled<'_, RegisterSummary> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let summary = self.value;
+        writeln!(f, "{}", "-".repeat(93))?;
+        write!(f, "{} posting{}", summary.postings, if summary.postings == 1 { "" } else { "s" })?;
+
+        for (total, sum, count) in summary.totals.values() {
+            let average = sum.clone() / *count as i64;
+            write!(f, "\n{:<82} {:>10}", "Total", total.styled(self.styles).to_string())?;
+            write!(f, "\n{:<82} {:>10}", "Average", average.styled(self.styles).to_string())?;
+        }
+
+        Ok(())
     }
 }
 
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use crate::journal::Journal;
+    use crate::output::{to_csv, to_json};
+    use crate::types::daterange::{DateRange, parse_date};
+    use crate::types::{amount::AmountError, interval::Interval};
+    use crate::types::lineformat::LineFormat;
+    use crate::types::commoditystyle::{CommodityStyles, WithStyles};
+    use super::{RegisterOptions, ReportLine, register_periodic_report, register_report, register_summary};
+
+    fn sample_journal() -> Journal {
+        let journal =
+r#"
+2023/03/01 opening balances
+    assets:savings               $1000.00
+    equity:opening-balances
+
+2023/03/07 Payroll Deposit
+    assets:savings               $2000
+    income:payroll:workplace    $-2000
+
+2023/03/07 Sandwich, Chili
+    assets:savings                    $-14.99
+    expenses:food:tim-hortons
+
+2023/05/18 Groceries
+    assets:savings                    $-41.06
+    expenses:food
+"#;
+        Journal::from_lines(journal.lines()).unwrap()
+    }
+
+    #[test]
+    fn test_register_periodic_report_monthly() {
+        let journal = sample_journal();
+        let query   = "assets:savings".parse().unwrap();
+        let lines: Vec<String> = register_periodic_report(&journal, &query, Interval::Monthly, &RegisterOptions::default()).unwrap()
+                                    .iter()
+                                    .map(|line| line.styled(&journal.commodities).to_string())
+                                    .collect();
+
+        assert_eq!(lines, vec![
+            "2023-03    assets:savings                   $2985.01   $2985.01",
+            "2023-05    assets:savings                    $-41.06   $2943.95",
+        ]);
+    }
+
+    #[test]
+    fn test_register_periodic_report_weekly() {
+        let journal = sample_journal();
+        let query   = "assets:savings".parse().unwrap();
+        let lines = register_periodic_report(&journal, &query, Interval::Weekly, &RegisterOptions::default()).unwrap();
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].period, "2023-W09");
+        assert_eq!(lines[1].period, "2023-W10");
+        assert_eq!(lines[1].total.to_string(), "$1985.01");
+        assert_eq!(lines[2].running_total.to_string(), "$2943.95");
+    }
+
+    #[test]
+    fn test_register_report_historical() {
+        let mut journal = sample_journal();
+        journal.restrict_to(&DateRange::new(parse_date("2023/05"), None));
+
+        let query   = "assets:savings".parse().unwrap();
+        let running = |options| register_report(&journal, &query, &options).map(Result::unwrap)
+                                    .map(|line| line.running_total.to_string())
+                                    .collect::<Vec<String>>();
+
+        assert_eq!(running(RegisterOptions::default()), vec!["$-41.06"]);
+        assert_eq!(running(RegisterOptions { historical: true, ..Default::default() }), vec!["$2943.95"]);
+
+        let options = RegisterOptions { historical: true, ..Default::default() };
+        let lines = register_periodic_report(&journal, &query, Interval::Monthly, &options).unwrap();
+        assert_eq!(lines[0].running_total.to_string(), "$2943.95");
+    }
+
+    #[test]
+    fn test_register_report_related() {
+        let journal = sample_journal();
+        let query   = "assets:savings".parse().unwrap();
+        let options = RegisterOptions { related: true, ..Default::default() };
+        let lines: Vec<(String, String, String)> = register_report(&journal, &query, &options).map(Result::unwrap)
+                                                       .map(|line| (line.posting.account().to_string(), line.amount.to_string(), line.running_total.to_string()))
+                                                       .collect();
+
+        assert_eq!(lines, vec![
+            ("equity:opening-balances".to_string(),   "$-1000.00".to_string(), "$-1000.00".to_string()),
+            ("income:payroll:workplace".to_string(),  "$-2000.00".to_string(), "$-3000.00".to_string()),
+            ("expenses:food:tim-hortons".to_string(), "$14.99".to_string(),    "$-2985.01".to_string()),
+            ("expenses:food".to_string(),             "$41.06".to_string(),    "$-2943.95".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_register_report_pattern() {
+        let journal = sample_journal();
+        let query   = "FOOD".parse().unwrap();
+        let lines: Vec<(String, String, String)> = register_report(&journal, &query, &RegisterOptions::default()).map(Result::unwrap)
+                                                       .map(|line| (line.posting.account().to_string(), line.amount.to_string(), line.running_total.to_string()))
+                                                       .collect();
+
+        assert_eq!(lines, vec![
+            ("expenses:food:tim-hortons".to_string(), "$14.99".to_string(), "$14.99".to_string()),
+            ("expenses:food".to_string(),             "$41.06".to_string(), "$56.05".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_register_report_exclusions() {
+        let journal = sample_journal();
+        let query   = "food not:tim-hortons".parse().unwrap();
+        let lines: Vec<(String, String)> = register_report(&journal, &query, &RegisterOptions::default()).map(Result::unwrap)
+                                               .map(|line| (line.posting.account().to_string(), line.running_total.to_string()))
+                                               .collect();
+
+        assert_eq!(lines, vec![("expenses:food".to_string(), "$41.06".to_string())]);
+    }
+
+    #[test]
+    fn test_register_report_query() {
+        let journal = sample_journal();
+        let query   = "(desc:sandwich or desc:groceries) ^assets".parse().unwrap();
+        let lines: Vec<(String, String)> = register_report(&journal, &query, &RegisterOptions::default()).map(Result::unwrap)
+                                               .map(|line| (line.posting.description().to_string(), line.amount.to_string()))
+                                               .collect();
+
+        assert_eq!(lines, vec![
+            ("Sandwich, Chili".to_string(), "$-14.99".to_string()),
+            ("Groceries".to_string(),       "$-41.06".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_register_report_overflow() {
+        let journal = Journal::from_lines(r#"
+2023/03/01 Windfall
+    assets:savings     $60000000000000000
+    income:windfall
+
+2023/04/01 Windfall
+    assets:savings     $60000000000000000
+    income:windfall
+"#.lines()).unwrap();
+        let query = "assets:savings".parse().unwrap();
+
+        // the first line's running total fits, the second's doesn't
+        let lines: Vec<_> = register_report(&journal, &query, &RegisterOptions::default()).collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].is_ok());
+        assert_eq!(lines[1].as_ref().err(), Some(&AmountError::Overflow));
+
+        assert_eq!(register_periodic_report(&journal, &query, Interval::Monthly, &RegisterOptions::default()).err(), Some(AmountError::Overflow));
+        assert_eq!(register_summary(&journal, &query, &RegisterOptions::default()).err(), Some(AmountError::Overflow));
+    }
+
+    #[test]
+    fn test_register_report_lines() {
+        let journal = sample_journal();
+        let query   = "savings or food".parse().unwrap();
+
+        // the lines come one at a time, with the date and description only on the first
+        // line of each transaction
+        let mut lines = register_report(&journal, &query, &RegisterOptions::default()).map(Result::unwrap);
+        let first = lines.next().unwrap();
+        assert_eq!((first.posting.description(), first.first), ("opening balances", true));
+        assert_eq!(lines.map(|line| (line.posting.account().to_string(), line.first)).collect::<Vec<_>>(), vec![
+            ("assets:savings".to_string(), true),
+            ("assets:savings".to_string(), true),
+            ("expenses:food:tim-hortons".to_string(), false),
+            ("assets:savings".to_string(), true),
+            ("expenses:food".to_string(), false),
+        ]);
+
+        // sorted, the groceries' postings end up apart and each get the date, while the
+        // sandwich's $-14.99 and $14.99 are still next to each other
+        let options = RegisterOptions { sort: Some("amount".parse().unwrap()), ..Default::default() };
+        let firsts: Vec<bool> = register_report(&journal, &query, &options).map(|line| line.unwrap().first).collect();
+        assert_eq!(firsts, vec![true, true, false, true, true, true]);
+    }
+
+    #[test]
+    fn test_register_report_mixed_units() {
+        let journal =
+r#"
+2023/03/01 Hydro
+    expenses:utilities:hydro         $61.20
+    assets:chequing
+
+2023/03/01 Hydro meter
+    expenses:utilities:hydro         308 kWh
+    equity:meter
+
+2023/04/01 Hydro
+    expenses:utilities:hydro         $58.80
+    assets:chequing
+
+2023/04/01 Hydro meter
+    expenses:utilities:hydro         292 kWh
+    equity:meter
+"#;
+        let journal = Journal::from_lines(journal.lines()).unwrap();
+        let query   = "hydro".parse().unwrap();
+
+        // each commodity keeps its own running total
+        let totals: Vec<String> = register_report(&journal, &query, &RegisterOptions::default()).map(Result::unwrap)
+                                      .map(|line| line.running_total.to_string())
+                                      .collect();
+        assert_eq!(totals, vec!["$61.20", "308.000 kWh", "$120.00", "600.000 kWh"]);
+
+        let lines = register_periodic_report(&journal, &query, Interval::Monthly, &RegisterOptions::default()).unwrap();
+        assert_eq!(lines.iter().map(|line| line.running_total.to_string()).collect::<Vec<String>>(),
+                   vec!["$61.20", "308.000 kWh", "$120.00", "600.000 kWh"]);
+    }
+
+    #[test]
+    fn test_register_report_invert() {
+        let journal = sample_journal();
+        let query   = "income:payroll:workplace".parse().unwrap();
+        let options = RegisterOptions { invert: true, ..Default::default() };
+        let lines: Vec<ReportLine> = register_report(&journal, &query, &options).map(Result::unwrap).collect();
+
+        assert_eq!(lines[0].amount.to_string(), "$2000.00");
+        assert_eq!(lines[0].running_total.to_string(), "$2000.00");
+
+        let lines = register_periodic_report(&journal, &query, Interval::Monthly, &options).unwrap();
+        assert_eq!(lines[0].total.to_string(), "$2000.00");
+    }
+
+    #[test]
+    fn test_register_report_sort() {
+        let journal = sample_journal();
+        let query   = "^assets or ^expenses".parse().unwrap();
+        let options = RegisterOptions { sort: Some("-amount".parse().unwrap()), ..Default::default() };
+        let lines: Vec<String> = register_report(&journal, &query, &options).map(Result::unwrap)
+                                     .map(|line| line.styled(&journal.commodities).to_string())
+                                     .collect();
+
+        assert_eq!(lines, vec![
+            "2023/03/07 Payroll Deposit                assets:savings                   $2000.00   $2000.00",
+            "2023/03/01 opening balances               assets:savings                   $1000.00   $3000.00",
+            "2023/05/18 Groceries                      expenses:food                      $41.06   $3041.06",
+            "2023/03/07 Sandwich, Chili                expenses:food:tim-hortons          $14.99   $3056.05",
+            "                                          assets:savings                    $-14.99   $3041.06",
+            "2023/05/18 Groceries                      assets:savings                    $-41.06   $3000.00",
+        ]);
+
+        let options = RegisterOptions { sort: Some("description".parse().unwrap()), ..Default::default() };
+        let descriptions: Vec<String> = register_report(&journal, &"assets:savings".parse().unwrap(), &options).map(Result::unwrap)
+                                            .map(|line| line.posting.description().to_string())
+                                            .collect();
+        assert_eq!(descriptions, vec!["Groceries", "opening balances", "Payroll Deposit", "Sandwich, Chili"]);
+    }
+
+    #[test]
+    fn test_register_report_forecast() {
+        let mut journal = Journal::from_lines("~ monthly  transfer\n    assets:savings  $100\n    assets:checking".lines()).unwrap();
+        journal.transactions = sample_journal().transactions;
+        journal.add_forecast(parse_date("2023/05/19").unwrap(), parse_date("2023/07/01").unwrap());
+
+        let query   = "assets:savings".parse().unwrap();
+        let lines: Vec<ReportLine> = register_report(&journal, &query, &RegisterOptions::default()).map(Result::unwrap).collect();
+        assert!(!lines[3].styled(&CommodityStyles::default()).to_string().ends_with('*'));
+        assert!(lines[4].styled(&CommodityStyles::default()).to_string().ends_with("$3043.95 *"));
+
+        let lines = register_periodic_report(&journal, &query, Interval::Monthly, &RegisterOptions::default()).unwrap();
+        assert_eq!(lines.iter().map(|line| line.period.as_str()).collect::<Vec<_>>(), vec!["2023-03", "2023-05", "2023-06*"]);
+    }
+
+    #[test]
+    fn test_register_report_amount_filter() {
+        let journal = sample_journal();
+        let query   = "assets:savings amt:<-10".parse().unwrap();
+        let lines: Vec<(String, String)> = register_report(&journal, &query, &RegisterOptions::default()).map(Result::unwrap)
+                                               .map(|line| (line.amount.to_string(), line.running_total.to_string()))
+                                               .collect();
+
+        assert_eq!(lines, vec![
+            ("$-14.99".to_string(), "$-14.99".to_string()),
+            ("$-41.06".to_string(), "$-56.05".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_register_periodic_report_moving_average() {
+        let journal =
+r#"
+2023/01/05 Loblaws
+    expenses:food:groceries            $90
+    assets:cash
+
+2023/02/05 Loblaws
+    expenses:food:groceries           $120
+    assets:cash
+
+2023/04/05 Loblaws
+    expenses:food:groceries           $150
+    assets:cash
+"#;
+        let journal = Journal::from_lines(journal.lines()).unwrap();
+        let query   = "expenses:food:groceries".parse().unwrap();
+        let options = RegisterOptions { moving_average: Some(2), ..Default::default() };
+        let lines: Vec<String> = register_periodic_report(&journal, &query, Interval::Monthly, &options).unwrap()
+                                    .iter()
+                                    .map(|line| line.styled(&CommodityStyles::default()).to_string())
+                                    .collect();
+
+        assert_eq!(lines, vec![
+            "2023-01    expenses:food:groceries            $90.00     $90.00",
+            "2023-02    expenses:food:groceries           $120.00    $210.00    $105.00",
+            "2023-04    expenses:food:groceries           $150.00    $360.00     $75.00",
+        ]);
+    }
+
+    #[test]
+    fn test_register_report_csv() {
+        let journal = sample_journal();
+        let query   = "assets:savings".parse().unwrap();
+        let report: Vec<ReportLine> = register_report(&journal, &query, &RegisterOptions::default()).map(Result::unwrap).collect();
+
+        assert_eq!(to_csv(&report[..].styled(&journal.commodities)),
+"date,description,account,amount,total
+2023-03-01,opening balances,assets:savings,$1000.00,$1000.00
+2023-03-07,Payroll Deposit,assets:savings,$2000.00,$3000.00
+2023-03-07,\"Sandwich, Chili\",assets:savings,$-14.99,$2985.01
+2023-05-18,Groceries,assets:savings,$-41.06,$2943.95
+");
+
+        let report = register_periodic_report(&journal, &query, Interval::Monthly, &RegisterOptions::default()).unwrap();
+        assert_eq!(to_csv(&report[..].styled(&journal.commodities)),
+"period,account,total,running_total,moving_average
+2023-03,assets:savings,$2985.01,$2985.01,
+2023-05,assets:savings,$-41.06,$2943.95,
+");
+    }
+
+    #[test]
+    fn test_register_report_format() {
+        let journal = sample_journal();
+        let query   = "assets:savings".parse().unwrap();
+        let report: Vec<ReportLine> = register_report(&journal, &query, &RegisterOptions::default()).map(Result::unwrap).collect();
+        let format  = LineFormat::from_str("%(date) | %-16(payee) | %9(amount)").unwrap();
+
+        let lines: Vec<String> = report.iter().map(|line| line.render(&format, &journal.commodities)).collect();
+        assert_eq!(lines, vec![
+            "2023/03/01 | opening balances |  $1000.00",
+            "2023/03/07 | Payroll Deposit  |  $2000.00",
+            "2023/03/07 | Sandwich, Chili  |   $-14.99",
+            "2023/05/18 | Groceries        |   $-41.06",
+        ]);
+    }
+
+    #[test]
+    fn test_register_report_json() {
+        let journal = sample_journal();
+        let query   = "assets:savings".parse().unwrap();
+        let report: Vec<ReportLine> = register_report(&journal, &query, &RegisterOptions::default()).map(Result::unwrap).collect();
+
+        assert_eq!(to_json(&report[2..3]),
+r#"[{"date":"2023-03-07","description":"Sandwich, Chili","account":"assets:savings","amount":{"quantity":-14.99,"commodity":"$","decimals":2},"running_total":{"quantity":2985.01,"commodity":"$","decimals":2},"forecast":false}]
+"#);
+
+        let report = register_periodic_report(&journal, &query, Interval::Monthly, &RegisterOptions::default()).unwrap();
+        assert_eq!(to_json(&report[1..]),
+r#"[{"period":"2023-05","account":"assets:savings","total":{"quantity":-41.06,"commodity":"$","decimals":2},"running_total":{"quantity":2943.95,"commodity":"$","decimals":2},"moving_average":null}]
+"#);
+    }
+
+    #[test]
+    fn test_register_summary() {
+        let journal = sample_journal();
+        let query   = "assets:savings".parse().unwrap();
+        let summary = register_summary(&journal, &query, &RegisterOptions::default()).unwrap();
+
+        assert_eq!(summary.postings, 4);
+        assert_eq!(summary.styled(&journal.commodities).to_string().lines().skip(1).collect::<Vec<&str>>(), vec![
+            "4 postings",
+            "Total                                                                                $2943.95",
+            "Average                                                                               $735.99",
+        ]);
+    }
+
+    #[test]
+    fn test_register_summary_invert() {
+        let mut journal = sample_journal();
+        journal.restrict_to(&DateRange::new(parse_date("2023/05"), None));
+
+        let query   = "assets:savings".parse().unwrap();
+        let options = RegisterOptions { historical: true, invert: true, ..Default::default() };
+        let last    = register_report(&journal, &query, &options).map(Result::unwrap).last().unwrap();
+        let summary = register_summary(&journal, &query, &options).unwrap();
+
+        assert_eq!(last.running_total.to_string(), "$-2943.95");
+        assert_eq!(summary.styled(&journal.commodities).to_string().lines().skip(1).collect::<Vec<&str>>(), vec![
+            "1 posting",
+            "Total                                                                               $-2943.95",
+            "Average                                                                                $41.06",
+        ]);
+    }
+
+    #[test]
+    fn test_register_summary_related() {
+        let journal = sample_journal();
+        let query   = "assets:savings".parse().unwrap();
+        let options = RegisterOptions { related: true, ..Default::default() };
+        let last    = register_report(&journal, &query, &options).map(Result::unwrap).last().unwrap();
+        let summary = register_summary(&journal, &query, &options).unwrap();
+
+        assert_eq!(last.running_total.to_string(), "$-2943.95");
+        assert_eq!(summary.styled(&journal.commodities).to_string().lines().skip(1).collect::<Vec<&str>>(), vec![
+            "4 postings",
+            "Total                                                                               $-2943.95",
+            "Average                                                                              $-735.99",
+        ]);
+    }
+}
@@ -0,0 +1,130 @@
+use chrono::NaiveDate;
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::journal::Journal;
+use crate::types::{Units, monthyear::MonthYear};
+
+
+/* Stats */
+
+// a quick overview of what's in the journal
+pub struct Stats {
+    pub files       : Vec<String>,
+    pub transactions: usize,
+    pub postings    : usize,
+    pub first_date  : Option<NaiveDate>,
+    pub last_date   : Option<NaiveDate>,
+    pub accounts    : usize,
+    pub payees      : usize,
+    pub commodities : BTreeSet<Units>,
+    pub by_month    : Vec<(MonthYear, usize)>,
+}
+
+pub fn stats(journal: &Journal,
+             files  : &[&str]) -> Stats
+{
+    let summary = journal.summary();
+    let payees: BTreeSet<&String> = journal.transactions.iter().map(|t| &t.description).collect();
+
+    Stats {
+        files       : files.iter().map(|f| f.to_string()).collect(),
+        transactions: summary.transactions,
+        postings    : summary.postings,
+        first_date  : summary.first_date,
+        last_date   : summary.last_date,
+        accounts    : summary.accounts.len(),
+        payees      : payees.len(),
+        commodities : summary.commodities,
+        by_month    : summary.by_month,
+    }
+}
+
+// Files          : journal.txt
+// Transactions   : 5
+// Date span      : 2023-03-01 to 2023-03-07 (7 days)
+// Postings       : 13
+// Accounts       : 9
+// Payees         : 5
+// Commodities    : 1 ($)
+// Transactions by month:
+//   2023-03        5
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:<15}: {}", "Files", self.files.join(", "))?;
+        writeln!(f, "{:<15}: {}", "Transactions", self.transactions)?;
+
+        if let (Some(first), Some(last)) = (self.first_date, self.last_date) {
+            let days = (last - first).num_days() + 1;
+            writeln!(f, "{:<15}: {} to {} ({} day{})", "Date span", first, last, days, if days == 1 { "" } else { "s" })?;
+        }
+
+        writeln!(f, "{:<15}: {}", "Postings", self.postings)?;
+        writeln!(f, "{:<15}: {}", "Accounts", self.accounts)?;
+        writeln!(f, "{:<15}: {}", "Payees", self.payees)?;
+
+        let commodities: Vec<&str> = self.commodities.iter().map(|c| c.as_str()).collect();
+        writeln!(f, "{:<15}: {} ({})", "Commodities", commodities.len(), commodities.join(", "))?;
+
+        writeln!(f, "Transactions by month:")?;
+        for (month, count) in &self.by_month {
+            writeln!(f, "  {} {:>8}", month, count)?;
+        }
+
+        Ok(())
+    }
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use crate::journal::Journal;
+    use super::stats;
+
+    #[test]
+    fn test_stats() {
+        let journal =
+r#"
+2023/03/01 opening balances
+    assets:cash                  $50
+    equity:opening-balances
+
+2023/03/07 Hydro
+    usage:power                  308 kWh
+    usage:meter
+
+2023/05/07 Hydro
+    usage:power                  290 kWh
+    usage:meter
+"#;
+        let journal = Journal::from_lines(journal.lines()).unwrap();
+        let stats = stats(&journal, &["test.journal"]);
+
+        assert_eq!(stats.to_string(),
+"Files          : test.journal
+Transactions   : 3
+Date span      : 2023-03-01 to 2023-05-07 (68 days)
+Postings       : 6
+Accounts       : 4
+Payees         : 2
+Commodities    : 2 ($, kWh)
+Transactions by month:
+  2023-03        2
+  2023-04        0
+  2023-05        1
+");
+    }
+
+    #[test]
+    fn test_stats_empty_journal() {
+        let journal = Journal::default();
+        let stats = stats(&journal, &["empty.journal"]);
+
+        assert_eq!(stats.transactions, 0);
+        assert_eq!(stats.first_date, None);
+        assert!(stats.by_month.is_empty());
+    }
+}
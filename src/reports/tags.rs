@@ -0,0 +1,123 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::common::case_insensitive;
+use crate::journal::Journal;
+use crate::query::Query;
+use crate::types::commoditystyle::{Styled, WithStyles};
+use crate::types::mixedamount::MixedAmount;
+
+
+/* Tag totals */
+
+pub struct TagsOptions<'a> {
+    pub tag  : &'a str,   // the tag to group by, like "trip"
+    pub query: &'a Query, // the postings to sum, the ones to expenses by default
+}
+
+// the total of the postings in the transactions with one value of the tag
+pub struct TagLine {
+    pub value : String,
+    pub amount: MixedAmount,
+}
+
+// The totals of the postings matching the query, grouped by the value of the tag on
+// their transactions, so tagged costs like a vacation's can be added up without accounts
+// of their own. Transactions without the tag are left out. Sorted by the tag's value
+pub fn tags_report(journal: &Journal,
+                   options: &TagsOptions) -> Vec<TagLine>
+{
+    let expenses = Query::Acct(case_insensitive("^expenses").unwrap());
+    let query    = if options.query.is_empty() { &expenses } else { options.query };
+
+    let mut totals: BTreeMap<&str, MixedAmount> = BTreeMap::new();
+    for transaction in &journal.transactions {
+        for (_, value) in transaction.tags.iter().filter(|(name, _)| name == options.tag) {
+            let total = totals.entry(value).or_default();
+
+            for entry in query.postings([transaction]) {
+                total.add_amount(&entry.amount);
+            }
+        }
+    }
+
+    totals.into_iter()
+          .map(|(value, amount)| TagLine { value: value.to_string(), amount })
+          .collect()
+}
+
+//        $1820.45  japan
+//         $312.00  peru
+//          $45.00  (no value)
+
+impl WithStyles for TagLine {}
+
+impl fmt::Display for Styled<'_, TagLine> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let line  = self.value;
+        let value = if line.value.is_empty() { "(no value)" } else { &line.value };
+        write!(f, "{:>15}  {}", line.amount.styled(self.styles).to_string(), value)
+    }
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use crate::journal::Journal;
+    use crate::query::Query;
+    use crate::types::commoditystyle::{CommodityStyles, WithStyles};
+    use super::{TagsOptions, tags_report};
+
+    fn sample_journal() -> Journal {
+        let journal =
+r#"
+2023/01/17 Ramen  ; trip:japan
+    expenses:food:restaurant            $22
+    assets:cash
+
+2023/01/18 Shinkansen  ; trip:japan
+    expenses:travel                    $140
+    credit:visa
+
+2023/04/02 Ceviche  ; trip:peru, reimbursable:
+    expenses:food:restaurant            $18
+    assets:cash
+
+2023/04/03 Refund  ; trip:peru
+    assets:cash                         $18
+    income:reimbursements
+
+2023/05/01 Sub
+    expenses:food:subway                $12
+    assets:cash
+"#;
+        Journal::from_lines(journal.lines()).unwrap()
+    }
+
+    fn render(options: &TagsOptions) -> Vec<String> {
+        tags_report(&sample_journal(), options).iter()
+                                               .map(|line| line.styled(&CommodityStyles::default()).to_string().trim().to_string())
+                                               .collect()
+    }
+
+    #[test]
+    fn test_tags_report() {
+        let options = TagsOptions { tag: "trip", query: &Query::default() };
+        assert_eq!(render(&options), vec![
+            "$162.00  japan",
+            "$18.00  peru",
+        ]);
+
+        let assets  = "^assets".parse().unwrap();
+        let options = TagsOptions { tag: "trip", query: &assets };
+        assert_eq!(render(&options), vec![
+            "$-22.00  japan",
+            "$0.00  peru",
+        ]);
+
+        let options = TagsOptions { tag: "reimbursable", query: &Query::default() };
+        assert_eq!(render(&options), vec!["$18.00  (no value)"]);
+    }
+}
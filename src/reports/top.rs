@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::journal::Journal;
+use crate::query::Query;
+use crate::transaction::{Transaction, posting::Posting};
+use crate::types::commoditystyle::{Styled, WithStyles};
+use crate::types::{Units, amount::Amount};
+
+
+/* Top */
+
+pub struct TopOptions<'a> {
+    pub query : &'a Query, // the postings to count, like the ones to expenses
+    pub payees: bool,      // rank payees instead of accounts
+    pub limit : usize,     // how many to show
+}
+
+// one of the largest accounts or payees and its share of the total in its commodity
+pub struct TopLine {
+    pub name   : String,
+    pub amount : Amount,
+    pub percent: f64,
+}
+
+// The accounts, or payees, with the largest totals for the postings matching the query,
+// largest first, with the percentage of the total each one makes up
+pub fn top_report(journal: &Journal,
+                  options: &TopOptions) -> Vec<TopLine>
+{
+    let mut totals: HashMap<(&str, &Units), Amount> = HashMap::new();
+    for posting in journal.transactions_for(options.query).flat_map(Transaction::postings) {
+        if !options.query.matches(&posting) {
+            continue
+        }
+
+        let Posting { transaction, entry } = posting;
+
+        let name = if options.payees { transaction.description.trim() } else { entry.account.as_str() };
+        totals.entry((name, &entry.amount.units))
+              .and_modify(|total| *total += &entry.amount)
+              .or_insert_with(|| entry.amount.clone());
+    }
+
+    let mut grand_totals: HashMap<&Units, f64> = HashMap::new();
+    for ((_, units), total) in &totals {
+        *grand_totals.entry(units).or_default() += total.to_f64();
+    }
+
+    let mut lines: Vec<TopLine> =
+        totals.into_iter()
+              .map(|((name, units), amount)| TopLine {
+                  name   : name.to_string(),
+                  percent: percent_of(amount.to_f64(), grand_totals[units]),
+                  amount,
+              })
+              .collect();
+
+    lines.sort_by(|a, b| b.amount.to_f64().total_cmp(&a.amount.to_f64())
+                          .then_with(|| a.name.cmp(&b.name)));
+    lines.truncate(options.limit);
+    lines
+}
+
+fn percent_of(part: f64, whole: f64) -> f64 {
+    if whole == 0.0 { 0.0 } else { part / whole * 100.0 }
+}
+
+//      $410.25   43.2%  expenses:food:groceries
+//      $180.00   18.9%  expenses:utilities:hydro
+
+impl WithStyles for TopLine {}
+
+impl fmt::Display for Styled<'_, TopLine> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let line = self.value;
+        write!(f, "{:>12}  {:>5.1}%  {}", line.amount.styled(self.styles).to_string(), line.percent, line.name)
+    }
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use crate::journal::Journal;
+    use crate::types::commoditystyle::{CommodityStyles, WithStyles};
+    use super::{TopOptions, top_report};
+
+    fn sample_journal() -> Journal {
+        let journal =
+r#"
+2023/03/01 Loblaws
+    expenses:food:groceries            $60
+    assets:cash
+
+2023/03/02 Tim Hortons
+    expenses:food:restaurant           $10
+    assets:cash
+
+2023/03/05 Hydro
+    expenses:utilities                 $20
+    assets:cash
+
+2023/03/09 Loblaws
+    expenses:food:groceries            $10
+    assets:cash
+"#;
+        Journal::from_lines(journal.lines()).unwrap()
+    }
+
+    fn render(options: &TopOptions) -> Vec<String> {
+        top_report(&sample_journal(), options).iter()
+                                              .map(|line| line.styled(&CommodityStyles::default()).to_string())
+                                              .collect()
+    }
+
+    #[test]
+    fn test_top_report_accounts() {
+        let query   = "expenses".parse().unwrap();
+        let options = TopOptions { query: &query, payees: false, limit: 2 };
+        assert_eq!(render(&options), vec![
+            "      $70.00   70.0%  expenses:food:groceries",
+            "      $20.00   20.0%  expenses:utilities",
+        ]);
+    }
+
+    #[test]
+    fn test_top_report_payees() {
+        let query   = "^expenses:food".parse().unwrap();
+        let options = TopOptions { query: &query, payees: true, limit: 10 };
+        assert_eq!(render(&options), vec![
+            "      $70.00   87.5%  Loblaws",
+            "      $10.00   12.5%  Tim Hortons",
+        ]);
+    }
+}
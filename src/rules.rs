@@ -0,0 +1,333 @@
+use chrono::NaiveDate;
+use regex::{Regex, RegexBuilder};
+use std::collections::HashMap;
+
+use crate::common::{is_all_whitespace, today};
+use crate::transaction::Transaction;
+use crate::types::{Account, Units, amount::Amount};
+
+
+/* Rules */
+
+// a rules file describes how to turn the records of a bank's CSV export into journal
+// transactions. it is modeled after hledger's CSV rules:
+//
+//   fields date, description, amount
+//   date-format %Y-%m-%d
+//   currency $
+//   account1 assets:checking
+//   account2 expenses:unknown
+//
+//   if TIM HORTONS|STARBUCKS
+//       account2 expenses:food:coffee
+//
+//   if AMAZON
+//       account2 expenses:shopping
+//       description Amazon
+//
+// the top-level settings apply to every record and each `if` block overrides them for
+// records matching its (case-insensitive) pattern. the first matching block wins
+
+#[derive(Debug)]
+pub struct Rules {
+    pub fields     : Vec<String>,
+    pub date_format: String,
+    pub currency   : Units,
+    pub account1   : Account,
+    pub account2   : Account,
+    pub rules      : Vec<Rule>,
+}
+
+// one `if` block from the rules file
+#[derive(Debug)]
+pub struct Rule {
+    pub line       : usize,
+    pub pattern    : Regex,
+    pub account1   : Option<Account>,
+    pub account2   : Option<Account>,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum RulesError {
+    #[error("line {0}: unknown directive '{1}'")]
+    UnknownDirective(usize, String),
+    #[error("line {0}: '{1}' needs a value")]
+    MissingValue(usize, String),
+    #[error("line {0}: invalid pattern '{1}'")]
+    InvalidPattern(usize, String),
+    #[error("Couldn't parse the date '{0}'")]
+    BadDate(String),
+    #[error("Couldn't parse the amount '{0}'")]
+    BadAmount(String),
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            fields     : vec![],
+            date_format: "%Y-%m-%d".to_owned(),
+            currency   : "$".into(),
+            account1   : "assets:unknown".into(),
+            account2   : "expenses:unknown".into(),
+            rules      : vec![],
+        }
+    }
+}
+
+impl Rules {
+
+    pub fn from_lines(lines: std::str::Lines) -> std::result::Result<Rules, RulesError> {
+        let mut rules = Rules::default();
+
+        for (number, line) in lines.enumerate() {
+            let number = number + 1;
+
+            if is_all_whitespace(line) || line.trim_start().starts_with('#') {
+                continue
+            }
+
+            let indented = line.starts_with(char::is_whitespace);
+            let (directive, value) = split_directive(line.trim());
+
+            // "if TIM HORTONS|STARBUCKS" starts a new rule
+            if !indented && directive == "if" {
+                let pattern = RegexBuilder::new(value)
+                                  .case_insensitive(true)
+                                  .build()
+                                  .map_err(|_| RulesError::InvalidPattern(number, value.to_owned()))?;
+
+                rules.rules.push(Rule {
+                    line       : number,
+                    pattern,
+                    account1   : None,
+                    account2   : None,
+                    description: None,
+                });
+                continue
+            }
+
+            if value.is_empty() {
+                return Err(RulesError::MissingValue(number, directive.to_owned()))
+            }
+
+            // "    account2 expenses:food:coffee" belongs to the most recent rule
+            if indented {
+                let rule = rules.rules.last_mut()
+                                .ok_or_else(|| RulesError::UnknownDirective(number, line.trim().to_owned()))?;
+                match directive {
+                    "account1"    => rule.account1    = Some(value.into()),
+                    "account2"    => rule.account2    = Some(value.into()),
+                    "description" => rule.description = Some(value.to_owned()),
+                    _ => return Err(RulesError::UnknownDirective(number, directive.to_owned()))
+                }
+                continue
+            }
+
+            match directive {
+                "fields"      => rules.fields      = value.split(',').map(|f| f.trim().to_owned()).collect(),
+                "date-format" => rules.date_format = value.to_owned(),
+                "currency"    => rules.currency    = value.into(),
+                "account1"    => rules.account1    = value.into(),
+                "account2"    => rules.account2    = value.into(),
+                _ => return Err(RulesError::UnknownDirective(number, directive.to_owned()))
+            }
+        }
+
+        Ok(rules)
+    }
+
+    // the first rule whose pattern matches somewhere in the sample
+    pub fn find_match(&self, sample: &str) -> Option<&Rule> {
+        self.rules
+            .iter()
+            .find(|rule| rule.pattern.is_match(sample))
+    }
+
+    // run a sample through the rules and build the transaction an import would produce.
+    // the sample is a CSV record if it has as many fields as the `fields` directive names,
+    // otherwise it's taken as a bare description dated today with a zero amount
+    pub fn apply(&self, sample: &str) -> std::result::Result<(Option<&Rule>, Transaction), RulesError> {
+        let record = self.to_record(sample);
+        let rule   = self.find_match(sample);
+
+        let date = match record.get("date") {
+            Some(date) => NaiveDate::parse_from_str(date, &self.date_format)
+                              .map_err(|_| RulesError::BadDate(date.to_string()))?,
+            None       => today(),
+        };
+
+        let amount = match record.get("amount") {
+            Some(amount) => parse_amount(&self.currency, amount)?,
+            None         => Amount::from(self.currency, 0.0),
+        };
+
+        let description = rule.and_then(|r| r.description.clone())
+                              .or_else(|| record.get("description").map(|d| d.to_string()))
+                              .unwrap_or_else(|| sample.trim().to_owned());
+
+        let account1 = rule.and_then(|r| r.account1.clone()).unwrap_or_else(|| self.account1.clone());
+        let account2 = rule.and_then(|r| r.account2.clone()).unwrap_or_else(|| self.account2.clone());
+
+        let transaction = Transaction::builder().date(date)
+                                                .description(description)
+                                                .posting(account1, amount.clone())
+                                                .posting(account2, -amount)
+                                                .build()
+                                                .expect("A rule's two postings always balance");
+
+        Ok((rule, transaction))
+    }
+
+    fn to_record<'a>(&'a self, sample: &'a str) -> HashMap<&'a str, &'a str> {
+        let values = split_csv_line(sample);
+
+        if self.fields.is_empty() || values.len() != self.fields.len() {
+            return HashMap::from([("description", sample.trim())])
+        }
+
+        self.fields
+            .iter()
+            .map(|f| f.as_str())
+            .zip(values)
+            .collect()
+    }
+}
+
+// "account2 expenses:food" -> ("account2", "expenses:food")
+fn split_directive(line: &str) -> (&str, &str) {
+    match line.split_once(char::is_whitespace) {
+        Some((directive, value)) => (directive, value.trim()),
+        None                     => (line, "")
+    }
+}
+
+// split a CSV line on commas, respecting double-quoted fields. the quotes themselves are
+// left out of the returned slices, but doubled "" escapes inside a field are not undone
+fn split_csv_line(line: &str) -> Vec<&str> {
+    let mut fields = vec![];
+    let mut start  = 0;
+    let mut quoted = false;
+
+    for (i, c) in line.char_indices() {
+        match c {
+            '"'            => quoted = !quoted,
+            ',' if !quoted => {
+                fields.push(unquote(&line[start..i]));
+                start = i + 1;
+            },
+            _ => {}
+        }
+    }
+    fields.push(unquote(&line[start..]));
+    fields
+}
+
+fn unquote(field: &str) -> &str {
+    let field = field.trim();
+    field.strip_prefix('"')
+         .and_then(|f| f.strip_suffix('"'))
+         .unwrap_or(field)
+}
+
+// bank exports write amounts in all sorts of ways: "-4.50", "$-4.50", "1,234.56"
+fn parse_amount(currency: &Units, amount: &str) -> std::result::Result<Amount, RulesError> {
+    let cleaned: String = amount.chars()
+                                .filter(|c| c.is_ascii_digit() || *c == '.' || *c == '-')
+                                .collect();
+    Amount::parse(*currency, &cleaned).ok_or_else(|| RulesError::BadAmount(amount.to_owned()))
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use super::{Rules, RulesError, split_csv_line};
+
+    const RULES: &str =
+r#"
+# checking account export
+fields date, description, amount
+date-format %Y/%m/%d
+account1 assets:checking
+
+if TIM HORTONS|STARBUCKS
+    account2 expenses:food:coffee
+
+if amazon
+    account2 expenses:shopping
+    description Amazon
+"#;
+
+    #[test]
+    fn test_from_lines() {
+        let rules = Rules::from_lines(RULES.lines()).unwrap();
+        assert_eq!(rules.fields, vec!["date", "description", "amount"]);
+        assert_eq!(rules.account1, "assets:checking");
+        assert_eq!(rules.account2, "expenses:unknown");
+        assert_eq!(rules.rules.len(), 2);
+        assert_eq!(rules.rules[0].line, 7);
+        assert_eq!(rules.rules[1].description, Some("Amazon".to_owned()));
+    }
+
+    #[test]
+    fn test_from_lines_errors() {
+        assert_eq!(Rules::from_lines("acount1 assets:checking".lines()).unwrap_err(),
+                   RulesError::UnknownDirective(1, "acount1".to_owned()));
+        assert_eq!(Rules::from_lines("account1".lines()).unwrap_err(),
+                   RulesError::MissingValue(1, "account1".to_owned()));
+        assert_eq!(Rules::from_lines("if (unclosed".lines()).unwrap_err(),
+                   RulesError::InvalidPattern(1, "(unclosed".to_owned()));
+        assert_eq!(Rules::from_lines("    account2 expenses:food".lines()).unwrap_err(),
+                   RulesError::UnknownDirective(1, "account2 expenses:food".to_owned()));
+    }
+
+    #[test]
+    fn test_apply_csv_record() {
+        let rules = Rules::from_lines(RULES.lines()).unwrap();
+        let (rule, transaction) = rules.apply("2023/03/15,\"AMAZON.CA, ORDER 123\",\"-1,234.56\"").unwrap();
+
+        assert_eq!(rule.unwrap().line, 10);
+        assert_eq!(transaction.date, NaiveDate::from_ymd_opt(2023, 3, 15).unwrap());
+        assert_eq!(transaction.description, "Amazon");
+        assert_eq!(transaction.to_string(),
+                   "2023-03-15 Amazon\n    assets:checking    $-1234.56\n    expenses:shopping    $1234.56\n");
+    }
+
+    #[test]
+    fn test_apply_description_only() {
+        let rules = Rules::from_lines(RULES.lines()).unwrap();
+        let (rule, transaction) = rules.apply("Starbucks #4411").unwrap();
+
+        assert_eq!(rule.unwrap().line, 7);
+        assert_eq!(transaction.description, "Starbucks #4411");
+        assert_eq!(transaction.entries[1].account, "expenses:food:coffee");
+        assert!(transaction.entries[1].amount.is_zero());
+    }
+
+    #[test]
+    fn test_apply_no_match() {
+        let rules = Rules::from_lines(RULES.lines()).unwrap();
+        let (rule, transaction) = rules.apply("2023/03/16,HYDRO ONE,-80.00").unwrap();
+
+        assert!(rule.is_none());
+        assert_eq!(transaction.description, "HYDRO ONE");
+        assert_eq!(transaction.entries[1].account, "expenses:unknown");
+    }
+
+    #[test]
+    fn test_apply_bad_date() {
+        let rules = Rules::from_lines(RULES.lines()).unwrap();
+        assert_eq!(rules.apply("15-03-2023,HYDRO ONE,-80.00").unwrap_err(),
+                   RulesError::BadDate("15-03-2023".to_owned()));
+    }
+
+    #[test]
+    fn test_split_csv_line() {
+        assert_eq!(split_csv_line("a,b,c"), vec!["a", "b", "c"]);
+        assert_eq!(split_csv_line("a, \"b,c\" ,d"), vec!["a", "b,c", "d"]);
+        assert_eq!(split_csv_line(""), vec![""]);
+    }
+}
@@ -1,40 +1,51 @@
+pub mod builder;
+pub mod posting;
+
 use lazy_static::lazy_static;
 use chrono::NaiveDate;
 use regex::Regex;
-use std::collections::HashMap;
+use smallvec::SmallVec;
 use std::fmt::{Display, Formatter, Result};
 
-use crate::types::{Account, Units};
-use crate::types::amount::{Amount, AmountType};
+use crate::types::{Account, mixedamount::MixedAmount};
+use crate::types::amount::{Amount, AmountError};
 
 
 /* Transaction */
 
 // a transaction is a collection of 2 or more entries whose total amount for each commodity is zero
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Transaction {
     pub date: NaiveDate,
     pub description: String,
-    pub entries: Vec<Entry>
+    pub entries: Entries,
+    pub tags: Vec<Tag>
 }
 
+// most transactions have two to four entries, which are kept inside the transaction
+// instead of in an allocation of their own. any more go on the heap like a Vec's
+pub type Entries = SmallVec<[Entry; 4]>;
+
+// a name and possibly empty value from a comment, "; trip:japan, reimbursable:"
+pub type Tag = (String, String);
+
+// the tag on transactions generated from periodic transactions by --forecast
+pub const FORECAST_TAG: &str = "forecast";
+
 impl Transaction {
 
-    // get the total for each commodity (the different units) in this transaction
-    pub fn totals(&self) -> HashMap<Units, Amount> {
-        let mut map: HashMap<Units, Amount> = HashMap::new();
+    // get the total for each commodity (the different units) in the transaction's postings
+    // of the kind, or an error if one is too large to hold
+    pub fn totals(&self, kind: PostingKind) -> std::result::Result<MixedAmount, AmountError> {
+        let mut totals = MixedAmount::default();
 
-        for entry in &self.entries {
-            if let Some(amount) = map.get_mut(&entry.amount.units) {
-                amount.add(&entry.amount);
-            } else {
-                map.insert(entry.amount.units.clone(),
-                           entry.amount.clone());
-            }
+        for entry in self.entries.iter().filter(|entry| entry.kind == kind) {
+            totals.checked_add_amount(&entry.weight())?;
         }
-        map
-    } 
+        Ok(totals)
+    }
 
     // start a (temporarily empty) transaction with this date and description
     pub fn parse_date_and_description(line: &str) -> Option<Transaction> {
@@ -46,9 +57,29 @@ impl Transaction {
         Some(Transaction {
             date,
             description,
-            entries: vec![],
+            entries: Entries::new(),
+            tags: vec![],
         })
     }
+
+    // whether this transaction has the tag, and if a value is given, with that value
+    pub fn has_tag(&self, name: &str, value: Option<&str>) -> bool {
+        self.tags.iter()
+                 .any(|(n, v)| n == name && value.is_none_or(|value| v == value))
+    }
+
+    // whether this transaction was forecast from a periodic transaction instead of recorded
+    pub fn is_forecast(&self) -> bool {
+        self.has_tag(FORECAST_TAG, None)
+    }
+}
+
+// the tags in a comment, which are words ending in a colon, each optionally followed by a
+// value running up to the next comma: "paid in cash, trip:japan, reimbursable:"
+pub fn parse_tags(comment: &str) -> Vec<Tag> {
+    TAG_REGEX.captures_iter(comment)
+             .map(|caps| (caps["name"].to_string(), caps["value"].trim().to_string()))
+             .collect()
 }
 
 impl Display for Transaction {
@@ -68,29 +99,80 @@ impl Display for Transaction {
 lazy_static! {
     static ref DATE_REGEX: Regex =
         Regex::new(r"^(?P<date>\d{4}/\d{2}/\d{2})\s+(?P<description>.+)$").unwrap();
+
+    static ref TAG_REGEX: Regex =
+        Regex::new(r"(?P<name>[^\s,:]+):(?P<value>[^,]*)").unwrap();
 }
 
 
 /* Entry */
 
-#[derive(Debug, PartialEq)]
+// an entry can say what its units changed hands for, which is what it counts for when the
+// transaction balances instead of the units themselves:
+//
+//     assets:brokerage              10 AAPL @ $150
+//     assets:checking                  $-1500
+//
+// the cost is kept as the total for all of the units, $1500 here, whether it was written
+// as a price for each with @, a total with @@ or a lot price in braces like {$150}. a
+// sale can give both, -5 AAPL {$150} @ $170, and then the cost is what it sold for and
+// the lot is what those units were bought for
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Entry {
     pub account: Account,
-    pub amount : Amount
+    pub amount : Amount,
+    pub cost   : Option<Amount>,
+    pub lot    : Option<Amount>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub kind   : PostingKind,
+}
+
+// a posting is to a real account unless its account is written in parentheses, like
+// (budget:food), for a virtual one that doesn't have to balance with the others, or in
+// brackets, [budget:food], for one that balances with the other bracketed postings.
+// reports take them in like any other posting, and --real leaves them out
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PostingKind {
+    #[default]
+    Real,
+    Virtual,
+    BalancedVirtual,
+}
+
+impl Entry {
+    // what the entry counts for in the transaction's totals, its cost with the sign of its
+    // amount, or else the amount itself
+    pub fn weight(&self) -> Amount {
+        match &self.cost {
+            Some(cost) if self.amount.is_negative() => -cost.clone(),
+            Some(cost)                              => cost.clone(),
+            None                                    => self.amount.clone(),
+        }
+    }
+
+    // the account the way the journal writes it, in parentheses or brackets if it's virtual
+    pub fn written_account(&self) -> String {
+        match self.kind {
+            PostingKind::Real            => self.account.to_string(),
+            PostingKind::Virtual         => format!("({})", self.account),
+            PostingKind::BalancedVirtual => format!("[{}]", self.account),
+        }
+    }
 }
 
 impl Display for Entry {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
-
-        // TODO: units
-        match self.amount.amount {
-            AmountType::Discrete(cents, _) => {
-                write!(f, "{}    ${:.2}", self.account, cents as f64 / 100.0)
-            }
-            AmountType::Float(amt) => {
-                write!(f, "{}    {:.3}", self.account, amt)
-            }
+        write!(f, "{}    {}", self.written_account(), self.amount)?;
+        if let Some(lot) = &self.lot {
+            write!(f, " {{{{{}}}}}", lot)?;
+        }
+        if let Some(cost) = &self.cost {
+            write!(f, " @@ {}", cost)?;
         }
+        Ok(())
     }
 }
 
@@ -102,15 +184,16 @@ mod tests {
     use chrono::NaiveDate;
     use crate::types::amount::{Amount, AmountType};
 
-    use super::{Entry, Transaction};
+    use super::{Entries, Entry, PostingKind, Transaction, parse_tags};
 
     #[test]
     fn test_parse_transaction_from_date_and_description() {
         let expected = 
             Some(Transaction {
-                date: NaiveDate::from_ymd_opt(2023, 03, 11).unwrap(),
+                date: NaiveDate::from_ymd_opt(2023, 3, 11).unwrap(),
                 description: "Meatball Sub".to_owned(),
-                entries: vec![]
+                entries: Entries::new(),
+                tags: vec![]
             });
 
         assert_eq!(Transaction::parse_date_and_description("2023/03/11 Meatball Sub"), expected);
@@ -122,11 +205,14 @@ mod tests {
 
     fn create_entry(account: &str, cents: i64) -> Entry {
         Entry {
-            account: account.to_string(),
+            account: account.into(),
             amount: Amount {
                 amount: AmountType::Discrete(cents, 2),
-                units: "$".to_owned()
-            }
+                units: "$".into()
+            },
+            cost: None,
+            lot : None,
+            kind: PostingKind::Real,
         }
     }
 
@@ -144,6 +230,36 @@ mod tests {
         assert_eq!(formatted, "account2    $-56.78");
     }
 
+    #[test]
+    fn test_entry_weight() {
+        let sold = Entry { account: "assets:brokerage".into(), amount: Amount::from("AAPL", -5.0), cost: Some(Amount::from("$", 850.0)), lot: Some(Amount::from("$", 750.0)), kind: PostingKind::Real };
+        assert_eq!(sold.weight(), Amount::from("$", -850.0));
+        assert_eq!(sold.to_string(), "assets:brokerage    -5.000 AAPL {{$750.00}} @@ $850.00");
+        assert_eq!(create_entry("account1", 1234).weight(), Amount::from("$", 12.34));
+    }
+
+    #[test]
+    fn test_transaction_totals() {
+        let posting = |account: &str, amount, kind| Entry { account: account.into(), amount, cost: None, lot: None, kind };
+        let transaction = Transaction {
+            date       : NaiveDate::from_ymd_opt(2023, 3, 1).unwrap(),
+            description: "Hydro".to_owned(),
+            entries    : Entries::from_vec(vec![
+                posting("expenses:hydro", Amount::from("$", 61.2), PostingKind::Real),
+                posting("expenses:hydro", Amount::from("kWh", 308.0), PostingKind::Real),
+                posting("assets:chequing", Amount::from("$", -61.2), PostingKind::Real),
+                posting("budget:hydro", Amount::from("$", -61.2), PostingKind::BalancedVirtual),
+            ]),
+            ..Default::default()
+        };
+
+        // each commodity totals separately, and the dollars balance out
+        let totals = transaction.totals(PostingKind::Real).unwrap();
+        assert_eq!(totals.to_string(), "$0.00, 308.000 kWh");
+        assert_eq!(totals.nonzero().collect::<Vec<_>>(), vec![&Amount::from("kWh", 308.0)]);
+        assert_eq!(transaction.totals(PostingKind::BalancedVirtual).unwrap().to_string(), "$-61.20");
+    }
+
     #[test]
     fn test_fmt_display_zero_cents() {
         let entry = create_entry("account3", 0);
@@ -164,4 +280,16 @@ mod tests {
         let formatted = format!("{}", entry);
         assert_eq!(formatted, "account5    $1.00");
     }
+
+    #[test]
+    fn test_parse_tags() {
+        assert_eq!(parse_tags(" paid in cash, trip:japan, reimbursable:"), vec![
+            ("trip".to_string(), "japan".to_string()),
+            ("reimbursable".to_string(), "".to_string()),
+        ]);
+        assert_eq!(parse_tags(" category: dining out "), vec![
+            ("category".to_string(), "dining out".to_string()),
+        ]);
+        assert!(parse_tags(" no tags here").is_empty());
+    }
 }
@@ -0,0 +1,137 @@
+//! Transaction builder
+
+use chrono::NaiveDate;
+
+use crate::journal::{ParseJournalError, balance_transaction};
+use crate::transaction::{Entries, Entry, PostingKind, Tag, Transaction};
+use crate::types::{Account, amount::Amount, commoditystyle::CommodityStyles};
+
+
+/* TransactionBuilder */
+
+// a transaction put together in code instead of parsed from a journal, for importers and
+// tests. build() checks it balances the same way the parser does, and refuses one that
+// doesn't
+//
+//   Transaction::builder().date(date)
+//                         .description("Groceries")
+//                         .posting("expenses:food",  Amount::from("$".to_string(), 41.06))
+//                         .posting("assets:savings", Amount::from("$".to_string(), -41.06))
+//                         .build()
+#[derive(Debug, Default)]
+pub struct TransactionBuilder {
+    date       : Option<NaiveDate>,
+    description: String,
+    entries    : Entries,
+    tags       : Vec<Tag>,
+}
+
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum BuildError {
+    #[error("A transaction needs a date")]
+    MissingDate,
+    #[error(transparent)]
+    Unbalanced(ParseJournalError),
+}
+
+impl Transaction {
+    pub fn builder() -> TransactionBuilder {
+        TransactionBuilder::default()
+    }
+}
+
+impl TransactionBuilder {
+    pub fn date(mut self, date: NaiveDate) -> Self {
+        self.date = Some(date);
+        self
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = description.into();
+        self
+    }
+
+    pub fn posting(mut self, account: impl Into<Account>, amount: Amount) -> Self {
+        self.entries.push(Entry { account: account.into(), amount, cost: None, lot: None, kind: PostingKind::Real });
+        self
+    }
+
+    // a posting to a virtual account in brackets, which balances with the other bracketed ones
+    pub fn balanced_virtual_posting(mut self, account: impl Into<Account>, amount: Amount) -> Self {
+        self.entries.push(Entry { account: account.into(), amount, cost: None, lot: None, kind: PostingKind::BalancedVirtual });
+        self
+    }
+
+    // a posting copied from another transaction, keeping its cost
+    pub fn entry(mut self, entry: Entry) -> Self {
+        self.entries.push(entry);
+        self
+    }
+
+    pub fn tag(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.tags.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn build(self) -> std::result::Result<Transaction, BuildError> {
+        let date = self.date.ok_or(BuildError::MissingDate)?;
+        let mut transaction = Transaction {
+            date,
+            description: self.description,
+            entries    : self.entries,
+            tags       : self.tags,
+        };
+
+        balance_transaction(&mut None, &mut transaction, &CommodityStyles::default()).map_err(BuildError::Unbalanced)?;
+        Ok(transaction)
+    }
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use crate::journal::{Journal, ParseJournalError};
+    use crate::transaction::Transaction;
+    use crate::types::amount::Amount;
+    use super::BuildError;
+
+    fn dollars(amount: f64) -> Amount {
+        Amount::from("$".to_string(), amount)
+    }
+
+    fn date() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2023, 3, 18).unwrap()
+    }
+
+    #[test]
+    fn test_builder_matches_parser() {
+        let built = Transaction::builder().date(date())
+                                          .description("Groceries")
+                                          .posting("expenses:food", dollars(41.06))
+                                          .posting("assets:savings", dollars(-41.06))
+                                          .build()
+                                          .unwrap();
+
+        let journal = "2023/03/18 Groceries\n    expenses:food  $41.06\n    assets:savings  $-41.06";
+        let parsed  = Journal::from_lines(journal.lines()).unwrap();
+
+        assert_eq!(built, parsed.transactions[0]);
+
+        let tagged = Transaction::builder().date(date()).tag("trip", "japan").build().unwrap();
+        assert!(tagged.has_tag("trip", Some("japan")));
+    }
+
+    #[test]
+    fn test_builder_errors() {
+        let unbalanced = Transaction::builder().date(date())
+                                               .posting("expenses:food", dollars(41.06))
+                                               .posting("assets:savings", dollars(-41.00))
+                                               .build();
+        assert!(matches!(unbalanced, Err(BuildError::Unbalanced(ParseJournalError::UnbalancedTransaction(_)))));
+
+        assert_eq!(Transaction::builder().description("Groceries").build(), Err(BuildError::MissingDate));
+    }
+}
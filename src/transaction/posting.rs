@@ -0,0 +1,70 @@
+//! Posting
+
+use chrono::NaiveDate;
+
+use crate::transaction::{Entry, Transaction};
+use crate::types::{Account, amount::Amount};
+
+
+/* Posting */
+
+// one entry of a transaction along with the transaction it's in, so a report going
+// through postings one at a time can still get at their date, description and tags
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Posting<'a> {
+    pub transaction: &'a Transaction,
+    pub entry      : &'a Entry,
+}
+
+impl Transaction {
+    pub fn postings(&self) -> impl Iterator<Item = Posting<'_>> {
+        self.entries.iter().map(move |entry| Posting { transaction: self, entry })
+    }
+}
+
+impl<'a> Posting<'a> {
+    pub fn date(&self) -> NaiveDate {
+        self.transaction.date
+    }
+
+    pub fn description(&self) -> &'a str {
+        &self.transaction.description
+    }
+
+    pub fn account(&self) -> &'a Account {
+        &self.entry.account
+    }
+
+    pub fn amount(&self) -> &'a Amount {
+        &self.entry.amount
+    }
+
+    // whether the other posting is from the very same transaction, not just an equal one
+    pub fn same_transaction(&self, other: &Posting) -> bool {
+        std::ptr::eq(self.transaction, other.transaction)
+    }
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use crate::journal::Journal;
+
+    #[test]
+    fn test_postings_reach_their_transaction() {
+        let journal = "2023/03/18 Groceries  ; trip:japan\n    expenses:food  $41.06\n    assets:savings\n\
+                       2023/03/18 Groceries\n    expenses:food  $41.06\n    assets:savings";
+        let journal = Journal::from_lines(journal.lines()).unwrap();
+
+        let postings: Vec<_> = journal.transactions.iter().flat_map(|t| t.postings()).collect();
+        assert_eq!(postings.len(), 4);
+        assert_eq!(postings[1].account(), "assets:savings");
+        assert_eq!(postings[1].amount().to_string(), "$-41.06");
+        assert!(postings[1].transaction.has_tag("trip", Some("japan")));
+
+        assert!(postings[0].same_transaction(&postings[1]));
+        assert!(!postings[1].same_transaction(&postings[2]));
+    }
+}
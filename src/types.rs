@@ -1,9 +1,138 @@
 pub mod monthyear;
 pub mod amount;
+pub mod amountfilter;
+pub mod commoditystyle;
+pub mod mixedamount;
+pub mod numberformat;
+pub mod daterange;
+pub mod interval;
+pub mod lineformat;
+pub mod period;
+pub mod periodexpr;
+pub mod sortkey;
+pub mod symbol;
+
+use symbol::Symbol;
 
 
 /* Account */
 
-// for now accounts and units are represented as a string
-pub type Account = String;
-pub type Units = String;
+// an account name like expenses:food:subway, whose colon-separated components make up a
+// hierarchy where expenses:food is the parent of expenses:food:subway. it reads as its
+// name as a str, so it can be matched and compared the same way
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(transparent))]
+pub struct Account(Symbol);
+
+// a commodity's symbol, like $ or kWh
+pub type Units = Symbol;
+
+impl Account {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    // expenses, food, subway
+    pub fn components(&self) -> std::str::Split<'_, char> {
+        self.0.split(':')
+    }
+
+    // expenses:food for expenses:food:subway, none for a top-level account
+    pub fn parent(&self) -> Option<&str> {
+        self.0.rsplit_once(':').map(|(parent, _)| parent)
+    }
+
+    // the number of components, expenses:food:subway has a depth of 3
+    pub fn depth(&self) -> usize {
+        self.components().count()
+    }
+
+    // the first `depth` components of the name, or all of them if it isn't that deep
+    pub fn ancestor(&self, depth: usize) -> &str {
+        match self.0.match_indices(':').nth(depth.max(1) - 1) {
+            Some((i, _)) => &self.0[..i],
+            None         => &self.0,
+        }
+    }
+
+    // the last component, subway for expenses:food:subway
+    pub fn name(&self) -> &str {
+        self.components().next_back().unwrap_or_default()
+    }
+
+    // whether this account is under the other one at any depth, which expenses:foodstuff
+    // isn't for expenses:food
+    pub fn is_descendant_of(&self, ancestor: &str) -> bool {
+        self.0.strip_prefix(ancestor).is_some_and(|rest| rest.starts_with(':'))
+    }
+}
+
+impl std::ops::Deref for Account {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for Account {
+    fn from(name: &str) -> Self {
+        Account(Symbol::from(name))
+    }
+}
+
+impl From<String> for Account {
+    fn from(name: String) -> Self {
+        Account(Symbol::from(name))
+    }
+}
+
+impl From<Symbol> for Account {
+    fn from(name: Symbol) -> Self {
+        Account(name)
+    }
+}
+
+impl PartialEq<str> for Account {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for Account {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+impl std::fmt::Display for Account {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use super::Account;
+
+    #[test]
+    fn test_account_hierarchy() {
+        let subway = Account::from("expenses:food:subway");
+        assert_eq!(subway.components().collect::<Vec<_>>(), vec!["expenses", "food", "subway"]);
+        assert_eq!(subway.parent(), Some("expenses:food"));
+        assert_eq!(subway.depth(), 3);
+        assert_eq!(subway.ancestor(2), "expenses:food");
+        assert_eq!(subway.ancestor(3), "expenses:food:subway");
+        assert_eq!(subway.ancestor(5), "expenses:food:subway");
+        assert_eq!(subway.ancestor(0), "expenses");
+        assert_eq!(subway.name(), "subway");
+
+        assert!(subway.is_descendant_of("expenses"));
+        assert!(!subway.is_descendant_of("expenses:food:subway"));
+        assert!(!Account::from("expenses:foodstuff").is_descendant_of("expenses:food"));
+        assert_eq!(Account::from("assets").parent(), None);
+    }
+}
@@ -1,31 +1,41 @@
+use rust_decimal::{Decimal, RoundingStrategy, prelude::ToPrimitive};
 use std::fmt;
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub};
+use std::str::FromStr;
 use super::Units;
+use super::commoditystyle::{CommodityStyle, Styled, WithStyles};
 
 // a generic amount of something
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum AmountType {
     // an integer number of smallest divisible units of the commodity
     // and a number of decimal places after the unit place value
     // so for $10.25: Discrete(1025, 2)
     Discrete(i64, usize),
 
-    Float(f64),
+    // an exact decimal number for commodities without a fixed number of decimal
+    // places, like 308.5 kWh, so adding them up doesn't drift the way floats do
+    Decimal(Decimal),
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Amount {
     pub units: Units,
     pub amount: AmountType
 }
     
 impl Amount {
-    pub fn from(units: String, amount: f64) -> Self {
+    pub fn from(units: impl Into<Units>, amount: f64) -> Self {
+        let units = units.into();
 
         // special case for the $ sign which we know divides into 100 cents
         let amount = if units == "$" {
             AmountType::Discrete((amount * 100.0).round() as i64, 2)
         } else {
-            AmountType::Float(amount)
+            // the shortest decimal that reads back as the same float, so 0.1 is exactly 0.1
+            AmountType::Decimal(Decimal::from_str(&amount.to_string()).unwrap_or_default())
         };
 
         Amount {
@@ -34,54 +44,255 @@ impl Amount {
         }
     }
 
+    // an amount from its quantity as written in the journal, like "10.25" or "-1.5e3",
+    // read exactly instead of going through a float
+    pub fn parse(units: impl Into<Units>, quantity: &str) -> Option<Self> {
+        let quantity = Decimal::from_str(quantity).or_else(|_| Decimal::from_scientific(quantity)).ok()?;
+        Amount::from_decimal(units, quantity)
+    }
+
+    // an amount from an exact quantity, or None if it's too large for the $ sign's cents
+    pub fn from_decimal(units: impl Into<Units>, quantity: Decimal) -> Option<Self> {
+        let units = units.into();
+        let amount = if units == "$" {
+            let cents = quantity.checked_mul(Decimal::ONE_HUNDRED)?
+                                .round_dp_with_strategy(0, RoundingStrategy::MidpointAwayFromZero);
+            AmountType::Discrete(cents.to_i64()?, 2)
+        } else {
+            AmountType::Decimal(quantity)
+        };
+
+        Some(Amount {
+            units,
+            amount,
+        })
+    }
+
     pub fn is_zero(&self) -> bool {
         match self.amount {
             AmountType::Discrete(amt, _) => amt == 0,
-            AmountType::Float(amt) => amt == 0.0,
+            AmountType::Decimal(amt) => amt.is_zero(),
+        }
+    }
+
+    pub fn is_negative(&self) -> bool {
+        match self.amount {
+            AmountType::Discrete(amt, _) => amt < 0,
+            AmountType::Decimal(amt)     => amt < Decimal::ZERO,
         }
     }
 
-    pub fn negate(self) -> Amount {
+    // the exact quantity as a decimal number without the units, like 10.25 or -308.5
+    pub fn quantity(&self) -> String {
+        match self.amount {
+            AmountType::Discrete(amt, dec) => {
+                let digits = format!("{:0>width$}", amt.unsigned_abs(), width = dec + 1);
+                let (whole, fraction) = digits.split_at(digits.len() - dec);
+                let sign = if amt < 0 { "-" } else { "" };
+
+                if dec == 0 { format!("{}{}", sign, whole) } else { format!("{}{}.{}", sign, whole, fraction) }
+            },
+            AmountType::Decimal(amt) => amt.to_string(),
+        }
+    }
+
+    // the exact quantity as a decimal number, for formatting
+    pub fn decimal(&self) -> Decimal {
+        match self.amount {
+            AmountType::Discrete(amt, dec) => Decimal::new(amt, dec as u32),
+            AmountType::Decimal(amt)       => amt,
+        }
+    }
+
+    // the amount as a plain number, for things like percentages that don't need to be exact
+    pub fn to_f64(&self) -> f64 {
+        match self.amount {
+            AmountType::Discrete(amt, dec) => amt as f64 / 10f64.powi(dec as i32),
+            AmountType::Decimal(amt)       => amt.to_f64().unwrap_or_default(),
+        }
+    }
+
+    // the sum of two amounts, or why they can't be added
+    pub fn checked_add(&self, other: &Amount) -> Result<Amount, AmountError> {
+        Ok(Amount {
+            units : self.units,
+            amount: self.add_quantity(other)?,
+        })
+    }
+
+    // the amount with its sign flipped, or an error for the one discrete amount whose
+    // opposite is too large to hold
+    pub fn checked_neg(&self) -> Result<Amount, AmountError> {
         let negated = match self.amount {
-            AmountType::Discrete(amt, dec) => AmountType::Discrete(-amt, dec),
-            AmountType::Float(amt)         => AmountType::Float(-amt),
+            AmountType::Discrete(amt, dec) => AmountType::Discrete(amt.checked_neg().ok_or(AmountError::Overflow)?, dec),
+            AmountType::Decimal(amt)       => AmountType::Decimal(-amt),
         };
-        Amount {
-            units: self.units,
-            amount: negated,
+        Ok(Amount { units: self.units, amount: negated })
+    }
+
+    // this amount times a whole number, like a monthly budget over a year
+    pub fn checked_mul(&self, factor: i64) -> Result<Amount, AmountError> {
+        let multiplied = match self.amount {
+            AmountType::Discrete(amt, dec) => AmountType::Discrete(amt.checked_mul(factor).ok_or(AmountError::Overflow)?, dec),
+            AmountType::Decimal(amt)       => AmountType::Decimal(amt.checked_mul(Decimal::from(factor)).ok_or(AmountError::Overflow)?),
+        };
+        Ok(Amount { units: self.units, amount: multiplied })
+    }
+
+    // this amount divided evenly, with a discrete amount rounded half away from zero to its
+    // smallest unit. the rounding is done in integers, so large amounts stay exact
+    pub fn checked_div(&self, divisor: i64) -> Result<Amount, AmountError> {
+        if divisor == 0 {
+            return Err(AmountError::DivideByZero)
         }
+
+        let divided = match self.amount {
+            AmountType::Discrete(amt, dec) => {
+                let (amt, divisor) = (amt as i128, divisor as i128);
+                let mut quotient   = amt / divisor;
+                if 2 * (amt % divisor).abs() >= divisor.abs() {
+                    quotient += amt.signum() * divisor.signum();
+                }
+                AmountType::Discrete(i64::try_from(quotient).map_err(|_| AmountError::Overflow)?, dec)
+            },
+            AmountType::Decimal(amt) => AmountType::Decimal(amt.checked_div(Decimal::from(divisor)).ok_or(AmountError::Overflow)?),
+        };
+        Ok(Amount { units: self.units, amount: divided })
+    }
+
+    // this amount times a number, like the share of a lot's cost that goes with the part
+    // of it sold. a discrete amount is rounded to its smallest unit
+    pub fn checked_scale(&self, factor: Decimal) -> Result<Amount, AmountError> {
+        let scaled = self.decimal().checked_mul(factor).ok_or(AmountError::Overflow)?;
+        let amount = match self.amount {
+            AmountType::Discrete(_, dec) => {
+                let smallest = scaled.round_dp_with_strategy(dec as u32, RoundingStrategy::MidpointAwayFromZero);
+                let units    = smallest.checked_mul(Decimal::from(10i64.pow(dec as u32))).and_then(|units| units.to_i64());
+                AmountType::Discrete(units.ok_or(AmountError::Overflow)?, dec)
+            },
+            AmountType::Decimal(_) => AmountType::Decimal(scaled),
+        };
+        Ok(Amount { units: self.units, amount })
     }
 
-    pub fn add(&mut self, other: &Self) {
+    fn add_quantity(&self, other: &Amount) -> Result<AmountType, AmountError> {
         if self.units != other.units {
-            panic!("Cannot add two amounts with different units")
+            return Err(AmountError::DifferentUnits)
         }
 
         match (&self.amount, &other.amount) {
             (AmountType::Discrete(l, d1), AmountType::Discrete(r, d2)) => {
-                if d1 != d2 {
-                    unimplemented!("Cannot add two discrete amounts with different decimal places")
-                }
-                self.amount = AmountType::Discrete(l+r, *d1);
-            },
-            (AmountType::Float(l), AmountType::Float(r)) => {
-                self.amount = AmountType::Float(l+r);
-            },
-            (AmountType::Discrete(_, _), AmountType::Float(_)) =>
-                panic!("Cannot add a discrete amount to a float amount"),
-            (AmountType::Float(_), AmountType::Discrete(_, _)) =>
-                panic!("Cannot add a float amount to a discrete amount")
+                let places = *d1.max(d2);
+                rescale(*l, *d1, places).zip(rescale(*r, *d2, places))
+                                        .and_then(|(l, r)| l.checked_add(r))
+                                        .map(|sum| AmountType::Discrete(sum, places))
+                                        .ok_or(AmountError::Overflow)
+            }
+            (AmountType::Decimal(l), AmountType::Decimal(r)) => {
+                l.checked_add(*r).map(AmountType::Decimal).ok_or(AmountError::Overflow)
+            }
+            _ => Err(AmountError::DiscreteAndDecimal),
         }
     }
 }
 
+// a discrete amount with more decimal places, so $1.5 is 150 at two places, or None if
+// it's too large to have them
+fn rescale(amount: i64, places: usize, to: usize) -> Option<i64> {
+    10i64.checked_pow((to - places) as u32)?.checked_mul(amount)
+}
+
+// why the arithmetic on amounts couldn't be done
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub enum AmountError {
+    #[error("Cannot add two amounts with different units")]
+    DifferentUnits,
+    #[error("Cannot add a discrete amount to a decimal amount")]
+    DiscreteAndDecimal,
+    #[error("Amounts add up to more than an amount can hold")]
+    Overflow,
+    #[error("Cannot divide an amount by zero")]
+    DivideByZero,
+}
+
+
+/* Arithmetic */
+
+// amounts add up like numbers as long as they're in the same units. adding different
+// units panics, so sums that might mix them should use checked_add or a MixedAmount.
+// so does a result too large for an amount to hold, instead of wrapping around to a wrong
+// one, and dividing by zero. arithmetic on amounts from a journal should use the checked
+// methods, so a journal that reads fine can't stop a report with a panic
+
+impl AddAssign<&Amount> for Amount {
+    fn add_assign(&mut self, other: &Amount) {
+        self.amount = self.add_quantity(other).unwrap_or_else(|error| panic!("{}", error));
+    }
+}
+
+impl Add for Amount {
+    type Output = Amount;
+
+    fn add(self, other: Amount) -> Amount {
+        self.checked_add(&other).unwrap_or_else(|error| panic!("{}", error))
+    }
+}
+
+impl Sub for Amount {
+    type Output = Amount;
+
+    fn sub(self, other: Amount) -> Amount {
+        self + -other
+    }
+}
+
+impl Neg for Amount {
+    type Output = Amount;
+
+    fn neg(self) -> Amount {
+        self.checked_neg().unwrap_or_else(|error| panic!("{}", error))
+    }
+}
+
+impl Mul<i64> for Amount {
+    type Output = Amount;
+
+    fn mul(self, factor: i64) -> Amount {
+        self.checked_mul(factor).unwrap_or_else(|error| panic!("{}", error))
+    }
+}
+
+// divide the amount evenly, rounding discrete amounts to the nearest smallest unit
+impl Div<i64> for Amount {
+    type Output = Amount;
+
+    fn div(self, divisor: i64) -> Amount {
+        self.checked_div(divisor).unwrap_or_else(|error| panic!("{}", error))
+    }
+}
+
+// the decimal rounded half away from zero to the number of places and padded out to them,
+// the way floats are formatted, where the decimal's own formatting would cut off the rest
+pub fn rounded(amount: Decimal, places: u32) -> String {
+    format!("{:.*}", places as usize, amount.round_dp_with_strategy(places, RoundingStrategy::MidpointAwayFromZero))
+}
+
+impl WithStyles for Amount {}
+
+// $-22.00  308.500 kWh  98.2%, in the commodity's default style for messages and tests
+
 impl fmt::Display for Amount {
-     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        // TODO: units
-        match self.amount {
-            AmountType::Discrete(amt, _) => write!(f, "${:.2}", amt as f64 / 100.0),
-            AmountType::Float(amt)       => write!(f, "{:.3}", amt as f64),
-        }
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", CommodityStyle::default_for(&self.units).format(&self.units, self.decimal()))
+    }
+}
+
+// $1,234.50  308.5 kWh, however the journal styles the commodity
+
+impl fmt::Display for Styled<'_, Amount> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let amount = self.value;
+        write!(f, "{}", self.styles.style(&amount.units).format(&amount.units, amount.decimal()))
     }
 }
 
@@ -94,17 +305,40 @@ mod tests {
     fn test_from() {
         let amount1 = Amount::from("$".to_string(), 10.25);
         assert_eq!(amount1, Amount {
-            units: "$".to_string(),
+            units: "$".into(),
             amount: AmountType::Discrete(1025, 2),
         });
 
         let amount2 = Amount::from("kg".to_string(), 2.5);
         assert_eq!(amount2, Amount {
-            units: "kg".to_string(),
-            amount: AmountType::Float(2.5),
+            units: "kg".into(),
+            amount: AmountType::Decimal(Decimal::new(25, 1)),
         });
     }
 
+    #[test]
+    fn test_parse() {
+        assert_eq!(Amount::parse("$".to_string(), "10.25"), Some(Amount::from("$".to_string(), 10.25)));
+        assert_eq!(Amount::parse("$".to_string(), "-1.005"), Some(Amount::from("$".to_string(), -1.01)));
+        assert_eq!(Amount::parse("kWh".to_string(), "308.5").unwrap().quantity(), "308.5");
+        assert_eq!(Amount::parse("kWh".to_string(), "+.5").unwrap().quantity(), "0.5");
+        assert_eq!(Amount::parse("kWh".to_string(), "1.5e3").unwrap(), Amount::from("kWh".to_string(), 1500.0));
+        assert_eq!(Amount::parse("kWh".to_string(), "1.2.3"), None);
+    }
+
+    #[test]
+    fn test_decimal_sums_are_exact() {
+        let tenth = Amount::parse("kWh".to_string(), "0.1").unwrap();
+        let sum   = tenth.clone() + Amount::parse("kWh".to_string(), "0.2").unwrap();
+        assert!((sum - Amount::parse("kWh".to_string(), "0.3").unwrap()).is_zero());
+
+        let mut total = Amount::from("kWh".to_string(), 0.0);
+        for _ in 0..1000 {
+            total += &tenth;
+        }
+        assert_eq!(total, Amount::from("kWh".to_string(), 100.0));
+    }
+
     #[test]
     fn test_is_zero() {
         let amount1 = Amount::from("$".to_string(), 0.0);
@@ -113,37 +347,134 @@ mod tests {
         assert!(amount2.is_zero());
     }
 
+    #[test]
+    fn test_quantity() {
+        assert_eq!(Amount::from("$".to_string(), 10.25).quantity(), "10.25");
+        assert_eq!(Amount::from("$".to_string(), -0.05).quantity(), "-0.05");
+        assert_eq!(Amount::from("$".to_string(), 0.0).quantity(), "0.00");
+        assert_eq!(Amount { units: "JPY".into(), amount: AmountType::Discrete(-1000, 0) }.quantity(), "-1000");
+        assert_eq!(Amount::from("kWh".to_string(), 308.5).quantity(), "308.5");
+    }
+
+    #[test]
+    fn test_to_f64() {
+        assert_eq!(Amount::from("$".to_string(), 10.25).to_f64(), 10.25);
+        assert_eq!(Amount::from("kg".to_string(), -2.5).to_f64(), -2.5);
+    }
+
     #[test]
     fn test_negate() {
         let amount1 = Amount::from("$".to_string(), 10.25);
         let amount2 = Amount::from("kg".to_string(), 2.5);
-        assert_eq!(amount1.negate(), Amount {
-            units: "$".to_string(),
+        assert_eq!(-amount1, Amount {
+            units: "$".into(),
             amount: AmountType::Discrete(-1025, 2),
         });
-        assert_eq!(amount2.negate(), Amount {
-            units: "kg".to_string(),
-            amount: AmountType::Float(-2.5),
+        assert_eq!(-amount2, Amount {
+            units: "kg".into(),
+            amount: AmountType::Decimal(Decimal::new(-25, 1)),
         });
     }
 
+    #[test]
+    fn test_divide() {
+        let amount1 = Amount::from("$".to_string(), 10.00);
+        assert_eq!(amount1.clone() / 3, Amount::from("$".to_string(), 3.33));
+        assert_eq!(-amount1 / 3, Amount::from("$".to_string(), -3.33));
+        assert_eq!(Amount::from("$".to_string(), 0.05) / 2, Amount::from("$".to_string(), 0.03));
+
+        let amount2 = Amount::from("kg".to_string(), 2.5);
+        assert_eq!(amount2 / 2, Amount::from("kg".to_string(), 1.25));
+    }
+
+    #[test]
+    fn test_multiply() {
+        assert_eq!(Amount::from("$".to_string(), 400.0) * 12, Amount::from("$".to_string(), 4800.0));
+        assert_eq!(Amount::from("kg".to_string(), 2.5) * -2, Amount::from("kg".to_string(), -5.0));
+    }
+
+    #[test]
+    fn test_checked_at_the_limits() {
+        let most  = Amount { units: "$".into(), amount: AmountType::Discrete(i64::MAX, 2) };
+        let least = Amount { units: "$".into(), amount: AmountType::Discrete(i64::MIN, 2) };
+
+        assert_eq!(most.checked_neg(), Ok(Amount { units: "$".into(), amount: AmountType::Discrete(-i64::MAX, 2) }));
+        assert_eq!(least.checked_neg(), Err(AmountError::Overflow));
+
+        assert_eq!(most.checked_mul(2), Err(AmountError::Overflow));
+        assert_eq!(least.checked_mul(-1), Err(AmountError::Overflow));
+        assert_eq!(most.checked_mul(-1), Ok(Amount { units: "$".into(), amount: AmountType::Discrete(-i64::MAX, 2) }));
+        assert_eq!(Amount::from("kg".to_string(), 1.0).checked_mul(i64::MAX).map(|amount| amount.quantity()), Ok(i64::MAX.to_string()));
+
+        // exact past the 2^53 a float can hold, where dividing through one would round
+        let past_float = Amount { units: "$".into(), amount: AmountType::Discrete(i64::MAX - 1, 2) };
+        assert_eq!(past_float.checked_div(1), Ok(past_float.clone()));
+        assert_eq!(most.checked_div(2), Ok(Amount { units: "$".into(), amount: AmountType::Discrete(i64::MAX / 2 + 1, 2) }));
+        assert_eq!(least.checked_div(-1), Err(AmountError::Overflow));
+        assert_eq!(least.checked_div(i64::MIN), Ok(Amount { units: "$".into(), amount: AmountType::Discrete(1, 2) }));
+
+        assert_eq!(most.checked_div(0), Err(AmountError::DivideByZero));
+        assert_eq!(Amount::from("kg".to_string(), 2.5).checked_div(0), Err(AmountError::DivideByZero));
+    }
+
+    #[test]
+    fn test_checked_scale() {
+        assert_eq!(Amount::from("$".to_string(), 150.25).checked_scale(Decimal::new(5, 1)), Ok(Amount::from("$".to_string(), 75.13)));
+        assert_eq!(Amount::from("kg".to_string(), 2.5).checked_scale(Decimal::new(-3, 0)), Ok(Amount::from("kg".to_string(), -7.5)));
+        assert_eq!(Amount::from("$".to_string(), 1.0).checked_scale(Decimal::MAX), Err(AmountError::Overflow));
+    }
+
     #[test]
     fn test_add_same_units() {
         let mut amount1 = Amount::from("$".to_string(), 10.25);
         let amount2 = Amount::from("$".to_string(), 5.25);
-        amount1.add(&amount2);
+        amount1 += &amount2;
         assert_eq!(amount1, Amount {
-            units: "$".to_string(),
+            units: "$".into(),
             amount: AmountType::Discrete(1550, 2),
         });
 
-        let mut amount3 = Amount::from("kg".to_string(), 2.5);
+        let amount3 = Amount::from("kg".to_string(), 2.5);
         let amount4 = Amount::from("kg".to_string(), 1.5);
-        amount3.add(&amount4);
-        assert_eq!(amount3, Amount {
-            units: "kg".to_string(),
-            amount: AmountType::Float(4.0),
+        assert_eq!(amount3 + amount4, Amount {
+            units: "kg".into(),
+            amount: AmountType::Decimal(Decimal::from(4)),
         });
+
+        assert_eq!(amount1 - amount2, Amount::from("$".to_string(), 10.25));
+    }
+
+    #[test]
+    fn test_checked_add() {
+        let dollars = Amount::from("$".to_string(), 10.25);
+        assert_eq!(dollars.checked_add(&dollars), Ok(Amount::from("$".to_string(), 20.50)));
+        assert_eq!(dollars.checked_add(&Amount::from("kg".to_string(), 2.5)), Err(AmountError::DifferentUnits));
+        assert_eq!(dollars.checked_add(&Amount { units: "$".into(), amount: AmountType::Discrete(15, 1) }),
+                   Ok(Amount { units: "$".into(), amount: AmountType::Discrete(1175, 2) }));
+        assert_eq!(Amount { units: "$".into(), amount: AmountType::Discrete(-15, 1) } + dollars.clone(),
+                   Amount { units: "$".into(), amount: AmountType::Discrete(875, 2) });
+        assert_eq!(dollars.checked_add(&Amount { units: "$".into(), amount: AmountType::Decimal(Decimal::new(15, 1)) }),
+                   Err(AmountError::DiscreteAndDecimal));
+
+        let most = Amount { units: "$".into(), amount: AmountType::Discrete(i64::MAX, 2) };
+        assert_eq!(most.checked_add(&Amount { units: "$".into(), amount: AmountType::Discrete(1, 2) }), Err(AmountError::Overflow));
+        assert_eq!(most.checked_add(&Amount { units: "$".into(), amount: AmountType::Discrete(0, 3) }), Err(AmountError::Overflow));
+        assert_eq!(most.checked_add(&-most.clone()), Ok(Amount { units: "$".into(), amount: AmountType::Discrete(0, 2) }));
+    }
+
+    #[test]
+    fn test_add_different_decimal_places() {
+        let dollars = |amount, places| Amount { units: "$".into(), amount: AmountType::Discrete(amount, places) };
+
+        // the sum is at the larger precision, whichever side it's on
+        assert_eq!(dollars(15, 1) + dollars(1025, 2), dollars(1175, 2));
+        assert_eq!(dollars(1025, 2) + dollars(15, 1), dollars(1175, 2));
+        assert_eq!(dollars(3, 0) + dollars(-1255, 3), dollars(1745, 3));
+
+        let mut total = dollars(10, 0);
+        total += &dollars(25, 1);
+        total += &dollars(-125, 2);
+        assert_eq!(total, dollars(1125, 2));
     }
 
     #[test]
@@ -151,6 +482,6 @@ mod tests {
     fn test_add_different_units() {
         let mut amount1 = Amount::from("$".to_string(), 10.25);
         let amount2 = Amount::from("kg".to_string(), 2.5);
-        amount1.add(&amount2);
+        amount1 += &amount2;
     }
-}
\ No newline at end of file
+}
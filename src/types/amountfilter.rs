@@ -0,0 +1,126 @@
+use std::fmt;
+use std::str::FromStr;
+
+use super::amount::Amount;
+
+
+// a comparison against a posting's amount from a query term like amt:>100, amt:<-50 or
+// amt:=0. like hledger, a number written with a sign (or zero) is compared with the signed
+// amount, and one without a sign with its size, so amt:>100 finds $150 and $-150 alike
+#[derive(Clone, Debug, PartialEq)]
+pub struct AmountFilter {
+    comparison: Comparison,
+    value     : f64,
+    signed    : bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+impl AmountFilter {
+    pub fn matches(&self, amount: &Amount) -> bool {
+        let amount = if self.signed { amount.to_f64() } else { amount.to_f64().abs() };
+
+        match self.comparison {
+            Comparison::Less           => amount < self.value,
+            Comparison::LessOrEqual    => amount <= self.value,
+            Comparison::Equal          => (amount - self.value).abs() < 1e-9,
+            Comparison::GreaterOrEqual => amount >= self.value,
+            Comparison::Greater        => amount > self.value,
+        }
+    }
+}
+
+impl FromStr for AmountFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let term = s.strip_prefix("amt:").ok_or_else(|| s.to_string())?;
+
+        let (comparison, number) = [("<=", Comparison::LessOrEqual),
+                                    (">=", Comparison::GreaterOrEqual),
+                                    ("<",  Comparison::Less),
+                                    (">",  Comparison::Greater),
+                                    ("=",  Comparison::Equal)]
+            .iter()
+            .find_map(|(op, comparison)| term.strip_prefix(op).map(|number| (*comparison, number)))
+            .unwrap_or((Comparison::Equal, term));
+
+        let value: f64 = number.trim().parse().map_err(|_| s.to_string())?;
+        let signed = number.starts_with(['+', '-']) || value == 0.0;
+
+        Ok(AmountFilter { comparison, value, signed })
+    }
+}
+
+// amt:>100  amt:<-50  amt:>+100  amt:=0
+
+impl fmt::Display for AmountFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let comparison = match self.comparison {
+            Comparison::Less           => "<",
+            Comparison::LessOrEqual    => "<=",
+            Comparison::Equal          => "=",
+            Comparison::GreaterOrEqual => ">=",
+            Comparison::Greater        => ">",
+        };
+        let sign = if self.signed && self.value > 0.0 { "+" } else { "" };
+
+        write!(f, "amt:{}{}{}", comparison, sign, self.value)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use crate::types::amount::Amount;
+    use super::AmountFilter;
+
+    fn matches(filter: &str, amount: f64) -> bool {
+        AmountFilter::from_str(filter).unwrap()
+                                      .matches(&Amount::from("$".to_string(), amount))
+    }
+
+    #[test]
+    fn test_amount_filter_unsigned() {
+        assert!(matches("amt:>100", 150.0));
+        assert!(matches("amt:>100", -150.0));
+        assert!(!matches("amt:>100", 100.0));
+        assert!(matches("amt:<=10", -10.0));
+        assert!(matches("amt:12.50", -12.50));
+    }
+
+    #[test]
+    fn test_amount_filter_signed() {
+        assert!(matches("amt:<-50", -50.01));
+        assert!(!matches("amt:<-50", 60.0));
+        assert!(matches("amt:>+100", 150.0));
+        assert!(!matches("amt:>+100", -150.0));
+        assert!(matches("amt:=0", 0.0));
+        assert!(!matches("amt:=0", 0.01));
+    }
+
+    #[test]
+    fn test_amount_filter_display() {
+        let display = |filter: &str| AmountFilter::from_str(filter).unwrap().to_string();
+        assert_eq!(display("amt:>100"), "amt:>100");
+        assert_eq!(display("amt:<-50.5"), "amt:<-50.5");
+        assert_eq!(display("amt:>+100"), "amt:>+100");
+        assert_eq!(display("amt:12"), "amt:=12");
+        assert_eq!(display("amt:=0"), "amt:=0");
+    }
+
+    #[test]
+    fn test_amount_filter_errors() {
+        assert!(AmountFilter::from_str(">100").is_err());
+        assert!(AmountFilter::from_str("amt:>").is_err());
+        assert!(AmountFilter::from_str("amt:lots").is_err());
+    }
+}
@@ -0,0 +1,253 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use super::Units;
+use super::amount::rounded;
+use super::numberformat::{NumberFormat, number_format};
+
+
+/* CommodityStyle */
+
+// how amounts of one commodity are written, like $1,000.00 or 308.5 kWh, from a
+// "commodity $1,000.00" directive or from how the journal writes its amounts. a directive
+// can also say how far from zero a transaction's total in the commodity can be and still
+// balance, for commodities like kWh whose amounts are rounded readings
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CommodityStyle {
+    pub side     : Side,
+    pub spaced   : bool,                 // whether there's a space between symbol and number
+    pub precision: u32,                  // the number of decimal places shown
+    pub number   : Option<NumberFormat>, // its own separators, instead of --number-format's
+    pub tolerance: Option<Decimal>,      // the most a balanced transaction can be off by
+}
+
+// which side of the number the commodity's symbol goes on
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Side {
+    Left,
+    Right,
+}
+
+lazy_static! {
+    static ref COMMODITY_REGEX: Regex =
+        Regex::new(r"(?x)
+            ^commodity\s+
+            (?:
+                (?P<units>[a-zA-Z\$]+)
+                (?P<space>\s*)
+                (?P<number>\d[\d.,]*)
+              |
+                (?P<number2>\d[\d.,]*)
+                (?P<space2>\s*)
+                (?P<units2>[a-zA-Z\$]+)
+            )
+            (?:\s+tolerance\s+(?P<tolerance>\d+(?:\.\d+)?))?
+            \s*$
+        ").unwrap();
+}
+
+impl CommodityStyle {
+    // the style for a commodity the journal says nothing about
+    pub fn default_for(units: &str) -> CommodityStyle {
+        match units {
+            "$" => CommodityStyle { side: Side::Left,  spaced: false, precision: 2, number: None, tolerance: None },
+            "%" => CommodityStyle { side: Side::Right, spaced: false, precision: 1, number: None, tolerance: None },
+            _   => CommodityStyle { side: Side::Right, spaced: true,  precision: 3, number: None, tolerance: None },
+        }
+    }
+
+    // parse a commodity directive, giving None if the line isn't one and the line back if
+    // it's one that can't be read
+    //
+    //   commodity $1,000.00
+    //   commodity 1.000,00 EUR
+    //   commodity 1000 kWh
+    //   commodity 1000.0 kWh  tolerance 0.05
+    pub fn parse(line: &str) -> Option<std::result::Result<(Units, CommodityStyle), String>> {
+        if !line.starts_with("commodity") {
+            return None
+        }
+
+        let parsed = COMMODITY_REGEX.captures(line).and_then(|captures| {
+            let (side, units, space, number) = match captures.name("units") {
+                Some(units) => (Side::Left, units, captures.name("space")?, captures.name("number")?),
+                None        => (Side::Right, captures.name("units2")?, captures.name("space2")?, captures.name("number2")?),
+            };
+            let number = number.as_str();
+
+            // a sample number without any marks has no decimal places, otherwise the last
+            // mark is the decimal mark
+            let (format, precision) = match number.rfind(['.', ',']) {
+                Some(mark) => (Some(NumberFormat::from_str(number).ok()?), number.len() - mark - 1),
+                None       => (None, 0),
+            };
+
+            let tolerance = match captures.name("tolerance") {
+                Some(tolerance) => Some(Decimal::from_str(tolerance.as_str()).ok()?),
+                None            => None,
+            };
+
+            Some((units.as_str().into(), CommodityStyle {
+                side,
+                spaced   : !space.as_str().is_empty(),
+                precision: precision as u32,
+                number   : format,
+                tolerance,
+            }))
+        });
+
+        Some(parsed.ok_or_else(|| line.to_string()))
+    }
+
+    // the quantity rounded to this style's precision, with its symbol: "$1,234.50" or "308.5 kWh"
+    pub fn format(&self, units: &str, quantity: Decimal) -> String {
+        let number = self.number.unwrap_or_else(number_format).format(&rounded(quantity, self.precision));
+        let space  = if self.spaced { " " } else { "" };
+
+        match self.side {
+            Side::Left  => format!("{}{}{}", units, space, number),
+            Side::Right => format!("{}{}{}", number, space, units),
+        }
+    }
+}
+
+
+/* CommodityStyles */
+
+// the style of each commodity in the journal. a commodity directive decides its style
+// outright, otherwise it's written the way the journal first writes it, with as many
+// decimal places as the journal ever gives it
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CommodityStyles {
+    declared: HashMap<Units, CommodityStyle>,
+    observed: HashMap<Units, CommodityStyle>,
+}
+
+impl CommodityStyles {
+    // the style from a commodity directive, which replaces any earlier one
+    pub fn declare(&mut self, units: Units, style: CommodityStyle) {
+        self.declared.insert(units, style);
+    }
+
+    // the style of an amount as it was written in the journal
+    pub fn observe(&mut self, units: Units, style: CommodityStyle) {
+        self.observed.entry(units)
+                     .and_modify(|seen| seen.precision = seen.precision.max(style.precision))
+                     .or_insert(style);
+    }
+
+    // the styles from a journal file read after this one, whose directives replace any
+    // earlier ones the same way they would later in one file
+    pub fn merge(&mut self, other: CommodityStyles) {
+        self.declared.extend(other.declared);
+        for (units, style) in other.observed {
+            self.observe(units, style);
+        }
+    }
+
+    pub fn get(&self, units: &str) -> Option<&CommodityStyle> {
+        let units = Units::from(units);
+        self.declared.get(&units).or_else(|| self.observed.get(&units))
+    }
+
+    // the style the journal gives the commodity, or its default if it doesn't give one
+    pub fn style(&self, units: &str) -> CommodityStyle {
+        self.get(units).copied().unwrap_or_else(|| CommodityStyle::default_for(units))
+    }
+
+    // how far from zero a transaction's total in the commodity can be and still balance,
+    // which is exactly zero unless its directive gives a tolerance
+    pub fn tolerance(&self, units: &str) -> Decimal {
+        self.declared.get(&Units::from(units)).and_then(|style| style.tolerance).unwrap_or_default()
+    }
+}
+
+
+
+/* Styled */
+
+// a value written in the journal's commodity styles. amounts and the reports made of them
+// display themselves through this, so each report is handed the styles of the journal it
+// was made from, including one that's just been reread
+pub struct Styled<'a, T: ?Sized> {
+    pub value : &'a T,
+    pub styles: &'a CommodityStyles,
+}
+
+pub trait WithStyles {
+    fn styled<'a>(&'a self, styles: &'a CommodityStyles) -> Styled<'a, Self> {
+        Styled { value: self, styles }
+    }
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal::Decimal;
+    use std::str::FromStr;
+    use crate::types::numberformat::NumberFormat;
+    use crate::types::Units;
+    use super::{CommodityStyle, CommodityStyles, Side};
+
+    fn parse(line: &str) -> (Units, CommodityStyle) {
+        CommodityStyle::parse(line).unwrap().unwrap()
+    }
+
+    fn decimal(number: &str) -> Decimal {
+        Decimal::from_str(number).unwrap()
+    }
+
+    #[test]
+    fn test_parse_commodity_directive() {
+        assert_eq!(parse("commodity $1,000.00"),
+                   ("$".into(), CommodityStyle { side     : Side::Left,
+                                                 spaced   : false,
+                                                 precision: 2,
+                                                 number   : Some(NumberFormat::from_str("1,000.00").unwrap()),
+                                                 tolerance: None }));
+
+        assert_eq!(parse("commodity 1000 kWh"),
+                   ("kWh".into(), CommodityStyle { side: Side::Right, spaced: true, precision: 0, number: None, tolerance: None }));
+
+        assert_eq!(parse("commodity 1000.0 kWh  tolerance 0.05").1.tolerance, Some(decimal("0.05")));
+
+        assert_eq!(CommodityStyle::parse("commodity kWh"), Some(Err("commodity kWh".to_string())));
+        assert_eq!(CommodityStyle::parse("commodity 1000 kWh tolerance"), Some(Err("commodity 1000 kWh tolerance".to_string())));
+        assert_eq!(CommodityStyle::parse("2023/03/15 commodity"), None);
+    }
+
+    #[test]
+    fn test_format() {
+        let (euros, style) = parse("commodity 1.000,00 EUR");
+        assert_eq!(style.format(&euros, decimal("-1234.5")), "-1.234,50 EUR");
+
+        let (kwh, style) = parse("commodity 1000.0 kWh");
+        assert_eq!(style.format(&kwh, decimal("308.25")), "308.3 kWh");
+
+        assert_eq!(CommodityStyle::default_for("$").format("$", decimal("-22")), "$-22.00");
+        assert_eq!(CommodityStyle::default_for("%").format("%", decimal("98.24")), "98.2%");
+    }
+
+    #[test]
+    fn test_declared_styles_win() {
+        let written  = CommodityStyle { side: Side::Right, spaced: true, precision: 1, number: None, tolerance: None };
+        let declared = CommodityStyle { precision: 0, ..written };
+
+        let mut styles = CommodityStyles::default();
+        styles.observe("kWh".into(), written);
+        styles.observe("kWh".into(), CommodityStyle { precision: 3, spaced: false, ..written });
+        assert_eq!(styles.get("kWh"), Some(&CommodityStyle { precision: 3, ..written }));
+
+        styles.declare("kWh".into(), declared);
+        assert_eq!(styles.get("kWh"), Some(&declared));
+        assert_eq!(styles.get("$"), None);
+    }
+}
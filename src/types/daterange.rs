@@ -0,0 +1,114 @@
+use chrono::NaiveDate;
+
+
+// a span of dates for restricting reports. the begin date is included and the end date
+// is not, so -b 2023/03 -e 2023/04 covers all of March. either side can be left open
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DateRange {
+    pub begin: Option<NaiveDate>,
+    pub end  : Option<NaiveDate>,
+}
+
+impl DateRange {
+    pub fn new(begin: Option<NaiveDate>, end: Option<NaiveDate>) -> Self {
+        DateRange { begin, end }
+    }
+
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.begin.is_none_or(|begin| date >= begin)
+            && self.end.is_none_or(|end| date < end)
+    }
+
+    pub fn is_unbounded(&self) -> bool {
+        self.begin.is_none() && self.end.is_none()
+    }
+
+    // the dates in both ranges, which is empty when they don't overlap
+    pub fn intersect(&self, other: &DateRange) -> DateRange {
+        DateRange {
+            begin: self.begin.max(other.begin),
+            end  : match (self.end, other.end) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b)             => a.or(b),
+            },
+        }
+    }
+
+    // the smallest range covering both, including any dates between them
+    pub fn span(&self, other: &DateRange) -> DateRange {
+        DateRange {
+            begin: self.begin.min(other.begin),
+            end  : self.end.zip(other.end).map(|(a, b)| a.max(b)),
+        }
+    }
+}
+
+// parse a full or partial date from the command line. a partial date means the first day
+// of its month or year, so "2023/03" is 2023/03/01 and "2023" is 2023/01/01. dashes work
+// as well as slashes
+pub fn parse_date(s: &str) -> Option<NaiveDate> {
+    let parts: Vec<&str> = s.trim().split(['/', '-']).collect();
+
+    let number = |i: usize| -> Option<u32> {
+        parts.get(i).map_or(Some(1), |p| p.parse().ok())
+    };
+
+    if parts.is_empty() || parts.len() > 3 || parts[0].len() != 4 {
+        return None
+    }
+
+    let year: i32 = parts[0].parse().ok()?;
+    NaiveDate::from_ymd_opt(year, number(1)?, number(2)?)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use super::{DateRange, parse_date};
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(parse_date("2023/03/15"), Some(date(2023, 3, 15)));
+        assert_eq!(parse_date("2023-03-15"), Some(date(2023, 3, 15)));
+        assert_eq!(parse_date("2023/03"), Some(date(2023, 3, 1)));
+        assert_eq!(parse_date("2023"), Some(date(2023, 1, 1)));
+        assert_eq!(parse_date("2023/13"), None);
+        assert_eq!(parse_date("2023/02/30"), None);
+        assert_eq!(parse_date("23/03/15"), None);
+        assert_eq!(parse_date("march"), None);
+        assert_eq!(parse_date(""), None);
+    }
+
+    #[test]
+    fn test_contains() {
+        let range = DateRange::new(Some(date(2023, 3, 1)), Some(date(2023, 4, 1)));
+        assert!(!range.contains(date(2023, 2, 28)));
+        assert!(range.contains(date(2023, 3, 1)));
+        assert!(range.contains(date(2023, 3, 31)));
+        assert!(!range.contains(date(2023, 4, 1)));
+
+        let open = DateRange::new(None, Some(date(2023, 4, 1)));
+        assert!(open.contains(date(1999, 1, 1)));
+        assert!(DateRange::default().contains(date(1999, 1, 1)));
+    }
+
+    #[test]
+    fn test_intersect_and_span() {
+        let march = DateRange::new(Some(date(2023, 3, 1)), Some(date(2023, 4, 1)));
+        let after = DateRange::new(Some(date(2023, 3, 15)), None);
+        let may   = DateRange::new(Some(date(2023, 5, 1)), Some(date(2023, 6, 1)));
+
+        assert_eq!(march.intersect(&after), DateRange::new(Some(date(2023, 3, 15)), Some(date(2023, 4, 1))));
+        assert_eq!(march.intersect(&DateRange::default()), march);
+        assert!(!march.intersect(&may).contains(date(2023, 3, 20)));
+
+        assert_eq!(march.span(&may), DateRange::new(Some(date(2023, 3, 1)), Some(date(2023, 6, 1))));
+        assert_eq!(march.span(&after), DateRange::new(Some(date(2023, 3, 1)), None));
+        assert!(march.span(&DateRange::default()).is_unbounded());
+    }
+}
@@ -0,0 +1,159 @@
+use chrono::{Datelike, Duration, NaiveDate};
+use std::fmt;
+use std::str::FromStr;
+
+use super::monthyear::MonthYear;
+
+
+// how often something repeats, such as a periodic transaction
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Interval {
+    Daily,
+    Weekly,
+    Monthly,
+    Quarterly,
+    Yearly,
+}
+
+impl Interval {
+
+    // the first day of the period of this length that the date falls in. weeks start on Monday
+    pub fn start_of(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Interval::Daily     => date,
+            Interval::Weekly    => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+            Interval::Monthly   => date.with_day(1).unwrap(),
+            Interval::Quarterly => NaiveDate::from_ymd_opt(date.year(), (date.month() - 1) / 3 * 3 + 1, 1).unwrap(),
+            Interval::Yearly    => NaiveDate::from_ymd_opt(date.year(), 1, 1).unwrap(),
+        }
+    }
+
+    // the first day of the period after the one the date falls in
+    pub fn next_start(&self, date: NaiveDate) -> NaiveDate {
+        let start = self.start_of(date);
+        match self {
+            Interval::Daily     => start + Duration::days(1),
+            Interval::Weekly    => start + Duration::days(7),
+            Interval::Monthly   => add_months(start, 1),
+            Interval::Quarterly => add_months(start, 3),
+            Interval::Yearly    => add_months(start, 12),
+        }
+    }
+
+    // what one period is called: "month" for monthly
+    pub fn period_name(&self) -> &'static str {
+        match self {
+            Interval::Daily     => "day",
+            Interval::Weekly    => "week",
+            Interval::Monthly   => "month",
+            Interval::Quarterly => "quarter",
+            Interval::Yearly    => "year",
+        }
+    }
+
+    // a short name for the period of this length that the date falls in:
+    // 2023-03-15, 2023-W11, 2023-03, 2023Q1, 2023
+    pub fn label(&self, date: NaiveDate) -> String {
+        match self {
+            Interval::Daily     => date.format("%Y-%m-%d").to_string(),
+            Interval::Weekly    => format!("{}-W{:02}", date.iso_week().year(), date.iso_week().week()),
+            Interval::Monthly   => date.format("%Y-%m").to_string(),
+            Interval::Quarterly => format!("{}Q{}", date.year(), MonthYear::from_naivedate(date).quarter()),
+            Interval::Yearly    => date.format("%Y").to_string(),
+        }
+    }
+}
+
+// the first of the month some number of months after the first of this month
+fn add_months(date: NaiveDate, months: u32) -> NaiveDate {
+    let index = date.year() * 12 + date.month0() as i32 + months as i32;
+    NaiveDate::from_ymd_opt(index.div_euclid(12), index.rem_euclid(12) as u32 + 1, 1).unwrap()
+}
+
+impl FromStr for Interval {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "daily"     => Ok(Interval::Daily),
+            "weekly"    => Ok(Interval::Weekly),
+            "monthly"   => Ok(Interval::Monthly),
+            "quarterly" => Ok(Interval::Quarterly),
+            "yearly"    => Ok(Interval::Yearly),
+            _           => Err(s.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Interval::Daily     => "daily",
+            Interval::Weekly    => "weekly",
+            Interval::Monthly   => "monthly",
+            Interval::Quarterly => "quarterly",
+            Interval::Yearly    => "yearly",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use std::str::FromStr;
+    use super::Interval;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_start_of() {
+        let wednesday = date(2023, 3, 15);
+        assert_eq!(Interval::Daily.start_of(wednesday), wednesday);
+        assert_eq!(Interval::Weekly.start_of(wednesday), date(2023, 3, 13));
+        assert_eq!(Interval::Monthly.start_of(wednesday), date(2023, 3, 1));
+        assert_eq!(Interval::Quarterly.start_of(wednesday), date(2023, 1, 1));
+        assert_eq!(Interval::Quarterly.start_of(date(2023, 12, 31)), date(2023, 10, 1));
+        assert_eq!(Interval::Yearly.start_of(wednesday), date(2023, 1, 1));
+    }
+
+    #[test]
+    fn test_next_start() {
+        let wednesday = date(2023, 3, 15);
+        assert_eq!(Interval::Daily.next_start(wednesday), date(2023, 3, 16));
+        assert_eq!(Interval::Weekly.next_start(wednesday), date(2023, 3, 20));
+        assert_eq!(Interval::Monthly.next_start(wednesday), date(2023, 4, 1));
+        assert_eq!(Interval::Monthly.next_start(date(2023, 12, 31)), date(2024, 1, 1));
+        assert_eq!(Interval::Quarterly.next_start(date(2023, 11, 5)), date(2024, 1, 1));
+        assert_eq!(Interval::Yearly.next_start(wednesday), date(2024, 1, 1));
+    }
+
+    #[test]
+    fn test_label() {
+        let wednesday = date(2023, 3, 15);
+        assert_eq!(Interval::Daily.label(wednesday), "2023-03-15");
+        assert_eq!(Interval::Weekly.label(wednesday), "2023-W11");
+        assert_eq!(Interval::Weekly.label(date(2023, 1, 1)), "2022-W52");
+        assert_eq!(Interval::Monthly.label(wednesday), "2023-03");
+        assert_eq!(Interval::Quarterly.label(wednesday), "2023Q1");
+        assert_eq!(Interval::Yearly.label(wednesday), "2023");
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!(Interval::from_str("monthly"), Ok(Interval::Monthly));
+        assert_eq!(Interval::from_str("Weekly"), Ok(Interval::Weekly));
+        assert_eq!(Interval::from_str("fortnightly"), Err("fortnightly".to_string()));
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        for interval in [Interval::Daily, Interval::Weekly, Interval::Monthly, Interval::Quarterly, Interval::Yearly] {
+            assert_eq!(Interval::from_str(&interval.to_string()), Ok(interval));
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use std::str::FromStr;
+
+use crate::color::{Style, paint};
+use crate::common::{pad_left, pad_right};
+
+
+// a template for a line of the register report, given with --format, like
+//
+//   %(date) %-30(payee) %10(amount) %10(total)
+//
+// each %(field) is replaced with the field's value. a width between the % and the field
+// pads the value to that many characters, to the right or, with a minus sign, to the left
+// like printf. a %% is a literal percent sign
+#[derive(Clone, Debug, PartialEq)]
+pub struct LineFormat {
+    pieces: Vec<Piece>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Piece {
+    Text(String),
+    Field {
+        field: Field,
+        width: usize, // 0 for no padding
+        left : bool,  // pad on the right instead of the left
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Field {
+    Date,
+    Payee,
+    Account,
+    Amount,
+    Total,
+}
+
+impl LineFormat {
+    // the line with each field's value and style from the given function
+    pub fn render<F>(&self, value: F) -> String
+        where F: Fn(Field) -> (String, Style)
+    {
+        let mut line = String::new();
+
+        for piece in &self.pieces {
+            match piece {
+                Piece::Text(text) => line.push_str(text),
+                Piece::Field { field, width, left } => {
+                    let (text, style) = value(*field);
+                    let padded = if *left { pad_right(&text, *width) } else { pad_left(&text, *width) };
+                    line.push_str(&paint(padded, style));
+                },
+            }
+        }
+
+        line
+    }
+}
+
+// the layout the register report has always had
+impl Default for LineFormat {
+    fn default() -> Self {
+        "%-10(date) %-30(payee) %-30(account) %10(amount) %10(total)".parse().unwrap()
+    }
+}
+
+impl FromStr for Field {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "date"                   => Ok(Field::Date),
+            "payee" | "description"  => Ok(Field::Payee),
+            "account"                => Ok(Field::Account),
+            "amount"                 => Ok(Field::Amount),
+            "total"                  => Ok(Field::Total),
+            _                        => Err(format!("unknown field \"{}\"", s)),
+        }
+    }
+}
+
+impl FromStr for LineFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut pieces = vec![];
+        let mut text = String::new();
+        let mut rest = s;
+
+        while let Some(i) = rest.find('%') {
+            text.push_str(&rest[..i]);
+            rest = &rest[i+1..];
+
+            if let Some(after) = rest.strip_prefix('%') {
+                text.push('%');
+                rest = after;
+                continue;
+            }
+
+            // %-20(account)
+            let (left, after) = match rest.strip_prefix('-') {
+                Some(after) => (true, after),
+                None        => (false, rest),
+            };
+            let digits = after.find(|c: char| !c.is_ascii_digit()).unwrap_or(after.len());
+            let width = if digits == 0 { 0 } else { after[..digits].parse().map_err(|_| s.to_string())? };
+
+            let after = after[digits..].strip_prefix('(')
+                                       .ok_or_else(|| format!("expected a field like %(amount) in \"{}\"", s))?;
+            let close = after.find(')')
+                             .ok_or_else(|| format!("missing a ) in \"{}\"", s))?;
+            let field = after[..close].trim().parse()?;
+
+            if !text.is_empty() {
+                pieces.push(Piece::Text(std::mem::take(&mut text)));
+            }
+            pieces.push(Piece::Field { field, width, left });
+            rest = &after[close+1..];
+        }
+
+        text.push_str(rest);
+        if !text.is_empty() {
+            pieces.push(Piece::Text(text));
+        }
+
+        Ok(LineFormat { pieces })
+    }
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use crate::color::Style;
+    use super::{Field, LineFormat};
+
+    fn render(format: &str) -> String {
+        LineFormat::from_str(format).unwrap()
+                                    .render(|field| match field {
+                                        Field::Date    => ("2023/03/18".to_string(), Style::Plain),
+                                        Field::Payee   => ("Groceries".to_string(), Style::Plain),
+                                        Field::Account => ("assets:savings".to_string(), Style::Dim),
+                                        Field::Amount  => ("$-41.06".to_string(), Style::Negative),
+                                        Field::Total   => ("$399.64".to_string(), Style::Plain),
+                                    })
+    }
+
+    #[test]
+    fn test_line_format() {
+        assert_eq!(render("%(date) %(payee) %(account) %(amount) %(total)"),
+                   "2023/03/18 Groceries assets:savings $-41.06 $399.64");
+        assert_eq!(render("%(date)|%-12(description)|%10(amount)"),
+                   "2023/03/18|Groceries   |   $-41.06");
+        assert_eq!(render("100%% %(account)"), "100% assets:savings");
+        assert_eq!(render("no fields"), "no fields");
+        assert_eq!(render("%3(payee)"), "Groceries");
+    }
+
+    #[test]
+    fn test_line_format_wide_characters() {
+        let format = LineFormat::from_str("%-10(payee)|%6(account)|").unwrap();
+        let line   = format.render(|field| match field {
+            Field::Payee => ("ラーメン".to_string(), Style::Plain),
+            _            => ("🍜".to_string(), Style::Plain),
+        });
+        assert_eq!(line, "ラーメン  |    🍜|");
+    }
+
+    #[test]
+    fn test_line_format_errors() {
+        assert!(LineFormat::from_str("%(payer)").is_err());
+        assert!(LineFormat::from_str("%(amount").is_err());
+        assert!(LineFormat::from_str("%amount").is_err());
+        assert!(LineFormat::from_str("50%").is_err());
+    }
+}
@@ -0,0 +1,185 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::AddAssign;
+
+use super::Units;
+use super::amount::{Amount, AmountError};
+use super::commoditystyle::{CommodityStyles, Styled, WithStyles};
+
+// a sum of amounts in possibly different commodities, one amount per commodity. adding
+// an amount in a new commodity starts a new amount instead of panicking like adding two
+// Amounts does. transaction totals, register running totals and balance cells are these
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MixedAmount {
+    amounts: BTreeMap<Units, Amount>,
+}
+
+impl MixedAmount {
+    // only a commodity the sum hasn't seen yet costs a copy of the amount and its units
+    pub fn add_amount(&mut self, amount: &Amount) {
+        self.checked_add_amount(amount).unwrap_or_else(|error| panic!("{}", error));
+    }
+
+    // add the amount, or leave the sum as it was if the amount's commodity would grow too
+    // large to hold
+    pub fn checked_add_amount(&mut self, amount: &Amount) -> Result<(), AmountError> {
+        match self.amounts.get_mut(&amount.units) {
+            Some(existing) => *existing = existing.checked_add(amount)?,
+            None           => { self.amounts.insert(amount.units, amount.clone()); }
+        }
+        Ok(())
+    }
+
+    pub fn add(&mut self, other: &MixedAmount) {
+        self.checked_add(other).unwrap_or_else(|error| panic!("{}", error));
+    }
+
+    // add each of the other sum's amounts, stopping at the first one too large to hold
+    pub fn checked_add(&mut self, other: &MixedAmount) -> Result<(), AmountError> {
+        for amount in other.amounts.values() {
+            self.checked_add_amount(amount)?;
+        }
+        Ok(())
+    }
+
+    pub fn negate(self) -> MixedAmount {
+        self.map(|amount| -amount)
+    }
+
+    pub fn divide(&self, divisor: i64) -> MixedAmount {
+        self.clone().map(|amount| amount / divisor)
+    }
+
+    fn map(self, f: impl Fn(Amount) -> Amount) -> MixedAmount {
+        MixedAmount {
+            amounts: self.amounts
+                         .into_iter()
+                         .map(|(units, amount)| (units, f(amount)))
+                         .collect(),
+        }
+    }
+
+    // the amounts in order of their units
+    pub fn amounts(&self) -> impl Iterator<Item = &Amount> {
+        self.amounts.values()
+    }
+
+    // the amount in one commodity, if there's been any
+    pub fn get(&self, units: &str) -> Option<&Amount> {
+        self.amounts.get(&Units::from(units))
+    }
+
+    // the amounts that aren't zero, like the commodities a transaction doesn't balance in
+    pub fn nonzero(&self) -> impl Iterator<Item = &Amount> {
+        self.amounts().filter(|amount| !amount.is_zero())
+    }
+
+    // whether any of the commodities is below zero
+    pub fn is_negative(&self) -> bool {
+        self.amounts.values().any(Amount::is_negative)
+    }
+
+    // the amount, if there's exactly one commodity
+    pub fn single(&self) -> Option<&Amount> {
+        match self.amounts.len() {
+            1 => self.amounts.values().next(),
+            _ => None,
+        }
+    }
+}
+
+impl From<Amount> for MixedAmount {
+    fn from(amount: Amount) -> Self {
+        MixedAmount {
+            amounts: BTreeMap::from([(amount.units, amount)]),
+        }
+    }
+}
+
+impl AddAssign<&MixedAmount> for MixedAmount {
+    fn add_assign(&mut self, other: &MixedAmount) {
+        self.add(other);
+    }
+}
+
+impl WithStyles for MixedAmount {}
+
+// $12.00, 308.000 kWh
+
+impl fmt::Display for MixedAmount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.styled(&CommodityStyles::default()))
+    }
+}
+
+impl fmt::Display for Styled<'_, MixedAmount> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.value.amounts.is_empty() {
+            return write!(f, "0")
+        }
+
+        let amounts: Vec<String> = self.value.amounts().map(|a| a.styled(self.styles).to_string()).collect();
+        write!(f, "{}", amounts.join(", "))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::types::amount::{Amount, AmountError, AmountType};
+    use super::MixedAmount;
+
+    fn dollars(amount: f64) -> Amount {
+        Amount::from("$".to_string(), amount)
+    }
+
+    fn kwh(amount: f64) -> Amount {
+        Amount::from("kWh".to_string(), amount)
+    }
+
+    #[test]
+    fn test_add_mixed_units() {
+        let mut mixed = MixedAmount::from(dollars(10.25));
+        mixed.add_amount(&kwh(300.0));
+        mixed.add_amount(&dollars(1.75));
+        mixed.add(&MixedAmount::from(kwh(8.0)));
+
+        assert_eq!(mixed.amounts().cloned().collect::<Vec<_>>(), vec![dollars(12.0), kwh(308.0)]);
+        assert_eq!(mixed.single(), None);
+        assert_eq!(mixed.to_string(), "$12.00, 308.000 kWh");
+    }
+
+    #[test]
+    fn test_checked_add_overflow() {
+        let most = Amount { units: "$".into(), amount: AmountType::Discrete(i64::MAX, 2) };
+        let mut mixed = MixedAmount::from(kwh(3.0));
+        mixed.add_amount(&most);
+
+        assert_eq!(mixed.checked_add_amount(&dollars(0.01)), Err(AmountError::Overflow));
+        assert_eq!(mixed.checked_add(&MixedAmount::from(most.clone())), Err(AmountError::Overflow));
+        assert_eq!(mixed.get("$"), Some(&most));
+    }
+
+    #[test]
+    fn test_negate_divide() {
+        let mut mixed = MixedAmount::from(dollars(10.0));
+        mixed.add_amount(&kwh(3.0));
+
+        assert_eq!(mixed.clone().negate().to_string(), "$-10.00, -3.000 kWh");
+        assert_eq!(mixed.divide(2).to_string(), "$5.00, 1.500 kWh");
+    }
+
+    #[test]
+    fn test_zero() {
+        assert_eq!(MixedAmount::default().to_string(), "0");
+
+        let mut mixed = MixedAmount::from(dollars(10.0));
+        mixed.add_amount(&dollars(-10.0));
+        assert_eq!(mixed.single(), Some(&dollars(0.0)));
+
+        mixed.add_amount(&kwh(3.0));
+        assert_eq!(mixed.nonzero().collect::<Vec<_>>(), vec![&kwh(3.0)]);
+        assert_eq!(mixed.get("$"), Some(&dollars(0.0)));
+        assert_eq!(mixed.get("EUR"), None);
+    }
+}
@@ -1,8 +1,12 @@
+use std::cmp::Ordering;
 use std::fmt;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
 use chrono::{NaiveDate, Datelike};
 
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MonthYear {
     pub month: u32,
     pub year : u32,
@@ -18,31 +22,137 @@ impl MonthYear {
     }
 
     pub fn next_month(&self) -> Self {
-        let (new_month, new_year) = if self.month == 12 {
-            (1, self.year + 1)
-        } else {
-            (self.month + 1, self.year)
-        };
+        self.add_months(1)
+    }
+
+    pub fn prev_month(&self) -> Self {
+        self.add_months(-1)
+    }
+
+    // the month this many months later, or earlier for a negative number
+    pub fn add_months(&self, months: i64) -> Self {
+        let index = self.index() + months;
         Self {
-            month: new_month,
-            year: new_year
+            month: index.rem_euclid(12) as u32 + 1,
+            year : index.div_euclid(12) as u32,
         }
     }
 
-    pub fn from_naivedate(date: NaiveDate) -> MonthYear {
-        Self {
-            month: date.month(),
-            year: date.year() as u32
+    // the number of months from this one to the other, negative if the other is earlier
+    pub fn months_between(&self, other: &MonthYear) -> i64 {
+        other.index() - self.index()
+    }
+
+    // 1 for January to March, up to 4 for October to December
+    pub fn quarter(&self) -> u32 {
+        (self.month - 1) / 3 + 1
+    }
+
+    // every month from the first to the last of the range, none if the last is before the first
+    //
+    //   MonthYear::range(first..=last)
+    pub fn range(months: RangeInclusive<MonthYear>) -> MonthRange {
+        let (first, last) = months.into_inner();
+        MonthRange {
+            front    : first,
+            back     : last,
+            remaining: usize::try_from(first.months_between(&last) + 1).unwrap_or(0),
         }
     }
+
+    pub fn first_day(&self) -> NaiveDate {
+        NaiveDate::from_ymd_opt(self.year as i32, self.month, 1).unwrap()
+    }
+
+    // months counted from January of year 0
+    fn index(&self) -> i64 {
+        self.year as i64 * 12 + self.month as i64 - 1
+    }
+
+    pub fn from_naivedate(date: NaiveDate) -> MonthYear {
+        MonthYear::new(date.month(), date.year() as u32)
+    }
+}
+
+// earlier months come first, by year and then by month
+impl Ord for MonthYear {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.year, self.month).cmp(&(other.year, other.month))
+    }
+}
+
+impl PartialOrd for MonthYear {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
 }
 
+// 2023-04
+
 impl fmt::Display for MonthYear {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}-{:02}", self.year, self.month)
     }
 }
 
+// the way it's displayed, 2023-04, or the way the journal writes dates, 2023/04
+impl FromStr for MonthYear {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (year, month) = s.split_once(['-', '/'])
+                             .filter(|(year, _)| year.len() == 4)
+                             .ok_or_else(|| s.to_string())?;
+
+        match (year.parse(), month.parse()) {
+            (Ok(year), Ok(month)) if (1..=12).contains(&month) => Ok(MonthYear { month, year }),
+            _                                                  => Err(s.to_string()),
+        }
+    }
+}
+
+
+/* MonthRange */
+
+// the months from MonthYear::range, which can be taken from either end
+pub struct MonthRange {
+    front    : MonthYear,
+    back     : MonthYear,
+    remaining: usize,
+}
+
+impl Iterator for MonthRange {
+    type Item = MonthYear;
+
+    fn next(&mut self) -> Option<MonthYear> {
+        if self.remaining == 0 {
+            return None
+        }
+        let month = self.front;
+        self.front = self.front.next_month();
+        self.remaining -= 1;
+        Some(month)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl DoubleEndedIterator for MonthRange {
+    fn next_back(&mut self) -> Option<MonthYear> {
+        if self.remaining == 0 {
+            return None
+        }
+        let month = self.back;
+        self.back = self.back.prev_month();
+        self.remaining -= 1;
+        Some(month)
+    }
+}
+
+impl ExactSizeIterator for MonthRange {}
+
 
 #[cfg(test)]
 mod tests {
@@ -78,6 +188,54 @@ mod tests {
         assert_eq!(next_month_year.year, 2023);
     }
 
+    #[test]
+    fn test_ord_across_years() {
+        assert!(MonthYear::new(11, 2022) < MonthYear::new(2, 2023));
+        assert!(MonthYear::new(1, 2023) > MonthYear::new(12, 2022));
+        assert_eq!(MonthYear::new(3, 2023).max(MonthYear::new(4, 2022)), MonthYear::new(3, 2023));
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!("2023-04".parse(), Ok(MonthYear::new(4, 2023)));
+        assert_eq!("2023/4".parse(), Ok(MonthYear::new(4, 2023)));
+        assert_eq!("2023-13".parse::<MonthYear>(), Err("2023-13".to_string()));
+        assert!("2023".parse::<MonthYear>().is_err());
+        assert!("2023/04/15".parse::<MonthYear>().is_err());
+        assert!("04/2023".parse::<MonthYear>().is_err());
+    }
+
+    #[test]
+    fn test_month_arithmetic() {
+        let january = MonthYear::new(1, 2023);
+        assert_eq!(january.prev_month(), MonthYear::new(12, 2022));
+        assert_eq!(january.add_months(14), MonthYear::new(3, 2024));
+        assert_eq!(january.add_months(-13), MonthYear::new(12, 2021));
+        assert_eq!(january.months_between(&MonthYear::new(3, 2024)), 14);
+        assert_eq!(january.months_between(&MonthYear::new(11, 2022)), -2);
+
+        assert_eq!(january.quarter(), 1);
+        assert_eq!(MonthYear::new(9, 2023).quarter(), 3);
+        assert_eq!(MonthYear::new(10, 2023).quarter(), 4);
+    }
+
+    #[test]
+    fn test_range() {
+        let months: Vec<String> = MonthYear::range(MonthYear::new(11, 2022)..=MonthYear::new(2, 2023))
+                                      .map(|month| month.to_string())
+                                      .collect();
+        assert_eq!(months, vec!["2022-11", "2022-12", "2023-01", "2023-02"]);
+
+        let mut range = MonthYear::range(MonthYear::new(11, 2022)..=MonthYear::new(2, 2023));
+        assert_eq!(range.len(), 4);
+        assert_eq!(range.next_back(), Some(MonthYear::new(2, 2023)));
+        assert_eq!(range.next(), Some(MonthYear::new(11, 2022)));
+        assert_eq!(range.rev().collect::<Vec<_>>(), vec![MonthYear::new(1, 2023), MonthYear::new(12, 2022)]);
+
+        assert_eq!(MonthYear::range(MonthYear::new(3, 2023)..=MonthYear::new(3, 2023)).count(), 1);
+        assert_eq!(MonthYear::range(MonthYear::new(3, 2023)..=MonthYear::new(2, 2023)).count(), 0);
+    }
+
     #[test]
     fn test_from_naivedate() {
         let naive_date = NaiveDate::from_ymd_opt(2022, 5, 15).unwrap();
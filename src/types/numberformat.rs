@@ -0,0 +1,118 @@
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+
+// how the digits of amounts are written, given with --number-format as a sample number
+// like 1,234.56 or 1.234,56. the last mark is the decimal mark and any mark before it
+// separates groups of thousands
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NumberFormat {
+    grouping: Option<char>,
+    decimal : char,
+}
+
+// chosen once at startup. amounts format themselves through Display, which has nowhere
+// to pass a setting like this through
+static NUMBER_FORMAT: OnceLock<NumberFormat> = OnceLock::new();
+
+pub fn set_number_format(format: NumberFormat) {
+    NUMBER_FORMAT.set(format).expect("The number format is already set");
+}
+
+// the format set at startup, or plain 1234.56 if there isn't one
+pub fn number_format() -> NumberFormat {
+    NUMBER_FORMAT.get().copied().unwrap_or_default()
+}
+
+impl NumberFormat {
+    // a plain number like -1234.56, from {:.2} or the like, with its digits grouped and
+    // its decimal mark replaced
+    pub fn format(&self, number: &str) -> String {
+        let (sign, digits) = match number.strip_prefix('-') {
+            Some(digits) => ("-", digits),
+            None         => ("", number),
+        };
+        let (whole, fraction) = match digits.split_once('.') {
+            Some((whole, fraction)) => (whole, Some(fraction)),
+            None                    => (digits, None),
+        };
+
+        let mut formatted = String::from(sign);
+        for (i, digit) in whole.chars().enumerate() {
+            if i > 0 && (whole.len() - i) % 3 == 0 {
+                if let Some(grouping) = self.grouping {
+                    formatted.push(grouping);
+                }
+            }
+            formatted.push(digit);
+        }
+
+        if let Some(fraction) = fraction {
+            formatted.push(self.decimal);
+            formatted.push_str(fraction);
+        }
+        formatted
+    }
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        NumberFormat { grouping: None, decimal: '.' }
+    }
+}
+
+impl FromStr for NumberFormat {
+    type Err = String;
+
+    // 1,234.56  1.234,56  1 234,56  1234.56  1234,56
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let marks: Vec<char> = s.chars().filter(|c| !c.is_ascii_digit()).collect();
+        let error = || format!("expected a sample number like 1,234.56 or 1.234,56, not \"{}\"", s);
+
+        if !s.starts_with(|c: char| c.is_ascii_digit()) || !s.ends_with(|c: char| c.is_ascii_digit()) {
+            return Err(error())
+        }
+
+        match marks.as_slice() {
+            [decimal] if ['.', ','].contains(decimal)
+                => Ok(NumberFormat { grouping: None, decimal: *decimal }),
+            [grouping @ .., decimal] if ['.', ','].contains(decimal)
+                                     && grouping.iter().all(|g| g == &grouping[0] && g != decimal)
+                => Ok(NumberFormat { grouping: Some(grouping[0]), decimal: *decimal }),
+            _   => Err(error()),
+        }
+    }
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use super::NumberFormat;
+
+    fn format(sample: &str, number: &str) -> String {
+        NumberFormat::from_str(sample).unwrap().format(number)
+    }
+
+    #[test]
+    fn test_number_format() {
+        assert_eq!(format("1,234.56", "1234567.89"), "1,234,567.89");
+        assert_eq!(format("1.234,56", "-1234.50"), "-1.234,50");
+        assert_eq!(format("1 234,56", "999.00"), "999,00");
+        assert_eq!(format("1234,56", "1234.56"), "1234,56");
+        assert_eq!(format("1,234.56", "-100000"), "-100,000");
+        assert_eq!(NumberFormat::default().format("1234.56"), "1234.56");
+    }
+
+    #[test]
+    fn test_number_format_errors() {
+        assert!(NumberFormat::from_str("1234").is_err());
+        assert!(NumberFormat::from_str("1,234,56").is_err());
+        assert!(NumberFormat::from_str("1_234.56").is_ok());
+        assert!(NumberFormat::from_str("$1,234.56").is_err());
+        assert!(NumberFormat::from_str("1.234.").is_err());
+    }
+}
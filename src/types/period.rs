@@ -0,0 +1,79 @@
+use chrono::{Duration, NaiveDate};
+use std::fmt;
+
+use super::daterange::DateRange;
+use super::interval::Interval;
+
+
+// one day, week, month, quarter or year on the calendar, known by its length and first
+// day, so anything stepping through time can do it the same way at any granularity
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Period {
+    pub interval: Interval,
+    pub start   : NaiveDate,
+}
+
+impl Period {
+    // the period of this length that the date falls in
+    pub fn containing(interval: Interval, date: NaiveDate) -> Period {
+        Period { interval, start: interval.start_of(date) }
+    }
+
+    pub fn next(&self) -> Period {
+        Period { interval: self.interval, start: self.interval.next_start(self.start) }
+    }
+
+    pub fn previous(&self) -> Period {
+        Period::containing(self.interval, self.start - Duration::days(1))
+    }
+
+    // the dates in the period, from its first day up to the start of the next one
+    pub fn range(&self) -> DateRange {
+        DateRange::new(Some(self.start), Some(self.next().start))
+    }
+
+    pub fn days(&self) -> i64 {
+        (self.next().start - self.start).num_days()
+    }
+}
+
+// 2023-03-15, 2023-W11, 2023-03, 2023Q1, 2023
+
+impl fmt::Display for Period {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.interval.label(self.start))
+    }
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use crate::types::interval::Interval;
+    use super::Period;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_period() {
+        let quarter = Period::containing(Interval::Quarterly, date(2023, 11, 5));
+        assert_eq!(quarter.start, date(2023, 10, 1));
+        assert_eq!(quarter.to_string(), "2023Q4");
+        assert_eq!(quarter.next().to_string(), "2024Q1");
+        assert_eq!(quarter.previous().to_string(), "2023Q3");
+        assert_eq!(quarter.days(), 92);
+
+        let range = quarter.range();
+        assert!(range.contains(date(2023, 12, 31)));
+        assert!(!range.contains(date(2024, 1, 1)));
+
+        let week = Period::containing(Interval::Weekly, date(2023, 1, 1));
+        assert_eq!(week.start, date(2022, 12, 26));
+        assert_eq!(week.next().start, date(2023, 1, 2));
+        assert_eq!(Period::containing(Interval::Monthly, date(2024, 2, 10)).days(), 29);
+    }
+}
@@ -0,0 +1,231 @@
+use chrono::{Datelike, Duration, NaiveDate};
+use std::str::FromStr;
+
+use super::daterange::DateRange;
+use super::interval::Interval;
+use super::monthyear::MonthYear;
+use super::period::Period;
+
+
+// Parse a period expression from the -p option into a date range and, if the expression
+// starts with one, a reporting interval. Relative expressions are worked out from today:
+//
+//   2023, 2023/03, 2023/03/15, 2023Q2, q2, march, today, yesterday
+//   this month, last quarter, next year
+//   jan..mar, 2023/01 to 2023/04, from 2023/01, since feb, until 2023/06
+//   monthly in 2023, weekly from jan to mar, quarterly
+//
+// a range runs from the start of its first span to the end of its last one, so jan..mar
+// covers all of March
+pub fn parse_period(expr : &str,
+                    today: NaiveDate) -> Result<(DateRange, Option<Interval>), String>
+{
+    let expr = expr.trim().to_lowercase();
+    let words: Vec<&str> = expr.split_whitespace().collect();
+
+    let (interval, words) = match words.split_first() {
+        Some((first, rest)) => match Interval::from_str(first) {
+            Ok(interval) => (Some(interval), rest),
+            Err(_)       => (None, &words[..]),
+        },
+        None => return Err(expr),
+    };
+
+    // "monthly in 2023" reads the same as "monthly 2023"
+    let words = match words.split_first() {
+        Some((&"in", rest)) if interval.is_some() => rest,
+        _                                         => words,
+    };
+
+    let range = parse_range(&words.join(" "), today).ok_or_else(|| expr.clone())?;
+    Ok((range, interval))
+}
+
+fn parse_range(expr: &str, today: NaiveDate) -> Option<DateRange> {
+    if expr.is_empty() {
+        return Some(DateRange::default())
+    }
+
+    let begin = |s: &str| parse_span(s.trim(), today).map(|(begin, _)| begin);
+    let end   = |s: &str| parse_span(s.trim(), today).map(|(_, end)| end);
+
+    if let Some((from, to)) = expr.split_once("..") {
+        return Some(DateRange::new(optional(from, begin)?, optional(to, end)?))
+    }
+
+    let expr = expr.strip_prefix("from ").unwrap_or(expr);
+    if let Some((from, to)) = expr.split_once(" to ") {
+        return Some(DateRange::new(Some(begin(from)?), Some(end(to)?)))
+    }
+
+    if let Some(from) = expr.strip_prefix("since ") {
+        return Some(DateRange::new(Some(begin(from)?), None))
+    }
+
+    if let Some(to) = expr.strip_prefix("until ").or_else(|| expr.strip_prefix("to ")) {
+        return Some(DateRange::new(None, Some(end(to)?)))
+    }
+
+    let (begin, end) = parse_span(expr, today)?;
+    Some(DateRange::new(Some(begin), Some(end)))
+}
+
+// an empty side of a .. range is left open
+fn optional(side : &str,
+            parse: impl Fn(&str) -> Option<NaiveDate>) -> Option<Option<NaiveDate>>
+{
+    if side.trim().is_empty() {
+        Some(None)
+    } else {
+        parse(side).map(Some)
+    }
+}
+
+// the first day of the span of dates the expression names, and the day after its last
+fn parse_span(expr: &str, today: NaiveDate) -> Option<(NaiveDate, NaiveDate)> {
+    let day = |date: NaiveDate| Some((date, date + Duration::days(1)));
+    let span = |interval: Interval, date: NaiveDate| {
+        let period = Period::containing(interval, date);
+        Some((period.start, period.next().start))
+    };
+
+    match expr {
+        "today"     => return day(today),
+        "yesterday" => return day(today - Duration::days(1)),
+        "tomorrow"  => return day(today + Duration::days(1)),
+        _           => {},
+    }
+
+    // this month, last quarter, next year
+    if let Some((relative, unit)) = expr.split_once(' ') {
+        let interval = unit_interval(unit)?;
+        let this   = Period::containing(interval, today);
+        let period = match relative {
+            "this" => this,
+            "last" => this.previous(),
+            "next" => this.next(),
+            _      => return None,
+        };
+        return span(interval, period.start)
+    }
+
+    // 2023Q2, or q2 of this year
+    if let Some(at) = expr.find('q') {
+        let year = if at == 0 { today.year() } else { expr[..at].parse().ok()? };
+        let quarter: u32 = expr[at + 1..].parse().ok().filter(|q| (1..=4).contains(q))?;
+        return span(Interval::Quarterly, NaiveDate::from_ymd_opt(year, quarter * 3 - 2, 1)?)
+    }
+
+    // march, of this year
+    if let Some(month) = month_number(expr) {
+        return span(Interval::Monthly, NaiveDate::from_ymd_opt(today.year(), month, 1)?)
+    }
+
+    // 2023/03 or 2023-03
+    if let Ok(month) = MonthYear::from_str(expr) {
+        return span(Interval::Monthly, month.first_day())
+    }
+
+    // 2023 or 2023/03/15
+    let parts = expr.split(['/', '-']).count();
+    let date = super::daterange::parse_date(expr)?;
+    match parts {
+        1 => span(Interval::Yearly, date),
+        _ => day(date),
+    }
+}
+
+fn unit_interval(unit: &str) -> Option<Interval> {
+    match unit {
+        "day"     => Some(Interval::Daily),
+        "week"    => Some(Interval::Weekly),
+        "month"   => Some(Interval::Monthly),
+        "quarter" => Some(Interval::Quarterly),
+        "year"    => Some(Interval::Yearly),
+        _         => None,
+    }
+}
+
+// january or jan is 1
+fn month_number(name: &str) -> Option<u32> {
+    const MONTHS: [&str; 12] = ["january", "february", "march", "april", "may", "june", "july",
+                                "august", "september", "october", "november", "december"];
+
+    if name.len() < 3 {
+        return None
+    }
+
+    MONTHS.iter()
+          .position(|month| month.starts_with(name))
+          .map(|i| i as u32 + 1)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use crate::types::{daterange::DateRange, interval::Interval};
+    use super::parse_period;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    // a Wednesday
+    fn today() -> NaiveDate {
+        date(2023, 5, 17)
+    }
+
+    fn range(expr: &str) -> (Option<NaiveDate>, Option<NaiveDate>) {
+        let (range, _) = parse_period(expr, today()).unwrap();
+        (range.begin, range.end)
+    }
+
+    #[test]
+    fn test_parse_period_spans() {
+        assert_eq!(range("2023"),       (Some(date(2023, 1, 1)),  Some(date(2024, 1, 1))));
+        assert_eq!(range("2023/03"),    (Some(date(2023, 3, 1)),  Some(date(2023, 4, 1))));
+        assert_eq!(range("2023-03-15"), (Some(date(2023, 3, 15)), Some(date(2023, 3, 16))));
+        assert_eq!(range("2023Q2"),     (Some(date(2023, 4, 1)),  Some(date(2023, 7, 1))));
+        assert_eq!(range("Q4"),         (Some(date(2023, 10, 1)), Some(date(2024, 1, 1))));
+        assert_eq!(range("March"),      (Some(date(2023, 3, 1)),  Some(date(2023, 4, 1))));
+        assert_eq!(range("yesterday"),  (Some(date(2023, 5, 16)), Some(date(2023, 5, 17))));
+    }
+
+    #[test]
+    fn test_parse_period_relative() {
+        assert_eq!(range("this month"),   (Some(date(2023, 5, 1)),  Some(date(2023, 6, 1))));
+        assert_eq!(range("last month"),   (Some(date(2023, 4, 1)),  Some(date(2023, 5, 1))));
+        assert_eq!(range("next quarter"), (Some(date(2023, 7, 1)),  Some(date(2023, 10, 1))));
+        assert_eq!(range("last year"),    (Some(date(2022, 1, 1)),  Some(date(2023, 1, 1))));
+        assert_eq!(range("this week"),    (Some(date(2023, 5, 15)), Some(date(2023, 5, 22))));
+    }
+
+    #[test]
+    fn test_parse_period_ranges() {
+        assert_eq!(range("jan..mar"),              (Some(date(2023, 1, 1)), Some(date(2023, 4, 1))));
+        assert_eq!(range("2023/01..2023/04"),      (Some(date(2023, 1, 1)), Some(date(2023, 5, 1))));
+        assert_eq!(range("..2023/04"),             (None,                   Some(date(2023, 5, 1))));
+        assert_eq!(range("from 2022 to 2023"),     (Some(date(2022, 1, 1)), Some(date(2024, 1, 1))));
+        assert_eq!(range("since feb"),             (Some(date(2023, 2, 1)), None));
+        assert_eq!(range("until last month"),      (None,                   Some(date(2023, 5, 1))));
+    }
+
+    #[test]
+    fn test_parse_period_interval() {
+        assert_eq!(parse_period("monthly in 2023", today()),
+                   Ok((DateRange::new(Some(date(2023, 1, 1)), Some(date(2024, 1, 1))), Some(Interval::Monthly))));
+        assert_eq!(parse_period("Weekly from jan to mar", today()).unwrap().1, Some(Interval::Weekly));
+        assert_eq!(parse_period("quarterly", today()), Ok((DateRange::default(), Some(Interval::Quarterly))));
+        assert_eq!(parse_period("2023", today()).unwrap().1, None);
+    }
+
+    #[test]
+    fn test_parse_period_errors() {
+        assert!(parse_period("", today()).is_err());
+        assert!(parse_period("someday", today()).is_err());
+        assert!(parse_period("2023Q5", today()).is_err());
+        assert!(parse_period("last fortnight", today()).is_err());
+        assert!(parse_period("ma", today()).is_err());
+    }
+}
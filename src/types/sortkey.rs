@@ -0,0 +1,57 @@
+use std::str::FromStr;
+
+
+// the order of the register report's lines, given with --sort as a field name like amount,
+// or -amount for largest first. lines that tie stay in journal order
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SortKey {
+    pub field     : SortField,
+    pub descending: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SortField {
+    Date,
+    Amount,
+    Account,
+    Description,
+}
+
+impl FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (descending, name) = match s.strip_prefix('-') {
+            Some(name) => (true, name),
+            None       => (false, s),
+        };
+
+        let field = match name {
+            "date"                  => SortField::Date,
+            "amount"                => SortField::Amount,
+            "account"               => SortField::Account,
+            "description" | "payee" => SortField::Description,
+            _                       => return Err(format!("expected date, amount, account or description, with a - for descending, not \"{}\"", s)),
+        };
+
+        Ok(SortKey { field, descending })
+    }
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use super::{SortField, SortKey};
+
+    #[test]
+    fn test_sort_key() {
+        assert_eq!(SortKey::from_str("amount"), Ok(SortKey { field: SortField::Amount, descending: false }));
+        assert_eq!(SortKey::from_str("-date"), Ok(SortKey { field: SortField::Date, descending: true }));
+        assert_eq!(SortKey::from_str("payee"), Ok(SortKey { field: SortField::Description, descending: false }));
+        assert!(SortKey::from_str("--amount").is_err());
+        assert!(SortKey::from_str("total").is_err());
+    }
+}
@@ -0,0 +1,177 @@
+use lazy_static::lazy_static;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Deref;
+use std::sync::RwLock;
+
+
+/* Symbol */
+
+// a name that comes up over and over in a journal, like an account or a commodity. it's
+// the name's number in the symbol table, so cloning, comparing and hashing one only
+// touches that number, and the text is looked up in the table when it's read
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    pub fn as_str(&self) -> &'static str {
+        SYMBOLS.read().unwrap_or_else(|error| error.into_inner()).name(*self)
+    }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialEq<String> for Symbol {
+    fn eq(&self, other: &String) -> bool {
+        self.as_str() == other
+    }
+}
+
+// ordered by the text, so accounts and commodities sort by name
+impl Ord for Symbol {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match self == other {
+            true  => Ordering::Equal,
+            false => self.as_str().cmp(other.as_str()),
+        }
+    }
+}
+
+impl PartialOrd for Symbol {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Deref for Symbol {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(name: &str) -> Self {
+        SymbolTable::intern(name)
+    }
+}
+
+impl From<String> for Symbol {
+    fn from(name: String) -> Self {
+        SymbolTable::intern(&name)
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Symbol {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Symbol {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Symbol::from)
+    }
+}
+
+
+/* SymbolTable */
+
+// every account and commodity name the program has read, kept once each and numbered in
+// the order they were first seen. the table only ever grows, so a symbol means the same
+// name in every journal, including one reread by --watch or merged in from another file,
+// and a journal's symbols are ready the moment its names are parsed
+struct SymbolTable {
+    ids  : HashMap<&'static str, Symbol>,
+    names: Vec<&'static str>,
+}
+
+lazy_static! {
+    // the empty name comes first, so the default symbol reads as ""
+    static ref SYMBOLS: RwLock<SymbolTable> =
+        RwLock::new(SymbolTable { ids: HashMap::from([("", Symbol(0))]), names: vec![""] });
+}
+
+impl SymbolTable {
+    // the symbol for the name, adding it to the table if it's the first time it's been seen
+    fn intern(name: &str) -> Symbol {
+        if let Some(symbol) = SYMBOLS.read().unwrap_or_else(|error| error.into_inner()).ids.get(name) {
+            return *symbol
+        }
+
+        // another thread may have added it between the two locks
+        let mut table = SYMBOLS.write().unwrap_or_else(|error| error.into_inner());
+        if let Some(symbol) = table.ids.get(name) {
+            return *symbol
+        }
+
+        let name: &'static str = Box::leak(name.into());
+        let symbol = Symbol(u32::try_from(table.names.len()).expect("more names than a symbol can number"));
+        table.names.push(name);
+        table.ids.insert(name, symbol);
+        symbol
+    }
+
+    fn name(&self, symbol: Symbol) -> &'static str {
+        self.names[symbol.0 as usize]
+    }
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use super::Symbol;
+
+    #[test]
+    fn test_intern() {
+        let first  = Symbol::from("expenses:food");
+        let second = Symbol::from("expenses:food".to_string());
+
+        assert_eq!(first.0, second.0);
+        assert!(std::ptr::eq(first.as_str(), second.as_str()));
+        assert_ne!(first, Symbol::from("expenses:rent"));
+        assert_eq!(first, "expenses:food");
+        assert_eq!(first.to_string(), "expenses:food");
+        assert_eq!(Symbol::default(), "");
+
+        let map: HashMap<Symbol, i32> = HashMap::from([(first, 1)]);
+        assert_eq!(map.get(&Symbol::from("expenses:food")), Some(&1));
+    }
+
+    #[test]
+    fn test_order_by_name() {
+        // numbered in the order they're seen, but sorted by their names
+        let (zoo, apple) = (Symbol::from("zzz:zoo"), Symbol::from("aaa:apple"));
+        assert!(zoo.0 < apple.0);
+        assert!(apple < zoo);
+    }
+}
@@ -0,0 +1,315 @@
+use chrono::NaiveDate;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use regex::Regex;
+use std::io;
+
+use crate::accounttree::AccountTree;
+use crate::color::{ColorChoice, set_color};
+use crate::journal::Journal;
+use crate::query::Query;
+use crate::reports::register::{RegisterOptions, register_report};
+use crate::types::{interval::Interval, lineformat::LineFormat, period::Period};
+
+
+/* Journal browser */
+
+// a read-only dashboard over the journal for `katana ui`, with the account tree on the
+// left and the register of the selected account and its subaccounts on the right
+//
+//   up/down, j/k     select an account
+//   pgup/pgdn        scroll the register
+//   p                cycle the period between all, years, quarters and months
+//   [ ]              step back or forward a period
+//   /                search the account names, narrowing the tree as you type
+//   esc              clear the search
+//   q                quit
+pub fn run(journal: &Journal) -> io::Result<()> {
+    // the register renders its own lines, and ratatui styles them instead
+    set_color(ColorChoice::Never);
+
+    let mut browser  = Browser::new(journal);
+    let mut terminal = ratatui::init();
+    let result = browse(&mut terminal, journal, &mut browser);
+    ratatui::restore();
+    result
+}
+
+fn browse(terminal: &mut DefaultTerminal, journal: &Journal, browser: &mut Browser) -> io::Result<()> {
+    loop {
+        terminal.draw(|frame| draw(frame, journal, browser))?;
+
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press && !browser.handle_key(key) {
+                return Ok(())
+            }
+        }
+    }
+}
+
+// what the browser is showing, apart from the drawing of it
+struct Browser {
+    accounts : Vec<String>,                  // every account and its parents, in tree order
+    selected : usize,                        // index into the accounts matching the search
+    search   : String,
+    searching: bool,                         // typing goes into the search
+    period   : Option<Period>,                // the period shown, or all of them
+    last_date: NaiveDate,                    // where switching to periods starts from
+    scroll   : u16,
+}
+
+impl Browser {
+    fn new(journal: &Journal) -> Self {
+        let accounts: Vec<String> = AccountTree::from(journal).walk()
+                                                              .map(|entry| entry.account.to_string())
+                                                              .collect();
+
+        let last_date = journal.transactions
+                               .iter()
+                               .map(|t| t.date)
+                               .max()
+                               .unwrap_or_else(crate::common::today);
+
+        Browser {
+            accounts,
+            selected : 0,
+            search   : String::new(),
+            searching: false,
+            period   : None,
+            last_date,
+            scroll   : 0,
+        }
+    }
+
+    // the accounts whose names contain the search, with their parents to keep the tree intact
+    fn visible(&self) -> Vec<&str> {
+        let search = self.search.to_lowercase();
+        let matching: Vec<&str> = self.accounts
+                                      .iter()
+                                      .filter(|a| a.to_lowercase().contains(&search))
+                                      .map(String::as_str)
+                                      .collect();
+
+        self.accounts
+            .iter()
+            .map(String::as_str)
+            .filter(|account| matching.iter().any(|m| m == account || m.starts_with(&format!("{}:", account))))
+            .collect()
+    }
+
+    fn selected_account(&self) -> Option<String> {
+        self.visible().get(self.selected).map(|a| a.to_string())
+    }
+
+    // the postings to the selected account or its subaccounts in the period shown
+    fn query(&self) -> Option<Query> {
+        let account = self.selected_account()?;
+        let pattern = Regex::new(&format!("^{}(:|$)", regex::escape(&account))).unwrap();
+
+        let mut terms = vec![Query::Acct(pattern)];
+        if let Some(period) = self.period {
+            terms.push(Query::DateRange(period.range()));
+        }
+        Some(Query::And(terms))
+    }
+
+    // 2023-03, 2023Q1, or "all dates"
+    fn period_label(&self) -> String {
+        match self.period {
+            Some(period) => period.to_string(),
+            None         => "all dates".to_string(),
+        }
+    }
+
+    // false when it's time to quit
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        if self.searching {
+            match key.code {
+                KeyCode::Char(c)   => self.search.push(c),
+                KeyCode::Backspace => { self.search.pop(); },
+                KeyCode::Enter     => self.searching = false,
+                KeyCode::Esc       => { self.search.clear(); self.searching = false },
+                _                  => return true,
+            }
+            self.selected = 0;
+            self.scroll = 0;
+            return true
+        }
+
+        match key.code {
+            KeyCode::Char('q')                    => return false,
+            KeyCode::Down | KeyCode::Char('j')    => self.select(1),
+            KeyCode::Up   | KeyCode::Char('k')    => self.select(-1),
+            KeyCode::PageDown                     => self.scroll = self.scroll.saturating_add(10),
+            KeyCode::PageUp                       => self.scroll = self.scroll.saturating_sub(10),
+            KeyCode::Char('p')                    => self.cycle_period(),
+            KeyCode::Char('[')                    => self.step_period(false),
+            KeyCode::Char(']')                    => self.step_period(true),
+            KeyCode::Char('/')                    => self.searching = true,
+            KeyCode::Esc                          => { self.search.clear(); self.selected = 0 },
+            _                                     => {},
+        }
+        true
+    }
+
+    fn select(&mut self, by: isize) {
+        let count = self.visible().len();
+        if count > 0 {
+            self.selected = self.selected.saturating_add_signed(by).min(count - 1);
+            self.scroll = 0;
+        }
+    }
+
+    // all dates, then the year, quarter and month of the latest transaction
+    fn cycle_period(&mut self) {
+        let next = match self.period {
+            None                                               => Some(Interval::Yearly),
+            Some(Period { interval: Interval::Yearly, .. })    => Some(Interval::Quarterly),
+            Some(Period { interval: Interval::Quarterly, .. }) => Some(Interval::Monthly),
+            Some(_)                                            => None,
+        };
+        self.period = next.map(|interval| Period::containing(interval, self.last_date));
+        self.scroll = 0;
+    }
+
+    fn step_period(&mut self, forward: bool) {
+        if let Some(period) = self.period {
+            self.period = Some(if forward { period.next() } else { period.previous() });
+            self.scroll = 0;
+        }
+    }
+}
+
+fn draw(frame: &mut Frame, journal: &Journal, browser: &Browser) {
+    let [main, status] = Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+    let [left, right]  = Layout::horizontal([Constraint::Percentage(30), Constraint::Percentage(70)]).areas(main);
+
+    // the account tree, each name shortened to its last component and indented by its depth
+    let items: Vec<ListItem> =
+        browser.visible()
+               .iter()
+               .map(|account| {
+                   let depth = account.matches(':').count();
+                   ListItem::new(format!("{}{}", "  ".repeat(depth), account.rsplit(':').next().unwrap()))
+               })
+               .collect();
+
+    let list = List::new(items).block(Block::default().borders(Borders::ALL).title(" Accounts "))
+                               .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut state = ListState::default().with_selected(Some(browser.selected));
+    frame.render_stateful_widget(list, left, &mut state);
+
+    // the register of the selected account
+    let format: LineFormat = "%-10(date) %-30(payee) %-30(account) %12(amount) %12(total)".parse().unwrap();
+    let (title, lines) = match browser.query() {
+        Some(query) => {
+            let lines: Vec<Line> = register_report(journal, &query, &RegisterOptions::default())
+                                       .map(|line| match line {
+                                           Ok(line)   => Line::from(line.render(&format, &journal.commodities)),
+                                           Err(error) => Line::from(error.to_string()),
+                                       })
+                                       .collect();
+            (format!(" {} ({}) ", browser.selected_account().unwrap(), browser.period_label()), lines)
+        },
+        None => (" No matching accounts ".to_string(), vec![]),
+    };
+
+    let register = Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title))
+                                        .scroll((browser.scroll, 0));
+    frame.render_widget(register, right);
+
+    let help = if browser.searching {
+        format!("/{}", browser.search)
+    } else {
+        "q quit  j/k select  pgup/pgdn scroll  p period  [ ] previous/next  / search".to_string()
+    };
+    frame.render_widget(Paragraph::new(help), status);
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use ratatui::crossterm::event::{KeyCode, KeyEvent};
+    use crate::journal::Journal;
+    use crate::reports::register::{RegisterOptions, register_report};
+    use super::{Browser, Interval, Period};
+
+    fn sample_journal() -> Journal {
+        let journal =
+r#"
+2023/03/07 Sandwich, Chili
+    expenses:food:tim-hortons          $14.99
+    assets:cash
+
+2023/04/09 Sub
+    expenses:food:subway                $12
+    assets:savings
+"#;
+        Journal::from_lines(journal.lines()).unwrap()
+    }
+
+    fn press(browser: &mut Browser, keys: &str) {
+        for c in keys.chars() {
+            browser.handle_key(KeyEvent::from(KeyCode::Char(c)));
+        }
+    }
+
+    #[test]
+    fn test_browser_accounts() {
+        let browser = Browser::new(&sample_journal());
+        assert_eq!(browser.visible(), vec![
+            "assets",
+            "assets:cash",
+            "assets:savings",
+            "expenses",
+            "expenses:food",
+            "expenses:food:subway",
+            "expenses:food:tim-hortons",
+        ]);
+    }
+
+    #[test]
+    fn test_browser_search() {
+        let mut browser = Browser::new(&sample_journal());
+        press(&mut browser, "/SUB");
+        assert_eq!(browser.visible(), vec!["expenses", "expenses:food", "expenses:food:subway"]);
+
+        browser.handle_key(KeyEvent::from(KeyCode::Enter));
+        press(&mut browser, "jj");
+        assert_eq!(browser.selected_account().as_deref(), Some("expenses:food:subway"));
+
+        browser.handle_key(KeyEvent::from(KeyCode::Esc));
+        assert_eq!(browser.visible().len(), 7);
+        assert!(!browser.handle_key(KeyEvent::from(KeyCode::Char('q'))));
+    }
+
+    #[test]
+    fn test_browser_periods() {
+        let journal = sample_journal();
+        let mut browser = Browser::new(&journal);
+        press(&mut browser, "jjjj");
+        assert_eq!(browser.selected_account().as_deref(), Some("expenses:food"));
+
+        let postings = |browser: &Browser| register_report(&journal, &browser.query().unwrap(), &RegisterOptions::default()).count();
+        assert_eq!(postings(&browser), 2);
+
+        press(&mut browser, "ppp");
+        assert_eq!(browser.period_label(), "2023-04");
+        assert_eq!(browser.period, Some(Period { interval: Interval::Monthly, start: NaiveDate::from_ymd_opt(2023, 4, 1).unwrap() }));
+        assert_eq!(postings(&browser), 1);
+
+        press(&mut browser, "[[");
+        assert_eq!(browser.period_label(), "2023-02");
+        assert_eq!(postings(&browser), 0);
+
+        press(&mut browser, "p");
+        assert_eq!(browser.period_label(), "all dates");
+    }
+}
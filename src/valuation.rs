@@ -0,0 +1,137 @@
+use chrono::NaiveDate;
+
+use crate::journal::Journal;
+use crate::price::PriceDb;
+use crate::transaction::Transaction;
+use crate::types::{Units, amount::{Amount, AmountError}};
+
+
+/* Valuation */
+
+// what to convert the journal's amounts into
+pub enum Valuation {
+    Market,          // -V, each commodity into the one its prices are quoted in
+    Exchange(Units), // -X, everything into this commodity
+}
+
+// Convert every posting amount into a single comparable commodity using the journal's
+// market prices. With a date, every price is looked up as of that day, which is usually the
+// end of the report. Without one, each posting is valued on the date of its transaction.
+// Amounts with no known price are left as they are, and a conversion too large for an
+// amount to hold is an error
+pub fn value_journal(journal  : &mut Journal,
+                     valuation: &Valuation,
+                     date     : Option<NaiveDate>) -> Result<(), AmountError>
+{
+    let prices = PriceDb::new(&journal.prices, &journal.commodities);
+
+    for transaction in journal.transactions.iter_mut().chain(journal.earlier.iter_mut()) {
+        value_transaction(transaction, &prices, valuation, date)?;
+    }
+    Ok(())
+}
+
+fn value_transaction(transaction: &mut Transaction,
+                     prices     : &PriceDb,
+                     valuation  : &Valuation,
+                     date       : Option<NaiveDate>) -> Result<(), AmountError>
+{
+    let date = date.unwrap_or(transaction.date);
+
+    for entry in transaction.entries.iter_mut() {
+        if let Some(valued) = value(&entry.amount, prices, valuation, date)? {
+            entry.amount = valued;
+        }
+    }
+    Ok(())
+}
+
+// the amount in the valuation commodity as of the date, if there's a price for it
+pub fn value(amount   : &Amount,
+             prices   : &PriceDb,
+             valuation: &Valuation,
+             date     : NaiveDate) -> Result<Option<Amount>, AmountError>
+{
+    let target = match valuation {
+        Valuation::Exchange(target) => target,
+        Valuation::Market           => match prices.price_at(&amount.units, date) {
+            Some(price) => &price.units,
+            None        => return Ok(None),
+        },
+    };
+
+    if amount.units == *target {
+        return Ok(None)
+    }
+
+    prices.convert(amount, target, date)
+}
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use crate::journal::Journal;
+    use crate::types::amount::Amount;
+    use super::{Valuation, value_journal};
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn sample_journal() -> Journal {
+        let journal =
+r#"
+P 2023/01/01 USD $1.30
+P 2023/03/01 USD $1.40
+P 2023/03/01 AAPL 150 USD
+
+2023/01/15 Trip savings
+    assets:usd                   100 USD
+    equity:opening-balances
+
+2023/02/15 Shares
+    assets:brokerage               2 AAPL
+    equity:opening-balances
+"#;
+        Journal::from_lines(journal.lines()).unwrap()
+    }
+
+    fn amounts(journal: &Journal) -> Vec<String> {
+        journal.transactions
+               .iter()
+               .flat_map(|t| &t.entries)
+               .map(|e| e.amount.to_string())
+               .collect()
+    }
+
+    #[test]
+    fn test_value_journal_exchange_at_transaction_dates() {
+        let mut journal = sample_journal();
+        value_journal(&mut journal, &Valuation::Exchange("$".into()), None).unwrap();
+
+        // no AAPL price yet in February, so the shares stay as they are
+        assert_eq!(amounts(&journal), vec!["$130.00", "$-130.00", "2.000 AAPL", "-2.000 AAPL"]);
+        assert_eq!(journal.transactions[1].entries[0].amount, Amount::from("AAPL".to_string(), 2.0));
+    }
+
+    #[test]
+    fn test_value_journal_market_at_end() {
+        let mut journal = sample_journal();
+        value_journal(&mut journal, &Valuation::Market, Some(date(2023, 3, 31))).unwrap();
+
+        // AAPL into USD and USD into $, each from their latest price
+        assert_eq!(amounts(&journal), vec!["$140.00", "$-140.00", "300.000 USD", "-300.000 USD"]);
+        assert_eq!(journal.transactions[1].entries[0].amount.units, "USD");
+    }
+
+    #[test]
+    fn test_value_journal_exchange_through_usd() {
+        let mut journal = sample_journal();
+        value_journal(&mut journal, &Valuation::Exchange("$".into()), Some(date(2023, 3, 31))).unwrap();
+
+        // AAPL has no price in $, but it has one in USD, which does
+        assert_eq!(amounts(&journal), vec!["$140.00", "$-140.00", "$420.00", "$-420.00"]);
+    }
+}
@@ -0,0 +1,167 @@
+use std::io;
+use std::net::SocketAddr;
+
+use crate::color::{ColorChoice, set_color};
+use crate::http::{Request, Response, serve};
+use crate::journal::{Journal, watch::{WatchedJournal, current}};
+use crate::output::{html_escape, to_html};
+use crate::query::Query;
+use crate::types::commoditystyle::WithStyles;
+use crate::reports::balance::{BalanceOptions, balance_periodic_report};
+use crate::reports::register::{RegisterOptions, ReportLine, register_report};
+use crate::types::interval::Interval;
+
+
+/* Web UI */
+
+// `katana web` serves the balance and register reports as web pages, so a journal on a
+// home server can be browsed from a phone. each page has a form for its query terms, which
+// are the same as on the command line
+//
+//   /                                         links to the reports
+//   /balance?interval=monthly&query=expenses  balance changes by period
+//   /register?query=assets:savings            postings with a running total
+pub fn run(journal: &Journal, mut watched: Option<WatchedJournal>, address: SocketAddr) -> io::Result<()> {
+    // the tables are plain text, without terminal colour codes
+    set_color(ColorChoice::Never);
+
+    eprintln!("Serving the journal at http://{}/", address);
+    serve(address, |request| page(current(&mut watched, journal), request))
+}
+
+fn page(journal: &Journal, request: &Request) -> Response {
+    let terms = request.param("query").unwrap_or("");
+    let query = match terms.parse::<Query>() {
+        Ok(query)  => query,
+        Err(error) => return Response::error(400, "text/html", layout("Invalid query", &paragraph(&error))),
+    };
+
+    match request.path.as_str() {
+        "/" => {
+            Response::ok("text/html", layout("katana", r#"<ul>
+<li><a href="/balance">Balance</a></li>
+<li><a href="/register">Register</a></li>
+</ul>
+"#))
+        },
+        "/balance" => {
+            let interval = match request.param("interval").unwrap_or("monthly").parse::<Interval>() {
+                Ok(interval) => interval,
+                Err(error)   => return Response::error(400, "text/html", layout("Invalid interval", &paragraph(&error))),
+            };
+            let options = BalanceOptions {
+                query,
+                ..Default::default()
+            };
+            let report = match balance_periodic_report(journal, &options, interval) {
+                Ok(report) => report,
+                Err(error) => return Response::error(500, "text/html", layout("Couldn't total the journal", &paragraph(&error.to_string()))),
+            };
+
+            let mut body = form("/balance", terms, Some(interval));
+            body += &to_html(&report.styled(&journal.commodities));
+            Response::ok("text/html", layout(&format!("Balance changes by {}", interval.period_name()), &body))
+        },
+        "/register" => {
+            let report: Vec<ReportLine> = match register_report(journal, &query, &RegisterOptions::default()).collect() {
+                Ok(report) => report,
+                Err(error) => return Response::error(500, "text/html", layout("Couldn't total the journal", &paragraph(&error.to_string()))),
+            };
+
+            let mut body = form("/register", terms, None);
+            body += &to_html(&report[..].styled(&journal.commodities));
+            Response::ok("text/html", layout("Register", &body))
+        },
+        _ => Response::error(404, "text/html", layout("Not found", &paragraph(&request.path))),
+    }
+}
+
+// a whole page, sized for a phone's screen
+fn layout(title: &str, body: &str) -> String {
+    format!(r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{title}</title>
+<style>
+body {{ font-family: sans-serif; margin: 1em; }}
+table {{ border-collapse: collapse; font-size: 0.9em; }}
+th, td {{ padding: 0.2em 0.6em; border-bottom: 1px solid #ddd; white-space: nowrap; }}
+td {{ font-family: monospace; }}
+div.report {{ overflow-x: auto; }}
+</style>
+</head>
+<body>
+<p><a href="/">katana</a></p>
+<h1>{title}</h1>
+<div class="report">
+{body}</div>
+</body>
+</html>
+"#, title = html_escape(title), body = body)
+}
+
+// the query terms, and for the balance report the period to show a column for
+fn form(action: &str, terms: &str, interval: Option<Interval>) -> String {
+    let mut form = format!(r#"<form action="{}">
+<input name="query" value="{}" placeholder="expenses not:food">
+"#, action, html_escape(terms));
+
+    if let Some(selected) = interval {
+        form += "<select name=\"interval\">\n";
+        for interval in [Interval::Daily, Interval::Weekly, Interval::Monthly, Interval::Quarterly, Interval::Yearly] {
+            let attribute = if interval == selected { " selected" } else { "" };
+            form += &format!("<option{}>{}</option>\n", attribute, interval);
+        }
+        form += "</select>\n";
+    }
+
+    form + "<button>Show</button>\n</form>\n"
+}
+
+fn paragraph(text: &str) -> String {
+    format!("<p>{}</p>\n", html_escape(text))
+}
+
+
+/* Tests */
+
+#[cfg(test)]
+mod tests {
+    use crate::http::{Request, test_support::sample_journal};
+    use super::page;
+
+    fn get(target: &str) -> (u16, String) {
+        let response = page(&sample_journal(), &Request::from_target(target));
+        (response.status, response.body)
+    }
+
+    #[test]
+    fn test_web_register() {
+        let (status, body) = get("/register?query=desc%3Asandwich+%5Eassets");
+        assert_eq!(status, 200);
+        assert!(body.contains(r#"<input name="query" value="desc:sandwich ^assets""#));
+        assert!(body.contains("<td>Sandwich, Chili</td>"));
+        assert!(body.contains("<td>$-14.99</td>"));
+        assert!(!body.contains("<td>Sub</td>"));
+    }
+
+    #[test]
+    fn test_web_balance() {
+        let (status, body) = get("/balance?interval=yearly&query=expenses");
+        assert_eq!(status, 200);
+        assert!(body.contains("<h1>Balance changes by year</h1>"));
+        assert!(body.contains("<option selected>yearly</option>"));
+        assert!(body.contains("<td>expenses:food:subway</td>"));
+        assert!(!body.contains("assets:cash"));
+    }
+
+    #[test]
+    fn test_web_errors() {
+        assert_eq!(get("/").0, 200);
+        assert_eq!(get("/ledger").0, 404);
+        assert_eq!(get("/balance?interval=hourly").0, 400);
+        assert_eq!(get("/register?query=amt%3A%3F").0, 400);
+    }
+}